@@ -0,0 +1,360 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::dialogs::alert;
+use crate::git::{blame_file, FileBlameLine};
+use crate::{BlameLine, CurrentWindow, DARK_CLASS, LIGHT_CLASS};
+use async_channel::Sender;
+use glib::Object;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{
+    gdk, gio, glib, pango, Box, EventControllerKey, GestureClick, Label, ListItem, ListView,
+    Orientation, ScrolledWindow, SignalListItemFactory, SingleSelection, Widget,
+};
+use libadwaita::prelude::*;
+use libadwaita::{HeaderBar, StyleManager, ToolbarView, Window};
+use log::trace;
+use std::path::PathBuf;
+
+glib::wrapper! {
+    pub struct BlameItem(ObjectSubclass<blame_item::BlameItem>);
+}
+
+mod blame_item {
+    use crate::git::FileBlameLine;
+    use glib::Properties;
+    use gtk4::glib;
+    use gtk4::prelude::*;
+    use gtk4::subclass::prelude::*;
+    use std::cell::RefCell;
+
+    #[derive(Properties, Default)]
+    #[properties(wrapper_type = super::BlameItem)]
+    pub struct BlameItem {
+        pub line: RefCell<Option<FileBlameLine>>,
+
+        #[property(get = Self::get_oid)]
+        pub oid: String,
+
+        #[property(get = Self::get_author)]
+        pub author: String,
+
+        #[property(get = Self::get_line_no)]
+        pub line_no: String,
+
+        #[property(get = Self::get_content)]
+        pub content: String,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BlameItem {
+        const NAME: &'static str = "StageBlameItem";
+        type Type = super::BlameItem;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for BlameItem {}
+
+    impl BlameItem {
+        pub fn get_oid(&self) -> String {
+            match self.line.borrow().as_ref().and_then(|l| l.oid) {
+                Some(oid) => format!(
+                    "<span color=\"#1C71D8\">{}</span>",
+                    oid.to_string().get(..8).unwrap_or("")
+                ),
+                None => String::from("<i>not committed</i>"),
+            }
+        }
+        pub fn get_author(&self) -> String {
+            self.line
+                .borrow()
+                .as_ref()
+                .map(|l| l.author.clone())
+                .unwrap_or_default()
+        }
+        pub fn get_line_no(&self) -> String {
+            self.line
+                .borrow()
+                .as_ref()
+                .map(|l| l.line_no.to_string())
+                .unwrap_or_default()
+        }
+        pub fn get_content(&self) -> String {
+            self.line
+                .borrow()
+                .as_ref()
+                .map(|l| l.content.clone())
+                .unwrap_or_default()
+        }
+    }
+}
+
+impl BlameItem {
+    pub fn new(line: FileBlameLine) -> Self {
+        let ob = Object::builder::<BlameItem>().build();
+        ob.imp().line.replace(Some(line));
+        ob
+    }
+
+    // uncommitted lines have nothing to show
+    pub fn show_file(&self, file_path: PathBuf, sender: &Sender<crate::Event>) {
+        if let Some(oid) = self.imp().line.borrow().as_ref().and_then(|l| l.oid) {
+            sender
+                .send_blocking(crate::Event::ShowFileAt(oid, file_path))
+                .expect("cant send through channel");
+        }
+    }
+
+    pub fn show_commit(&self, file_path: PathBuf, sender: &Sender<crate::Event>) {
+        if let Some(line) = self.imp().line.borrow().as_ref() {
+            if let Some(oid) = line.oid {
+                sender
+                    .send_blocking(crate::Event::ShowOid(
+                        oid,
+                        None,
+                        Some(BlameLine {
+                            file_path,
+                            hunk_start: line.orig_line_no,
+                            content: line.content.clone(),
+                        }),
+                    ))
+                    .expect("cant send through channel");
+            }
+        }
+    }
+}
+
+pub fn item_factory(file_path: PathBuf, sender: Sender<crate::Event>) -> SignalListItemFactory {
+    let factory = SignalListItemFactory::new();
+    factory.connect_setup(move |_, list_item| {
+        let oid_label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .width_chars(14)
+            .max_width_chars(14)
+            .xalign(0.0)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        let gesture_controller = GestureClick::new();
+        gesture_controller.connect_released({
+            let list_item = list_item.clone();
+            let sender = sender.clone();
+            let file_path = file_path.clone();
+            move |_gesture, _some, _wx, _wy| {
+                let list_item = list_item.downcast_ref::<ListItem>().unwrap();
+                let blame_item = list_item.item().unwrap();
+                let blame_item = blame_item.downcast_ref::<BlameItem>().unwrap();
+                blame_item.show_commit(file_path.clone(), &sender);
+            }
+        });
+        oid_label.add_controller(gesture_controller);
+
+        let author_label = Label::builder()
+            .label("")
+            .width_chars(18)
+            .max_width_chars(18)
+            .xalign(0.0)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+
+        let line_no_label = Label::builder()
+            .label("")
+            .width_chars(6)
+            .xalign(1.0)
+            .css_classes(["dim-label"])
+            .build();
+
+        let content_label = Label::builder()
+            .label("")
+            .lines(1)
+            .single_line_mode(true)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(pango::EllipsizeMode::End)
+            .css_classes(["monospace"])
+            .build();
+
+        let bx = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .margin_top(0)
+            .margin_bottom(0)
+            .margin_start(2)
+            .margin_end(2)
+            .spacing(12)
+            .can_focus(true)
+            .focusable(true)
+            .build();
+
+        bx.append(&oid_label);
+        bx.append(&author_label);
+        bx.append(&line_no_label);
+        bx.append(&content_label);
+
+        let list_item = list_item
+            .downcast_ref::<ListItem>()
+            .expect("Needs to be ListItem");
+        list_item.set_child(Some(&bx));
+        list_item.set_selectable(true);
+        list_item.set_activatable(true);
+        list_item.set_focusable(true);
+
+        let item = list_item.property_expression("item");
+        item.chain_property::<BlameItem>("oid")
+            .bind(&oid_label, "label", Widget::NONE);
+        item.chain_property::<BlameItem>("author")
+            .bind(&author_label, "label", Widget::NONE);
+        item.chain_property::<BlameItem>("line_no")
+            .bind(&line_no_label, "label", Widget::NONE);
+        item.chain_property::<BlameItem>("content")
+            .bind(&content_label, "label", Widget::NONE);
+    });
+    factory.connect_bind(|_, list_item| {
+        let list_item = list_item
+            .downcast_ref::<ListItem>()
+            .expect("Needs to be ListItem");
+        let blame_item = list_item.item().unwrap();
+        let blame_item = blame_item.downcast_ref::<BlameItem>().unwrap();
+        let committed = blame_item
+            .imp()
+            .line
+            .borrow()
+            .as_ref()
+            .map(|l| l.oid.is_some())
+            .unwrap_or(false);
+        let bx = list_item.child().unwrap();
+        let oid_label = bx.first_child().unwrap();
+        // uncommitted lines are dimmed and are not clickable
+        if committed {
+            oid_label.remove_css_class("dim-label");
+            oid_label.set_cursor(gdk::Cursor::from_name("pointer", None).as_ref());
+        } else {
+            oid_label.add_css_class("dim-label");
+            oid_label.set_cursor(None);
+        }
+        list_item.set_activatable(committed);
+    });
+    factory
+}
+
+pub fn listview_factory(file_path: PathBuf, sender: Sender<crate::Event>) -> ListView {
+    let store = gio::ListStore::new::<BlameItem>();
+    let selection_model = SingleSelection::new(Some(store));
+
+    let factory = item_factory(file_path.clone(), sender.clone());
+    let mut classes = glib::collections::strv::StrV::new();
+    classes.extend_from_slice(if StyleManager::default().is_dark() {
+        &[DARK_CLASS]
+    } else {
+        &[LIGHT_CLASS]
+    });
+    let list_view = ListView::builder()
+        .model(&selection_model)
+        .factory(&factory)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .css_classes(classes)
+        .build();
+    list_view.connect_activate(move |list_view, pos| {
+        let selection_model = list_view.model().unwrap();
+        if let Some(item) = selection_model.item(pos) {
+            let blame_item = item.downcast_ref::<BlameItem>().unwrap();
+            blame_item.show_commit(file_path.clone(), &sender);
+        }
+    });
+    list_view
+}
+
+pub fn show_blame_window(
+    repo_path: PathBuf,
+    file_path: PathBuf,
+    app_window: CurrentWindow,
+    main_sender: Sender<crate::Event>,
+) -> Window {
+    let mut builder = Window::builder().default_width(1280).default_height(960);
+    match app_window {
+        CurrentWindow::Window(w) => {
+            builder = builder.transient_for(&w);
+        }
+        CurrentWindow::ApplicationWindow(w) => {
+            builder = builder.transient_for(&w);
+        }
+    }
+    let window = builder.build();
+    let list_view = listview_factory(file_path.clone(), main_sender.clone());
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_child(Some(&list_view));
+
+    let tb = ToolbarView::builder().content(&scroll).build();
+    let hb = HeaderBar::new();
+    let title = Label::builder()
+        .label(format!("Blame {}", file_path.display()))
+        .single_line_mode(true)
+        .build();
+    hb.set_title_widget(Some(&title));
+    tb.add_top_bar(&hb);
+    window.set_content(Some(&tb));
+
+    let event_controller = EventControllerKey::new();
+    event_controller.connect_key_pressed({
+        let window = window.clone();
+        let list_view = list_view.clone();
+        let file_path = file_path.clone();
+        let main_sender = main_sender.clone();
+        move |_, key, _, modifier| {
+            match (key, modifier) {
+                (gdk::Key::w, gdk::ModifierType::CONTROL_MASK) => {
+                    window.close();
+                }
+                (gdk::Key::v, _) => {
+                    // whole file as of commit of selected line
+                    let selection_model = list_view.model().unwrap();
+                    let selection_model =
+                        selection_model.downcast_ref::<SingleSelection>().unwrap();
+                    if let Some(item) = selection_model.selected_item() {
+                        let blame_item = item.downcast_ref::<BlameItem>().unwrap();
+                        blame_item.show_file(file_path.clone(), &main_sender);
+                    }
+                }
+                (gdk::Key::Escape, _) => {
+                    window.close();
+                }
+                (key, modifier) => {
+                    trace!("key pressed {:?} {:?}", key, modifier);
+                }
+            }
+            glib::Propagation::Proceed
+        }
+    });
+    window.add_controller(event_controller);
+    window.present();
+    list_view.grab_focus();
+
+    glib::spawn_future_local({
+        let window = window.clone();
+        let list_view = list_view.clone();
+        async move {
+            let lines = gio::spawn_blocking(move || blame_file(repo_path, file_path))
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(Vec::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    Vec::new()
+                });
+            let selection_model = list_view.model().unwrap();
+            let selection_model = selection_model.downcast_ref::<SingleSelection>().unwrap();
+            let store = selection_model.model().unwrap();
+            let store = store.downcast_ref::<gio::ListStore>().unwrap();
+            let items: Vec<BlameItem> = lines.into_iter().map(BlameItem::new).collect();
+            store.extend_from_slice(&items);
+        }
+    });
+    window
+}
@@ -4,8 +4,11 @@
 
 use async_channel::Sender;
 
-use crate::dialogs::{alert, confirm_dialog_factory, PROCEED};
-use crate::git::{branch, merge, rebase, remote};
+use crate::dialogs::{
+    alert, confirm_dialog_factory, ConfirmWithOptions, DangerDialog, PROCEED, YES,
+};
+use crate::git::{branch, commit, merge, rebase, remote, worktree};
+use crate::keymap;
 use crate::{DARK_CLASS, LIGHT_CLASS};
 use git2::BranchType;
 use glib::{closure, Object};
@@ -14,11 +17,12 @@ use gtk4::subclass::prelude::*;
 use gtk4::{
     gdk, gio, glib, pango, Align, Box, Button, EventControllerKey, Image, Label, ListBox,
     ListHeader, ListItem, ListView, Orientation, ScrolledWindow, SearchBar, SearchEntry,
-    SectionModel, SelectionMode, SignalListItemFactory, SingleSelection, Spinner, Widget,
+    SectionModel, SelectionMode, SignalListItemFactory, SingleSelection, Spinner, ToggleButton,
+    Widget,
 };
 use libadwaita::prelude::*;
 use libadwaita::{
-    ApplicationWindow, EntryRow, HeaderBar, StyleManager, SwitchRow, ToolbarView, Window,
+    ApplicationWindow, ComboRow, EntryRow, HeaderBar, StyleManager, SwitchRow, ToolbarView, Window,
 };
 
 use log::{info, trace};
@@ -51,6 +55,9 @@ mod branch_item {
         #[property(get = Self::get_branch_is_local)]
         pub is_local: RefCell<bool>,
 
+        #[property(get = Self::get_branch_is_merged)]
+        pub is_merged: RefCell<bool>,
+
         #[property(get, set)]
         pub title: RefCell<String>,
 
@@ -84,6 +91,10 @@ mod branch_item {
         pub fn get_branch_is_local(&self) -> bool {
             self.branch.borrow().branch_type == git2::BranchType::Local
         }
+
+        pub fn get_branch_is_merged(&self) -> bool {
+            self.branch.borrow().is_merged
+        }
     }
 }
 
@@ -132,7 +143,7 @@ mod branch_list {
     use gtk4::glib;
     use gtk4::prelude::*;
     use gtk4::subclass::prelude::*;
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     #[derive(Properties, Default)]
     #[properties(wrapper_type = super::BranchList)]
@@ -140,6 +151,12 @@ mod branch_list {
         pub original_list: RefCell<Vec<super::branch::BranchData>>,
         pub list: RefCell<Vec<super::BranchItem>>,
 
+        // branches come sorted by last commit date
+        pub sort_by_name: Cell<bool>,
+
+        // drop remote tracking branches deleted on remote during update
+        pub prune: Cell<bool>,
+
         #[property(get, set)]
         pub selected_pos: RefCell<u32>,
 
@@ -217,13 +234,44 @@ impl BranchList {
                 .original_list
                 .borrow()
                 .iter()
-                .filter(|bd| bd.name.to_str().contains(&term))
+                .filter(|bd| bd.matches(&term))
                 .map(|b| BranchItem::new(b, is_dark))
                 .collect(),
         );
         self.items_changed(0, 0, self.imp().list.borrow().len() as u32);
     }
 
+    pub fn sort(&self, by_name: bool) {
+        self.imp().sort_by_name.replace(by_name);
+        self.imp()
+            .original_list
+            .borrow_mut()
+            .sort_by(|a, b| branch::compare_branches(a, b, by_name));
+        if self.imp().list.borrow().is_empty() {
+            return;
+        }
+        let selected = self
+            .imp()
+            .list
+            .borrow()
+            .get(self.selected_pos() as usize)
+            .map(|bi| bi.imp().branch.borrow().name.clone());
+        // sort current items, so search results are kept
+        let mut items = self.imp().list.take();
+        let le = items.len() as u32;
+        items.sort_by(|a, b| {
+            branch::compare_branches(&a.imp().branch.borrow(), &b.imp().branch.borrow(), by_name)
+        });
+        let pos = items
+            .iter()
+            .position(|bi| Some(&bi.imp().branch.borrow().name) == selected.as_ref())
+            .unwrap_or(0);
+        items[pos].set_initial_focus(true);
+        self.imp().list.replace(items);
+        self.items_changed(0, le, le);
+        self.set_selected_pos(pos as u32);
+    }
+
     pub fn get_branches(
         &self,
         repo_path: PathBuf,
@@ -249,6 +297,10 @@ impl BranchList {
                 if branches.is_empty() {
                     return;
                 }
+                let mut branches = branches;
+                if branch_list.imp().sort_by_name.get() {
+                    branches.sort_by(|a, b| branch::compare_branches(a, b, true));
+                }
                 branch_list.imp().original_list.replace(branches);
                 let is_dark = StyleManager::default().is_dark();
                 branch_list.imp().list.replace(
@@ -266,6 +318,25 @@ impl BranchList {
     }
 
     pub fn checkout(&self, repo_path: PathBuf, window: &Window, sender: Sender<crate::Event>) {
+        self.checkout_inner(repo_path, window, sender, false);
+    }
+
+    pub fn checkout_and_pull(
+        &self,
+        repo_path: PathBuf,
+        window: &Window,
+        sender: Sender<crate::Event>,
+    ) {
+        self.checkout_inner(repo_path, window, sender, true);
+    }
+
+    fn checkout_inner(
+        &self,
+        repo_path: PathBuf,
+        window: &Window,
+        sender: Sender<crate::Event>,
+        need_pull: bool,
+    ) {
         glib::spawn_future_local({
             let branch_list = self.clone();
             let window = window.clone();
@@ -275,8 +346,28 @@ impl BranchList {
                 let selected_item = selected_item.downcast_ref::<BranchItem>().unwrap();
 
                 let branch_data = selected_item.imp().branch.borrow().clone();
-                let new_branch_data = gio::spawn_blocking(move || {
-                    branch::checkout_branch(repo_path, branch_data, sender)
+                if branch_data.branch_type == BranchType::Remote {
+                    let local_name = branch_data.local_name();
+                    let local_exists = branch_list.imp().original_list.borrow().iter().any(|bd| {
+                        bd.branch_type == BranchType::Local && bd.name.to_str() == local_name
+                    });
+                    if local_exists {
+                        let dialog = confirm_dialog_factory(
+                            Some(&Label::new(Some(&format!(
+                                "Local branch {} already exists. Switch to it?",
+                                local_name
+                            )))),
+                            "Checkout",
+                            "Switch",
+                        );
+                        if PROCEED != dialog.choose_future(&window).await {
+                            return;
+                        }
+                    }
+                }
+                let new_branch_data = gio::spawn_blocking({
+                    let sender = sender.clone();
+                    move || branch::checkout_branch(repo_path, branch_data, sender)
                 })
                 .await
                 .unwrap_or_else(|e| {
@@ -300,10 +391,28 @@ impl BranchList {
                     .iter()
                     .any(|b| b.name == new_branch_data.name)
                 {
-                    branch_list.update_head_branch(new_branch_data);
+                    branch_list.update_head_branch(new_branch_data.clone());
                 } else {
-                    branch_list.add_new_branch_item(new_branch_data, true);
+                    branch_list.add_new_branch_item(new_branch_data.clone(), true);
                 };
+                if !need_pull {
+                    return;
+                }
+                if new_branch_data.remote_name.is_none() {
+                    sender
+                        .send_blocking(crate::Event::Toast(format!(
+                            "Branch {} has no upstream. Nothing to pull",
+                            new_branch_data.name
+                        )))
+                        .expect("cant send through channel");
+                    return;
+                }
+                // pull goes through main window to show
+                // progress and conflicts if any
+                sender
+                    .send_blocking(crate::Event::Pull)
+                    .expect("cant send through channel");
+                window.close();
             }
         });
     }
@@ -373,7 +482,8 @@ impl BranchList {
             let branch_list = self.clone();
             let window = window.clone();
             async move {
-                gio::spawn_blocking(move || remote::update_remote(repo_path, sender))
+                let prune = branch_list.imp().prune.get();
+                gio::spawn_blocking(move || remote::update_remote(repo_path, prune, sender))
                     .await
                     .unwrap_or_else(|e| {
                         alert(format!("{:?}", e)).present(Some(&window));
@@ -426,6 +536,81 @@ impl BranchList {
         });
     }
 
+    /// Commit where head and selected branch diverged.
+    /// It could be opened as regular commit, or each side
+    /// could be diffed against it.
+    pub fn show_merge_base(
+        &self,
+        repo_path: PathBuf,
+        window: &Window,
+        sender: Sender<crate::Event>,
+    ) {
+        let current_branch = self.get_head_branch().expect("cant get current branch");
+        let selected_branch = self.get_selected_branch();
+        if selected_branch.is_head {
+            return;
+        }
+        glib::spawn_future_local({
+            let window = window.clone();
+            async move {
+                let (head_oid, oid) = (current_branch.oid, selected_branch.oid);
+                let result =
+                    gio::spawn_blocking(move || commit::merge_base(repo_path, head_oid, oid)).await;
+                let base = match result {
+                    Ok(Ok(base)) => base,
+                    // e.g. unrelated histories. message alone is clear
+                    Ok(Err(e)) => {
+                        alert(e.message().to_string()).present(Some(&window));
+                        return;
+                    }
+                    Err(e) => {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        return;
+                    }
+                };
+                let (head_name, name) =
+                    (current_branch.name.to_str(), selected_branch.name.to_str());
+                let head_changes = format!("Changes of {} since merge base", head_name);
+                let changes = format!("Changes of {} since merge base", name);
+                let choices =
+                    gtk4::StringList::new(&["Merge base commit", &head_changes, &changes]);
+                let choice = ComboRow::builder()
+                    .title("Show")
+                    .model(&choices)
+                    .selected(1)
+                    .build();
+                let list_box = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                list_box.append(&choice);
+                let response = alert(ConfirmWithOptions(
+                    String::from("Merge base"),
+                    format!(
+                        "{} and {} diverged at {}",
+                        head_name,
+                        name,
+                        &base.to_string()[..7]
+                    ),
+                    list_box.into(),
+                ))
+                .choose_future(&window)
+                .await;
+                if response != YES {
+                    return;
+                }
+                let event = match choice.selected() {
+                    0 => crate::Event::ShowOid(base, None, None),
+                    1 => crate::Event::ShowOidFromBase(head_oid, base),
+                    _ => crate::Event::ShowOidFromBase(oid, base),
+                };
+                sender
+                    .send_blocking(event)
+                    .expect("cant send through sender");
+            }
+        });
+    }
+
     pub fn merge(&self, repo_path: PathBuf, window: &Window, sender: Sender<crate::Event>) {
         let current_branch = self.get_head_branch().expect("cant get current branch");
         let selected_branch = self.get_selected_branch();
@@ -490,18 +675,85 @@ impl BranchList {
                     return;
                 }
                 let name = branch_data.name.clone();
-                let result = gio::spawn_blocking(move || {
-                    branch::kill_branch(repo_path, branch_data, sender)
-                })
-                .await
-                .unwrap_or_else(|e| {
-                    alert(format!("{:?}", e)).present(Some(&window));
-                    Ok(None)
-                })
-                .unwrap_or_else(|e| {
-                    alert(e).present(Some(&window));
-                    None
-                });
+                let result = if branch_data.branch_type == BranchType::Remote {
+                    let remote_name = branch_data.remote_name.clone().unwrap_or_default();
+                    let branch_name = branch_data.local_name();
+                    let response = alert(DangerDialog(
+                        String::from("Delete remote branch"),
+                        format!(
+                            "Branch refs/heads/{} will be deleted on remote {}.\nRemote tracking ref refs/remotes/{}/{} will be deleted too.",
+                            branch_name, remote_name, remote_name, branch_name
+                        ),
+                    ))
+                    .choose_future(&window)
+                    .await;
+                    if response != YES {
+                        return;
+                    }
+                    gio::spawn_blocking(move || {
+                        remote::delete_branch(repo_path, remote_name, branch_name, sender).map(Some)
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        Ok(None)
+                    })
+                    .unwrap_or_else(|e| {
+                        alert(e).present(Some(&window));
+                        None
+                    })
+                } else {
+                    let is_merged = gio::spawn_blocking({
+                        let repo_path = repo_path.clone();
+                        let branch_data = branch_data.clone();
+                        move || branch::is_merged(repo_path, &branch_data)
+                    })
+                    .await
+                    .unwrap_or_else(|e| {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        Ok(false)
+                    })
+                    .unwrap_or_else(|e| {
+                        alert(e).present(Some(&window));
+                        false
+                    });
+                    if is_merged {
+                        let dialog = confirm_dialog_factory(
+                            Some(&Label::new(Some(&format!(
+                                "Branch {} is fully merged",
+                                name.to_str()
+                            )))),
+                            "Delete branch",
+                            "Delete",
+                        );
+                        if PROCEED != dialog.choose_future(&window).await {
+                            return;
+                        }
+                    } else {
+                        let response = alert(DangerDialog(
+                            String::from("Force delete branch"),
+                            format!(
+                                "Branch {} is not fully merged. Commits which are only on this branch will be lost.",
+                                name.to_str()
+                            ),
+                        ))
+                        .choose_future(&window)
+                        .await;
+                        if response != YES {
+                            return;
+                        }
+                    }
+                    gio::spawn_blocking(move || branch::kill_branch(repo_path, branch_data, sender))
+                        .await
+                        .unwrap_or_else(|e| {
+                            alert(format!("{:?}", e)).present(Some(&window));
+                            Ok(None)
+                        })
+                        .unwrap_or_else(|e| {
+                            alert(e).present(Some(&window));
+                            None
+                        })
+                };
 
                 if result.is_none() {
                     return;
@@ -640,6 +892,301 @@ impl BranchList {
         });
     }
 
+    pub fn rename_branch(&self, repo_path: PathBuf, window: &Window, sender: Sender<crate::Event>) {
+        let selected_branch = self.get_selected_branch();
+        if selected_branch.branch_type != BranchType::Local {
+            return;
+        }
+        let title = format!("rename branch {}", selected_branch.name.to_str());
+
+        glib::spawn_future_local({
+            let branch_list = self.clone();
+            let branch_data = selected_branch.clone();
+            let window = window.clone();
+            async move {
+                let lb = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let input = EntryRow::builder()
+                    .title("New branch name:")
+                    .show_apply_button(false)
+                    .css_classes(vec!["input_field"])
+                    .build();
+                input.set_text(branch_data.name.to_str());
+                lb.append(&input);
+                let dialog = confirm_dialog_factory(Some(&lb), &title, "Rename");
+                dialog.connect_realize({
+                    let input = input.clone();
+                    move |_| {
+                        input.grab_focus();
+                    }
+                });
+
+                let enter_pressed = Rc::new(Cell::new(false));
+                input.connect_entry_activated({
+                    let enter_pressed = enter_pressed.clone();
+                    let dialog = dialog.clone();
+                    move |_entry| {
+                        // someone pressed enter
+                        enter_pressed.replace(true);
+                        dialog.close();
+                    }
+                });
+
+                let response = dialog.choose_future(&window).await;
+                if !(PROCEED == response || enter_pressed.get()) {
+                    return;
+                }
+                let new_branch_name = format!("{}", input.text());
+                if new_branch_name.is_empty() || new_branch_name == branch_data.name.to_str() {
+                    return;
+                }
+                if branch_list.imp().original_list.borrow().iter().any(|bd| {
+                    bd.branch_type == BranchType::Local && bd.name.to_str() == new_branch_name
+                }) {
+                    alert(format!("Branch {} already exists", new_branch_name))
+                        .present(Some(&window));
+                    return;
+                }
+                let old_name = branch_data.name.clone();
+                let was_head = branch_data.is_head;
+                let new_branch_data = gio::spawn_blocking(move || {
+                    branch::rename_branch(repo_path, branch_data, new_branch_name).map(Some)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(None)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    None
+                });
+                if let Some(new_branch_data) = new_branch_data {
+                    branch_list.replace_branch_item(&old_name, new_branch_data.clone());
+                    if was_head {
+                        branch_list.update_head_branch(new_branch_data);
+                        sender
+                            .send_blocking(crate::Event::Refresh)
+                            .expect("cant send through channel");
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn create_worktree(
+        &self,
+        repo_path: PathBuf,
+        window: &Window,
+        sender: Sender<crate::Event>,
+    ) {
+        let selected_branch = self.get_selected_branch();
+        if selected_branch.branch_type != BranchType::Local {
+            return;
+        }
+        if selected_branch.is_head {
+            alert(format!(
+                "Branch {} is already checked out",
+                selected_branch.name
+            ))
+            .present(Some(window));
+            return;
+        }
+        let branch_name = selected_branch.name.to_string();
+        let title = format!("Worktree for branch {}", branch_name);
+        let default_path = match worktree::default_path(&repo_path, &branch_name) {
+            Ok(default_path) => default_path,
+            Err(e) => {
+                alert(e).present(Some(window));
+                return;
+            }
+        };
+
+        glib::spawn_future_local({
+            let window = window.clone();
+            async move {
+                let lb = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let input = EntryRow::builder()
+                    .title("Worktree path:")
+                    .show_apply_button(false)
+                    .css_classes(vec!["input_field"])
+                    .build();
+                input.set_text(&default_path.to_string_lossy());
+                lb.append(&input);
+                let dialog = confirm_dialog_factory(Some(&lb), &title, "Create");
+                dialog.connect_realize({
+                    let input = input.clone();
+                    move |_| {
+                        input.grab_focus();
+                    }
+                });
+
+                let enter_pressed = Rc::new(Cell::new(false));
+                input.connect_entry_activated({
+                    let enter_pressed = enter_pressed.clone();
+                    let dialog = dialog.clone();
+                    move |_entry| {
+                        // someone pressed enter
+                        enter_pressed.replace(true);
+                        dialog.close();
+                    }
+                });
+
+                let response = dialog.choose_future(&window).await;
+                if !(PROCEED == response || enter_pressed.get()) {
+                    return;
+                }
+                let worktree_path = PathBuf::from(input.text().trim());
+                if worktree_path.as_os_str().is_empty() {
+                    return;
+                }
+                if worktree_path.exists() {
+                    alert(format!("{} already exists", worktree_path.display()))
+                        .present(Some(&window));
+                    return;
+                }
+                let created = gio::spawn_blocking(move || {
+                    worktree::add(repo_path, branch_name, worktree_path).map(Some)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(None)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    None
+                });
+                let Some(created) = created else {
+                    return;
+                };
+                let response = confirm_dialog_factory(
+                    None::<&Widget>,
+                    &format!("Open worktree {}?", created.path.display()),
+                    "Open",
+                )
+                .choose_future(&window)
+                .await;
+                if response == PROCEED {
+                    window.close();
+                    sender
+                        .send_blocking(crate::Event::OpenRepo(created.path))
+                        .expect("cant send through channel");
+                }
+            }
+        });
+    }
+
+    pub fn set_upstream(&self, repo_path: PathBuf, window: &Window, sender: Sender<crate::Event>) {
+        let selected_branch = self.get_selected_branch();
+        if selected_branch.branch_type != BranchType::Local {
+            return;
+        }
+        let title = format!("Upstream for branch {}", selected_branch.name.to_str());
+        let mut remote_names: Vec<String> = self
+            .imp()
+            .original_list
+            .borrow()
+            .iter()
+            .filter(|bd| bd.branch_type == BranchType::Remote)
+            .map(|bd| bd.name.to_string())
+            .collect();
+        remote_names.sort();
+        let no_upstream = String::from("No upstream");
+
+        glib::spawn_future_local({
+            let branch_list = self.clone();
+            let branch_data = selected_branch.clone();
+            let window = window.clone();
+            async move {
+                let current_upstream = gio::spawn_blocking({
+                    let repo_path = repo_path.clone();
+                    let branch_data = branch_data.clone();
+                    move || branch::get_upstream_name(repo_path, &branch_data)
+                })
+                .await
+                .unwrap_or(Ok(None))
+                .unwrap_or(None);
+                let mut names = vec![no_upstream.clone()];
+                names.extend(remote_names);
+                let selected = current_upstream
+                    .and_then(|upstream| names.iter().position(|name| *name == upstream))
+                    .unwrap_or(0);
+                let model =
+                    gtk4::StringList::new(&names.iter().map(|n| n.as_str()).collect::<Vec<&str>>());
+                let lb = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let combo = ComboRow::builder()
+                    .title("Track")
+                    .model(&model)
+                    .selected(selected as u32)
+                    .css_classes(vec!["input_field"])
+                    .build();
+                lb.append(&combo);
+                let dialog = confirm_dialog_factory(Some(&lb), &title, "Set");
+                let result = dialog.choose_future(&window).await;
+                if PROCEED != result {
+                    return;
+                }
+                let upstream_name = match combo.selected() {
+                    0 => None,
+                    pos => names.get(pos as usize).cloned(),
+                };
+                let new_branch_data = gio::spawn_blocking(move || {
+                    branch::set_upstream(repo_path, branch_data, upstream_name)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(None)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    None
+                });
+                if let Some(new_branch_data) = new_branch_data {
+                    let name = new_branch_data.name.clone();
+                    let is_head = new_branch_data.is_head;
+                    branch_list.replace_branch_item(&name, new_branch_data);
+                    if is_head {
+                        // headerbar sync indicator depends on upstream
+                        sender
+                            .send_blocking(crate::Event::Refresh)
+                            .expect("cant send through channel");
+                    }
+                }
+            }
+        });
+    }
+
+    fn replace_branch_item(&self, old_name: &branch::BranchName, branch_data: branch::BranchData) {
+        for bd in self.imp().original_list.borrow_mut().iter_mut() {
+            if &bd.name == old_name {
+                *bd = branch_data.clone();
+            }
+        }
+        let pos = self
+            .imp()
+            .list
+            .borrow()
+            .iter()
+            .position(|bi| &bi.imp().branch.borrow().name == old_name);
+        if let Some(pos) = pos {
+            let item = BranchItem::new(&branch_data, StyleManager::default().is_dark());
+            item.set_initial_focus(true);
+            self.imp().list.borrow_mut()[pos] = item;
+            self.items_changed(pos as u32, 1, 1);
+            self.set_selected_pos(pos as u32);
+        }
+    }
+
     fn add_new_branch_item(&self, branch_data: branch::BranchData, need_checkout: bool) {
         self.imp()
             .original_list
@@ -703,6 +1250,9 @@ pub fn item_factory() -> SignalListItemFactory {
         let image = Image::new();
         image.set_margin_top(4);
 
+        let merged_image = Image::new();
+        merged_image.set_margin_top(4);
+
         let label_title = Label::builder()
             .label("")
             .lines(1)
@@ -751,6 +1301,7 @@ pub fn item_factory() -> SignalListItemFactory {
             .focusable(true)
             .build();
         bx.append(&image);
+        bx.append(&merged_image);
         bx.append(&label_title);
         bx.append(&label_commit);
         bx.append(&label_dt);
@@ -810,6 +1361,25 @@ pub fn item_factory() -> SignalListItemFactory {
                 }
             }))
             .bind(&image, "icon-name", Widget::NONE);
+
+        item.chain_property::<BranchItem>("is-merged")
+            .chain_closure::<String>(closure!(|_: Option<Object>, is_merged: bool| {
+                if is_merged {
+                    String::from("emblem-ok-symbolic")
+                } else {
+                    String::from("")
+                }
+            }))
+            .bind(&merged_image, "icon-name", Widget::NONE);
+        item.chain_property::<BranchItem>("is-merged")
+            .chain_closure::<String>(closure!(|_: Option<Object>, is_merged: bool| {
+                if is_merged {
+                    String::from("fully merged")
+                } else {
+                    String::from("")
+                }
+            }))
+            .bind(&merged_image, "tooltip-text", Widget::NONE);
         item.chain_property::<BranchItem>("title")
             .bind(&label_title, "label", Widget::NONE);
 
@@ -1052,7 +1622,35 @@ pub fn headerbar_factory(
         .transform_to(set_sensitive)
         .build();
 
+    let sort_btn = ToggleButton::builder()
+        .icon_name("view-sort-ascending-symbolic")
+        .tooltip_text("Sort by name")
+        .can_focus(false)
+        .active(branch_list.imp().sort_by_name.get())
+        .build();
+    sort_btn.connect_toggled({
+        let branch_list = branch_list.clone();
+        move |btn| {
+            branch_list.sort(btn.is_active());
+        }
+    });
+
+    let prune_btn = ToggleButton::builder()
+        .icon_name("edit-clear-all-symbolic")
+        .tooltip_text("Always prune deleted remote branches on update, even if git config says no")
+        .can_focus(false)
+        .active(branch_list.imp().prune.get())
+        .build();
+    prune_btn.connect_toggled({
+        let branch_list = branch_list.clone();
+        move |btn| {
+            branch_list.imp().prune.set(btn.is_active());
+        }
+    });
+
     hb.set_title_widget(Some(&search));
+    hb.pack_start(&sort_btn);
+    hb.pack_start(&prune_btn);
     hb.pack_end(&new_btn);
     hb.pack_end(&merge_btn);
     hb.pack_end(&rebase_btn);
@@ -1156,31 +1754,35 @@ pub fn show_branches_window(
         let repo_path = repo_path.clone();
         let sender = sender.clone();
 
-        move |_, key, _, modifier| {
-            match (key, modifier) {
-                (gdk::Key::w, gdk::ModifierType::CONTROL_MASK) => {
-                    window.close();
-                }
-                (gdk::Key::Escape, _) => {
+        move |controller, key, _, modifier| {
+            match keymap::action(controller, "branches") {
+                // closing is the same in all windows and is not remapped
+                None if key == gdk::Key::Escape
+                    || (key == gdk::Key::w && modifier == gdk::ModifierType::CONTROL_MASK) =>
+                {
                     window.close();
                 }
-                (gdk::Key::n | gdk::Key::c, _) => {
+                Some("create") => {
                     let branch_list = get_branch_list(&list_view);
                     branch_list.create_branch(repo_path.clone(), &window, sender.clone());
                 }
-                (gdk::Key::k, _) => {
+                Some("kill") => {
                     let branch_list = get_branch_list(&list_view);
                     branch_list.kill_branch(repo_path.clone(), &window, sender.clone());
                 }
-                (gdk::Key::m, _) => {
+                Some("merge") => {
                     let branch_list = get_branch_list(&list_view);
                     branch_list.merge(repo_path.clone(), &window, sender.clone())
                 }
-                (gdk::Key::r, _) => {
+                Some("rebase") => {
                     let branch_list = get_branch_list(&list_view);
                     branch_list.rebase(repo_path.clone(), &window, sender.clone())
                 }
-                (gdk::Key::l, _) => {
+                Some("merge_base") => {
+                    let branch_list = get_branch_list(&list_view);
+                    branch_list.show_merge_base(repo_path.clone(), &window, sender.clone())
+                }
+                Some("log") => {
                     let branch_list = get_branch_list(&list_view);
                     let selected_branch = branch_list.get_selected_branch();
                     let oid = selected_branch.oid;
@@ -1191,7 +1793,7 @@ pub fn show_branches_window(
                         ))
                         .expect("cant send through sender");
                 }
-                (gdk::Key::a, _) => {
+                Some("cherry_pick") => {
                     let branch_list = get_branch_list(&list_view);
                     let selected_branch = branch_list.get_selected_branch();
                     let oid = selected_branch.oid;
@@ -1201,11 +1803,41 @@ pub fn show_branches_window(
                         )))
                         .expect("cant send through sender");
                 }
-                (gdk::Key::u, _) => {
+                Some("pull") => {
+                    let branch_list = get_branch_list(&list_view);
+                    branch_list.checkout_and_pull(repo_path.clone(), &window, sender.clone());
+                }
+                Some("rename") => {
+                    let branch_list = get_branch_list(&list_view);
+                    branch_list.rename_branch(repo_path.clone(), &window, sender.clone());
+                }
+                Some("upstream") => {
+                    let branch_list = get_branch_list(&list_view);
+                    branch_list.set_upstream(repo_path.clone(), &window, sender.clone());
+                }
+                Some("worktree") => {
+                    let branch_list = get_branch_list(&list_view);
+                    branch_list.create_worktree(repo_path.clone(), &window, sender.clone());
+                }
+                Some("copy") => {
+                    let branch_list = get_branch_list(&list_view);
+                    let selected_branch = branch_list.get_selected_branch();
+                    window
+                        .display()
+                        .clipboard()
+                        .set_text(selected_branch.name.to_str());
+                    sender
+                        .send_blocking(crate::Event::Toast(format!(
+                            "Copied {}",
+                            selected_branch.name
+                        )))
+                        .expect("cant send through sender");
+                }
+                Some("update_remote") => {
                     let branch_list = get_branch_list(&list_view);
                     branch_list.update_remote(repo_path.clone(), &window, sender.clone());
                 }
-                (gdk::Key::s, _) => {
+                Some("search") => {
                     let search_bar = hb.title_widget().unwrap();
                     let search_bar = search_bar.downcast_ref::<SearchBar>().unwrap();
                     let search_entry = search_bar.child().unwrap();
@@ -1213,8 +1845,8 @@ pub fn show_branches_window(
                     trace!("enter search");
                     search_entry.grab_focus();
                 }
-                (key, modifier) => {
-                    trace!("key pressed {:?} {:?}", key, modifier);
+                action => {
+                    trace!("key pressed {:?} {:?} {:?}", action, key, modifier);
                 }
             }
             glib::Propagation::Proceed
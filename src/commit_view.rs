@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::dialogs::alert;
-use crate::git::{blame, commit, stash::StashNum};
+use crate::git::{blame, commit, remote, stash::StashNum};
 use crate::status_view::context::StatusRenderContext;
 use crate::status_view::{
     render::ViewContainer, stage_view::StageView, view::View, CursorPosition,
@@ -11,33 +11,42 @@ use crate::status_view::{
 };
 use crate::{ApplyOp, BlameLine, CurrentWindow, Event, HunkLineNo, StageOp};
 use async_channel::Sender;
-use git2::Oid;
+use git2::{Delta, Oid};
 
 use gtk4::prelude::*;
 use gtk4::{
-    gdk, gio, glib, Button, EventControllerKey, Label, ScrolledWindow, TextBuffer, TextIter,
+    gdk, gio, glib, Box, Button, EventControllerKey, GestureClick, Label, Orientation,
+    ScrolledWindow, TextBuffer, TextIter,
 };
 use libadwaita::prelude::*;
 use libadwaita::{HeaderBar, ToolbarView, Window};
 use log::{debug, info, trace};
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 pub fn headerbar_factory(
     sender: Sender<Event>,
     oid: Oid,
     stash_num: Option<StashNum>,
+    base: Option<Oid>,
 ) -> HeaderBar {
     let hb = HeaderBar::builder().build();
     let (btn_tooltip, title) = if stash_num.is_some() {
-        ("Apply stash", "Stash")
+        ("Apply stash", String::from("Stash"))
+    } else if let Some(base) = base {
+        ("", format!("Changes since {}", &base.to_string()[..7]))
     } else {
-        ("Cherry pick", "Commit")
+        ("Cherry pick", String::from("Commit"))
     };
 
     let lbl = Label::builder().label(title).single_line_mode(true).build();
 
     hb.set_title_widget(Some(&lbl));
+    // changes of several commits could not be applied at once
+    if base.is_some() {
+        return hb;
+    }
 
     let cherry_pick_btn = Button::builder()
         .icon_name("emblem-shared-symbolic")
@@ -83,6 +92,70 @@ pub fn headerbar_factory(
     hb
 }
 
+pub fn copy_oid(oid: Oid, widget: &impl IsA<gtk4::Widget>, sender: Sender<Event>) {
+    widget.display().clipboard().set_text(&oid.to_string());
+    sender
+        .send_blocking(Event::Toast(format!("Copied {}", oid)))
+        .expect("cant send through channel");
+}
+
+// unknown hosting gets raw sha
+pub fn copy_commit_url(
+    repo_path: PathBuf,
+    oid: Oid,
+    widget: &impl IsA<gtk4::Widget>,
+    sender: Sender<Event>,
+) {
+    glib::spawn_future_local({
+        let widget = widget.clone();
+        async move {
+            let url = gio::spawn_blocking(move || remote::commit_url(repo_path, oid))
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&widget));
+                    Ok(None)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&widget));
+                    None
+                });
+            let (text, message) = match url {
+                Some(url) => (url.clone(), format!("Copied {}", url)),
+                None => (
+                    oid.to_string(),
+                    format!("No web url is known for remote. Copied {}", oid),
+                ),
+            };
+            widget.display().clipboard().set_text(&text);
+            sender
+                .send_blocking(Event::Toast(message))
+                .expect("cant send through channel");
+        }
+    });
+}
+
+// click opens parent in its own commit window
+fn parent_label(parent: Oid, title: &str, sender: Sender<Event>) -> Label {
+    let label = Label::builder()
+        .label(format!(
+            "{}: <span color=\"#1C71D8\">{}</span>",
+            title,
+            &parent.to_string()[..7]
+        ))
+        .use_markup(true)
+        .tooltip_text(parent.to_string())
+        .cursor(&gdk::Cursor::from_name("pointer", None).unwrap())
+        .build();
+    let gesture_controller = GestureClick::new();
+    gesture_controller.connect_released(move |_gesture, _some, _wx, _wy| {
+        sender
+            .send_blocking(Event::ShowOid(parent, None, None))
+            .expect("cant send through sender");
+    });
+    label.add_controller(gesture_controller);
+    label
+}
+
 #[derive(Debug, Clone)]
 pub struct MultiLineLabel {
     pub labels: Vec<TextViewLabel>,
@@ -256,14 +329,29 @@ impl commit::CommitDiff {
     }
 }
 
+/// How commit window shows commit: as stash, from blame line,
+/// as changes since base. And colors for its diff.
+#[derive(Default)]
+pub struct CommitWindowOptions {
+    pub stash_num: Option<StashNum>,
+    pub blame_line: Option<BlameLine>,
+    pub base: Option<Oid>,
+    pub diff_colors: HashMap<String, String>,
+}
+
 pub fn show_commit_window(
     repo_path: PathBuf,
     oid: Oid,
-    stash_num: Option<StashNum>,
-    blame_line: Option<BlameLine>,
+    options: CommitWindowOptions,
     app_window: CurrentWindow,
     main_sender: Sender<Event>, // i need that to trigger revert and cherry-pick.
 ) -> Window {
+    let CommitWindowOptions {
+        stash_num,
+        blame_line,
+        base,
+        diff_colors,
+    } = options;
     let (sender, receiver) = async_channel::unbounded();
 
     let mut diff: Option<commit::CommitDiff> = None;
@@ -284,25 +372,60 @@ pub fn show_commit_window(
     let window = builder.build();
     let scroll = ScrolledWindow::new();
 
-    let hb = headerbar_factory(main_sender.clone(), oid, stash_num);
+    let hb = headerbar_factory(main_sender.clone(), oid, stash_num, base);
 
-    let txt = crate::stage_factory(sender.clone(), "commit_view");
+    let txt = crate::stage_factory(sender.clone(), "commit_view", &diff_colors);
 
     scroll.set_child(Some(&txt));
 
     let tb = ToolbarView::builder().content(&scroll).build();
     tb.add_top_bar(&hb);
 
+    // filled when commit is loaded
+    let parents_box = Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(12)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .visible(false)
+        .build();
+    tb.add_top_bar(&parents_box);
+
+    // which branches and tags contain commit. computed
+    // only once, when window is opened
+    let containment = Label::builder()
+        .use_markup(true)
+        .wrap(true)
+        .xalign(0.0)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .css_classes(["dim-label"])
+        .visible(false)
+        .build();
+    tb.add_bottom_bar(&containment);
+
     window.set_content(Some(&tb));
 
     let event_controller = EventControllerKey::new();
     event_controller.connect_key_pressed({
         let window = window.clone();
+        let main_sender = main_sender.clone();
+        let repo_path = repo_path.clone();
         move |_, key, _, modifier| {
             match (key, modifier) {
                 (gdk::Key::w, gdk::ModifierType::CONTROL_MASK) | (gdk::Key::Escape, _) => {
                     window.close();
                 }
+                (gdk::Key::y, _) => {
+                    copy_oid(oid, &window, main_sender.clone());
+                }
+                (gdk::Key::Y, gdk::ModifierType::SHIFT_MASK) => {
+                    copy_commit_url(repo_path.clone(), oid, &window, main_sender.clone());
+                }
                 _ => {}
             }
             glib::Propagation::Proceed
@@ -322,26 +445,57 @@ pub fn show_commit_window(
         let sender = sender.clone();
         let path = path.clone();
         async move {
-            let diff = gio::spawn_blocking(move || commit::get_commit_diff(path.clone(), oid))
-                .await
-                .unwrap_or_else(|e| {
-                    alert(format!("{:?}", e)).present(Some(&window));
-                    Ok(commit::CommitDiff::default())
-                })
-                .unwrap_or_else(|e| {
-                    alert(e).present(Some(&window));
-                    commit::CommitDiff::default()
-                });
+            let diff = gio::spawn_blocking(move || match base {
+                Some(base) => commit::get_diff_from_base(path, base, oid),
+                None => commit::get_commit_diff(path, oid),
+            })
+            .await
+            .unwrap_or_else(|e| {
+                alert(format!("{:?}", e)).present(Some(&window));
+                Ok(commit::CommitDiff::default())
+            })
+            .unwrap_or_else(|e| {
+                alert(e).present(Some(&window));
+                commit::CommitDiff::default()
+            });
             sender
                 .send_blocking(Event::CommitDiff(diff))
                 .expect("Could not send through channel");
         }
     });
 
-    let mut labels: [TextViewLabel; 3] = [
+    if stash_num.is_none() && base.is_none() {
+        glib::spawn_future_local({
+            let path = path.clone();
+            async move {
+                let result =
+                    gio::spawn_blocking(move || commit::describe_containment(path, oid)).await;
+                // just an info. no need to alert
+                let Ok(Ok((branches, tags))) = result else {
+                    return;
+                };
+                let describe = |names: Vec<String>| {
+                    if names.is_empty() {
+                        String::from("none")
+                    } else {
+                        html_escape::encode_text(&names.join(", ")).to_string()
+                    }
+                };
+                containment.set_label(&format!(
+                    "Branches: {}\nTags: {}",
+                    describe(branches),
+                    describe(tags)
+                ));
+                containment.set_visible(true);
+            }
+        });
+    }
+
+    let mut labels: [TextViewLabel; 4] = [
         TextViewLabel::from_string(&format!("commit: <span color=\"#4a708b\">{:?}</span>", oid)),
         TextViewLabel::from_string(""),
         TextViewLabel::from_string(""),
+        TextViewLabel::from_string(""),
     ];
 
     glib::spawn_future_local({
@@ -352,6 +506,27 @@ pub fn show_commit_window(
                 match event {
                     Event::CommitDiff(mut commit_diff) => {
                         info!("CommitDiff");
+                        // diff is made against base, not parents
+                        if let Some(base) = base {
+                            parents_box.append(&parent_label(base, "base", main_sender.clone()));
+                            parents_box.set_visible(true);
+                        // stash parents are its internals, not history
+                        } else if stash_num.is_none() && !commit_diff.parents.is_empty() {
+                            let numbered = commit_diff.parents.len() > 1;
+                            for (i, parent) in commit_diff.parents.iter().enumerate() {
+                                let title = if numbered {
+                                    format!("parent {}", i + 1)
+                                } else {
+                                    String::from("parent")
+                                };
+                                parents_box.append(&parent_label(
+                                    *parent,
+                                    &title,
+                                    main_sender.clone(),
+                                ));
+                            }
+                            parents_box.set_visible(true);
+                        }
 
                         labels[1].content = format!(
                             "Author: <span color=\"#4a708b\">{}</span>",
@@ -361,6 +536,10 @@ pub fn show_commit_window(
                             "Date: <span color=\"#4a708b\">{}</span>",
                             commit_diff.commit_dt
                         );
+                        labels[3].content = format!(
+                            "Changes: <span color=\"#4a708b\">{}</span>",
+                            commit_diff.diff.stats()
+                        );
                         body_label.replace(MultiLineLabel::new(
                             &commit_diff.message,
                             txt.calc_max_char_width(MAX_WIDTH),
@@ -411,7 +590,7 @@ pub fn show_commit_window(
                             debug!("Tag: {}", tag.name().unwrap());
                         }
                     }
-                    Event::Stage(op) if op != StageOp::Kill => {
+                    Event::Stage(op) if op != StageOp::Kill && base.is_none() => {
                         info!(
                             "Stage/Unstage or r pressed {:?} cursor position {:?}",
                             op, cursor_position
@@ -506,6 +685,30 @@ pub fn show_commit_window(
                             });
                         }
                     }
+                    Event::ShowFile => {
+                        let file_idx = match cursor_position {
+                            CursorPosition::CursorFile(_, file_idx)
+                            | CursorPosition::CursorHunk(_, file_idx, _)
+                            | CursorPosition::CursorLine(_, file_idx, _, _) => Some(file_idx),
+                            _ => None,
+                        };
+                        if let (Some(diff), Some(file_idx)) = (&diff, file_idx) {
+                            let file = &diff.diff.files[file_idx];
+                            // deleted file is shown as it was before
+                            // deletion, so it could be restored
+                            let before = base.or(diff.parents.first().copied());
+                            let file_oid = match (file.status, before) {
+                                (Delta::Deleted, Some(before)) => before,
+                                _ => oid,
+                            };
+                            main_sender
+                                .send_blocking(crate::Event::ShowFileAt(
+                                    file_oid,
+                                    file.path.clone(),
+                                ))
+                                .expect("cant send through channel");
+                        }
+                    }
                     _ => {
                         trace!("unhandled event in commit_view {:?}", event);
                     }
@@ -181,6 +181,27 @@ impl AlertConversation for ConfirmWithOptions {
     }
 }
 
+#[derive(Clone)]
+pub struct DangerWithOptions(pub String, pub String, pub Widget);
+
+impl AlertConversation for DangerWithOptions {
+    fn heading_and_message(&self) -> (String, String) {
+        (
+            format!("<span color=\"#ff0000\">{}</span>", self.0),
+            self.1.to_string(),
+        )
+    }
+    fn get_response(&self) -> Vec<(&str, &str, ResponseAppearance)> {
+        vec![
+            (NO, NO, ResponseAppearance::Default),
+            (YES, YES, ResponseAppearance::Destructive),
+        ]
+    }
+    fn extra_child(&mut self) -> Option<impl IsA<Widget>> {
+        Some(self.2.clone())
+    }
+}
+
 pub fn alert<AC>(mut conversation: AC) -> AlertDialog
 where
     AC: AlertConversation,
@@ -2,11 +2,15 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::git::merge;
+use async_channel::Sender;
 use gtk4::prelude::*;
 use gtk4::{gio, glib};
 use log::info;
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 pub fn open_at_line_via_dbus(executable: PathBuf, path: PathBuf, line_no: i32, col_no: i32) {
     let proxy = gio::DBusProxy::for_bus_sync(
@@ -95,3 +99,116 @@ pub fn try_open_editor(path: PathBuf, line_no: i32, col_no: i32) {
         app_info.launch(&[file], opts).expect("cant launch app");
     }
 }
+
+// commands for well known tools, when mergetool.<tool>.cmd is not set
+fn default_mergetool_cmd(tool: &str) -> Option<String> {
+    match tool {
+        "meld" => Some(String::from(
+            "meld \"$LOCAL\" \"$BASE\" \"$REMOTE\" --output=\"$MERGED\"",
+        )),
+        "kdiff3" => Some(String::from(
+            "kdiff3 \"$BASE\" \"$LOCAL\" \"$REMOTE\" -o \"$MERGED\"",
+        )),
+        "vimdiff" | "nvimdiff" | "gvimdiff" => Some(format!(
+            "{} -d \"$LOCAL\" \"$BASE\" \"$REMOTE\" \"$MERGED\"",
+            tool.trim_end_matches("diff")
+        )),
+        _ => None,
+    }
+}
+
+// file.rs -> file_LOCAL.rs, like git mergetool does
+fn stage_file_path(dir: &Path, file_path: &Path, stage: &str) -> PathBuf {
+    let stem = file_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match file_path.extension() {
+        Some(ext) => format!(
+            "{}_{}_{}.{}",
+            stem,
+            stage,
+            std::process::id(),
+            ext.to_string_lossy()
+        ),
+        None => format!("{}_{}_{}", stem, stage, std::process::id()),
+    };
+    dir.join(name)
+}
+
+/// Runs merge tool configured in git (merge.tool) for conflicted file
+/// and waits for it. BASE/LOCAL/REMOTE are written from index stages
+/// to temp files. Conflict state is rechecked after tool exits.
+pub fn try_open_mergetool(
+    path: PathBuf,
+    file_path: PathBuf,
+    sender: Sender<crate::Event>,
+) -> Result<(), String> {
+    let repo = git2::Repository::open(path.clone()).map_err(|e| e.message().to_string())?;
+    let config = repo.config().map_err(|e| e.message().to_string())?;
+    let tool = config.get_string("merge.tool").map_err(|_| {
+        String::from(
+            "No merge tool is configured.\n\nSet it with\ngit config --global merge.tool meld",
+        )
+    })?;
+    let cmd = config
+        .get_string(&format!("mergetool.{}.cmd", tool))
+        .ok()
+        .or_else(|| default_mergetool_cmd(&tool))
+        .ok_or_else(|| {
+            format!(
+                "Command for merge tool {} is unknown.\n\nSet it with\ngit config --global mergetool.{}.cmd",
+                tool, tool
+            )
+        })?;
+    let index = repo.index().map_err(|e| e.message().to_string())?;
+    let conflict = index
+        .conflicts()
+        .map_err(|e| e.message().to_string())?
+        .flatten()
+        .find(|conflict| {
+            [&conflict.our, &conflict.their, &conflict.ancestor]
+                .into_iter()
+                .flatten()
+                .any(|entry| Path::new(&*String::from_utf8_lossy(&entry.path)) == file_path)
+        })
+        .ok_or_else(|| format!("{:?} is not conflicted", file_path))?;
+
+    let temp_dir = std::env::temp_dir();
+    let mut stage_files = Vec::new();
+    for (stage, entry) in [
+        ("BASE", &conflict.ancestor),
+        ("LOCAL", &conflict.our),
+        ("REMOTE", &conflict.their),
+    ] {
+        let stage_path = stage_file_path(&temp_dir, &file_path, stage);
+        let content = match entry {
+            Some(entry) => repo
+                .find_blob(entry.id)
+                .map_err(|e| e.message().to_string())?
+                .content()
+                .to_vec(),
+            None => Vec::new(),
+        };
+        fs::write(&stage_path, content).map_err(|e| e.to_string())?;
+        stage_files.push((stage, stage_path));
+    }
+    let workdir = repo.workdir().ok_or("no workdir")?;
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&cmd)
+        .current_dir(workdir)
+        .env("MERGED", workdir.join(&file_path));
+    for (stage, stage_path) in &stage_files {
+        command.env(stage, stage_path);
+    }
+    info!("running merge tool {:?} {:?}", tool, cmd);
+    let status = command.status();
+    for (_, stage_path) in &stage_files {
+        let _ = fs::remove_file(stage_path);
+    }
+    let status = status.map_err(|e| format!("can't run {}: {}", tool, e))?;
+    info!("merge tool exited {:?}", status);
+    merge::try_finalize_conflict(path, sender, Some(file_path)).map_err(|e| e.to_string())
+}
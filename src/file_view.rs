@@ -0,0 +1,236 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Read-only view of file as of some commit, to look at
+//! past version without checking it out. That version could
+//! be restored into workdir, e.g. when file was deleted.
+use crate::dialogs::{alert, confirm_dialog_factory, PROCEED};
+use crate::git::commit;
+use crate::status_view::tags;
+use crate::syntax;
+use crate::CurrentWindow;
+use async_channel::Sender;
+use git2::Oid;
+use gtk4::prelude::*;
+use gtk4::{gdk, gio, glib, EventControllerKey, Label, ScrolledWindow, Widget};
+use libadwaita::prelude::*;
+use libadwaita::{HeaderBar, ToolbarView, Window};
+use log::{info, trace};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// syntax ranges are in bytes, buffer wants chars. ranges
+// go in document order, so content is walked only once
+fn char_ranges(content: &str, ranges: &[(usize, usize)]) -> Vec<(i32, i32)> {
+    let mut byte = 0;
+    let mut chars = 0;
+    ranges
+        .iter()
+        .filter_map(|(from, to)| {
+            if *from < byte {
+                byte = 0;
+                chars = 0;
+            }
+            chars += content.get(byte..*from)?.chars().count();
+            byte = *from;
+            let len = content.get(*from..*to)?.chars().count();
+            Some((chars as i32, (chars + len) as i32))
+        })
+        .collect()
+}
+
+// workdir file with other content is overwritten only
+// after confirmation
+fn restore(
+    repo_path: PathBuf,
+    oid: Oid,
+    file_path: PathBuf,
+    window: &Window,
+    main_sender: Sender<crate::Event>,
+) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let overwrites = gio::spawn_blocking({
+                let repo_path = repo_path.clone();
+                let file_path = file_path.clone();
+                move || commit::restore_overwrites(repo_path, oid, file_path)
+            })
+            .await
+            .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+            match overwrites {
+                Ok(true) => {
+                    let dialog = confirm_dialog_factory(
+                        None::<&Widget>,
+                        &format!(
+                            "{} has other content in working tree. Overwrite and stage it?",
+                            file_path.display()
+                        ),
+                        "Overwrite",
+                    );
+                    if dialog.choose_future(&window).await != PROCEED {
+                        return;
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    alert(e).present(Some(&window));
+                    return;
+                }
+            }
+            let restored = gio::spawn_blocking({
+                let file_path = file_path.clone();
+                let sender = main_sender.clone();
+                move || commit::restore_file(repo_path, oid, file_path, sender)
+            })
+            .await
+            .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+            match restored {
+                Ok(()) => {
+                    info!("restored {:?}", file_path);
+                    main_sender
+                        .send_blocking(crate::Event::Toast(format!(
+                            "Restored and staged {}",
+                            file_path.display()
+                        )))
+                        .expect("cant send through channel");
+                }
+                Err(e) => alert(e).present(Some(&window)),
+            }
+        }
+    });
+}
+
+pub fn show_file_window(
+    repo_path: PathBuf,
+    oid: Oid,
+    file_path: PathBuf,
+    app_window: CurrentWindow,
+    diff_colors: &HashMap<String, String>,
+    main_sender: Sender<crate::Event>,
+) -> Window {
+    let mut builder = Window::builder().default_width(1280).default_height(960);
+    match app_window {
+        CurrentWindow::Window(w) => {
+            builder = builder.transient_for(&w);
+        }
+        CurrentWindow::ApplicationWindow(w) => {
+            builder = builder.transient_for(&w);
+        }
+    }
+    let window = builder.build();
+
+    // stage view brings colors of syntax tags. its events
+    // are of no interest here
+    let (sender, receiver) = async_channel::unbounded();
+    let txt = crate::stage_factory(sender, "file_view", diff_colors);
+    glib::spawn_future_local(async move {
+        while let Ok(event) = receiver.recv().await {
+            trace!("unhandled event in file_view {:?}", event);
+        }
+    });
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_child(Some(&txt));
+
+    let tb = ToolbarView::builder().content(&scroll).build();
+    let hb = HeaderBar::new();
+    let title = Label::builder()
+        .label(format!(
+            "{} at {}",
+            file_path.display(),
+            &oid.to_string()[..7]
+        ))
+        .single_line_mode(true)
+        .build();
+    hb.set_title_widget(Some(&title));
+    tb.add_top_bar(&hb);
+    window.set_content(Some(&tb));
+
+    let event_controller = EventControllerKey::new();
+    event_controller.connect_key_pressed({
+        let window = window.clone();
+        let repo_path = repo_path.clone();
+        let file_path = file_path.clone();
+        move |_, key, _, modifier| {
+            match (key, modifier) {
+                (gdk::Key::w, gdk::ModifierType::CONTROL_MASK) | (gdk::Key::Escape, _) => {
+                    window.close();
+                }
+                (gdk::Key::r, _) => {
+                    restore(
+                        repo_path.clone(),
+                        oid,
+                        file_path.clone(),
+                        &window,
+                        main_sender.clone(),
+                    );
+                }
+                (key, modifier) => {
+                    trace!("key pressed {:?} {:?}", key, modifier);
+                }
+            }
+            glib::Propagation::Proceed
+        }
+    });
+    window.add_controller(event_controller);
+    window.present();
+    txt.grab_focus();
+
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let loaded = gio::spawn_blocking(move || {
+                let Some(content) = commit::file_at(repo_path, oid, file_path.clone())? else {
+                    return Ok((
+                        format!("{} is binary. Press r to restore it", file_path.display()),
+                        Vec::new(),
+                        Vec::new(),
+                    ));
+                };
+                let (keywords, identifiers) = syntax::file_ranges(&file_path, &content);
+                let keywords = char_ranges(&content, &keywords);
+                let identifiers = char_ranges(&content, &identifiers);
+                Ok::<_, git2::Error>((content, keywords, identifiers))
+            })
+            .await
+            .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+            let (content, keywords, identifiers) = match loaded {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    alert(e).present(Some(&window));
+                    return;
+                }
+            };
+            let buffer = txt.buffer();
+            buffer.set_text(&content);
+            for (tag, ranges) in [(tags::SYNTAX, keywords), (tags::SYNTAX_1, identifiers)] {
+                for (from, to) in ranges {
+                    buffer.apply_tag_by_name(
+                        tag,
+                        &buffer.iter_at_offset(from),
+                        &buffer.iter_at_offset(to),
+                    );
+                }
+            }
+            buffer.place_cursor(&buffer.start_iter());
+        }
+    });
+    window
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_char_ranges() {
+        let content = "let ё = 1;\nlet x";
+        // "let" twice and "ё" which is 2 bytes
+        let ranges = vec![(0, 3), (4, 6), (12, 15)];
+        assert_eq!(
+            char_ranges(content, &ranges),
+            vec![(0, 3), (4, 5), (11, 14)]
+        );
+    }
+}
@@ -2,15 +2,25 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod bisect;
 pub mod branch;
 pub mod commit;
 pub mod conflict;
+pub mod credentials;
+pub mod format_patch;
 pub mod git_log;
+pub mod hooks;
+pub mod json;
 pub mod merge;
+pub mod patch;
+pub mod porcelain;
 pub mod remote;
+pub mod repo_settings;
 pub mod stash;
 pub mod tag;
 pub mod test_conflict;
+pub mod undo;
+pub mod worktree;
 use crate::branch::BranchData;
 use crate::commit::CommitRepr;
 use crate::gio;
@@ -24,20 +34,21 @@ use chrono::{DateTime, FixedOffset};
 use git2::build::CheckoutBuilder;
 use git2::{
     ApplyLocation, ApplyOptions, Branch, Commit, Delta, Diff as GitDiff, DiffDelta, DiffFile,
-    DiffFormat, DiffHunk, DiffLine, DiffLineType, DiffOptions, Error, ObjectType, Oid,
-    RebaseOptions, Repository, RepositoryState, ResetType, StatusOptions,
+    DiffFindOptions, DiffFormat, DiffHunk, DiffLine, DiffLineType, DiffOptions, Error, ObjectType,
+    Oid, RebaseOptions, Repository, RepositoryState, ResetType, StatusOptions,
 };
 use log::{debug, error, info, trace};
 use regex::Regex;
 //use std::time::SystemTime;
+use std::cell::Cell;
 use std::fmt;
 use std::num::ParseIntError;
 use std::ops::{Add, Sub};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::{
     collections::{HashMap, HashSet},
-    str,
+    fs, str,
 };
 
 pub fn make_diff_options() -> DiffOptions {
@@ -47,6 +58,50 @@ pub fn make_diff_options() -> DiffOptions {
     opts
 }
 
+pub fn is_whitespace_ignored(repo: &Repository) -> bool {
+    repo_settings::applied(repo).ignore_whitespace
+}
+
+// options for diffs which are only rendered.
+// everything which is applied (stage/unstage/kill)
+// must use make_diff_options, to get real bytes
+pub fn make_display_diff_options(repo: &Repository) -> DiffOptions {
+    let mut opts = make_diff_options();
+    if is_whitespace_ignored(repo) {
+        opts.ignore_whitespace(true);
+        opts.ignore_whitespace_eol(true);
+    }
+    opts
+}
+
+/// Pairs deleted and added files into renames, so pure rename
+/// is rendered as one file instead of delete plus add.
+pub fn find_renames(git_diff: &mut GitDiff) -> Result<(), Error> {
+    git_diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+}
+
+fn renamed_from(delta: &DiffDelta) -> Option<PathBuf> {
+    if delta.status() != Delta::Renamed {
+        return None;
+    }
+    delta.old_file().path().map(PathBuf::from)
+}
+
+// mode change pseudo hunk. real hunk headers start with @@
+const MODE_HEADER_PREFIX: &str = "mode ";
+
+fn mode_change_hunk(delta: &DiffDelta, kind: DiffKind) -> Option<Hunk> {
+    if delta.status() != Delta::Modified {
+        return None;
+    }
+    let old_mode = i32::from(delta.old_file().mode());
+    let new_mode = i32::from(delta.new_file().mode());
+    if old_mode == new_mode {
+        return None;
+    }
+    Some(Hunk::from_mode_change(kind, old_mode, new_mode))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct HunkLineNo(u32);
 
@@ -94,6 +149,9 @@ pub enum LineKind {
     Ours(i32),
     Theirs(i32),
     ConflictMarker(String),
+    // "\ No newline at end of file". not a line of file,
+    // so it goes to patch without +/- prefix
+    NoNewlineAtEof,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -171,6 +229,21 @@ pub const MARKER_THEIRS: &str = ">>>>>>>";
 pub const MINUS: &str = "-";
 pub const SPACE: &str = " ";
 
+// context runs longer than this are folded inside hunk
+pub const FOLD_CONTEXT_LINES: usize = 20;
+// context lines which remain visible around changes
+pub const FOLD_CONTEXT_MARGIN: usize = 3;
+
+/// Run of context lines inside hunk, shown as single
+/// "… N unchanged lines …" line while folded. First line
+/// of the run renders that marker, the rest are hidden.
+#[derive(Debug, Clone)]
+pub struct ContextFold {
+    pub start: usize,
+    pub len: usize,
+    pub folded: Cell<bool>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Hunk {
     pub view: View,
@@ -185,6 +258,7 @@ pub struct Hunk {
     pub buf: String,
     pub keyword_ranges: Vec<(usize, usize)>,
     pub identifier_ranges: Vec<(usize, usize)>,
+    pub folds: Vec<ContextFold>,
 }
 
 impl fmt::Display for Hunk {
@@ -210,7 +284,56 @@ impl Hunk {
             buf: String::new(),
             keyword_ranges: Vec::new(),
             identifier_ranges: Vec::new(),
+            folds: Vec::new(),
+        }
+    }
+
+    /// Pseudo hunk without lines for file mode change (e.g. +x bit).
+    /// It is staged like other hunks: applying delta without
+    /// any of its hunks changes mode only.
+    pub fn from_mode_change(kind: DiffKind, old_mode: i32, new_mode: i32) -> Self {
+        let mut hunk = Self::new(kind);
+        hunk.header = format!("{}{:o} → {:o}", MODE_HEADER_PREFIX, old_mode, new_mode);
+        hunk
+    }
+
+    pub fn is_mode_header(header: &str) -> bool {
+        header.starts_with(MODE_HEADER_PREFIX)
+    }
+
+    pub fn is_mode_change(&self) -> bool {
+        Self::is_mode_header(&self.header)
+    }
+
+    /// Old and new octal modes of mode change pseudo hunk.
+    pub fn mode_change(&self) -> Option<(&str, &str)> {
+        self.header
+            .strip_prefix(MODE_HEADER_PREFIX)?
+            .split_once(" → ")
+    }
+
+    /// Raw lines of hunk, optionally with +/- prefixes as in patch.
+    pub fn text(&self, with_prefixes: bool) -> String {
+        let mut result = String::new();
+        for line in &self.lines {
+            if line.kind == LineKind::NoNewlineAtEof {
+                if with_prefixes {
+                    result.push_str(line.content(self));
+                    result.push('\n');
+                }
+                continue;
+            }
+            if with_prefixes {
+                result.push(match line.origin {
+                    DiffLineType::Addition => '+',
+                    DiffLineType::Deletion => '-',
+                    _ => ' ',
+                });
+            }
+            result.push_str(line.content(self));
+            result.push('\n');
         }
+        result
     }
 
     pub fn get_header_from(dh: &DiffHunk) -> String {
@@ -326,6 +449,58 @@ impl Hunk {
             .sum()
     }
 
+    pub fn fold_context(&mut self) {
+        self.folds.clear();
+        let mut run_start: Option<usize> = None;
+        for i in 0..=self.lines.len() {
+            let is_context = self
+                .lines
+                .get(i)
+                .is_some_and(|l| l.origin == DiffLineType::Context);
+            match (is_context, run_start) {
+                (true, None) => {
+                    run_start.replace(i);
+                }
+                (false, Some(start)) => {
+                    run_start = None;
+                    if i - start > FOLD_CONTEXT_LINES {
+                        self.folds.push(ContextFold {
+                            start: start + FOLD_CONTEXT_MARGIN,
+                            len: i - start - 2 * FOLD_CONTEXT_MARGIN,
+                            folded: Cell::new(true),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Number of folded lines when line renders fold marker.
+    pub fn folded_at(&self, line: &Line) -> Option<usize> {
+        self.folds
+            .iter()
+            .find(|f| f.folded.get() && std::ptr::eq(&self.lines[f.start], line))
+            .map(|f| f.len)
+    }
+
+    pub fn is_folded_away(&self, line_idx: usize) -> bool {
+        self.folds
+            .iter()
+            .any(|f| f.folded.get() && line_idx > f.start && line_idx < f.start + f.len)
+    }
+
+    // added and deleted lines, as in git diff --stat
+    pub fn stats(&self) -> (usize, usize) {
+        self.lines
+            .iter()
+            .fold((0, 0), |(added, deleted), l| match l.origin {
+                DiffLineType::Addition => (added + 1, deleted),
+                DiffLineType::Deletion => (added, deleted + 1),
+                _ => (added, deleted),
+            })
+    }
+
     pub fn push_line(
         &mut self,
         diff_line: &DiffLine,
@@ -350,6 +525,19 @@ impl Hunk {
         // self.buf.push_str(content);
         // self.buf.push_str("\n");
 
+        if matches!(
+            line.origin,
+            DiffLineType::ContextEOFNL | DiffLineType::AddEOFNL | DiffLineType::DeleteEOFNL
+        ) {
+            line.kind = LineKind::NoNewlineAtEof;
+            self.buf.push_str(content);
+            self.buf.push('\n');
+            line.fill_char_indices(&self.buf);
+            self.lines.push(line);
+            // marker does not break ours/theirs sequence
+            return prev_line_kind;
+        }
+
         if self.kind != DiffKind::Conflicted {
             match line.origin {
                 DiffLineType::FileHeader | DiffLineType::HunkHeader | DiffLineType::Binary => {}
@@ -425,6 +613,8 @@ impl Hunk {
 pub struct File {
     pub view: View,
     pub path: PathBuf,
+    // set when file is renamed
+    pub old_path: Option<PathBuf>,
     pub hunks: Vec<Hunk>,
     pub kind: DiffKind,
     pub status: Delta,
@@ -435,6 +625,7 @@ impl File {
         Self {
             view: View::new(),
             path: PathBuf::new(),
+            old_path: None,
             hunks: Vec::new(),
             kind,
             status: Delta::Unmodified,
@@ -445,6 +636,7 @@ impl File {
         File {
             view: View::new(),
             path,
+            old_path: None,
             hunks: Vec::new(),
             kind,
             status,
@@ -455,6 +647,7 @@ impl File {
         if let Some(parser) = parser {
             parser.parse_hunk(&mut hunk);
         }
+        hunk.fold_context();
         self.hunks.push(hunk);
     }
 }
@@ -468,6 +661,29 @@ pub enum DiffKind {
     Commit,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DiffStats {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl fmt::Display for DiffStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} file{} changed, {} insertion{}(+), {} deletion{}(-)",
+            self.files,
+            plural(self.files),
+            self.insertions,
+            plural(self.insertions),
+            self.deletions,
+            plural(self.deletions)
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Diff {
     pub files: Vec<File>,
@@ -502,6 +718,24 @@ impl Diff {
         self.files.is_empty()
     }
 
+    pub fn stats(&self) -> DiffStats {
+        self.files
+            .iter()
+            .flat_map(|f| &f.hunks)
+            .map(|h| h.stats())
+            .fold(
+                DiffStats {
+                    files: self.files.len(),
+                    ..Default::default()
+                },
+                |stats, (added, deleted)| DiffStats {
+                    insertions: stats.insertions + added,
+                    deletions: stats.deletions + deleted,
+                    ..stats
+                },
+            )
+    }
+
     pub fn has_conflicts(&self) -> bool {
         self.files
             .iter()
@@ -534,16 +768,43 @@ impl State {
     pub fn need_rebase_continue(&self) -> bool {
         matches!(self.state, RepositoryState::RebaseMerge)
     }
+    // git cherry-pick/revert A..B stopped on conflict
+    pub fn need_sequence_continue(&self) -> bool {
+        matches!(
+            self.state,
+            RepositoryState::CherryPickSequence | RepositoryState::RevertSequence
+        )
+    }
 
     fn from_git_state(state: git2::RepositoryState, path: PathBuf) -> State {
+        if state == RepositoryState::Bisect {
+            return State::new(state, bisect::summary(&path));
+        }
+        if matches!(
+            state,
+            RepositoryState::Rebase
+                | RepositoryState::RebaseMerge
+                | RepositoryState::RebaseInteractive
+        ) {
+            // step where rebase is stopped
+            let subject = Repository::open(&path)
+                .ok()
+                .and_then(|repo| {
+                    let mut rebase = repo.open_rebase(None).ok()?;
+                    rebase_progress(&repo, &mut rebase)
+                })
+                .map(|(current, total, subject)| format!("{}/{}: {}", current, total, subject))
+                .unwrap_or_default();
+            return State::new(state, subject);
+        }
         let mut subject = String::from("");
         if let Some(path_to_read_subject) = match state {
-            RepositoryState::CherryPick => {
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
                 let mut pth = path.clone();
                 pth.push(CHERRY_PICK_HEAD);
                 Some(pth)
             }
-            RepositoryState::Revert => {
+            RepositoryState::Revert | RepositoryState::RevertSequence => {
                 let mut pth = path.clone();
                 pth.push(REVERT_HEAD);
                 Some(pth)
@@ -629,6 +890,46 @@ pub fn get_upstream(path: PathBuf) -> Result<Head, Error> {
     Err(git2::Error::from_str("No upstream yet"))
 }
 
+// above this amount of changed files
+// user will be asked before building diffs
+pub const MANY_CHANGES: usize = 5000;
+
+// quick count of changed files, without building diffs
+pub fn count_changes(path: PathBuf) -> Result<usize, Error> {
+    let repo = Repository::discover(path)?;
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false);
+    Ok(repo.statuses(Some(&mut opts))?.len())
+}
+
+// any path inside repo (file or subdir) resolves to its workdir
+pub fn discover_workdir(path: PathBuf) -> Result<PathBuf, Error> {
+    let repo = Repository::discover(path)?;
+    Ok(repo
+        .workdir()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| repo.path().to_path_buf()))
+}
+
+/// git init [--bare]. Optional initial commit has empty tree,
+/// so branches could be created right away.
+pub fn init(path: PathBuf, bare: bool, initial_commit: bool) -> Result<PathBuf, Error> {
+    let repo = if bare {
+        Repository::init_bare(&path)?
+    } else {
+        Repository::init(&path)?
+    };
+    if initial_commit {
+        let me = repo.signature()?;
+        let tree_oid = repo.treebuilder(None)?.write()?;
+        let tree = repo.find_tree(tree_oid)?;
+        repo.commit(Some("HEAD"), &me, &me, "Initial commit", &tree, &[])?;
+    }
+    Ok(path)
+}
+
 pub const CHERRY_PICK_HEAD: &str = "CHERRY_PICK_HEAD";
 pub const REVERT_HEAD: &str = "REVERT_HEAD";
 
@@ -761,33 +1062,82 @@ pub fn get_current_repo_status(
     Ok(())
 }
 
+/// Index against HEAD, as it is shown in status.
+pub fn staged_diff(repo: &Repository) -> Result<Diff, Error> {
+    let mut git_diff = if let Ok(ob) = repo.revparse_single("HEAD^{tree}") {
+        let tree = repo.find_tree(ob.id())?;
+        repo.diff_tree_to_index(
+            Some(&tree),
+            None,
+            Some(&mut make_display_diff_options(repo)),
+        )?
+    } else {
+        repo.diff_tree_to_index(None, None, Some(&mut make_display_diff_options(repo)))?
+    };
+    find_renames(&mut git_diff)?;
+    Ok(make_diff(&git_diff, DiffKind::Staged))
+}
+
+/// Workdir against index, as it is shown in status.
+pub fn unstaged_diff(repo: &Repository) -> Result<Diff, Error> {
+    let git_diff = repo.diff_index_to_workdir(None, Some(&mut make_display_diff_options(repo)))?;
+    Ok(make_diff(&git_diff, DiffKind::Unstaged))
+}
+
 fn get_staged(path: PathBuf, sender: Sender<crate::Event>) {
     let repo = Repository::open(path).expect("can't open repo");
-    let git_diff = {
+    let diff = staged_diff(&repo).expect("can't get diff tree to index");
+    sender
+        .send_blocking(crate::Event::Staged(if diff.is_empty() {
+            None
+        } else {
+            Some(diff)
+        }))
+        .expect("Could not send through channel");
+}
+
+/// Staged and unstaged changes of single file. Used after
+/// stage/unstage of known file instead of whole status.
+pub fn get_file_changes(path: PathBuf, file_path: PathBuf, sender: Sender<crate::Event>) {
+    let repo = Repository::open(path.clone()).expect("can't open repo");
+    if repo
+        .index()
+        .map(|index| index.has_conflicts())
+        .unwrap_or(true)
+    {
+        // conflicts are filtered out of staged/unstaged
+        // and could be resolved by this op
+        get_current_repo_status(Some(path), sender).expect("cant get status");
+        return;
+    }
+    let make_opts = || {
+        let mut opts = make_display_diff_options(&repo);
+        opts.pathspec(&file_path);
+        opts.disable_pathspec_match(true);
+        opts
+    };
+    let staged = {
         if let Ok(ob) = repo.revparse_single("HEAD^{tree}") {
             let tree = repo.find_tree(ob.id()).expect("no working tree");
-            repo.diff_tree_to_index(Some(&tree), None, Some(&mut make_diff_options()))
+            repo.diff_tree_to_index(Some(&tree), None, Some(&mut make_opts()))
                 .expect("can't get diff tree to index")
         } else {
-            repo.diff_tree_to_index(None, None, Some(&mut make_diff_options()))
+            repo.diff_tree_to_index(None, None, Some(&mut make_opts()))
                 .expect("can't get diff tree to index")
         }
     };
-    let diff = make_diff(&git_diff, DiffKind::Staged);
+    let staged = make_diff(&staged, DiffKind::Staged).files.pop();
+    let unstaged = repo
+        .diff_index_to_workdir(None, Some(&mut make_opts()))
+        .expect("can't get diff index to workdir");
+    let unstaged = make_diff(&unstaged, DiffKind::Unstaged).files.pop();
     sender
-        .send_blocking(crate::Event::Staged(if diff.is_empty() {
-            None
-        } else {
-            Some(diff)
-        }))
+        .send_blocking(crate::Event::FileChanges(file_path, staged, unstaged))
         .expect("Could not send through channel");
 }
 
 fn get_unstaged(repo: &git2::Repository, sender: Sender<crate::Event>) {
-    let git_diff = repo
-        .diff_index_to_workdir(None, Some(&mut make_diff_options()))
-        .unwrap();
-    let diff = make_diff(&git_diff, DiffKind::Unstaged);
+    let diff = unstaged_diff(repo).expect("can't get diff index to workdir");
     sender
         .send_blocking(crate::Event::Unstaged(if diff.is_empty() {
             None
@@ -797,6 +1147,76 @@ fn get_unstaged(repo: &git2::Repository, sender: Sender<crate::Event>) {
         .expect("Could not send through channel");
 }
 
+// untracked file bigger than this is not previewed
+const UNTRACKED_PREVIEW_LIMIT: u64 = 64 * 1024;
+
+/// There is no baseline for untracked file, so its content
+/// becomes single hunk of added lines. Binary and huge files
+/// get placeholder line instead of content.
+fn untracked_hunk(full_path: &Path) -> Option<Hunk> {
+    let metadata = fs::metadata(full_path).ok()?;
+    if !metadata.is_file() || metadata.len() == 0 {
+        return None;
+    }
+    let mut hunk = Hunk::new(DiffKind::Untracked);
+    let placeholder = if metadata.len() > UNTRACKED_PREVIEW_LIMIT {
+        Some(format!(
+            "File is too big to preview ({} KB)",
+            metadata.len() / 1024
+        ))
+    } else {
+        let bytes = fs::read(full_path).ok()?;
+        match String::from_utf8(bytes) {
+            Ok(content) if !content.contains('\0') => {
+                for (i, content) in content.lines().enumerate() {
+                    let mut line = Line {
+                        new_line_no: Some(HunkLineNo(i as u32 + 1)),
+                        content_idx: (hunk.buf.len(), content.len()),
+                        ..Default::default()
+                    };
+                    hunk.buf.push_str(content);
+                    hunk.buf.push('\n');
+                    line.fill_char_indices(&hunk.buf);
+                    hunk.lines.push(line);
+                }
+                None
+            }
+            _ => Some(String::from("Binary file")),
+        }
+    };
+    if let Some(placeholder) = placeholder {
+        let mut line = Line {
+            origin: DiffLineType::Context,
+            content_idx: (0, placeholder.len()),
+            ..Default::default()
+        };
+        hunk.buf.push_str(&placeholder);
+        hunk.buf.push('\n');
+        line.fill_char_indices(&hunk.buf);
+        hunk.lines.push(line);
+    } else {
+        hunk.new_start = HunkLineNo(1);
+        hunk.new_lines = hunk.lines.len() as u32;
+    }
+    hunk.header = format!("@@ -0,0 +{},{} @@", hunk.new_start, hunk.new_lines);
+    Some(hunk)
+}
+
+/// Content of untracked file is read only when file is expanded.
+/// Reading all untracked files on every refresh is too expensive.
+pub fn untracked_preview(path: &Path, file: &mut File) {
+    let Some(workdir) = Repository::open(path)
+        .ok()
+        .and_then(|repo| repo.workdir().map(|workdir| workdir.to_path_buf()))
+    else {
+        return;
+    };
+    if let Some(hunk) = untracked_hunk(&workdir.join(&file.path)) {
+        let language = syntax::language_key(&file.path);
+        syntax::with_parsers(|parsers| file.push_hunk(hunk, parsers.get(language.as_deref())));
+    }
+}
+
 pub fn get_untracked(path: PathBuf, sender: Sender<crate::Event>) {
     let repo = Repository::open(path.clone()).expect("can't open repo");
     let mut opts = make_diff_options();
@@ -841,87 +1261,192 @@ pub fn get_untracked(path: PathBuf, sender: Sender<crate::Event>) {
     }
 }
 
+/// What git clean -f would delete: untracked files, untracked
+/// dirs (ending with /) with -d, ignored ones with -x.
+/// Nested repos are kept, same as git does without -ff.
+pub fn clean_candidates(path: PathBuf, dirs: bool, ignored: bool) -> Result<Vec<PathBuf>, Error> {
+    let repo = Repository::open(path)?;
+    let workdir = repo.workdir().ok_or(Error::from_str("bare repo"))?;
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(ignored)
+        .recurse_ignored_dirs(false)
+        .include_unmodified(false);
+    let statuses = repo.statuses(Some(&mut opts))?;
+    Ok(statuses
+        .iter()
+        .filter(|entry| entry.status().is_wt_new() || (ignored && entry.status().is_ignored()))
+        .filter_map(|entry| entry.path().map(String::from))
+        .filter(|entry| {
+            !entry.ends_with('/') || (dirs && !workdir.join(entry).join(".git").exists())
+        })
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Patterns for .gitignore offered for untracked file: file
+/// itself anchored to root and all files with same extension.
+pub fn ignore_patterns(file_path: &Path) -> Vec<String> {
+    let mut patterns = vec![format!("/{}", file_path.display())];
+    if let Some(ext) = file_path.extension().and_then(|ext| ext.to_str()) {
+        if !file_path.to_string_lossy().ends_with('/') {
+            patterns.push(format!("*.{}", ext));
+        }
+    }
+    patterns
+}
+
+// pattern with slash in the middle is anchored to root anyway,
+// so leading slash does not make it another pattern
+fn anchored_pattern(pattern: &str) -> String {
+    let pattern = pattern.trim();
+    if pattern.starts_with('/') || !pattern.trim_end_matches('/').contains('/') {
+        return pattern.to_string();
+    }
+    format!("/{}", pattern)
+}
+
+/// Appends pattern to .gitignore in workdir root. Pattern which
+/// is already there is reported, so no redundant lines are added.
+/// Pattern edited by user may not match file at all, that is
+/// reported too.
+pub fn add_to_gitignore(
+    path: PathBuf,
+    file_path: PathBuf,
+    pattern: String,
+    sender: Sender<crate::Event>,
+) -> Result<(), Error> {
+    info!("ignore {:?} with {:?}", file_path, pattern);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = Repository::open(path)?;
+    let workdir = repo.workdir().ok_or(Error::from_str("bare repo"))?;
+    let gitignore = workdir.join(".gitignore");
+    let mut content = fs::read_to_string(&gitignore).unwrap_or_default();
+    // untracked file is not ignored by existing rules. so same
+    // pattern could be there only being negated by later one
+    let anchored = anchored_pattern(&pattern);
+    if content
+        .lines()
+        .any(|line| anchored_pattern(line) == anchored)
+    {
+        return Err(Error::from_str(&format!(
+            "{} is already in .gitignore. Perhaps it is negated by other rule",
+            pattern
+        )));
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&pattern);
+    content.push('\n');
+    fs::write(&gitignore, content).map_err(|e| Error::from_str(&e.to_string()))?;
+    if !repo.is_path_ignored(&file_path)? {
+        return Err(Error::from_str(&format!(
+            "{} is added to .gitignore, but {} is still not ignored",
+            pattern,
+            file_path.display()
+        )));
+    }
+    Ok(())
+}
+
 pub fn make_diff(git_diff: &GitDiff, kind: DiffKind) -> Diff {
     let mut diff = Diff::new(kind);
     let mut current_file = File::new(kind);
     let mut current_hunk = Hunk::new(kind);
     let mut prev_line_kind = LineKind::None;
-    let mut parser: Option<syntax::LanguageWrapper> = None;
-
-    let _res = git_diff.print(DiffFormat::Patch, |diff_delta, o_diff_hunk, diff_line| {
-        let status = diff_delta.status();
-        if status == Delta::Conflicted && (kind == DiffKind::Staged || kind == DiffKind::Unstaged) {
-            return true;
-        }
-        let file: DiffFile = match status {
-            Delta::Modified | Delta::Conflicted => diff_delta.new_file(),
-            Delta::Deleted => diff_delta.old_file(),
-            Delta::Added => match diff.kind {
-                DiffKind::Staged | DiffKind::Commit => diff_delta.new_file(),
-                DiffKind::Unstaged => {
-                    todo!("delta added in unstaged {:?}", diff_delta)
-                }
-                DiffKind::Conflicted => {
-                    todo!("delta added in conflicted {:?}", diff_delta)
-                }
-                DiffKind::Untracked => {
-                    panic!("untracked is not used with git diffs")
-                }
-            },
-            _ => {
-                todo!(
-                    "unhandled status ---> {:?} === {:?}, kind === {:?}",
-                    status,
-                    diff_delta,
-                    diff.kind
-                )
+    let mut language: Option<String> = None;
+
+    syntax::with_parsers(|parsers| {
+        let _res = git_diff.print(DiffFormat::Patch, |diff_delta, o_diff_hunk, diff_line| {
+            let status = diff_delta.status();
+            if status == Delta::Conflicted
+                && (kind == DiffKind::Staged || kind == DiffKind::Unstaged)
+            {
+                return true;
             }
-        };
+            let file: DiffFile = match status {
+                Delta::Modified | Delta::Conflicted | Delta::Renamed => diff_delta.new_file(),
+                Delta::Deleted => diff_delta.old_file(),
+                Delta::Added => match diff.kind {
+                    DiffKind::Staged | DiffKind::Commit => diff_delta.new_file(),
+                    DiffKind::Unstaged => {
+                        todo!("delta added in unstaged {:?}", diff_delta)
+                    }
+                    DiffKind::Conflicted => {
+                        todo!("delta added in conflicted {:?}", diff_delta)
+                    }
+                    DiffKind::Untracked => {
+                        panic!("untracked is not used with git diffs")
+                    }
+                },
+                _ => {
+                    todo!(
+                        "unhandled status ---> {:?} === {:?}, kind === {:?}",
+                        status,
+                        diff_delta,
+                        diff.kind
+                    )
+                }
+            };
 
-        if file.path().is_none() {
-            todo!();
-        }
-        let current_path = file.path().unwrap();
-        if current_file.path.capacity() == 0 {
-            // init new file
-            current_file = File::from_diff_file(&file, kind, status);
-            parser = syntax::choose_parser(current_path)
-        }
-        if current_file.path != current_path {
-            // go to next file
-            // push current_hunk to file and init new empty hunk
-            current_file.push_hunk(current_hunk.clone(), parser.as_mut());
-            current_hunk = Hunk::new(kind);
-            // push current_file to diff and change to new file
-            diff.push_file(current_file.clone());
-            current_file = File::from_diff_file(&file, kind, status);
-            parser = syntax::choose_parser(current_path);
-        }
-        if let Some(diff_hunk) = o_diff_hunk {
-            let hh = Hunk::get_header_from(&diff_hunk);
-            if current_hunk.header.is_empty() {
-                // init hunk
-                prev_line_kind = LineKind::None;
-                current_hunk.fill_from_git_hunk(&diff_hunk)
+            if file.path().is_none() {
+                todo!();
+            }
+            let current_path = file.path().unwrap();
+            if current_file.path.capacity() == 0 {
+                // init new file
+                current_file = File::from_diff_file(&file, kind, status);
+                current_file.old_path = renamed_from(&diff_delta);
+                current_file
+                    .hunks
+                    .extend(mode_change_hunk(&diff_delta, kind));
+                language = syntax::language_key(current_path)
             }
-            if current_hunk.header != hh {
-                // go to next hunk
-                prev_line_kind = LineKind::None;
-                current_file.push_hunk(current_hunk.clone(), parser.as_mut());
+            if current_file.path != current_path {
+                // go to next file
+                // push current_hunk to file and init new empty hunk.
+                // pure rename has no hunks at all
+                if !current_hunk.header.is_empty() {
+                    current_file.push_hunk(current_hunk.clone(), parsers.get(language.as_deref()));
+                }
                 current_hunk = Hunk::new(kind);
-                current_hunk.fill_from_git_hunk(&diff_hunk)
+                // push current_file to diff and change to new file
+                diff.push_file(current_file.clone());
+                current_file = File::from_diff_file(&file, kind, status);
+                current_file.old_path = renamed_from(&diff_delta);
+                current_file
+                    .hunks
+                    .extend(mode_change_hunk(&diff_delta, kind));
+                language = syntax::language_key(current_path);
+            }
+            if let Some(diff_hunk) = o_diff_hunk {
+                let hh = Hunk::get_header_from(&diff_hunk);
+                if current_hunk.header.is_empty() {
+                    // init hunk
+                    prev_line_kind = LineKind::None;
+                    current_hunk.fill_from_git_hunk(&diff_hunk)
+                }
+                if current_hunk.header != hh {
+                    // go to next hunk
+                    prev_line_kind = LineKind::None;
+                    current_file.push_hunk(current_hunk.clone(), parsers.get(language.as_deref()));
+                    current_hunk = Hunk::new(kind);
+                    current_hunk.fill_from_git_hunk(&diff_hunk)
+                }
+                prev_line_kind = current_hunk.push_line(&diff_line, prev_line_kind.clone());
+            } else {
+                // this is file header line.
+                prev_line_kind = current_hunk.push_line(&diff_line, prev_line_kind.clone())
             }
-            prev_line_kind = current_hunk.push_line(&diff_line, prev_line_kind.clone());
-        } else {
-            // this is file header line.
-            prev_line_kind = current_hunk.push_line(&diff_line, prev_line_kind.clone())
-        }
 
-        true
+            true
+        });
+        if !current_hunk.header.is_empty() {
+            current_file.push_hunk(current_hunk, parsers.get(language.as_deref()));
+        }
     });
-    if !current_hunk.header.is_empty() {
-        current_file.push_hunk(current_hunk, parser.as_mut());
-    }
     if current_file.path.capacity() != 0 {
         diff.push_file(current_file);
     }
@@ -959,6 +1484,200 @@ pub fn stage_untracked(
     Ok(())
 }
 
+/// Files inside untracked entries (files or dirs ending with /).
+/// Ignored files are listed only when asked for.
+pub fn untracked_files(
+    path: PathBuf,
+    entries: Vec<PathBuf>,
+    include_ignored: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let repo = Repository::open(path)?;
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(include_ignored)
+        .recurse_ignored_dirs(include_ignored)
+        .include_unmodified(false);
+    for entry in &entries {
+        opts.pathspec(entry);
+    }
+    let statuses = repo.statuses(Some(&mut opts))?;
+    Ok(statuses
+        .iter()
+        .filter(|entry| {
+            entry.status().is_wt_new() || (include_ignored && entry.status().is_ignored())
+        })
+        .filter_map(|entry| entry.path().map(PathBuf::from))
+        .filter(|file| entries.iter().any(|entry| file.starts_with(entry)))
+        .collect())
+}
+
+/// Deletes untracked entries from disk. Dirs left
+/// empty are removed too. Returns number of deleted files.
+pub fn delete_untracked(
+    path: PathBuf,
+    entries: Vec<PathBuf>,
+    include_ignored: bool,
+    sender: Sender<crate::Event>,
+) -> Result<usize, Error> {
+    info!("delete untracked {:?} ignored {}", entries, include_ignored);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let files = untracked_files(path.clone(), entries.clone(), include_ignored)?;
+    let repo = Repository::open(path)?;
+    let workdir = repo.workdir().ok_or(Error::from_str("bare repo"))?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    for file in &files {
+        std::fs::remove_file(workdir.join(file))
+            .map_err(|e| Error::from_str(&format!("can't delete {:?}: {}", file, e)))?;
+    }
+    // deepest dirs go first. non empty dir (e.g. with ignored
+    // files left) fails to be removed and that is ok
+    let mut dirs: Vec<PathBuf> = files
+        .iter()
+        .flat_map(|file| file.ancestors().skip(1))
+        .filter(|dir| entries.iter().any(|entry| dir.starts_with(entry)))
+        .map(PathBuf::from)
+        .collect();
+    dirs.sort_by(|a, b| {
+        b.components()
+            .count()
+            .cmp(&a.components().count())
+            .then(a.cmp(b))
+    });
+    dirs.dedup();
+    for dir in dirs {
+        let _ = std::fs::remove_dir(workdir.join(dir));
+    }
+    Ok(files.len())
+}
+
+/// Discards all workdir changes of file, like git checkout -- file.
+/// Unlike reverse apply it does not depend on eol and filters.
+/// File removed from index is restored from HEAD.
+pub fn discard_file(
+    path: PathBuf,
+    file_path: PathBuf,
+    sender: Sender<crate::Event>,
+) -> Result<(), Error> {
+    info!("discard file {:?}", file_path);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = Repository::open(path.clone())?;
+    let mut index = repo.index()?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    let mut builder = CheckoutBuilder::new();
+    builder
+        .force()
+        .disable_pathspec_match(true)
+        .path(&file_path);
+    if index.get_path(&file_path, 0).is_some() {
+        return repo.checkout_index(Some(&mut index), Some(&mut builder));
+    }
+    // removed from index, but still committed
+    if let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) {
+        if tree.get_path(&file_path).is_ok() {
+            builder.update_index(false);
+            return repo.checkout_tree(tree.as_object(), Some(&mut builder));
+        }
+    }
+    // files on disk only are deleted via untracked files,
+    // with their own confirmation
+    Err(Error::from_str(&format!(
+        "{} is not tracked. Kill it in untracked files",
+        file_path.display()
+    )))
+}
+
+fn conflicted_paths(index: &git2::Index) -> Result<HashSet<PathBuf>, Error> {
+    let mut paths = HashSet::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        for entry in [conflict.our, conflict.their, conflict.ancestor]
+            .into_iter()
+            .flatten()
+        {
+            paths.insert(PathBuf::from(
+                String::from_utf8_lossy(&entry.path).to_string(),
+            ));
+        }
+    }
+    Ok(paths)
+}
+
+/// Stages all changes of tracked files, like git add -u.
+/// Conflicted files are skipped: they must be resolved one by one.
+pub fn stage_all(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), Error> {
+    info!("stage all");
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = Repository::open(path.clone())?;
+    let mut index = repo.index()?;
+    let conflicted = conflicted_paths(&index)?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    // non zero skips path
+    let mut skip_conflicted =
+        |file_path: &Path, _: &[u8]| -> i32 { conflicted.contains(file_path) as i32 };
+    index.update_all(
+        ["*"],
+        Some(&mut skip_conflicted as &mut git2::IndexMatchedPath),
+    )?;
+    index.write()
+}
+
+/// Resets index to HEAD tree, keeping conflicted entries.
+pub fn unstage_all(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), Error> {
+    info!("unstage all");
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = Repository::open(path.clone())?;
+    let index = repo.index()?;
+    let conflicted = conflicted_paths(&index)?;
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel(ObjectType::Commit).ok());
+    let head_tree = match &head {
+        Some(head) => Some(head.peel_to_tree()?),
+        None => None,
+    };
+    let git_diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+    let paths: Vec<PathBuf> = git_diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .filter(|file_path| !conflicted.contains(*file_path))
+        .map(|file_path| file_path.to_path_buf())
+        .collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    repo.reset_default(head.as_ref(), paths)
+}
+
+// old path when file_path is staged as rename
+fn staged_rename_source(repo: &Repository, file_path: &Path) -> Option<PathBuf> {
+    let head_tree = repo
+        .revparse_single("HEAD^{tree}")
+        .ok()
+        .and_then(|ob| repo.find_tree(ob.id()).ok());
+    let mut git_diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), None, Some(&mut make_diff_options()))
+        .ok()?;
+    find_renames(&mut git_diff).ok()?;
+    git_diff
+        .deltas()
+        .filter(|delta| delta.new_file().path() == Some(file_path))
+        .find_map(|delta| renamed_from(&delta))
+}
+
 pub fn stage_via_apply(
     path: PathBuf,
     file_path: Option<PathBuf>,
@@ -970,25 +1689,41 @@ pub fn stage_via_apply(
         "stage via apply {:?} {:?} {:?}",
         file_path, hunk_header, subject
     );
-    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
     let repo = Repository::open(path.clone())?;
+    let renamed_from = file_path
+        .as_ref()
+        .and_then(|file_path| staged_rename_source(&repo, file_path));
+    // single file refresh does not know about renames
+    let _updater = if renamed_from.is_some() {
+        DeferRefresh::new(path.clone(), sender.clone(), true, true)
+    } else {
+        DeferRefresh::new(path.clone(), sender.clone(), true, true).only_file(file_path.clone())
+    };
 
     let mut opts = make_diff_options();
 
     if let Some(file_path) = &file_path {
         opts.pathspec(file_path.clone());
     }
+    if let Some(renamed_from) = &renamed_from {
+        opts.pathspec(renamed_from.clone());
+    }
 
     let git_diff = match subject {
         crate::StageOp::Stage => repo.diff_index_to_workdir(None, Some(&mut opts))?,
         crate::StageOp::Unstage => {
             opts.reverse(true);
-            if let Ok(ob) = repo.revparse_single("HEAD^{tree}") {
+            let mut git_diff = if let Ok(ob) = repo.revparse_single("HEAD^{tree}") {
                 let current_tree = repo.find_tree(ob.id()).expect("no working tree");
                 repo.diff_tree_to_index(Some(&current_tree), None, Some(&mut opts))?
             } else {
                 repo.diff_tree_to_index(None, None, Some(&mut opts))?
+            };
+            // hunk headers of renamed file are rendered against old path
+            if renamed_from.is_some() {
+                find_renames(&mut git_diff)?;
             }
+            git_diff
         }
         crate::StageOp::Kill => {
             opts.reverse(true);
@@ -996,13 +1731,16 @@ pub fn stage_via_apply(
         }
     };
 
+    // when whitespace is ignored, rendered hunks could differ from
+    // the real ones and there will be nothing to apply
+    let hunk_found = std::cell::Cell::new(false);
     let mut options = ApplyOptions::new();
 
     options.hunk_callback(|odh| -> bool {
         if let Some(hunk_header) = &hunk_header {
             if let Some(dh) = odh {
                 let header = Hunk::get_header_from(&dh);
-                return match subject {
+                let found = match subject {
                     crate::StageOp::Stage => hunk_header == &header,
                     crate::StageOp::Unstage => hunk_header == &Hunk::reverse_header(&header),
                     crate::StageOp::Kill => {
@@ -1010,6 +1748,10 @@ pub fn stage_via_apply(
                         hunk_header == &reversed
                     }
                 };
+                if found {
+                    hunk_found.replace(true);
+                }
+                return found;
             }
         }
         true
@@ -1019,7 +1761,9 @@ pub fn stage_via_apply(
         if let Some(file_path) = &file_path {
             if let Some(dd) = odd {
                 let path: PathBuf = dd.new_file().path().unwrap().into();
-                return file_path == &path;
+                // reversed rename has file_path on old side
+                let old_path = dd.old_file().path();
+                return file_path == &path || old_path == Some(file_path.as_path());
             }
         }
         true
@@ -1028,12 +1772,37 @@ pub fn stage_via_apply(
         crate::StageOp::Stage | crate::StageOp::Unstage => ApplyLocation::Index,
         crate::StageOp::Kill => ApplyLocation::WorkDir,
     };
+    // apply changes mode of delta along with any of its hunks,
+    // so mode is restored when content hunk is staged/unstaged
+    let is_mode_op = hunk_header.as_deref().is_some_and(Hunk::is_mode_header);
+    let index_mode = match (&file_path, subject) {
+        (Some(file_path), crate::StageOp::Stage | crate::StageOp::Unstage)
+            if hunk_header.is_some() && !is_mode_op =>
+        {
+            repo.index()?.get_path(file_path, 0).map(|entry| entry.mode)
+        }
+        _ => None,
+    };
 
     sender
         .send_blocking(crate::Event::LockMonitors(true))
         .expect("Could not send through channel");
     repo.apply(&git_diff, apply_location, Some(&mut options))?;
-
+    if let (Some(mode), Some(file_path)) = (index_mode, &file_path) {
+        let mut index = repo.index()?;
+        if let Some(mut entry) = index.get_path(file_path, 0) {
+            if entry.mode != mode {
+                entry.mode = mode;
+                index.add(&entry)?;
+                index.write()?;
+            }
+        }
+    }
+    if hunk_header.is_some() && !is_mode_op && !hunk_found.get() && is_whitespace_ignored(&repo) {
+        return Err(Error::from_str(
+            "Hunk differs when whitespace is ignored. Stage whole file or show whitespace changes.",
+        ));
+    }
     Ok(())
 }
 
@@ -1042,6 +1811,7 @@ pub struct DeferRefresh {
     pub sender: Sender<crate::Event>,
     pub update_status: bool,
     pub unlock_monitors: bool,
+    pub file_path: Option<PathBuf>,
 }
 
 impl DeferRefresh {
@@ -1056,8 +1826,15 @@ impl DeferRefresh {
             sender,
             update_status,
             unlock_monitors,
+            file_path: None,
         }
     }
+
+    /// Op touched only this file. Refresh just its changes.
+    pub fn only_file(mut self, file_path: Option<PathBuf>) -> Self {
+        self.file_path = file_path;
+        self
+    }
 }
 
 impl Drop for DeferRefresh {
@@ -1066,8 +1843,13 @@ impl Drop for DeferRefresh {
             gio::spawn_blocking({
                 let path = self.path.clone();
                 let sender = self.sender.clone();
+                let file_path = self.file_path.take();
                 move || {
-                    get_current_repo_status(Some(path), sender).expect("cant get status");
+                    if let Some(file_path) = file_path {
+                        get_file_changes(path, file_path, sender);
+                    } else {
+                        get_current_repo_status(Some(path), sender).expect("cant get status");
+                    }
                 }
             });
         }
@@ -1201,7 +1983,7 @@ pub fn abort_rebase(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), E
 }
 
 pub fn continue_rebase(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), Error> {
-    let _updater = DeferRefresh::new(path.clone(), sender, true, true);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
 
     let repo = Repository::open(path)?;
 
@@ -1215,16 +1997,51 @@ pub fn continue_rebase(path: PathBuf, sender: Sender<crate::Event>) -> Result<()
 
     let me = repo.signature()?;
     rebase.commit(None, &me, None)?;
-    loop {
-        if let Some(result) = rebase.next() {
-            debug!("rebase result {:?}", result);
-            rebase.commit(None, &me, None)?;
-        } else {
-            rebase.finish(Some(&me))?;
-            break;
+    run_rebase(&repo, &mut rebase, &me, &sender)
+}
+
+/// Step of rebase: 1 based number of current operation, total
+/// operations and subject of commit being applied.
+pub fn rebase_progress(
+    repo: &Repository,
+    rebase: &mut git2::Rebase,
+) -> Option<(usize, usize, String)> {
+    let current = rebase.operation_current()?;
+    let oid = rebase.nth(current)?.id();
+    let subject = repo
+        .find_commit(oid)
+        .ok()
+        .and_then(|commit| commit.summary().map(String::from))
+        .unwrap_or_default();
+    Some((current + 1, rebase.len(), subject))
+}
+
+// progress goes to banner until rebase is over or stopped.
+// stopped one keeps its step in State subject
+fn run_rebase(
+    repo: &Repository,
+    rebase: &mut git2::Rebase,
+    me: &git2::Signature,
+    sender: &Sender<crate::Event>,
+) -> Result<(), Error> {
+    let mut apply = || {
+        while let Some(op) = rebase.next() {
+            let op = op?;
+            debug!("rebase op {:?} {:?}", op.id(), op.kind());
+            if let Some(progress) = rebase_progress(repo, rebase) {
+                sender
+                    .send_blocking(crate::Event::RebaseProgress(Some(progress)))
+                    .expect("Could not send through channel");
+            }
+            rebase.commit(None, me, None)?;
         }
-    }
-    Ok(())
+        rebase.finish(Some(me))
+    };
+    let result = apply();
+    sender
+        .send_blocking(crate::Event::RebaseProgress(None))
+        .expect("Could not send through channel");
+    result
 }
 
 pub fn rebase(
@@ -1233,7 +2050,7 @@ pub fn rebase(
     _onto: Option<Oid>,
     sender: Sender<crate::Event>,
 ) -> Result<bool, Error> {
-    let _defer = DeferRefresh::new(path.clone(), sender, true, true);
+    let _defer = DeferRefresh::new(path.clone(), sender.clone(), true, true);
 
     let repo = Repository::open(path)?;
     let upstream_commit = repo.find_annotated_commit(upstream)?;
@@ -1245,24 +2062,8 @@ pub fn rebase(
     let rebase_options = rebase_options.checkout_options(builder);
 
     let mut rebase = repo.rebase(None, Some(&upstream_commit), None, Some(rebase_options))?;
-    debug!(
-        "THATS REBASE {:?} {:?}",
-        rebase.operation_current(),
-        rebase.len()
-    );
     let me = repo.signature()?;
-    loop {
-        if let Some(result) = rebase.next() {
-            debug!("MAIN got result in rebase ..... {:?}", result);
-            let op = result?;
-            debug!("MAIN rebase op {:?} {:?}", op.id(), op.kind());
-            rebase.commit(None, &me, None)?;
-        } else {
-            debug!("MAIN rebase is over!");
-            rebase.finish(Some(&me))?;
-            break;
-        }
-    }
+    run_rebase(&repo, &mut rebase, &me, &sender)?;
     Ok(true)
 }
 
@@ -1301,3 +2102,77 @@ pub fn blame(
         HunkLineNo(blame_hunk.orig_start_line() as u32),
     ))
 }
+
+#[derive(Debug, Clone)]
+pub struct FileBlameLine {
+    // None for lines which are not committed yet
+    pub oid: Option<Oid>,
+    pub author: String,
+    pub line_no: HunkLineNo,
+    // line no in commit which introduced the line
+    pub orig_line_no: HunkLineNo,
+    pub content: String,
+}
+
+/// Blame whole file in workdir. Uncommitted changes are blamed too
+/// (via blame buffer), so each line of file gets its own entry.
+pub fn blame_file(path: PathBuf, file_path: PathBuf) -> Result<Vec<FileBlameLine>> {
+    let repo = git2::Repository::open(path.clone())?;
+    let workdir = repo.workdir().context("no workdir")?;
+    let bytes = std::fs::read(workdir.join(&file_path))?;
+    let blame = repo.blame_file(&file_path, None)?;
+    let blame = blame.blame_buffer(&bytes)?;
+    let content = String::from_utf8_lossy(&bytes);
+    let mut result = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let (oid, author, orig_line_no) = match blame.get_line(line_no) {
+            Some(hunk) if !hunk.final_commit_id().is_zero() => (
+                Some(hunk.final_commit_id()),
+                hunk.final_signature().name().unwrap_or("").to_string(),
+                hunk.orig_start_line() + (line_no - hunk.final_start_line()),
+            ),
+            _ => (None, String::from(""), line_no),
+        };
+        result.push(FileBlameLine {
+            oid,
+            author,
+            line_no: HunkLineNo(line_no as u32),
+            orig_line_no: HunkLineNo(orig_line_no as u32),
+            content: line.to_string(),
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{commit_files, temp_repo};
+    use std::fs;
+
+    #[test]
+    fn test_get_file_changes() {
+        let (path, repo) = temp_repo("file-changes");
+        commit_files(&repo, &[("a.txt", "a\n"), ("b.txt", "b\n")], "initial");
+        fs::write(path.join("a.txt"), "a\nstaged\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(path.join("b.txt"), "b\nunstaged\n").unwrap();
+
+        let (sender, receiver) = async_channel::unbounded();
+        get_file_changes(path.clone(), PathBuf::from("a.txt"), sender);
+        match receiver.try_recv().unwrap() {
+            crate::Event::FileChanges(file_path, staged, unstaged) => {
+                assert_eq!(file_path, PathBuf::from("a.txt"));
+                // other changed files are not touched
+                assert_eq!(staged.unwrap().path, PathBuf::from("a.txt"));
+                assert!(unstaged.is_none());
+            }
+            _ => panic!("no file changes"),
+        }
+        assert!(receiver.try_recv().is_err());
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Bisect on top of raw refs and files, cause git2 has no bisect api.
+//! Layout is the same as git uses, so `git bisect` from terminal
+//! could continue (or reset) the session started here and vice versa:
+//! refs/bisect/bad, refs/bisect/good-<sha>, BISECT_START (where to
+//! return on reset), BISECT_LOG (its presence means Bisect state).
+use crate::git::{hooks, DeferRefresh};
+use async_channel::Sender;
+use git2;
+use log::info;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const BISECT_START: &str = "BISECT_START";
+const BISECT_LOG: &str = "BISECT_LOG";
+const BISECT_TERMS: &str = "BISECT_TERMS";
+const BISECT_NAMES: &str = "BISECT_NAMES";
+const BISECT_EXPECTED_REV: &str = "BISECT_EXPECTED_REV";
+const BISECT_FILES: [&str; 7] = [
+    BISECT_START,
+    BISECT_LOG,
+    BISECT_TERMS,
+    BISECT_NAMES,
+    BISECT_EXPECTED_REV,
+    "BISECT_ANCESTORS_OK",
+    "BISECT_RUN",
+];
+const BAD_REF: &str = "refs/bisect/bad";
+const GOOD_REF_PREFIX: &str = "refs/bisect/good-";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BisectStep {
+    // both good and bad are required to choose next commit
+    NeedMore,
+    Next(git2::Oid, usize),
+    Found(git2::Oid),
+}
+
+fn to_git_error(e: std::io::Error) -> git2::Error {
+    git2::Error::from_str(&e.to_string())
+}
+
+fn append_log(repo: &git2::Repository, line: &str) -> Result<(), git2::Error> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(repo.path().join(BISECT_LOG))
+        .map_err(to_git_error)?;
+    writeln!(file, "{}", line).map_err(to_git_error)
+}
+
+fn summary_of(repo: &git2::Repository, oid: git2::Oid) -> String {
+    repo.find_commit(oid)
+        .ok()
+        .and_then(|c| c.summary().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+pub fn is_bisecting(repo: &git2::Repository) -> bool {
+    repo.path().join(BISECT_LOG).exists()
+}
+
+fn bad_oid(repo: &git2::Repository) -> Option<git2::Oid> {
+    repo.refname_to_id(BAD_REF).ok()
+}
+
+fn good_oids(repo: &git2::Repository) -> Result<Vec<git2::Oid>, git2::Error> {
+    let mut result = Vec::new();
+    for reference in repo.references_glob(&format!("{}*", GOOD_REF_PREFIX))? {
+        if let Some(oid) = reference?.target() {
+            result.push(oid);
+        }
+    }
+    Ok(result)
+}
+
+// commits which are reachable from bad and not reachable from
+// any good, topologically sorted (children first)
+fn candidates(
+    repo: &git2::Repository,
+    bad: git2::Oid,
+    goods: &[git2::Oid],
+) -> Result<Vec<git2::Oid>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+    revwalk.push(bad)?;
+    for good in goods {
+        revwalk.hide(*good)?;
+    }
+    revwalk.collect()
+}
+
+fn checkout_detached(
+    repo: &git2::Repository,
+    oid: git2::Oid,
+    sender: &Sender<crate::Event>,
+) -> Result<(), git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    let prev_oid = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .unwrap_or(git2::Oid::zero());
+    let mut builder = git2::build::CheckoutBuilder::new();
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("can send through channel");
+    repo.checkout_tree(commit.as_object(), Some(builder.safe()))?;
+    repo.set_head_detached(oid)?;
+    hooks::post_checkout(repo.path().to_path_buf(), prev_oid, oid, sender.clone());
+    Ok(())
+}
+
+/// Remembers where to return on reset. Current HEAD is not marked:
+/// user marks it good or bad as a first step.
+pub fn start(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
+    info!("bisect start");
+    let _updater = DeferRefresh::new(path.clone(), sender, true, false);
+    let repo = git2::Repository::open(path)?;
+    if is_bisecting(&repo) {
+        return Err(git2::Error::from_str("Bisect is already started"));
+    }
+    let head = repo.head()?;
+    let start = if head.is_branch() {
+        head.shorthand().unwrap_or("").to_string()
+    } else {
+        head.target()
+            .ok_or(git2::Error::from_str("no head"))?
+            .to_string()
+    };
+    let git_dir = repo.path();
+    for (name, content) in [
+        (BISECT_START, format!("{}\n", start)),
+        (BISECT_TERMS, String::from("bad\ngood\n")),
+        (BISECT_NAMES, String::from("\n")),
+        (BISECT_LOG, String::from("git bisect start\n")),
+    ] {
+        fs::write(git_dir.join(name), content).map_err(to_git_error)?;
+    }
+    Ok(())
+}
+
+/// Marks HEAD and checks out next commit to test, if there is enough
+/// info for it. Commit in the middle of topological list of candidates
+/// is taken, which halves linear history exactly.
+pub fn mark(
+    path: PathBuf,
+    good: bool,
+    sender: Sender<crate::Event>,
+) -> Result<BisectStep, git2::Error> {
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path)?;
+    if !is_bisecting(&repo) {
+        return Err(git2::Error::from_str("Bisect is not started"));
+    }
+    let head_oid = repo
+        .head()?
+        .target()
+        .ok_or(git2::Error::from_str("no head"))?;
+    let term = if good { "good" } else { "bad" };
+    info!("bisect {} {}", term, head_oid);
+    let message = format!("bisect {}: {}", term, head_oid);
+    if good {
+        repo.reference(
+            &format!("{}{}", GOOD_REF_PREFIX, head_oid),
+            head_oid,
+            true,
+            &message,
+        )?;
+    } else {
+        repo.reference(BAD_REF, head_oid, true, &message)?;
+    }
+    append_log(
+        &repo,
+        &format!(
+            "# {}: [{}] {}\ngit bisect {} {}",
+            term,
+            head_oid,
+            summary_of(&repo, head_oid),
+            term,
+            head_oid
+        ),
+    )?;
+
+    let goods = good_oids(&repo)?;
+    let Some(bad) = bad_oid(&repo) else {
+        return Ok(BisectStep::NeedMore);
+    };
+    if goods.is_empty() {
+        return Ok(BisectStep::NeedMore);
+    }
+    let candidates = candidates(&repo, bad, &goods)?;
+    match candidates.len() {
+        0 => Err(git2::Error::from_str(
+            "Bad commit is reachable from good one. Nothing to bisect",
+        )),
+        1 => {
+            append_log(
+                &repo,
+                &format!("# first bad commit: [{}] {}", bad, summary_of(&repo, bad)),
+            )?;
+            Ok(BisectStep::Found(bad))
+        }
+        len => {
+            let next = candidates[len / 2];
+            checkout_detached(&repo, next, &sender)?;
+            fs::write(repo.path().join(BISECT_EXPECTED_REV), format!("{}\n", next))
+                .map_err(to_git_error)?;
+            Ok(BisectStep::Next(next, len - 1))
+        }
+    }
+}
+
+/// Returns to commit bisect was started on and removes all bisect state.
+pub fn reset(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
+    info!("bisect reset");
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path)?;
+    let git_dir = repo.path().to_path_buf();
+    let start = fs::read_to_string(git_dir.join(BISECT_START))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default();
+    if !start.is_empty() {
+        let branch_ref = format!("refs/heads/{}", start);
+        if let Ok(oid) = repo.refname_to_id(&branch_ref) {
+            checkout_detached(&repo, oid, &sender)?;
+            repo.set_head(&branch_ref)?;
+        } else if let Ok(oid) = git2::Oid::from_str(&start) {
+            checkout_detached(&repo, oid, &sender)?;
+        }
+    }
+    let refs: Vec<String> = repo
+        .references_glob("refs/bisect/*")?
+        .flatten()
+        .filter_map(|r| r.name().map(|n| n.to_string()))
+        .collect();
+    for name in refs {
+        repo.find_reference(&name)?.delete()?;
+    }
+    for name in BISECT_FILES {
+        let file = git_dir.join(name);
+        if file.exists() {
+            fs::remove_file(file).map_err(to_git_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Short description of bisect progress for status State line.
+pub fn summary(git_dir: &Path) -> String {
+    let Ok(repo) = git2::Repository::open(git_dir) else {
+        return String::new();
+    };
+    let goods = good_oids(&repo).unwrap_or_default();
+    let Some(bad) = bad_oid(&repo) else {
+        return format!("good: {}, bad: none. Mark HEAD good or bad", goods.len());
+    };
+    let short_bad = bad.to_string()[..7].to_string();
+    if goods.is_empty() {
+        return format!("good: none, bad: {}. Mark HEAD good or bad", short_bad);
+    }
+    match candidates(&repo, bad, &goods).map(|c| c.len()) {
+        Ok(1) => format!("first bad commit: {} {}", short_bad, summary_of(&repo, bad)),
+        Ok(0) | Err(_) => String::new(),
+        Ok(len) => format!(
+            "good: {}, bad: {}. {} revisions left",
+            goods.len(),
+            short_bad,
+            len - 1
+        ),
+    }
+}
@@ -3,14 +3,10 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::commit::CommitRepr;
-use crate::git::{
-    remote::{make_authorized_remote, set_remote_callbacks, Authorizer},
-    DeferRefresh,
-};
+use crate::git::{hooks, DeferRefresh};
 use async_channel::Sender;
 use chrono::{DateTime, FixedOffset};
 use git2;
-use gtk4::gio;
 use log::info;
 use std::cell::RefCell;
 use std::cmp::Ordering;
@@ -67,6 +63,7 @@ pub struct BranchData {
     pub is_head: bool,
     pub commit_dt: DateTime<FixedOffset>,
     pub remote_name: Option<String>,
+    pub is_merged: bool,
 }
 
 impl Default for BranchData {
@@ -80,6 +77,7 @@ impl Default for BranchData {
             is_head: false,
             commit_dt: DateTime::<FixedOffset>::MIN_UTC.into(),
             remote_name: None,
+            is_merged: false,
         }
     }
 }
@@ -118,6 +116,7 @@ impl BranchData {
                 is_head,
                 commit_dt,
                 remote_name,
+                is_merged: false,
             }))
         } else {
             Ok(None)
@@ -127,38 +126,85 @@ impl BranchData {
     pub fn local_name(&self) -> String {
         self.name.to_local(self.remote_name.as_deref())
     }
+
+    /// Search by name, same for branches window and quick switcher.
+    pub fn matches(&self, term: &str) -> bool {
+        self.name.to_str().contains(term)
+    }
+}
+
+// branch is merged if it is reachable from HEAD
+// or from main/master, like git branch -d checks it
+fn merge_targets(repo: &git2::Repository) -> Vec<git2::Oid> {
+    let mut targets = Vec::new();
+    if let Ok(head) = repo.head() {
+        if let Some(oid) = head.target() {
+            targets.push(oid);
+        }
+    }
+    for name in ["main", "master"] {
+        if let Ok(branch) = repo.find_branch(name, git2::BranchType::Local) {
+            if let Some(oid) = branch.get().target() {
+                if !targets.contains(&oid) {
+                    targets.push(oid);
+                }
+            }
+        }
+    }
+    targets
+}
+
+fn is_merged_into(repo: &git2::Repository, oid: git2::Oid, targets: &[git2::Oid]) -> bool {
+    targets
+        .iter()
+        .any(|target| *target == oid || repo.graph_descendant_of(*target, oid).unwrap_or(false))
+}
+
+pub fn is_merged(path: PathBuf, branch_data: &BranchData) -> Result<bool, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let targets = merge_targets(&repo);
+    Ok(is_merged_into(&repo, branch_data.oid, &targets))
 }
 
 pub fn get_branches(path: PathBuf) -> Result<Vec<BranchData>, git2::Error> {
     let repo = git2::Repository::open(path.clone())?;
     let mut result = Vec::new();
     let branches = repo.branches(None)?;
+    let targets = merge_targets(&repo);
     branches.for_each(|item| {
         let (branch, branch_type) = item.unwrap();
-        if let Ok(Some(branch_data)) = BranchData::from_branch(&branch, branch_type) {
+        if let Ok(Some(mut branch_data)) = BranchData::from_branch(&branch, branch_type) {
+            branch_data.is_merged = is_merged_into(&repo, branch_data.oid, &targets);
             result.push(branch_data);
         }
     });
-    result.sort_by(|a, b| {
-        // let head be always on top
-        if a.is_head {
-            return Ordering::Less;
-        }
-        if b.is_head {
-            return Ordering::Greater;
-        }
-
-        if a.branch_type == git2::BranchType::Local && b.branch_type != git2::BranchType::Local {
-            return Ordering::Less;
-        }
-        if b.branch_type == git2::BranchType::Local && a.branch_type != git2::BranchType::Local {
-            return Ordering::Greater;
-        }
-        b.commit_dt.cmp(&a.commit_dt)
-    });
+    result.sort_by(|a, b| compare_branches(a, b, false));
     Ok(result)
 }
 
+// head goes first, then local branches, then remote ones.
+// inside each group - by name or by last commit date
+pub fn compare_branches(a: &BranchData, b: &BranchData, by_name: bool) -> Ordering {
+    // let head be always on top
+    if a.is_head {
+        return Ordering::Less;
+    }
+    if b.is_head {
+        return Ordering::Greater;
+    }
+
+    if a.branch_type == git2::BranchType::Local && b.branch_type != git2::BranchType::Local {
+        return Ordering::Less;
+    }
+    if b.branch_type == git2::BranchType::Local && a.branch_type != git2::BranchType::Local {
+        return Ordering::Greater;
+    }
+    if by_name {
+        return a.name.to_str().cmp(b.name.to_str());
+    }
+    b.commit_dt.cmp(&a.commit_dt)
+}
+
 pub fn checkout_branch(
     path: PathBuf,
     mut branch_data: BranchData,
@@ -167,7 +213,23 @@ pub fn checkout_branch(
     info!("checkout branch");
     let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
     let repo = git2::Repository::open(path.clone())?;
+    if branch_data.branch_type == git2::BranchType::Remote {
+        // local branch with the same name already exists
+        // (user was asked about it). just switch to it
+        if let Ok(branch) = repo.find_branch(&branch_data.local_name(), git2::BranchType::Local) {
+            if let Some(local_branch_data) =
+                BranchData::from_branch(&branch, git2::BranchType::Local)?
+            {
+                branch_data = local_branch_data;
+            }
+        }
+    }
     let commit = repo.find_commit(branch_data.oid)?;
+    let prev_oid = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .unwrap_or(git2::Oid::zero());
 
     let mut builder = git2::build::CheckoutBuilder::new();
     let conflict_paths = Rc::new(RefCell::new(String::new()));
@@ -205,11 +267,7 @@ pub fn checkout_branch(
     match branch_data.branch_type {
         git2::BranchType::Local => {}
         git2::BranchType::Remote => {
-            let created = repo.branch(&branch_data.local_name(), &commit, false);
-            let mut branch = match created {
-                Ok(branch) => branch,
-                Err(_) => repo.find_branch(&branch_data.local_name(), git2::BranchType::Local)?,
-            };
+            let mut branch = repo.branch(&branch_data.local_name(), &commit, false)?;
             branch.set_upstream(Some(&branch_data.name.to_string()))?;
             if let Some(new_branch_data) =
                 BranchData::from_branch(&branch, git2::BranchType::Local)?
@@ -219,8 +277,10 @@ pub fn checkout_branch(
         }
     }
     repo.set_head(&branch_data.refname)?;
+    hooks::post_checkout(path, prev_oid, commit.id(), sender);
 
     branch_data.is_head = true;
+    branch_data.is_merged = true;
     Ok(Some(branch_data))
 }
 
@@ -244,6 +304,52 @@ pub fn create_branch(
     Ok(None)
 }
 
+pub fn rename_branch(
+    path: PathBuf,
+    branch_data: BranchData,
+    new_branch_name: String,
+) -> Result<BranchData, git2::Error> {
+    info!("rename branch {} to {}", branch_data.name, new_branch_name);
+    let repo = git2::Repository::open(path.clone())?;
+    let mut branch = repo.find_branch(branch_data.name.to_str(), branch_data.branch_type)?;
+    let renamed = branch.rename(&new_branch_name, false)?;
+    let mut new_branch_data = BranchData::from_branch(&renamed, branch_data.branch_type)?
+        .ok_or(git2::Error::from_str("cant get renamed branch"))?;
+    new_branch_data.is_merged = branch_data.is_merged;
+    Ok(new_branch_data)
+}
+
+pub fn get_upstream_name(
+    path: PathBuf,
+    branch_data: &BranchData,
+) -> Result<Option<String>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let branch = repo.find_branch(branch_data.name.to_str(), git2::BranchType::Local)?;
+    if let Ok(upstream) = branch.upstream() {
+        return Ok(Some(BranchName::from(&upstream).to_string()));
+    }
+    Ok(None)
+}
+
+pub fn set_upstream(
+    path: PathBuf,
+    branch_data: BranchData,
+    upstream_name: Option<String>,
+) -> Result<Option<BranchData>, git2::Error> {
+    info!(
+        "set upstream for {} to {:?}",
+        branch_data.name, upstream_name
+    );
+    let repo = git2::Repository::open(path.clone())?;
+    let mut branch = repo.find_branch(branch_data.name.to_str(), git2::BranchType::Local)?;
+    branch.set_upstream(upstream_name.as_deref())?;
+    if let Some(mut new_branch_data) = BranchData::from_branch(&branch, git2::BranchType::Local)? {
+        new_branch_data.is_merged = branch_data.is_merged;
+        return Ok(Some(new_branch_data));
+    }
+    Ok(None)
+}
+
 pub fn kill_branch(
     path: PathBuf,
     branch_data: BranchData,
@@ -251,40 +357,10 @@ pub fn kill_branch(
 ) -> Result<Option<()>, git2::Error> {
     let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
     let repo = git2::Repository::open(path.clone())?;
-    let name = &branch_data.local_name();
     let kind = branch_data.branch_type;
+    // only local branches are killed here. remote ones are deleted
+    // on remote via remote::delete_branch, which prunes tracking ref too
     let mut branch = repo.find_branch(branch_data.name.to_str(), kind)?;
-    if kind == git2::BranchType::Remote {
-        gio::spawn_blocking({
-            let path = path.clone();
-            let name = name.clone();
-            move || {
-                let repo = git2::Repository::open(path.clone()).expect("can't open repo");
-                let remote_name = branch_data.remote_name.expect("no remote name");
-                let (mut remote, authorizer) = make_authorized_remote(
-                    &repo,
-                    &remote_name,
-                    git2::Direction::Push,
-                    Authorizer::default(),
-                    sender.clone(),
-                )
-                .expect("cant get authed remote");
-                // let mut remote = repo
-                //     .find_remote(&branch_data.remote_name.expect("no remote name"))
-                //     .expect("no remote");
-                let mut opts = git2::PushOptions::new();
-                // let mut callbacks = git2::RemoteCallbacks::new();
-                let mut callbacks = authorizer.callbacks();
-                set_remote_callbacks(&mut callbacks);
-                opts.remote_callbacks(callbacks);
-
-                let refspec = format!(":refs/heads/{}", name);
-                remote
-                    .push(&[refspec], Some(&mut opts))
-                    .expect("cant push to remote");
-            }
-        });
-    }
     branch.delete()?;
     Ok(Some(()))
 }
@@ -2,14 +2,21 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::git::{get_head, make_diff, make_diff_options, DeferRefresh, Diff, DiffKind, Hunk};
+use crate::git::{
+    find_renames, get_head, make_diff, make_diff_options, make_display_diff_options, undo,
+    DeferRefresh, Diff, DiffKind, Hunk, CHERRY_PICK_HEAD, REVERT_HEAD,
+};
 use anyhow::Result;
 use async_channel::Sender;
 use chrono::{DateTime, FixedOffset, LocalResult, TimeZone};
 use git2;
 use gtk4::gio;
 use log::info;
-use std::path::PathBuf;
+use regex::Regex;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 pub trait CommitRepr {
     fn dt(&self) -> DateTime<FixedOffset>;
@@ -19,14 +26,19 @@ pub trait CommitRepr {
     fn author(&self) -> String;
 }
 
+/// Time of git signature in its own timezone.
+pub fn git_time_dt(time: git2::Time) -> DateTime<FixedOffset> {
+    let tz = FixedOffset::east_opt(time.offset_minutes() * 60).unwrap();
+    match tz.timestamp_opt(time.seconds(), 0) {
+        LocalResult::Single(dt) => dt,
+        LocalResult::Ambiguous(dt, _) => dt,
+        _ => todo!("not implemented"),
+    }
+}
+
 impl CommitRepr for git2::Commit<'_> {
     fn dt(&self) -> DateTime<FixedOffset> {
-        let tz = FixedOffset::east_opt(self.time().offset_minutes() * 60).unwrap();
-        match tz.timestamp_opt(self.time().seconds(), 0) {
-            LocalResult::Single(dt) => dt,
-            LocalResult::Ambiguous(dt, _) => dt,
-            _ => todo!("not implemented"),
-        }
+        git_time_dt(self.time())
     }
     fn log_message(&self) -> String {
         let message = self
@@ -83,6 +95,9 @@ pub struct CommitLog {
     pub commit_dt: DateTime<FixedOffset>,
     pub author: String,
     pub from: CommitRelation,
+    pub parents: Vec<git2::Oid>,
+    // row of commit graph. see git_log::graph_row
+    pub graph: String,
 }
 
 impl CommitLog {
@@ -93,6 +108,8 @@ impl CommitLog {
             commit_dt: CommitRepr::dt(&commit),
             author: CommitRepr::author(&commit),
             from,
+            parents: commit.parent_ids().collect(),
+            graph: String::from(""),
         }
     }
 }
@@ -104,6 +121,8 @@ impl Default for CommitLog {
             commit_dt: DateTime::<FixedOffset>::MIN_UTC.into(),
             author: String::from(""),
             from: CommitRelation::None,
+            parents: Vec::new(),
+            graph: String::from(""),
         }
     }
 }
@@ -114,6 +133,7 @@ pub struct CommitDiff {
     pub message: String,
     pub commit_dt: DateTime<FixedOffset>,
     pub author: String,
+    pub parents: Vec<git2::Oid>,
     pub diff: Diff,
 }
 
@@ -124,6 +144,7 @@ impl Default for CommitDiff {
             message: String::from(""),
             commit_dt: DateTime::<FixedOffset>::MIN_UTC.into(),
             author: String::from(""),
+            parents: Vec::new(),
             diff: Diff::new(DiffKind::Unstaged),
         }
     }
@@ -136,6 +157,7 @@ impl CommitDiff {
             message: CommitRepr::message(&commit),
             commit_dt: CommitRepr::dt(&commit),
             author: CommitRepr::author(&commit),
+            parents: commit.parent_ids().collect(),
             diff,
         }
     }
@@ -145,23 +167,305 @@ impl CommitDiff {
     }
 }
 
+fn diff_from_tree(
+    repo: &git2::Repository,
+    commit: git2::Commit,
+    from_tree: Option<git2::Tree>,
+) -> Result<CommitDiff, git2::Error> {
+    let tree = commit.tree()?;
+    let mut git_diff = repo.diff_tree_to_tree(
+        from_tree.as_ref(),
+        Some(&tree),
+        Some(&mut make_display_diff_options(repo)),
+    )?;
+    find_renames(&mut git_diff)?;
+    Ok(CommitDiff::new(
+        commit,
+        make_diff(&git_diff, DiffKind::Commit), // was Staged
+    ))
+}
+
 pub fn get_commit_diff(path: PathBuf, oid: git2::Oid) -> Result<CommitDiff, git2::Error> {
     let repo = git2::Repository::open(path)?;
     let commit = repo.find_commit(oid)?;
-    let tree = commit.tree()?;
     let mut parent_tree: Option<git2::Tree> = None;
     if let Ok(parent) = commit.parent(0) {
         let tree = parent.tree()?;
         parent_tree.replace(tree);
     }
-    let git_diff = repo.diff_tree_to_tree(
-        parent_tree.as_ref(),
-        Some(&tree),
-        Some(&mut make_diff_options()),
-    )?;
-    Ok(CommitDiff::new(
-        commit,
-        make_diff(&git_diff, DiffKind::Commit), // was Staged
+    diff_from_tree(&repo, commit, parent_tree)
+}
+
+/// All changes made on the way from base to commit, e.g.
+/// what branch adds since merge base.
+pub fn get_diff_from_base(
+    path: PathBuf,
+    base: git2::Oid,
+    oid: git2::Oid,
+) -> Result<CommitDiff, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let commit = repo.find_commit(oid)?;
+    let base_tree = repo.find_commit(base)?.tree()?;
+    diff_from_tree(&repo, commit, Some(base_tree))
+}
+
+pub const COMMIT_TEMPLATE: &str = "commit-template";
+
+// template is searched in configured path, then in commit.template
+// from git config, then in .git/commit-template.
+// comment lines are stripped, as git does.
+pub fn get_commit_template(
+    path: PathBuf,
+    template_path: String,
+) -> Result<Option<String>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let workdir = repo.workdir().unwrap_or(repo.path()).to_path_buf();
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if !template_path.is_empty() {
+        candidates.push(workdir.join(template_path));
+    }
+    if let Ok(config_path) = repo.config()?.get_path("commit.template") {
+        candidates.push(workdir.join(config_path));
+    }
+    candidates.push(repo.path().join(COMMIT_TEMPLATE));
+    for candidate in candidates {
+        if let Ok(content) = fs::read_to_string(&candidate) {
+            info!("commit template {:?}", candidate);
+            let template = content
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<&str>>()
+                .join("\n");
+            let template = template.trim_end();
+            if template.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(template.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// MERGE_MSG prepared by merge, cherry-pick or revert, without comments.
+pub fn merge_message(path: PathBuf) -> Result<Option<String>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    match repo.message() {
+        Ok(message) => Ok(Some(
+            message
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .trim()
+                .to_string(),
+        )),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// ticket id from branch name, e.g. PROJ-123 from feature/PROJ-123-fix.
+// first capture group is used if regex has one
+pub fn ticket_from_branch(branch_name: &str, ticket_regex: &str) -> Option<String> {
+    if ticket_regex.is_empty() {
+        return None;
+    }
+    let re = Regex::new(ticket_regex).ok()?;
+    let captures = re.captures(branch_name)?;
+    captures
+        .get(1)
+        .or(captures.get(0))
+        .map(|m| m.as_str().to_string())
+}
+
+// DCO sign-off trailer. if message already ends with trailers
+// block, sign-off is appended to it. otherwise it goes after blank line
+pub fn sign_off(message: &str, name: &str, email: &str) -> String {
+    let trailer = format!("Signed-off-by: {} <{}>", name, email);
+    let message = message.trim_end();
+    if message.lines().any(|line| line.trim() == trailer) {
+        return message.to_string();
+    }
+    let trailer_re = Regex::new(r"^[A-Za-z0-9-]+: ").unwrap();
+    let in_trailers = match message.rsplit_once("\n\n") {
+        Some((_, last_paragraph)) => last_paragraph.lines().all(|line| trailer_re.is_match(line)),
+        None => false,
+    };
+    if in_trailers {
+        format!("{}\n{}\n", message, trailer)
+    } else {
+        format!("{}\n\n{}\n", message, trailer)
+    }
+}
+
+/// Message signed off by current user. Sign-off goes before
+/// commit-msg hook, so hook sees the final message.
+pub fn sign_off_message(path: PathBuf, message: String) -> Result<String, git2::Error> {
+    if message.trim().is_empty() {
+        return Ok(message);
+    }
+    let repo = git2::Repository::open(path)?;
+    let me = repo.signature()?;
+    Ok(sign_off(
+        &message,
+        me.name().unwrap_or_default(),
+        me.email().unwrap_or_default(),
+    ))
+}
+
+#[derive(Debug, Clone)]
+pub enum CommitSigning {
+    // key id. empty means user.signingkey from git config or gpg default key
+    Gpg(String),
+    // path to key file. empty means user.signingkey from git config
+    Ssh(String),
+}
+
+impl CommitSigning {
+    fn sign(&self, repo: &git2::Repository, buffer: &str) -> Result<String, git2::Error> {
+        let configured_key = repo
+            .config()?
+            .get_string("user.signingkey")
+            .unwrap_or_default();
+        let (program, args) = match self {
+            Self::Gpg(key) => {
+                let key = if key.is_empty() {
+                    configured_key
+                } else {
+                    key.clone()
+                };
+                let mut args = vec![String::from("--status-fd=2"), String::from("-bsa")];
+                if !key.is_empty() {
+                    args.push(String::from("-u"));
+                    args.push(key);
+                }
+                ("gpg", args)
+            }
+            Self::Ssh(key) => {
+                let key = if key.is_empty() {
+                    configured_key
+                } else {
+                    key.clone()
+                };
+                if key.is_empty() {
+                    return Err(git2::Error::from_str("Key is required for ssh signing"));
+                }
+                (
+                    "ssh-keygen",
+                    vec![
+                        String::from("-Y"),
+                        String::from("sign"),
+                        String::from("-n"),
+                        String::from("git"),
+                        String::from("-f"),
+                        key,
+                    ],
+                )
+            }
+        };
+        info!("signing commit with {} {:?}", program, args);
+        let mut child = Command::new(program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| git2::Error::from_str(&format!("can't run {}: {}", program, e)))?;
+        child
+            .stdin
+            .take()
+            .expect("no stdin")
+            .write_all(buffer.as_bytes())
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        let output = child
+            .wait_with_output()
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        if !output.status.success() {
+            return Err(git2::Error::from_str(&format!(
+                "Commit signing failed. {} {}\n{}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+// git2 does not sign commits by itself. commit buffer is signed
+// by external program, then HEAD is moved to signed commit by hands
+fn commit_signed(
+    repo: &git2::Repository,
+    signing: &CommitSigning,
+    author: &git2::Signature,
+    me: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+) -> Result<git2::Oid, git2::Error> {
+    let buffer = repo.commit_create_buffer(author, me, message, tree, parents)?;
+    let buffer = buffer
+        .as_str()
+        .ok_or(git2::Error::from_str("commit buffer is not utf8"))?;
+    let signature = signing.sign(repo, buffer)?;
+    let oid = repo.commit_signed(buffer, &signature, None)?;
+    match repo.head() {
+        Ok(mut head) => {
+            head.set_target(oid, "commit (signed)")?;
+        }
+        Err(_) => {
+            // unborn branch
+            let head = repo.find_reference("HEAD")?;
+            let target = head
+                .symbolic_target()
+                .ok_or(git2::Error::from_str("HEAD has no target"))?;
+            repo.reference(target, oid, true, "commit (initial, signed)")?;
+        }
+    }
+    Ok(oid)
+}
+
+// author date as it is shown and edited in amend dialog
+pub const AUTHOR_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+    pub date: DateTime<FixedOffset>,
+}
+
+impl Author {
+    pub fn parse_date(date: &str) -> Result<DateTime<FixedOffset>, git2::Error> {
+        DateTime::parse_from_str(date.trim(), AUTHOR_DATE_FORMAT).map_err(|_| {
+            git2::Error::from_str(&format!(
+                "Wrong date: {}. Expected format is YYYY-MM-DD HH:MM:SS +HHMM",
+                date
+            ))
+        })
+    }
+
+    pub fn signature(&self) -> Result<git2::Signature<'static>, git2::Error> {
+        let time = git2::Time::new(
+            self.date.timestamp(),
+            self.date.offset().local_minus_utc() / 60,
+        );
+        git2::Signature::new(&self.name, &self.email, &time)
+    }
+}
+
+/// Author of HEAD commit, to prefill amend dialog.
+pub fn head_author(path: PathBuf) -> Result<(git2::Oid, Author), git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    let author = commit.author();
+    Ok((
+        commit.id(),
+        Author {
+            name: author.name().unwrap_or_default().to_string(),
+            email: author.email().unwrap_or_default().to_string(),
+            date: git_time_dt(author.when()),
+        },
     ))
 }
 
@@ -169,10 +473,17 @@ pub fn create(
     path: PathBuf,
     message: String,
     amend: bool,
+    author: Option<Author>,
+    signing: Option<CommitSigning>,
     sender: Sender<crate::Event>,
 ) -> Result<(), git2::Error> {
     let repo = git2::Repository::open(path.clone())?;
     let me = repo.signature()?;
+    // other author is possible only when amending
+    let author = match author {
+        Some(author) if amend => author.signature()?,
+        _ => me.clone(),
+    };
     if message.is_empty() {
         return Err(git2::Error::from_str("Commit message is required"));
     }
@@ -180,12 +491,46 @@ pub fn create(
 
     let tree = repo.find_tree(tree_oid)?;
 
-    if let Ok(ob) = repo.revparse_single("HEAD^{commit}") {
+    // merge in progress: commits from MERGE_HEAD are parents too
+    let mut merge_heads = Vec::new();
+    if !amend && repo.state() == git2::RepositoryState::Merge {
+        repo.mergehead_foreach(|oid| {
+            merge_heads.push(*oid);
+            true
+        })?;
+    }
+    let merge_heads = merge_heads
+        .into_iter()
+        .map(|oid| repo.find_commit(oid))
+        .collect::<Result<Vec<git2::Commit>, git2::Error>>()?;
+
+    if let Some(signing) = &signing {
+        if let Ok(ob) = repo.revparse_single("HEAD^{commit}") {
+            let parent_commit = repo.find_commit(ob.id())?;
+            if amend {
+                let parents: Vec<git2::Commit> = parent_commit.parents().collect();
+                let parents: Vec<&git2::Commit> = parents.iter().collect();
+                commit_signed(&repo, signing, &author, &me, &message, &tree, &parents)?;
+            } else {
+                let parents: Vec<&git2::Commit> = std::iter::once(&parent_commit)
+                    .chain(merge_heads.iter())
+                    .collect();
+                commit_signed(&repo, signing, &author, &me, &message, &tree, &parents)?;
+            }
+        } else {
+            commit_signed(&repo, signing, &author, &me, &message, &tree, &[])?;
+        }
+    } else if let Ok(ob) = repo.revparse_single("HEAD^{commit}") {
         let parent_commit = repo.find_commit(ob.id())?;
-        if amend {
+        if !merge_heads.is_empty() {
+            let parents: Vec<&git2::Commit> = std::iter::once(&parent_commit)
+                .chain(merge_heads.iter())
+                .collect();
+            repo.commit(Some("HEAD"), &me, &me, &message, &tree, &parents)?;
+        } else if amend {
             parent_commit.amend(
                 Some("HEAD"),
-                Some(&me),
+                Some(&author),
                 Some(&me),
                 None, // message encoding
                 Some(&message),
@@ -197,12 +542,26 @@ pub fn create(
     } else {
         repo.commit(Some("HEAD"), &me, &me, &message, &tree, &[])?;
     }
+    // final commit of merge/cherry-pick/revert. sequences go on
+    // with continue_cherry_pick
+    if matches!(
+        repo.state(),
+        git2::RepositoryState::Merge
+            | git2::RepositoryState::CherryPick
+            | git2::RepositoryState::Revert
+    ) {
+        repo.cleanup_state()?;
+    }
 
     // update staged changes.
     let ob = repo.revparse_single("HEAD^{tree}")?;
     let current_tree = repo.find_tree(ob.id())?;
-    let git_diff =
-        repo.diff_tree_to_index(Some(&current_tree), None, Some(&mut make_diff_options()))?;
+    let mut git_diff = repo.diff_tree_to_index(
+        Some(&current_tree),
+        None,
+        Some(&mut make_display_diff_options(&repo)),
+    )?;
+    find_renames(&mut git_diff)?;
 
     let diff = make_diff(&git_diff, DiffKind::Staged);
     sender
@@ -220,7 +579,7 @@ pub fn create(
         move || {
             let repo = git2::Repository::open(path).expect("can't open repo");
             let git_diff = repo
-                .diff_index_to_workdir(None, Some(&mut make_diff_options()))
+                .diff_index_to_workdir(None, Some(&mut make_display_diff_options(&repo)))
                 .expect("cant' get diff index to workdir");
             let diff = make_diff(&git_diff, DiffKind::Unstaged);
             sender
@@ -239,6 +598,318 @@ pub fn create(
     Ok(())
 }
 
+// commit is pushed if upstream of current branch contains it
+pub fn is_pushed(path: PathBuf, oid: git2::Oid) -> Result<bool, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Ok(false);
+    }
+    let branch = git2::Branch::wrap(head);
+    if let Ok(upstream) = branch.upstream() {
+        if let Some(upstream_oid) = upstream.get().target() {
+            return Ok(upstream_oid == oid || repo.graph_descendant_of(upstream_oid, oid)?);
+        }
+    }
+    Ok(false)
+}
+
+// refs to check for containment. repos with lots of
+// branches and tags should not slow commit window down
+pub const CONTAINMENT_SCAN_LIMIT: usize = 500;
+
+fn contains(repo: &git2::Repository, tip: git2::Oid, oid: git2::Oid) -> bool {
+    tip == oid || repo.graph_descendant_of(tip, oid).unwrap_or(false)
+}
+
+/// Best common ancestor of a and b, i.e. the commit where
+/// their histories diverged.
+pub fn merge_base(path: PathBuf, a: git2::Oid, b: git2::Oid) -> Result<git2::Oid, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    repo.merge_base(a, b).map_err(|e| {
+        if e.code() == git2::ErrorCode::NotFound {
+            git2::Error::from_str("Histories are unrelated. There is no merge base")
+        } else {
+            e
+        }
+    })
+}
+
+/// Local and remote branches and tags which contain commit.
+/// When scan limit is reached list ends with "…".
+pub fn describe_containment(
+    path: PathBuf,
+    oid: git2::Oid,
+) -> Result<(Vec<String>, Vec<String>), git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let mut branches = Vec::new();
+    for (i, item) in repo.branches(None)?.enumerate() {
+        if i == CONTAINMENT_SCAN_LIMIT {
+            branches.push(String::from("…"));
+            break;
+        }
+        let (branch, _) = item?;
+        let (Some(name), Some(tip)) = (branch.name()?, branch.get().target()) else {
+            continue;
+        };
+        if contains(&repo, tip, oid) {
+            branches.push(name.to_string());
+        }
+    }
+    let mut tags = Vec::new();
+    for (i, reference) in repo.references_glob("refs/tags/*")?.enumerate() {
+        if i == CONTAINMENT_SCAN_LIMIT {
+            tags.push(String::from("…"));
+            break;
+        }
+        let reference = reference?;
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        if contains(&repo, commit.id(), oid) {
+            if let Some(name) = reference.shorthand() {
+                tags.push(name.to_string());
+            }
+        }
+    }
+    Ok((branches, tags))
+}
+
+pub fn get_raw_message(path: PathBuf, oid: git2::Oid) -> Result<String, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let commit = repo.find_commit(oid)?;
+    Ok(commit.message().unwrap_or("").to_string())
+}
+
+// blob and filemode of file in commit tree
+fn blob_at<'a>(
+    repo: &'a git2::Repository,
+    oid: git2::Oid,
+    file_path: &Path,
+) -> Result<(git2::Blob<'a>, i32), git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    let entry = match commit.tree()?.get_path(file_path) {
+        Ok(entry) => entry,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {
+            return Err(git2::Error::from_str(&format!(
+                "{} does not exist at {}",
+                file_path.display(),
+                &oid.to_string()[..7]
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+    let filemode = entry.filemode();
+    let blob = entry
+        .to_object(repo)?
+        .into_blob()
+        .map_err(|_| git2::Error::from_str(&format!("{} is not a file", file_path.display())))?;
+    Ok((blob, filemode))
+}
+
+/// Full content of file as of commit, None for binary file.
+/// Missing file is reported as error.
+pub fn file_at(
+    path: PathBuf,
+    oid: git2::Oid,
+    file_path: PathBuf,
+) -> Result<Option<String>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let (blob, _) = blob_at(&repo, oid, &file_path)?;
+    if blob.is_binary() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+}
+
+/// True if file exists in workdir and its content differs
+/// from one in commit, so restoring will lose changes.
+pub fn restore_overwrites(
+    path: PathBuf,
+    oid: git2::Oid,
+    file_path: PathBuf,
+) -> Result<bool, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let (blob, _) = blob_at(&repo, oid, &file_path)?;
+    let workdir = repo.workdir().ok_or(git2::Error::from_str("bare repo"))?;
+    match fs::read(workdir.join(&file_path)) {
+        Ok(content) => Ok(content != blob.content()),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Writes file as of commit to workdir with its mode from commit
+/// and stages it, e.g. to bring back deleted file.
+pub fn restore_file(
+    path: PathBuf,
+    oid: git2::Oid,
+    file_path: PathBuf,
+    sender: Sender<crate::Event>,
+) -> Result<(), git2::Error> {
+    info!("restore {:?} from {:?}", file_path, oid);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path)?;
+    let (blob, filemode) = blob_at(&repo, oid, &file_path)?;
+    let workdir = repo.workdir().ok_or(git2::Error::from_str("bare repo"))?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    undo::write_blob(&repo, &workdir.join(&file_path), blob.id(), filemode as u32)?;
+    let mut index = repo.index()?;
+    index.add_path(&file_path)?;
+    index.write()
+}
+
+/// Changes message of any commit in current branch.
+/// Commits on top of it are replayed via in memory rebase,
+/// so workdir and index are not touched at all.
+pub fn reword(
+    path: PathBuf,
+    oid: git2::Oid,
+    message: String,
+    sender: Sender<crate::Event>,
+) -> Result<(), git2::Error> {
+    let _updater = DeferRefresh::new(path.clone(), sender, true, true);
+    if message.is_empty() {
+        return Err(git2::Error::from_str("Commit message is required"));
+    }
+    let repo = git2::Repository::open(path)?;
+    let commit = repo.find_commit(oid)?;
+    let mut head_ref = repo.head()?;
+    let head_oid = head_ref.peel_to_commit()?.id();
+    if head_oid != oid && !repo.graph_descendant_of(head_oid, oid)? {
+        return Err(git2::Error::from_str("Commit is not in current branch"));
+    }
+    let parent_oid = commit
+        .parent_id(0)
+        .map_err(|_| git2::Error::from_str("Can't reword root commit"))?;
+
+    // rebase can't replay merges
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(head_oid)?;
+    for parent in commit.parent_ids() {
+        revwalk.hide(parent)?;
+    }
+    for walked in revwalk {
+        if repo.find_commit(walked?)?.parent_count() > 1 {
+            return Err(git2::Error::from_str(
+                "Can't reword commit which is followed by merge commits",
+            ));
+        }
+    }
+
+    let upstream = repo.find_annotated_commit(parent_oid)?;
+    let mut rebase_options = git2::RebaseOptions::new();
+    rebase_options.inmemory(true);
+    let mut rebase = repo.rebase(None, Some(&upstream), None, Some(&mut rebase_options))?;
+    let me = repo.signature()?;
+    let mut new_head = parent_oid;
+    let result = (|| -> Result<(), git2::Error> {
+        while let Some(op) = rebase.next() {
+            let op = op?;
+            let op_message = if op.id() == oid {
+                Some(message.as_str())
+            } else {
+                None
+            };
+            new_head = rebase.commit(None, &me, op_message)?;
+        }
+        Ok(())
+    })();
+    if let Err(error) = result {
+        rebase.abort()?;
+        return Err(error);
+    }
+    rebase.finish(Some(&me))?;
+    info!("reworded {:?}. new head {:?}", oid, new_head);
+    head_ref.set_target(new_head, &format!("reword: {}", oid))?;
+    Ok(())
+}
+
+// commits which could be squashed: all the first parent chain
+// except root, and commits which are not in upstream yet (if any)
+pub fn squash_limits(path: PathBuf) -> Result<(usize, Option<usize>), git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let head = repo.head()?;
+    let mut commit = head.peel_to_commit()?;
+    let mut depth = 0;
+    while let Ok(parent) = commit.parent(0) {
+        depth += 1;
+        commit = parent;
+    }
+    let mut not_pushed = None;
+    if head.is_branch() {
+        let head_oid = head.peel_to_commit()?.id();
+        let branch = git2::Branch::wrap(head);
+        if let Ok(upstream) = branch.upstream() {
+            if let Some(upstream_oid) = upstream.get().target() {
+                let (ahead, _behind) = repo.graph_ahead_behind(head_oid, upstream_oid)?;
+                not_pushed.replace(ahead);
+            }
+        }
+    }
+    Ok((depth, not_pushed))
+}
+
+// last count commits of first parent chain (newest first)
+// and the commit they are based on
+fn squashed<'a>(
+    repo: &'a git2::Repository,
+    count: usize,
+) -> Result<(Vec<git2::Commit<'a>>, git2::Commit<'a>), git2::Error> {
+    let mut commit = repo.head()?.peel_to_commit()?;
+    let mut commits = Vec::new();
+    for _ in 0..count {
+        let parent = commit
+            .parent(0)
+            .map_err(|_| git2::Error::from_str("Not enough commits to squash"))?;
+        commits.push(commit);
+        commit = parent;
+    }
+    Ok((commits, commit))
+}
+
+/// Messages of last count commits (oldest first) for
+/// the new commit. HEAD is not touched.
+pub fn squash_message(path: PathBuf, count: usize) -> Result<String, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let (commits, _) = squashed(&repo, count)?;
+    let messages: Vec<String> = commits
+        .iter()
+        .rev()
+        .map(|commit| commit.message().unwrap_or("").trim_end().to_string())
+        .collect();
+    Ok(messages.join("\n\n"))
+}
+
+/// Soft reset to HEAD~count. Squashed changes stay staged.
+/// Returns HEAD before reset, to get back there if commit fails.
+pub fn squash(
+    path: PathBuf,
+    count: usize,
+    sender: Sender<crate::Event>,
+) -> Result<git2::Oid, git2::Error> {
+    let _updater = DeferRefresh::new(path.clone(), sender, true, true);
+    let repo = git2::Repository::open(path)?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    let (_, base) = squashed(&repo, count)?;
+    info!("squash {} commits. reset to {:?}", count, base.id());
+    repo.reset(base.as_object(), git2::ResetType::Soft, None)?;
+    Ok(head)
+}
+
+/// Soft reset back to HEAD which was before squash.
+pub fn undo_squash(
+    path: PathBuf,
+    head: git2::Oid,
+    sender: Sender<crate::Event>,
+) -> Result<(), git2::Error> {
+    let _updater = DeferRefresh::new(path.clone(), sender, true, true);
+    let repo = git2::Repository::open(path)?;
+    info!("undo squash. reset to {:?}", head);
+    repo.reset(&repo.find_object(head, None)?, git2::ResetType::Soft, None)
+}
+
 pub fn apply(
     path: PathBuf,
     oid: git2::Oid,
@@ -298,6 +969,165 @@ pub fn apply(
     Ok(())
 }
 
+// git cherry-pick/revert A..B keeps commits to go in sequencer dir.
+// commit which stopped on conflict is the first one in todo
+const SEQUENCER_TODO: &str = "sequencer/todo";
+const SEQUENCER_HEAD: &str = "sequencer/head";
+
+// (revert, oid) from "pick <sha> <subject>" and "revert <sha> <subject>" lines
+fn read_todo(repo: &git2::Repository) -> Result<Vec<(bool, git2::Oid)>, git2::Error> {
+    let content = fs::read_to_string(repo.path().join(SEQUENCER_TODO))
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    let mut todo = Vec::new();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let revert = match parts.next() {
+            Some("pick" | "p") => false,
+            Some("revert") => true,
+            _ => continue,
+        };
+        if let Some(sha) = parts.next() {
+            todo.push((revert, repo.revparse_single(sha)?.peel_to_commit()?.id()));
+        }
+    }
+    Ok(todo)
+}
+
+fn write_todo(repo: &git2::Repository, todo: &[(bool, git2::Oid)]) -> Result<(), git2::Error> {
+    let mut content = String::new();
+    for (revert, oid) in todo {
+        let summary = repo.find_commit(*oid)?.summary().unwrap_or("").to_string();
+        let command = if *revert { "revert" } else { "pick" };
+        content.push_str(&format!("{} {} {}\n", command, oid, summary));
+    }
+    fs::write(repo.path().join(SEQUENCER_TODO), content)
+        .map_err(|e| git2::Error::from_str(&e.to_string()))
+}
+
+// cherry-picked commit keeps its author. sequencer dir is kept,
+// only state of this step is removed
+fn commit_sequence_step(
+    repo: &git2::Repository,
+    picked: &git2::Commit,
+    revert: bool,
+) -> Result<(), git2::Error> {
+    let me = repo.signature()?;
+    let author = if revert { me.clone() } else { picked.author() };
+    // message prepared by libgit2 or by git, perhaps edited by user
+    let message = repo
+        .message()
+        .unwrap_or_else(|_| picked.message().unwrap_or("").to_string());
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.commit(Some("HEAD"), &author, &me, &message, &tree, &[&head])?;
+    for name in [CHERRY_PICK_HEAD, REVERT_HEAD, "MERGE_MSG"] {
+        let file = repo.path().join(name);
+        if file.exists() {
+            fs::remove_file(file).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Commits resolved step of cherry-pick/revert sequence and goes on
+/// with the rest of todo, until next conflict or the end of sequence.
+pub fn continue_cherry_pick(
+    path: PathBuf,
+    sender: Sender<crate::Event>,
+) -> Result<(), git2::Error> {
+    info!("continue cherry-pick sequence");
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path)?;
+    if repo.index()?.has_conflicts() {
+        return Err(git2::Error::from_str("Resolve conflicts first"));
+    }
+    let mut todo = read_todo(&repo)?;
+    for (head_file, revert) in [(CHERRY_PICK_HEAD, false), (REVERT_HEAD, true)] {
+        let Ok(sha) = fs::read_to_string(repo.path().join(head_file)) else {
+            continue;
+        };
+        let picked = repo.find_commit(git2::Oid::from_str(sha.trim())?)?;
+        commit_sequence_step(&repo, &picked, revert)?;
+        if todo.first().map(|(_, oid)| *oid) == Some(picked.id()) {
+            todo.remove(0);
+        }
+    }
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("can send through channel");
+    while let Some((revert, oid)) = todo.first().copied() {
+        let picked = repo.find_commit(oid)?;
+        if revert {
+            repo.revert(&picked, None)?;
+        } else {
+            repo.cherrypick(&picked, None)?;
+        }
+        if repo.index()?.has_conflicts() {
+            return write_todo(&repo, &todo);
+        }
+        commit_sequence_step(&repo, &picked, revert)?;
+        todo.remove(0);
+    }
+    repo.cleanup_state()
+}
+
+/// Returns to commit the sequence was started on.
+pub fn abort_cherry_pick(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
+    info!("abort cherry-pick sequence");
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path)?;
+    let start = fs::read_to_string(repo.path().join(SEQUENCER_HEAD))
+        .ok()
+        .and_then(|sha| git2::Oid::from_str(sha.trim()).ok());
+    let target = match start {
+        Some(oid) => repo.find_object(oid, Some(git2::ObjectType::Commit))?,
+        None => repo.head()?.peel(git2::ObjectType::Commit)?,
+    };
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("can send through channel");
+    repo.reset(&target, git2::ResetType::Hard, None)?;
+    repo.cleanup_state()
+}
+
+// dry run for cherry-pick/revert/stash apply.
+// everything happens in memory index against HEAD, workdir is not touched.
+// returns paths which will be conflicted.
+// mainline is required for merge commits (stash is a merge commit too)
+pub fn apply_op_preview(
+    path: PathBuf,
+    oid: git2::Oid,
+    revert: bool,
+    mainline: u32,
+    file_path: Option<PathBuf>,
+) -> Result<Vec<PathBuf>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let commit = repo.find_commit(oid)?;
+    let our_commit = repo.head()?.peel_to_commit()?;
+    let memory_index = if revert {
+        repo.revert_commit(&commit, &our_commit, mainline, None)?
+    } else {
+        repo.cherrypick_commit(&commit, &our_commit, mainline, None)?
+    };
+    let mut result = Vec::new();
+    if !memory_index.has_conflicts() {
+        return Ok(result);
+    }
+    for conflict in memory_index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            let conflict_path = PathBuf::from(String::from_utf8_lossy(&entry.path).to_string());
+            if let Some(file_path) = &file_path {
+                if file_path != &conflict_path {
+                    continue;
+                }
+            }
+            result.push(conflict_path);
+        }
+    }
+    Ok(result)
+}
+
 pub fn from_short_sha(path: PathBuf, short_sha: String) -> Result<git2::Oid> {
     let repo = git2::Repository::open(path.clone())?;
     let object = repo.revparse_single(&short_sha)?;
@@ -372,3 +1202,171 @@ pub fn partial_apply(
     repo.apply(&git_diff, git2::ApplyLocation::WorkDir, Some(&mut options))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{commit_files, temp_repo};
+    use std::os::unix::fs::PermissionsExt;
+
+    // picking of first, second commits onto diverged HEAD
+    // is stopped on conflict in first one, as git cherry-pick A..B does
+    fn conflicted_sequence(name: &str) -> (PathBuf, git2::Oid, git2::Oid, git2::Oid) {
+        let (path, repo) = temp_repo(name);
+        let base = commit_files(&repo, &[("a.txt", "base\n")], "base");
+        let first = commit_files(&repo, &[("a.txt", "first\n")], "first");
+        let second = commit_files(&repo, &[("b.txt", "second\n")], "second");
+        let base = repo.find_object(base, None).unwrap();
+        repo.reset(&base, git2::ResetType::Hard, None).unwrap();
+        let start = commit_files(&repo, &[("a.txt", "diverged\n")], "diverged");
+
+        fs::create_dir_all(repo.path().join("sequencer")).unwrap();
+        fs::write(repo.path().join(SEQUENCER_HEAD), format!("{}\n", start)).unwrap();
+        write_todo(&repo, &[(false, first), (false, second)]).unwrap();
+        repo.cherrypick(&repo.find_commit(first).unwrap(), None)
+            .unwrap();
+        assert!(repo.index().unwrap().has_conflicts());
+        (path, start, first, second)
+    }
+
+    #[test]
+    fn test_continue_cherry_pick() {
+        let (path, start, first, second) = conflicted_sequence("continue-cherry-pick");
+        let (sender, _receiver) = async_channel::unbounded();
+        assert!(continue_cherry_pick(path.clone(), sender.clone()).is_err());
+
+        fs::write(path.join("a.txt"), "resolved\n").unwrap();
+        let repo = git2::Repository::open(&path).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        continue_cherry_pick(path.clone(), sender).unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let second = repo.find_commit(second).unwrap();
+        assert_eq!(head.message(), second.message());
+        assert_eq!(head.author().name(), second.author().name());
+        let picked = head.parent(0).unwrap();
+        assert_eq!(picked.message(), repo.find_commit(first).unwrap().message());
+        assert_eq!(picked.parent_id(0).unwrap(), start);
+        assert_eq!(
+            fs::read_to_string(path.join("a.txt")).unwrap(),
+            "resolved\n"
+        );
+        assert_eq!(fs::read_to_string(path.join("b.txt")).unwrap(), "second\n");
+        assert!(!repo.path().join(CHERRY_PICK_HEAD).exists());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_restore_file() {
+        let (path, repo) = temp_repo("restore-file");
+        commit_files(&repo, &[("run.sh", "echo\n")], "script");
+        fs::set_permissions(path.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("run.sh")).unwrap();
+        index.write().unwrap();
+        let oid = commit_files(&repo, &[], "executable");
+        fs::remove_file(path.join("run.sh")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("run.sh")).unwrap();
+        index.write().unwrap();
+        commit_files(&repo, &[], "deleted");
+
+        let (sender, _receiver) = async_channel::unbounded();
+        restore_file(path.clone(), oid, PathBuf::from("run.sh"), sender).unwrap();
+        let metadata = fs::metadata(path.join("run.sh")).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o755);
+        let mut index = repo.index().unwrap();
+        index.read(true).unwrap();
+        assert!(index.get_path(Path::new("run.sh"), 0).is_some());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_ticket_from_branch() {
+        assert_eq!(
+            ticket_from_branch("feature/PROJ-123-fix", r"[A-Z]+-\d+"),
+            Some(String::from("PROJ-123"))
+        );
+        assert_eq!(
+            ticket_from_branch("feature/42-fix", r"feature/(\d+)"),
+            Some(String::from("42"))
+        );
+        assert_eq!(ticket_from_branch("master", r"[A-Z]+-\d+"), None);
+        assert_eq!(ticket_from_branch("feature/PROJ-123", ""), None);
+        assert_eq!(ticket_from_branch("feature/PROJ-123", "("), None);
+    }
+
+    #[test]
+    fn test_commit_template() {
+        let (path, repo) = temp_repo("commit-template");
+        fs::write(path.join("tpl.txt"), "from config\n# comment\n\nbody\n").unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("commit.template", "tpl.txt")
+            .unwrap();
+        assert_eq!(
+            get_commit_template(path.clone(), String::new()).unwrap(),
+            Some(String::from("from config\n\nbody"))
+        );
+        // configured in app goes first
+        fs::write(path.join("custom.txt"), "custom\n").unwrap();
+        assert_eq!(
+            get_commit_template(path.clone(), String::from("custom.txt")).unwrap(),
+            Some(String::from("custom"))
+        );
+        fs::write(path.join("custom.txt"), "# only comments\n").unwrap();
+        assert_eq!(
+            get_commit_template(path.clone(), String::from("custom.txt")).unwrap(),
+            None
+        );
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_undo_squash() {
+        let (path, repo) = temp_repo("undo-squash");
+        commit_files(&repo, &[("a.txt", "a\n")], "base");
+        commit_files(&repo, &[("a.txt", "b\n")], "first");
+        let head = commit_files(&repo, &[("a.txt", "c\n")], "second");
+        let (sender, _receiver) = async_channel::unbounded();
+
+        assert_eq!(squash(path.clone(), 2, sender.clone()).unwrap(), head);
+        assert_eq!(
+            repo.head().unwrap().peel_to_commit().unwrap().message(),
+            Some("base")
+        );
+        undo_squash(path.clone(), head, sender).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+        let mut index = repo.index().unwrap();
+        index.read(true).unwrap();
+        let diff = repo
+            .diff_tree_to_index(
+                Some(&repo.find_commit(head).unwrap().tree().unwrap()),
+                Some(&index),
+                None,
+            )
+            .unwrap();
+        assert_eq!(diff.deltas().len(), 0);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_abort_cherry_pick() {
+        let (path, start, _first, _second) = conflicted_sequence("abort-cherry-pick");
+        let (sender, _receiver) = async_channel::unbounded();
+        abort_cherry_pick(path.clone(), sender).unwrap();
+
+        let repo = git2::Repository::open(&path).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(start));
+        assert!(!repo.index().unwrap().has_conflicts());
+        assert_eq!(
+            fs::read_to_string(path.join("a.txt")).unwrap(),
+            "diverged\n"
+        );
+        assert!(!path.join("b.txt").exists());
+        assert!(!repo.path().join(CHERRY_PICK_HEAD).exists());
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -254,6 +254,7 @@ pub fn choose_conflict_side_of_hunk(
                     bytes.write_all(SPACE.as_bytes())?;
                 }
             }
+            LineKind::NoNewlineAtEof => {}
             _ => {
                 bytes.write_all(MINUS.as_bytes())?;
             }
@@ -263,3 +264,40 @@ pub fn choose_conflict_side_of_hunk(
     }
     Ok(())
 }
+
+/// Resolves whole file by taking ours or theirs version of it from
+/// conflict@index. File is written to workdir and staged, so it is
+/// no longer conflicted. Missing side means file was deleted on that side.
+pub fn choose_conflict_side_of_file(
+    repo: &git2::Repository,
+    file_path: &path::Path,
+    ours: bool,
+) -> Result<()> {
+    let mut index = repo.index()?;
+    let mut side: Option<Option<git2::IndexEntry>> = None;
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let entry_path = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .map(|entry| path::PathBuf::from(String::from_utf8_lossy(&entry.path).to_string()))
+            .next();
+        if entry_path.as_deref() == Some(file_path) {
+            side.replace(if ours { conflict.our } else { conflict.their });
+            break;
+        }
+    }
+    let side = side.context("file is not conflicted")?;
+    let abs_file_path = repo.workdir().context("no workdir")?.join(file_path);
+    debug!("choose side of file {:?} ours {:?}", abs_file_path, ours);
+    index.remove_path(file_path)?;
+    if let Some(entry) = side {
+        let blob = repo.find_blob(entry.id)?;
+        fs::write(&abs_file_path, blob.content())?;
+        index.add_path(file_path)?;
+    } else if abs_file_path.exists() {
+        fs::remove_file(&abs_file_path)?;
+    }
+    index.write()?;
+    Ok(())
+}
@@ -0,0 +1,110 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Credentials cache for http remotes, keyed by remote url.
+//! Login is stored in repo config as credential.<url>.username
+//! (same key git uses itself). Password goes only to system
+//! secret service (via secret-tool) and only if user asked for it.
+use crate::LoginPassword;
+use git2;
+use log::{debug, info};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const SECRET_TOOL: &str = "secret-tool";
+
+fn username_key(url: &str) -> String {
+    format!("credential.{}.username", url)
+}
+
+fn secret_attributes(url: &str) -> [&str; 4] {
+    ["application", "stage", "url", url]
+}
+
+fn lookup_password(url: &str) -> Option<String> {
+    let output = Command::new(SECRET_TOOL)
+        .arg("lookup")
+        .args(secret_attributes(url))
+        .output()
+        .inspect_err(|e| debug!("cant run {} {:?}", SECRET_TOOL, e))
+        .ok()?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn store_password(url: &str, password: &str) -> Result<(), String> {
+    let mut child = Command::new(SECRET_TOOL)
+        .arg("store")
+        .arg(format!("--label=Stage: {}", url))
+        .args(secret_attributes(url))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("can't run {}: {}", SECRET_TOOL, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(password.as_bytes())
+            .map_err(|e| format!("can't pass password to {}: {}", SECRET_TOOL, e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("{} failed: {}", SECRET_TOOL, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            SECRET_TOOL,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn clear_password(url: &str) {
+    let result = Command::new(SECRET_TOOL)
+        .arg("clear")
+        .args(secret_attributes(url))
+        .output();
+    debug!("clear password for {} {:?}", url, result.map(|o| o.status));
+}
+
+/// Cached login for url, if any. Password is filled
+/// only when it was saved to the keyring.
+pub fn get(repo: &git2::Repository, url: &str) -> Option<LoginPassword> {
+    let config = repo.config().ok()?;
+    let login = config.get_string(&username_key(url)).ok()?;
+    let password = lookup_password(url).unwrap_or_default();
+    Some(LoginPassword {
+        login,
+        password,
+        ..LoginPassword::default()
+    })
+}
+
+/// Saves credentials according to the choices made in auth dialog.
+pub fn store(repo: &git2::Repository, url: &str, login_pass: &LoginPassword) -> Result<(), String> {
+    if !login_pass.remember && !login_pass.save_password {
+        return Ok(());
+    }
+    info!("remember credentials for {}", url);
+    let mut config = repo.config().map_err(|e| e.message().to_string())?;
+    config
+        .set_str(&username_key(url), &login_pass.login)
+        .map_err(|e| e.message().to_string())?;
+    if login_pass.save_password {
+        store_password(url, &login_pass.password)?;
+    }
+    Ok(())
+}
+
+pub fn forget(repo: &git2::Repository, url: &str) {
+    info!("forget credentials for {}", url);
+    if let Ok(mut config) = repo.config() {
+        // key could be absent
+        let _ = config.remove(&username_key(url));
+    }
+    clear_password(url);
+}
@@ -0,0 +1,103 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `git format-patch` for mailing lists: one mbox file per commit,
+//! subjects numbered as [PATCH n/m], diffstat after `---`.
+//! Email text itself is produced by libgit2, same as git does.
+use crate::git::make_diff_options;
+use git2::{Email, EmailCreateOptions, Error, Oid, Repository, Sort};
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// git cuts file names of patches at this length
+const MAX_SLUG_LEN: usize = 52;
+
+// "Fix: the thing!" -> "Fix-the-thing"
+fn slug(summary: &str) -> String {
+    let mut result = String::new();
+    for c in summary.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            result.push(c);
+        } else if !result.is_empty() && !result.ends_with('-') {
+            result.push('-');
+        }
+        if result.len() >= MAX_SLUG_LEN {
+            break;
+        }
+    }
+    result.trim_end_matches(['-', '.']).to_string()
+}
+
+// from first (inclusive) to last, oldest first. merges are skipped,
+// like git format-patch does
+fn commit_range(repo: &Repository, first: Oid, last: Oid) -> Result<Vec<Oid>, Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push(last)?;
+    for parent in repo.find_commit(first)?.parent_ids() {
+        revwalk.hide(parent)?;
+    }
+    let mut result = Vec::new();
+    for oid in revwalk {
+        let oid = oid?;
+        if repo.find_commit(oid)?.parent_count() <= 1 {
+            result.push(oid);
+        }
+    }
+    Ok(result)
+}
+
+/// Writes 0001-<subject>.patch, 0002-... into dir for commits
+/// from first up to last. Returns written files.
+pub fn format_patch(
+    path: PathBuf,
+    first: Oid,
+    last: Oid,
+    dir: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    info!("format patch {}..{} to {:?}", first, last, dir);
+    let repo = Repository::open(path)?;
+    let oids = commit_range(&repo, first, last)?;
+    let mut written = Vec::new();
+    for (i, oid) in oids.iter().enumerate() {
+        let commit = repo.find_commit(*oid)?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&tree),
+            Some(&mut make_diff_options()),
+        )?;
+        let summary = commit.summary().unwrap_or("");
+        let email = Email::from_diff(
+            &diff,
+            i + 1,
+            oids.len(),
+            oid,
+            summary,
+            commit.body().unwrap_or(""),
+            &commit.author(),
+            &mut EmailCreateOptions::default(),
+        )?;
+        let file_path = dir.join(format!("{:04}-{}.patch", i + 1, slug(summary)));
+        fs::write(&file_path, email.as_slice()).map_err(|e| Error::from_str(&e.to_string()))?;
+        written.push(file_path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Fix: the thing!"), "Fix-the-thing");
+        assert_eq!(slug("Bump version to 1.2.3."), "Bump-version-to-1.2.3");
+        assert_eq!(slug("   "), "");
+    }
+}
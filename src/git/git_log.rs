@@ -2,12 +2,16 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::git::commit::{CommitLog, CommitRelation, CommitRepr};
+use crate::git::commit::{git_time_dt, CommitLog, CommitRelation, CommitRepr};
+use chrono::{DateTime, FixedOffset};
 use log::trace;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 pub const COMMIT_PAGE_SIZE: usize = 500;
+// search returns matched commits by small pages,
+// cause matches could be far away in history
+pub const SEARCH_PAGE_SIZE: usize = 50;
 
 pub fn revwalk(
     path: PathBuf,
@@ -16,6 +20,8 @@ pub fn revwalk(
 ) -> Result<Vec<CommitLog>, git2::Error> {
     let repo = git2::Repository::open(path.clone())?;
     let mut revwalk = repo.revwalk()?;
+    // graph requires children to go before parents
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
     if let Some(oid) = start {
         revwalk.push(oid)?;
     } else {
@@ -24,10 +30,11 @@ pub fn revwalk(
 
     let limit = {
         if search_term.is_some() {
+            // start commit is already in list (it matched previous page)
             if start.is_some() {
-                2
+                SEARCH_PAGE_SIZE + 1
             } else {
-                1
+                SEARCH_PAGE_SIZE
             }
         } else {
             COMMIT_PAGE_SIZE
@@ -82,7 +89,11 @@ pub fn revwalk(
                                     right_commits
                                         .insert(right, commit.message().unwrap_or("").to_string());
                                 }
-                                return Some((None, (left_commits.clone(), right_commits.clone())));
+                                // merge commits are shown too, to see fork/join in graph
+                                return Some((
+                                    Some(commit),
+                                    (left_commits.clone(), right_commits.clone()),
+                                ));
                             }
                             _ => {
                                 panic!("got nor 1 nor 2 parents !!!!!!!!!!!! {:?}", commit);
@@ -95,12 +106,13 @@ pub fn revwalk(
         )
         .filter_map(|(commit, (left_commits, right_commits))| {
             if let Some(commit) = commit {
-                // search by oid and author
+                // search by message, oid and author name/email
                 if let Some(ref term) = search_term {
                     let mut found = false;
                     for el in [
                         commit.message().unwrap_or("").to_lowercase(),
                         commit.author().name().unwrap_or("").to_lowercase(),
+                        commit.author().email().unwrap_or("").to_lowercase(),
                         commit.id().to_string(),
                     ] {
                         if el.contains(term) {
@@ -129,3 +141,154 @@ pub fn revwalk(
         .collect::<Vec<CommitLog>>();
     Ok(commits)
 }
+
+#[derive(Debug, Clone)]
+pub struct ReflogEntry {
+    pub num: usize,
+    pub old_oid: git2::Oid,
+    pub new_oid: git2::Oid,
+    pub message: String,
+    pub committer: String,
+    pub dt: DateTime<FixedOffset>,
+}
+
+/// HEAD reflog, newest first. Entries are numbered like HEAD@{n}.
+pub fn reflog(path: PathBuf) -> Result<Vec<ReflogEntry>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let reflog = repo.reflog("HEAD")?;
+    let entries = reflog
+        .iter()
+        .enumerate()
+        .map(|(num, entry)| {
+            let committer = entry.committer();
+            ReflogEntry {
+                num,
+                old_oid: entry.id_old(),
+                new_oid: entry.id_new(),
+                message: entry.message().unwrap_or("").to_string(),
+                committer: committer.name().unwrap_or("").to_string(),
+                dt: git_time_dt(committer.when()),
+            }
+        })
+        .collect();
+    Ok(entries)
+}
+
+// one row of commit graph. lanes keep oids which are expected
+// in next rows (1 lane - 1 column).
+// ● - commit, ◉ - merge commit, │ - lane passes by,
+// ╮╭ - new lane is forked by merge, ┤├ - merge goes to existing lane,
+// ╯╰ - lane joins commit (it was parent for several children),
+// ┴┬ - join/fork crossed by line to the farther one
+pub fn graph_row(
+    lanes: &mut Vec<Option<git2::Oid>>,
+    oid: git2::Oid,
+    parents: &[git2::Oid],
+) -> String {
+    let col = match lanes.iter().position(|lane| *lane == Some(oid)) {
+        Some(col) => col,
+        None => match lanes.iter().position(|lane| lane.is_none()) {
+            Some(col) => col,
+            None => {
+                lanes.push(None);
+                lanes.len() - 1
+            }
+        },
+    };
+    let mut cells: Vec<char> = lanes
+        .iter()
+        .map(|lane| if lane.is_some() { '│' } else { ' ' })
+        .collect();
+    // positions connected to commit by horizontal line
+    let mut connected: Vec<usize> = Vec::new();
+
+    for (i, lane) in lanes.iter_mut().enumerate() {
+        if i != col && *lane == Some(oid) {
+            *lane = None;
+            cells[i] = if i > col { '╯' } else { '╰' };
+            connected.push(i);
+        }
+    }
+    cells[col] = if parents.len() > 1 { '◉' } else { '●' };
+    lanes[col] = parents.first().copied();
+
+    for parent in parents.iter().skip(1) {
+        if let Some(i) = lanes.iter().position(|lane| *lane == Some(*parent)) {
+            if i != col {
+                cells[i] = if i > col { '┤' } else { '├' };
+                connected.push(i);
+            }
+            continue;
+        }
+        let i = match lanes.iter().position(|lane| lane.is_none()) {
+            Some(i) => i,
+            None => {
+                lanes.push(None);
+                cells.push(' ');
+                lanes.len() - 1
+            }
+        };
+        lanes[i] = Some(*parent);
+        cells[i] = if i > col { '╮' } else { '╭' };
+        connected.push(i);
+    }
+    for i in &connected {
+        let (from, to) = if *i > col {
+            (col + 1, *i)
+        } else {
+            (*i + 1, col)
+        };
+        for cell in cells.iter_mut().take(to).skip(from) {
+            *cell = match *cell {
+                ' ' => '─',
+                '│' => '┼',
+                c => c,
+            };
+        }
+    }
+    // joins/forks which are crossed by horizontal line to the farther ones
+    let leftmost = connected.iter().min().copied().unwrap_or(col).min(col);
+    let rightmost = connected.iter().max().copied().unwrap_or(col).max(col);
+    for (i, cell) in cells.iter_mut().enumerate() {
+        if i > leftmost && i < rightmost && i != col {
+            *cell = match *cell {
+                '╯' | '╰' => '┴',
+                '╮' | '╭' => '┬',
+                c => c,
+            };
+        }
+    }
+    while let Some(None) = lanes.last() {
+        lanes.pop();
+    }
+
+    let mut row = String::new();
+    for (k, cell) in cells.iter().enumerate() {
+        row.push(*cell);
+        let bridged = connected.iter().any(|i| {
+            let (from, to) = if *i > col { (col, *i) } else { (*i, col) };
+            from <= k && k < to
+        });
+        row.push(if bridged { '─' } else { ' ' });
+    }
+    row.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_row() {
+        let oid = |n: u8| git2::Oid::from_bytes(&[n; 20]).unwrap();
+        let (merge, ours, theirs, base) = (oid(1), oid(2), oid(3), oid(4));
+        let mut lanes = Vec::new();
+        // merge forks lane for its second parent
+        // and both lanes join on common base
+        assert_eq!(graph_row(&mut lanes, merge, &[ours, theirs]), "◉─╮");
+        assert_eq!(graph_row(&mut lanes, ours, &[base]), "● │");
+        assert_eq!(graph_row(&mut lanes, theirs, &[base]), "│ ●");
+        assert_eq!(graph_row(&mut lanes, base, &[]), "●─╯");
+        assert!(lanes.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::git::repo_settings;
+use async_channel::Sender;
+use git2::{Oid, Repository};
+use gtk4::gio;
+use log::info;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+pub const PRE_COMMIT: &str = "pre-commit";
+pub const COMMIT_MSG: &str = "commit-msg";
+pub const COMMIT_EDITMSG: &str = "COMMIT_EDITMSG";
+pub const POST_CHECKOUT: &str = "post-checkout";
+pub const POST_MERGE: &str = "post-merge";
+
+// native repo hooks could be disabled in settings (like --no-verify)
+pub fn are_hooks_enabled(repo: &Repository) -> bool {
+    repo_settings::applied(repo).run_hooks
+}
+
+fn output_text(output: &Output) -> String {
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.is_empty() {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&stderr);
+    }
+    text
+}
+
+fn failure(name: &str, output: &Output) -> String {
+    format!(
+        "{} failed ({})\n\n{}",
+        html_escape::encode_text(name),
+        output.status,
+        html_escape::encode_text(&output_text(output))
+    )
+}
+
+fn hooks_dir(repo: &Repository) -> PathBuf {
+    if let Ok(config) = repo.config() {
+        if let Ok(hooks_path) = config.get_path("core.hooksPath") {
+            if hooks_path.is_absolute() {
+                return hooks_path;
+            }
+            if let Some(workdir) = repo.workdir() {
+                return workdir.join(hooks_path);
+            }
+        }
+    }
+    repo.path().join("hooks")
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Runs git hook (e.g. pre-commit) from repo hooks dir, if it is present.
+/// Err contains hook output.
+pub fn run_hook(path: PathBuf, name: &str, args: &[&str]) -> Result<(), String> {
+    let repo = Repository::open(path).map_err(|e| e.message().to_string())?;
+    if !are_hooks_enabled(&repo) {
+        return Ok(());
+    }
+    let hook = hooks_dir(&repo).join(name);
+    if !is_executable(&hook) {
+        return Ok(());
+    }
+    let workdir = repo.workdir().unwrap_or(repo.path());
+    info!("running hook {:?}", hook);
+    let mut command = Command::new(&hook);
+    command.args(args).current_dir(workdir);
+    if name == PRE_COMMIT || name == COMMIT_MSG {
+        // same env as git commit gives to its hooks. there is no tty:
+        // stdin is closed, so hooks asking user for input fail
+        // instead of hanging. commit object is not created yet,
+        // hooks see staged state via index.
+        command
+            .env("GIT_INDEX_FILE", repo.path().join("index"))
+            .env("GIT_EDITOR", ":");
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("can't run {}: {}", name, e))?;
+    if !output.status.success() {
+        return Err(failure(name, &output));
+    }
+    Ok(())
+}
+
+/// Runs user command via shell in repo root.
+/// Err contains command output.
+pub fn run_command(path: PathBuf, command: &str) -> Result<(), String> {
+    let repo = Repository::open(path).map_err(|e| e.message().to_string())?;
+    let workdir = repo.workdir().unwrap_or(repo.path());
+    info!("running command {:?}", command);
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(workdir)
+        .output()
+        .map_err(|e| format!("can't run {}: {}", command, e))?;
+    if !output.status.success() {
+        return Err(failure(command, &output));
+    }
+    Ok(())
+}
+
+/// User configured command goes first, then
+/// native pre-commit hook of the repo.
+pub fn pre_commit(path: PathBuf, command: String) -> Result<(), String> {
+    if !command.trim().is_empty() {
+        run_command(path.clone(), command.trim())?;
+    }
+    run_hook(path, PRE_COMMIT, &[])
+}
+
+/// Runs commit-msg hook with message file.
+/// Hook is allowed to rewrite the message, so the result
+/// is the message read back from file.
+pub fn commit_msg(path: PathBuf, message: String) -> Result<String, String> {
+    let repo = Repository::open(path.clone()).map_err(|e| e.message().to_string())?;
+    if !are_hooks_enabled(&repo) || !is_executable(&hooks_dir(&repo).join(COMMIT_MSG)) {
+        return Ok(message);
+    }
+    let message_file = repo.path().join(COMMIT_EDITMSG);
+    fs::write(&message_file, &message)
+        .map_err(|e| format!("can't write {:?}: {}", message_file, e))?;
+    run_hook(path, COMMIT_MSG, &[&message_file.to_string_lossy()])?;
+    fs::read_to_string(&message_file).map_err(|e| format!("can't read {:?}: {}", message_file, e))
+}
+
+/// Runs hook in background, without waiting for it.
+/// Failure is reported via toast. Status is refreshed after hook,
+/// cause it could modify files (e.g. LFS smudge in post-checkout)
+/// when monitors are still locked by the op.
+pub fn run_hook_detached(
+    path: PathBuf,
+    name: &'static str,
+    args: Vec<String>,
+    sender: Sender<crate::Event>,
+) {
+    gio::spawn_blocking(move || {
+        let Ok(repo) = Repository::open(path.clone()) else {
+            return;
+        };
+        if !are_hooks_enabled(&repo) || !is_executable(&hooks_dir(&repo).join(name)) {
+            return;
+        }
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let result = run_hook(path, name, &args);
+        sender
+            .send_blocking(crate::Event::Refresh)
+            .expect("cant send through channel");
+        if let Err(output) = result {
+            let last_line = output
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .last()
+                .unwrap_or_default()
+                .to_string();
+            sender
+                .send_blocking(crate::Event::Toast(format!(
+                    "{} hook failed: {}",
+                    name, last_line
+                )))
+                .expect("cant send through channel");
+        }
+    });
+}
+
+pub fn post_checkout(path: PathBuf, prev: Oid, new: Oid, sender: Sender<crate::Event>) {
+    // last arg 1 means branch checkout (not file checkout)
+    run_hook_detached(
+        path,
+        POST_CHECKOUT,
+        vec![prev.to_string(), new.to_string(), String::from("1")],
+        sender,
+    );
+}
+
+pub fn post_merge(path: PathBuf, squash: bool, sender: Sender<crate::Event>) {
+    let squash = if squash { "1" } else { "0" };
+    run_hook_detached(path, POST_MERGE, vec![String::from(squash)], sender);
+}
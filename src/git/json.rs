@@ -0,0 +1,241 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! JSON export of staged/unstaged changes for external tools.
+//!
+//! The format is stable. New keys could be added, but existing
+//! ones will not be renamed or removed:
+//!
+//! ```text
+//! {
+//!   "staged": Diff | null,
+//!   "unstaged": Diff | null
+//! }
+//! Diff = {"kind": Kind, "files": [File]}
+//! Kind = "staged" | "unstaged" | "conflicted" | "untracked" | "commit"
+//! File = {"path": string, "status": Status, "hunks": [Hunk]}
+//! Status = "added" | "deleted" | "modified" | "renamed" | "copied"
+//!        | "conflicted" | "untracked" | "typechange" | "unmodified"
+//! Hunk = {
+//!   "header": string,
+//!   "old_start": number, "old_lines": number,
+//!   "new_start": number, "new_lines": number,
+//!   "lines": [Line]
+//! }
+//! Line = {
+//!   "origin": Origin,
+//!   "old_line_no": number | null,
+//!   "new_line_no": number | null,
+//!   "content": string
+//! }
+//! Origin = " " (context) | "+" (added) | "-" (deleted)
+//!        | "=" | ">" | "<" (no newline at end of file markers, as in git2)
+//! ```
+//!
+//! - diff is null when there are no changes of that kind.
+//! - path is relative to repo workdir. For renamed files it is new path.
+//! - header is hunk header as git prints it, e.g. "@@ -1,3 +1,4 @@ fn main".
+//!   File mode changes have no hunks in export.
+//! - line numbers are 1-based. Added lines have no old_line_no,
+//!   deleted ones have no new_line_no.
+//! - content is line text without trailing newline.
+use crate::git::{make_diff, make_diff_options, Diff, DiffKind, File, Hunk, HunkLineNo, Line};
+use git2::{Delta, DiffLineType, Error, Repository};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn line_no(no: Option<HunkLineNo>) -> String {
+    match no {
+        Some(no) => no.to_string(),
+        None => String::from("null"),
+    }
+}
+
+fn origin(origin: DiffLineType) -> &'static str {
+    match origin {
+        DiffLineType::Context => " ",
+        DiffLineType::Addition => "+",
+        DiffLineType::Deletion => "-",
+        DiffLineType::ContextEOFNL => "=",
+        DiffLineType::AddEOFNL => ">",
+        DiffLineType::DeleteEOFNL => "<",
+        DiffLineType::FileHeader => "F",
+        DiffLineType::HunkHeader => "H",
+        DiffLineType::Binary => "B",
+    }
+}
+
+fn status(delta: Delta) -> &'static str {
+    match delta {
+        Delta::Added => "added",
+        Delta::Deleted => "deleted",
+        Delta::Modified => "modified",
+        Delta::Renamed => "renamed",
+        Delta::Copied => "copied",
+        Delta::Conflicted => "conflicted",
+        Delta::Untracked => "untracked",
+        Delta::Typechange => "typechange",
+        _ => "unmodified",
+    }
+}
+
+impl Line {
+    pub fn to_json(&self, hunk: &Hunk) -> String {
+        format!(
+            "{{\"origin\":{},\"old_line_no\":{},\"new_line_no\":{},\"content\":{}}}",
+            escape(origin(self.origin)),
+            line_no(self.old_line_no),
+            line_no(self.new_line_no),
+            escape(self.content(hunk))
+        )
+    }
+}
+
+impl Hunk {
+    pub fn to_json(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(|l| l.to_json(self)).collect();
+        format!(
+            "{{\"header\":{},\"old_start\":{},\"old_lines\":{},\"new_start\":{},\"new_lines\":{},\"lines\":[{}]}}",
+            escape(&self.header),
+            self.old_start,
+            self.old_lines,
+            self.new_start,
+            self.new_lines,
+            lines.join(",")
+        )
+    }
+}
+
+impl File {
+    pub fn to_json(&self) -> String {
+        let hunks: Vec<String> = self
+            .hunks
+            .iter()
+            .filter(|h| !h.is_mode_change())
+            .map(|h| h.to_json())
+            .collect();
+        format!(
+            "{{\"path\":{},\"status\":{},\"hunks\":[{}]}}",
+            escape(&self.path.to_string_lossy()),
+            escape(status(self.status)),
+            hunks.join(",")
+        )
+    }
+}
+
+impl Diff {
+    pub fn to_json(&self) -> String {
+        let kind = match self.kind {
+            DiffKind::Staged => "staged",
+            DiffKind::Unstaged => "unstaged",
+            DiffKind::Conflicted => "conflicted",
+            DiffKind::Untracked => "untracked",
+            DiffKind::Commit => "commit",
+        };
+        let files: Vec<String> = self.files.iter().map(|f| f.to_json()).collect();
+        format!(
+            "{{\"kind\":{},\"files\":[{}]}}",
+            escape(kind),
+            files.join(",")
+        )
+    }
+}
+
+pub fn changes_to_json(staged: Option<&Diff>, unstaged: Option<&Diff>) -> String {
+    let diff_or_null = |diff: Option<&Diff>| match diff {
+        Some(diff) => diff.to_json(),
+        None => String::from("null"),
+    };
+    format!(
+        "{{\"staged\":{},\"unstaged\":{}}}",
+        diff_or_null(staged),
+        diff_or_null(unstaged)
+    )
+}
+
+// used from command line, without running the app
+pub fn repo_changes_to_json(path: PathBuf) -> Result<String, Error> {
+    let repo = Repository::discover(path)?;
+    let git_diff = if let Ok(ob) = repo.revparse_single("HEAD^{tree}") {
+        let tree = repo.find_tree(ob.id())?;
+        repo.diff_tree_to_index(Some(&tree), None, Some(&mut make_diff_options()))?
+    } else {
+        repo.diff_tree_to_index(None, None, Some(&mut make_diff_options()))?
+    };
+    let staged = make_diff(&git_diff, DiffKind::Staged);
+    let git_diff = repo.diff_index_to_workdir(None, Some(&mut make_diff_options()))?;
+    let unstaged = make_diff(&git_diff, DiffKind::Unstaged);
+    Ok(changes_to_json(
+        if staged.is_empty() {
+            None
+        } else {
+            Some(&staged)
+        },
+        if unstaged.is_empty() {
+            None
+        } else {
+            Some(&unstaged)
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{commit_files, temp_repo};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a \"b\"\\\tc\u{1}"),
+            "\"a \\\"b\\\"\\\\\\tc\\u0001\""
+        );
+    }
+
+    #[test]
+    fn test_changes_to_json() {
+        let (path, repo) = temp_repo("json");
+        commit_files(&repo, &[("a.txt", "one\ntwo\nthree\n")], "initial");
+        fs::write(path.join("a.txt"), "one\ntwo \"2\"\nthree\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+
+        assert_eq!(
+            repo_changes_to_json(path.clone()).unwrap(),
+            concat!(
+                r#"{"staged":{"kind":"staged","files":[{"path":"a.txt","status":"modified","hunks":["#,
+                r#"{"header":"@@ -1,3 +1,3 @@","old_start":1,"old_lines":3,"new_start":1,"new_lines":3,"lines":["#,
+                r#"{"origin":" ","old_line_no":1,"new_line_no":1,"content":"one"},"#,
+                r#"{"origin":"-","old_line_no":2,"new_line_no":null,"content":"two"},"#,
+                r#"{"origin":"+","old_line_no":null,"new_line_no":2,"content":"two \"2\""},"#,
+                r#"{"origin":" ","old_line_no":3,"new_line_no":3,"content":"three"}"#,
+                r#"]}]}]},"unstaged":null}"#
+            )
+        );
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::git::{
-    branch::BranchName, conflict, get_current_repo_status, get_staged, make_diff,
+    branch::BranchName, conflict, get_current_repo_status, get_staged, hooks, make_diff,
     make_diff_options, stage_via_apply, BranchData, DeferRefresh, DiffKind, Hunk, Line, State,
 };
 use crate::StageOp;
@@ -20,34 +20,6 @@ use std::{
 
 //pub const STAGE_FLAG: u16 = 0x3000;
 
-pub fn final_commit(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
-    let repo = git2::Repository::open(path.clone())?;
-    let me = repo.signature()?;
-
-    let my_oid = repo.revparse_single("HEAD^{commit}")?.id();
-
-    let my_commit = repo.find_commit(my_oid)?;
-
-    let message = repo.message()?;
-
-    let head_ref = repo.head()?;
-    assert!(head_ref.is_branch());
-
-    let tree_oid = repo.index()?.write_tree()?;
-
-    let tree = repo.find_tree(tree_oid)?;
-
-    repo.commit(Some("HEAD"), &me, &me, &message, &tree, &[&my_commit])?;
-
-    repo.cleanup_state()?;
-    gio::spawn_blocking({
-        move || {
-            get_current_repo_status(Some(path), sender).expect("cant get status");
-        }
-    });
-    Ok(())
-}
-
 pub fn final_merge_commit(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
     let mut repo = git2::Repository::open(path.clone())?;
     let me = repo.signature()?;
@@ -147,6 +119,7 @@ pub fn branch(
             if !squash {
                 repo.reset(&ob, git2::ResetType::Soft, None)?;
             }
+            hooks::post_merge(path.clone(), squash, sender.clone());
         }
         Ok((analysis, preference)) if analysis.is_normal() && !preference.is_fastforward_only() => {
             info!("merge.normal");
@@ -170,6 +143,7 @@ pub fn branch(
             if !squash {
                 final_merge_commit(path.clone(), sender.clone())?;
             }
+            hooks::post_merge(path.clone(), squash, sender.clone());
         }
         Ok((analysis, preference)) => {
             todo!("not implemented case {:?} {:?}", analysis, preference);
@@ -185,24 +159,24 @@ pub fn branch(
     BranchData::from_branch(&branch, git2::BranchType::Local)
 }
 
+/// git merge --abort. Merged paths in index and workdir are reset
+/// to HEAD, other workdir changes are kept. MERGE_HEAD and
+/// MERGE_MSG are removed, so repo is not in Merge state anymore.
 pub fn abort(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
     info!("git.abort merge");
     let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
     let repo = git2::Repository::open(path.clone())?;
     let mut checkout_builder = git2::build::CheckoutBuilder::new();
 
+    // merge could be aborted when all conflicts are resolved as well
+    let mut paths: Vec<PathBuf> = Vec::new();
     let index = repo.index()?;
     let conflicts = index.conflicts()?;
-    let mut has_conflicts = false;
     for conflict in conflicts.flatten() {
         if let Some(our) = conflict.our {
-            checkout_builder.path(our.path);
-            has_conflicts = true;
+            paths.push(PathBuf::from(from_utf8(&our.path).unwrap()));
         }
     }
-    if !has_conflicts {
-        panic!("no way to abort merge without conflicts");
-    }
 
     let ob = repo.revparse_single("HEAD^{tree}")?;
     let current_tree = repo.find_tree(ob.id())?;
@@ -211,13 +185,16 @@ pub fn abort(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Er
     git_diff.foreach(
         &mut |d: git2::DiffDelta, _| {
             let path = d.new_file().path().expect("cant get path");
-            checkout_builder.path(path);
+            paths.push(path.into());
             true
         },
         None,
         None,
         None,
     )?;
+    for path in &paths {
+        checkout_builder.path(path);
+    }
 
     let head_ref = repo.head()?;
 
@@ -227,7 +204,14 @@ pub fn abort(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Er
         .send_blocking(crate::Event::LockMonitors(true))
         .expect("Could not send through channel");
 
-    repo.reset(&ob, git2::ResetType::Hard, Some(&mut checkout_builder))?;
+    // checkout builder without paths checks out everything,
+    // so nothing to reset means no reset at all
+    if !paths.is_empty() {
+        repo.reset(&ob, git2::ResetType::Hard, Some(&mut checkout_builder))?;
+    }
+    // hard reset cleans up state by itself, but MERGE_HEAD
+    // or MERGE_MSG left behind would keep Merge state
+    repo.cleanup_state()?;
 
     // cleanup conflicted
     sender
@@ -328,6 +312,28 @@ pub fn choose_conflict_side_of_hunk(
     Ok(())
 }
 
+pub fn choose_conflict_side_of_file(
+    path: PathBuf,
+    file_path: PathBuf,
+    ours: bool,
+    sender: Sender<crate::Event>,
+) -> Result<()> {
+    info!(
+        "choose conflict side of file {:?} ours {:?}",
+        file_path, ours
+    );
+    let repo = git2::Repository::open(path.clone())?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    let result = conflict::choose_conflict_side_of_file(&repo, &file_path, ours);
+    sender
+        .send_blocking(crate::Event::LockMonitors(false))
+        .expect("Could not send through channel");
+    result?;
+    try_finalize_conflict(path, sender, Some(file_path))
+}
+
 pub fn try_finalize_conflict(
     path: PathBuf,
     sender: Sender<crate::Event>,
@@ -390,3 +396,52 @@ pub fn try_finalize_conflict(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{commit_files, temp_repo};
+
+    // conflict resolved in favor of ours leaves index equal to HEAD
+    #[test]
+    fn test_abort_resolved_merge() {
+        let (path, repo) = temp_repo("abort-merge");
+        let base = commit_files(&repo, &[("a.txt", "base\n"), ("b.txt", "b\n")], "base");
+        let main = repo.head().unwrap().name().unwrap().to_string();
+        commit_files(&repo, &[("a.txt", "ours\n")], "ours");
+        repo.branch("other", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        repo.set_head("refs/heads/other").unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        let theirs = commit_files(&repo, &[("a.txt", "theirs\n")], "theirs");
+        repo.set_head(&main).unwrap();
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+
+        let annotated = repo.find_annotated_commit(theirs).unwrap();
+        repo.merge(&[&annotated], None, None).unwrap();
+        assert!(repo.index().unwrap().has_conflicts());
+        std::fs::write(path.join("a.txt"), "ours\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        std::fs::write(path.join("b.txt"), "unrelated change\n").unwrap();
+
+        let (sender, _receiver) = async_channel::unbounded();
+        abort(path.clone(), sender).unwrap();
+
+        let repo = git2::Repository::open(&path).unwrap();
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        assert!(!repo.path().join("MERGE_HEAD").exists());
+        assert!(!repo.path().join("MERGE_MSG").exists());
+        assert_eq!(
+            std::fs::read_to_string(path.join("b.txt")).unwrap(),
+            "unrelated change\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(path.join("a.txt")).unwrap(),
+            "ours\n"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Patch files: applying external ones (e.g. received by email)
+//! and exporting changes from status.
+use crate::git::{DeferRefresh, DiffKind, File, Hunk};
+use async_channel::Sender;
+use git2::{ApplyLocation, ApplyOptions, Delta, Diff as GitDiff, Error, Patch, Repository};
+use log::info;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+// (file, hunk header) which could not be applied
+type Rejected = (String, String);
+
+// every hunk is checked on its own, cause libgit2 applies
+// patch atomically and tells nothing about failed hunk
+fn rejected_hunks(
+    repo: &Repository,
+    git_diff: &GitDiff,
+    location: ApplyLocation,
+) -> Result<Vec<Rejected>, Error> {
+    let mut rejected = Vec::new();
+    for delta_idx in 0..git_diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(git_diff, delta_idx)? else {
+            continue;
+        };
+        let path = patch
+            .delta()
+            .new_file()
+            .path()
+            .or(patch.delta().old_file().path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for hunk_idx in 0..patch.num_hunks() {
+            let (dh, _) = patch.hunk(hunk_idx)?;
+            let header = Hunk::get_header_from(&dh);
+            let current_delta = Cell::new(0);
+            let current_hunk = Cell::new(0);
+            let mut options = ApplyOptions::new();
+            options.check(true);
+            options.delta_callback(|_| {
+                current_hunk.set(0);
+                let is_target = current_delta.get() == delta_idx;
+                current_delta.set(current_delta.get() + 1);
+                is_target
+            });
+            options.hunk_callback(|_| {
+                let is_target = current_hunk.get() == hunk_idx;
+                current_hunk.set(current_hunk.get() + 1);
+                is_target
+            });
+            if repo.apply(git_diff, location, Some(&mut options)).is_err() {
+                rejected.push((path.clone(), header));
+            }
+        }
+    }
+    Ok(rejected)
+}
+
+/// Applies patch file to workdir, or to workdir and index when stage is set.
+/// Nothing is applied if any hunk is rejected: error lists rejected ones.
+pub fn apply_patch(
+    path: PathBuf,
+    patch_path: PathBuf,
+    stage: bool,
+    sender: Sender<crate::Event>,
+) -> Result<(), Error> {
+    info!("apply patch {:?} stage {}", patch_path, stage);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = Repository::open(path)?;
+    let bytes = fs::read(&patch_path).map_err(|e| Error::from_str(&e.to_string()))?;
+    let git_diff = GitDiff::from_buffer(&bytes)?;
+    if git_diff.deltas().len() == 0 {
+        return Err(Error::from_str("There is nothing to apply in this file"));
+    }
+    let location = if stage {
+        ApplyLocation::Both
+    } else {
+        ApplyLocation::WorkDir
+    };
+    let rejected = rejected_hunks(&repo, &git_diff, location)?;
+    if !rejected.is_empty() {
+        let rejected: Vec<String> = rejected
+            .into_iter()
+            .map(|(file, header)| format!("{} {}", file, header))
+            .collect();
+        return Err(Error::from_str(&format!(
+            "Patch is not applied. Rejected hunks:\n{}",
+            rejected.join("\n")
+        )));
+    }
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    repo.apply(&git_diff, location, None)
+}
+
+impl File {
+    /// Unified diff of file (or of its single hunk) in `git diff` format.
+    /// Hunks of file go under one file header, even if they are
+    /// not contiguous.
+    pub fn to_patch(&self, hunk_idx: Option<usize>) -> String {
+        let hunks: Vec<&Hunk> = match hunk_idx {
+            Some(hunk_idx) => self.hunks.get(hunk_idx).into_iter().collect(),
+            None => self.hunks.iter().collect(),
+        };
+        let old_path = self.old_path.as_ref().unwrap_or(&self.path);
+        let mut patch = format!(
+            "diff --git a/{} b/{}\n",
+            old_path.display(),
+            self.path.display()
+        );
+        for (old_mode, new_mode) in hunks.iter().filter_map(|hunk| hunk.mode_change()) {
+            let _ = write!(patch, "old mode {}\nnew mode {}\n", old_mode, new_mode);
+        }
+        if self.old_path.is_some() {
+            let _ = write!(
+                patch,
+                "rename from {}\nrename to {}\n",
+                old_path.display(),
+                self.path.display()
+            );
+        }
+        // untracked file which is too big or binary has placeholder
+        // line instead of content
+        let content_hunks: Vec<&&Hunk> = hunks
+            .iter()
+            .filter(|hunk| !hunk.is_mode_change())
+            .filter(|hunk| self.kind != DiffKind::Untracked || hunk.new_lines > 0)
+            .collect();
+        if content_hunks.is_empty() {
+            return patch;
+        }
+        let old_side = if self.status == Delta::Added || self.kind == DiffKind::Untracked {
+            String::from("/dev/null")
+        } else {
+            format!("a/{}", old_path.display())
+        };
+        let new_side = if self.status == Delta::Deleted {
+            String::from("/dev/null")
+        } else {
+            format!("b/{}", self.path.display())
+        };
+        let _ = write!(patch, "--- {}\n+++ {}\n", old_side, new_side);
+        for hunk in content_hunks {
+            patch.push_str(&hunk.header);
+            patch.push('\n');
+            patch.push_str(&hunk.text(true));
+        }
+        patch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{make_diff, make_diff_options, LineKind};
+    use crate::tests::{commit_files, temp_repo};
+
+    #[test]
+    fn test_no_newline_at_eof() {
+        let (path, repo) = temp_repo("no_newline");
+        commit_files(&repo, &[("a.txt", "one\ntwo\n")], "initial");
+        fs::write(path.join("a.txt"), "one\ntwo").unwrap();
+        let git_diff = repo
+            .diff_index_to_workdir(None, Some(&mut make_diff_options()))
+            .unwrap();
+        let diff = make_diff(&git_diff, DiffKind::Unstaged);
+        let file = &diff.files[0];
+        let hunk = &file.hunks[0];
+        assert_eq!(hunk.lines.last().unwrap().kind, LineKind::NoNewlineAtEof);
+        assert_eq!(hunk.text(false), "one\ntwo\ntwo\n");
+        assert_eq!(
+            hunk.text(true),
+            " one\n-two\n+two\n\\ No newline at end of file\n"
+        );
+
+        // exported patch brings marker back
+        let git_diff = GitDiff::from_buffer(file.to_patch(None).as_bytes()).unwrap();
+        fs::write(path.join("a.txt"), "one\ntwo\n").unwrap();
+        repo.apply(&git_diff, ApplyLocation::WorkDir, None).unwrap();
+        assert_eq!(fs::read_to_string(path.join("a.txt")).unwrap(), "one\ntwo");
+        fs::remove_dir_all(path).unwrap();
+    }
+}
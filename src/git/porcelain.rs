@@ -0,0 +1,151 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Text status of repo, close to git status --porcelain, for scripts.
+//! Diffs are the same as in the app, so what is printed
+//! is what the status view shows:
+//!
+//! ```text
+//! ## branch
+//! XY path
+//! R  old_path -> path
+//! ?? untracked_path
+//! ```
+//! X is status in index, Y is status in working tree.
+use crate::git::{staged_diff, unstaged_diff, Diff};
+use git2::{Delta, Error, Repository, StatusOptions};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn code(delta: Delta) -> char {
+    match delta {
+        Delta::Added => 'A',
+        Delta::Deleted => 'D',
+        Delta::Modified => 'M',
+        Delta::Renamed => 'R',
+        Delta::Copied => 'C',
+        Delta::Conflicted => 'U',
+        Delta::Typechange => 'T',
+        _ => ' ',
+    }
+}
+
+/// Lines are sorted by path, as git does.
+pub fn format_status(
+    branch: &str,
+    staged: &Diff,
+    unstaged: &Diff,
+    untracked: &[PathBuf],
+) -> String {
+    // path -> (old path, X, Y)
+    let mut entries: BTreeMap<PathBuf, (Option<PathBuf>, char, char)> = BTreeMap::new();
+    for file in &staged.files {
+        let entry = entries.entry(file.path.clone()).or_insert((None, ' ', ' '));
+        entry.0 = file.old_path.clone();
+        entry.1 = code(file.status);
+    }
+    for file in &unstaged.files {
+        let entry = entries.entry(file.path.clone()).or_insert((None, ' ', ' '));
+        entry.2 = code(file.status);
+    }
+    let mut result = format!("## {}\n", branch);
+    for (path, (old_path, x, y)) in entries {
+        match old_path {
+            Some(old_path) => result.push_str(&format!(
+                "{}{} {} -> {}\n",
+                x,
+                y,
+                old_path.display(),
+                path.display()
+            )),
+            None => result.push_str(&format!("{}{} {}\n", x, y, path.display())),
+        }
+    }
+    for path in untracked {
+        result.push_str(&format!("?? {}\n", path.display()));
+    }
+    result
+}
+
+// used from command line, without running the app
+pub fn repo_status(path: PathBuf) -> Result<String, Error> {
+    let repo = Repository::discover(path)?;
+    let branch = match repo.head() {
+        Ok(head) if head.is_branch() => head.shorthand().unwrap_or_default().to_string(),
+        Ok(head) => format!(
+            "HEAD (detached at {})",
+            &head.peel_to_commit()?.id().to_string()[..7]
+        ),
+        Err(_) => String::from("No commits yet"),
+    };
+    let staged = staged_diff(&repo)?;
+    let unstaged = unstaged_diff(&repo)?;
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_unmodified(false);
+    let untracked: Vec<PathBuf> = repo
+        .statuses(Some(&mut opts))?
+        .iter()
+        .filter(|entry| entry.status().is_wt_new())
+        .filter_map(|entry| entry.path().map(PathBuf::from))
+        .collect();
+    Ok(format_status(&branch, &staged, &unstaged, &untracked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{DiffKind, File};
+    use crate::tests::{commit_files, temp_repo};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_format_status() {
+        let mut staged = Diff::new(DiffKind::Staged);
+        let mut unstaged = Diff::new(DiffKind::Unstaged);
+        for (diff, path, old_path, status) in [
+            (&mut staged, "b.rs", None, Delta::Modified),
+            (&mut staged, "new.rs", Some("old.rs"), Delta::Renamed),
+            (&mut unstaged, "b.rs", None, Delta::Modified),
+            (&mut unstaged, "a.rs", None, Delta::Deleted),
+        ] {
+            let mut file = File::new(diff.kind);
+            file.path = PathBuf::from(path);
+            file.old_path = old_path.map(PathBuf::from);
+            file.status = status;
+            diff.files.push(file);
+        }
+        assert_eq!(
+            format_status("main", &staged, &unstaged, &[PathBuf::from("dir/")]),
+            "## main\n D a.rs\nMM b.rs\nR  old.rs -> new.rs\n?? dir/\n"
+        );
+    }
+
+    #[test]
+    fn test_repo_status() {
+        let (path, repo) = temp_repo("porcelain");
+        commit_files(
+            &repo,
+            &[("a.txt", "a\n"), ("b.txt", "renamed content\n")],
+            "initial",
+        );
+        let mut index = repo.index().unwrap();
+        fs::rename(path.join("b.txt"), path.join("c.txt")).unwrap();
+        index.remove_path(Path::new("b.txt")).unwrap();
+        index.add_path(Path::new("c.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(path.join("a.txt"), "a\nchanged\n").unwrap();
+        fs::write(path.join("d.txt"), "untracked\n").unwrap();
+
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        assert_eq!(
+            repo_status(path.clone()).unwrap(),
+            format!("## {}\n M a.txt\nR  b.txt -> c.txt\n?? d.txt\n", branch)
+        );
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -2,19 +2,91 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::git::{branch::BranchData, get_upstream, merge, DeferRefresh};
+use crate::git::{
+    branch::BranchData, credentials, get_upstream, merge, rebase, repo_settings, DeferRefresh,
+};
 use anyhow::{anyhow, Result};
 use async_channel::Sender;
 use git2;
 use log::{debug, trace};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 
 const PLAIN_PASSWORD: &str = "plain text password required";
+const CANCELLED: &str = "Remote operation is cancelled";
+const SSH_FAILED: &str =
+    "ssh agent failed to authorize. Private key could be set in remotes dialog";
+
+// passphrase for private key from settings is asked
+// once and is kept for the session
+static SSH_PASSPHRASE: Mutex<Option<(PathBuf, String)>> = Mutex::new(None);
+
+fn passphrase_for(key: &Path) -> Option<String> {
+    SSH_PASSPHRASE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|(for_key, _)| for_key == key)
+        .map(|(_, passphrase)| passphrase.clone())
+}
+
+fn ask_passphrase(key: &Path, sender: &Sender<crate::Event>) -> Option<String> {
+    let request = crate::LoginPassword {
+        key: Some(key.display().to_string()),
+        ..crate::LoginPassword::default()
+    };
+    let auth_request = Arc::new((Mutex::new(request), Condvar::new()));
+    sender
+        .send_blocking(crate::Event::UserInputRequired(auth_request.clone()))
+        .expect("cant send through channel");
+    let mut request = auth_request.0.lock().unwrap();
+    while request.pending {
+        request = auth_request.1.wait(request).unwrap();
+    }
+    if request.cancel {
+        return None;
+    }
+    Some(request.password.clone())
+}
+
+// libgit2 calls credentials callback again when previous
+// credentials are rejected. so ssh goes in steps: agent,
+// configured key, configured key with passphrase from user
+fn ssh_credentials(
+    username: &str,
+    attempt: usize,
+    key: Option<&Path>,
+    sender: Option<&Sender<crate::Event>>,
+) -> Result<git2::Cred, git2::Error> {
+    if attempt == 0 {
+        return git2::Cred::ssh_key_from_agent(username);
+    }
+    let key = key.ok_or(git2::Error::from_str(SSH_FAILED))?;
+    if attempt == 1 {
+        let passphrase = passphrase_for(key);
+        return git2::Cred::ssh_key(username, None, key, passphrase.as_deref());
+    }
+    SSH_PASSPHRASE.lock().unwrap().take();
+    if let (2, Some(sender)) = (attempt, sender) {
+        let passphrase =
+            ask_passphrase(key, sender).ok_or(git2::Error::from_str(PLAIN_PASSWORD))?;
+        SSH_PASSPHRASE
+            .lock()
+            .unwrap()
+            .replace((key.to_path_buf(), passphrase.clone()));
+        return git2::Cred::ssh_key(username, None, key, Some(&passphrase));
+        return git2::Cred::ssh_key(username, None, &key, Some(&passphrase));
+    }
+    Err(git2::Error::from_str(&format!(
+        "ssh authentication with {} failed",
+        key.display()
+    )))
+}
 
 #[derive(Debug, Default)]
 pub struct RemoteResponse {
@@ -52,21 +124,31 @@ impl From<String> for RemoteResponse {
 #[derive(Debug, Clone, Default)]
 pub struct Authorizer {
     login_pass: Option<crate::LoginPassword>,
+    // private key from settings of the repo
+    ssh_key: Option<PathBuf>,
 }
 
 impl Authorizer {
     pub fn new(login_pass: crate::LoginPassword) -> Self {
         Self {
             login_pass: Some(login_pass),
+            ssh_key: None,
         }
     }
 
     pub fn callbacks(&self) -> git2::RemoteCallbacks {
         let mut callbacks = git2::RemoteCallbacks::new();
+        let mut ssh_attempt = 0;
         callbacks.credentials({
             move |_url, username_from_url, allowed_types| {
                 if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-                    return git2::Cred::ssh_key_from_agent(username_from_url.unwrap());
+                    ssh_attempt += 1;
+                    return ssh_credentials(
+                        username_from_url.unwrap_or("git"),
+                        ssh_attempt - 1,
+                        self.ssh_key.as_deref(),
+                        None,
+                    );
                 }
                 if allowed_types == git2::CredentialType::USER_PASS_PLAINTEXT {
                     let login_pass = self.login_pass.clone().unwrap();
@@ -89,14 +171,28 @@ pub fn make_authorized_remote<'a>(
     let mut callbacks = git2::RemoteCallbacks::new();
 
     let stored: Rc<RefCell<Authorizer>> = Rc::new(RefCell::new(received_from_user.clone()));
+    let url = repo
+        .find_remote(remote_name)?
+        .url()
+        .unwrap_or_default()
+        .to_string();
 
+    let ssh_key = repo_settings::applied(repo).ssh_key;
+    let mut ssh_attempt = 0;
     callbacks.credentials({
         let sender = sender.clone();
         let stored = stored.clone();
+        let url = url.clone();
+        let ssh_key = ssh_key.clone();
         move |_url, username_from_url, allowed_types| {
             if allowed_types.contains(git2::CredentialType::SSH_KEY) {
-                let result = git2::Cred::ssh_key_from_agent(username_from_url.unwrap());
-                return result;
+                ssh_attempt += 1;
+                return ssh_credentials(
+                    username_from_url.unwrap_or("git"),
+                    ssh_attempt - 1,
+                    ssh_key.as_deref(),
+                    Some(&sender),
+                );
             }
             if allowed_types == git2::CredentialType::USER_PASS_PLAINTEXT {
                 if let Some(login_pass) = &received_from_user.login_pass {
@@ -104,8 +200,19 @@ pub fn make_authorized_remote<'a>(
                         git2::Cred::userpass_plaintext(&login_pass.login, &login_pass.password);
                     return plain_result;
                 } else {
-                    let auth_request =
-                        Arc::new((Mutex::new(crate::LoginPassword::default()), Condvar::new()));
+                    let mut prefilled = crate::LoginPassword::default();
+                    if stored.borrow().login_pass.is_some() {
+                        // callback is called again when previous
+                        // credentials were rejected
+                        credentials::forget(repo, &url);
+                    } else if let Some(cached) = credentials::get(repo, &url) {
+                        if !cached.password.is_empty() {
+                            stored.replace(Authorizer::new(cached.clone()));
+                            return git2::Cred::userpass_plaintext(&cached.login, &cached.password);
+                        }
+                        prefilled.login = cached.login;
+                    }
+                    let auth_request = Arc::new((Mutex::new(prefilled), Condvar::new()));
                     let ui_auth_request = auth_request.clone();
                     sender
                         .send_blocking(crate::Event::UserInputRequired(ui_auth_request))
@@ -132,6 +239,9 @@ pub fn make_authorized_remote<'a>(
 
     let mut remote = repo.find_remote(remote_name).unwrap();
     if let Err(error) = remote.connect_auth(direction, Some(callbacks), None) {
+        if error.code() == git2::ErrorCode::Auth && stored.borrow().login_pass.is_some() {
+            credentials::forget(repo, &url);
+        }
         if error.message() != PLAIN_PASSWORD {
             // "unknown ssl error" and valid login_pass was already provided
             // by user. looks like it happens when some time pass while user
@@ -150,46 +260,138 @@ pub fn make_authorized_remote<'a>(
         }
         return Err(error.into());
     }
-    let authorizer = (*stored.borrow()).clone();
+    let mut authorizer = (*stored.borrow()).clone();
+    authorizer.ssh_key = ssh_key;
+    if let Some(login_pass) = &authorizer.login_pass {
+        if let Err(message) = credentials::store(repo, &url, login_pass) {
+            sender
+                .send_blocking(crate::Event::Toast(format!(
+                    "Can't save credentials: {}",
+                    message
+                )))
+                .expect("cant send through channel");
+        }
+    }
     Ok((remote, authorizer))
 }
 
-pub fn set_remote_callbacks(callbacks: &mut git2::RemoteCallbacks) -> Rc<RefCell<RemoteResponse>> {
-    callbacks.push_transfer_progress(|s1, s2, s3| {
-        debug!("push_transfer_progress {:?} {:?} {:?}", s1, s2, s3);
+// (received, total) is sent only when percent changes, otherwise
+// channel is flooded on large fetches
+fn progress_sender(sender: &Sender<crate::Event>) -> impl FnMut(usize, usize) {
+    let sender = sender.clone();
+    let mut last_percent = None;
+    move |received, total| {
+        if total == 0 {
+            return;
+        }
+        let percent = received * 100 / total;
+        if last_percent == Some(percent) {
+            return;
+        }
+        last_percent.replace(percent);
+        sender
+            .send_blocking(crate::Event::RemoteProgress(received, total))
+            .expect("cant send through channel");
+    }
+}
+
+// cancel flag is checked in callbacks which are able to stop
+// libgit2. on cancel error is stored in response, cause libgit2
+// reports just "callback returned error". refs are updated by
+// fetch only after pack is fully received, so aborted fetch
+// leaves repo as it was. cancelled fetch and pull return error
+// as usual, so their DeferRefresh unlocks monitors and refreshes status
+fn is_cancelled(cancel: &AtomicBool, response: &RefCell<RemoteResponse>) -> bool {
+    if !cancel.load(Ordering::Relaxed) {
+        return false;
+    }
+    response.borrow_mut().error.replace(String::from(CANCELLED));
+    true
+}
+
+// fetch returns git2::Error. replace it with cancel message
+// if operation was cancelled by user
+fn fetch_error(response: &RefCell<RemoteResponse>, error: git2::Error) -> git2::Error {
+    match &response.borrow().error {
+        Some(message) if message == CANCELLED => git2::Error::from_str(CANCELLED),
+        _ => error,
+    }
+}
+
+/// Common callbacks for fetch and push: progress, response from
+/// remote and cancellation. Cancel flag is sent to ui, so operation
+/// could be stopped from headerbar. Pack upload during push could
+/// not be stopped: git2 ignores result of push progress callback.
+/// So push could be cancelled only before upload and cancel
+/// is withdrawn from ui when upload starts.
+pub fn set_remote_callbacks(
+    callbacks: &mut git2::RemoteCallbacks,
+    sender: &Sender<crate::Event>,
+) -> Rc<RefCell<RemoteResponse>> {
+    let response = Rc::new(RefCell::new(RemoteResponse::default()));
+    let cancel = Arc::new(AtomicBool::new(false));
+    sender
+        .send_blocking(crate::Event::RemoteCancel(Some(cancel.clone())))
+        .expect("cant send through channel");
+
+    let mut send_push_progress = progress_sender(sender);
+    // server could apply pushed pack anyway, so after upload
+    // push is not cancelled even if flag was set
+    let upload_started = Rc::new(Cell::new(false));
+    callbacks.push_transfer_progress({
+        let sender = sender.clone();
+        let upload_started = upload_started.clone();
+        move |current, total, bytes| {
+            trace!(
+                "push_transfer_progress {:?} {:?} {:?}",
+                current,
+                total,
+                bytes
+            );
+            if !upload_started.replace(true) {
+                sender
+                    .send_blocking(crate::Event::RemoteCancel(None))
+                    .expect("cant send through channel");
+            }
+            send_push_progress(current, total);
+        }
     });
 
-    let mut progress_counts: HashMap<usize, usize> = HashMap::new();
-    callbacks.transfer_progress(move |progress| {
-        let bytes = progress.received_bytes();
-        if let Some(cnt) = progress_counts.get(&bytes) {
-            progress_counts.insert(bytes, cnt + 1);
-        } else {
-            progress_counts.insert(bytes, 1);
-        }
-        // progress_counts[] = 1;
-        trace!("transfer progress {:?}", bytes);
-        true
+    let mut send_fetch_progress = progress_sender(sender);
+    callbacks.transfer_progress({
+        let cancel = cancel.clone();
+        let r = response.clone();
+        move |progress| {
+            trace!(
+                "transfer progress {:?}/{:?} {:?}",
+                progress.received_objects(),
+                progress.total_objects(),
+                progress.received_bytes()
+            );
+            send_fetch_progress(progress.received_objects(), progress.total_objects());
+            !is_cancelled(&cancel, &r)
+        }
     });
 
     callbacks.pack_progress(|stage, s1, s2| {
         debug!("pack progress {:?} {:?} {:?}", stage, s1, s2);
     });
 
-    let response = Rc::new(RefCell::new(RemoteResponse::default()));
-
     callbacks.sideband_progress({
+        let cancel = cancel.clone();
         let r = response.clone();
         move |response| {
             let str_resp = String::from_utf8_lossy(response).into_owned();
             debug!("push.sideband progress {:?}", str_resp);
-            let mut rr = r.borrow_mut();
-            if let Some(body) = &mut rr.body {
-                body.push(str_resp);
-            } else {
-                rr.body.replace(vec![str_resp]);
+            {
+                let mut rr = r.borrow_mut();
+                if let Some(body) = &mut rr.body {
+                    body.push(str_resp);
+                } else {
+                    rr.body.replace(vec![str_resp]);
+                }
             }
-            true
+            upload_started.get() || !is_cancelled(&cancel, &r)
         }
     });
 
@@ -212,27 +414,47 @@ pub fn set_remote_callbacks(callbacks: &mut git2::RemoteCallbacks) -> Rc<RefCell
         Ok(git2::CertificateCheckStatus::CertificateOk)
     });
 
-    callbacks.push_negotiation(|update| {
-        if !update.is_empty() {
-            debug!(
-                "push_negotiation {:?} {:?}",
-                update[0].src_refname(),
-                update[0].dst_refname()
-            );
+    callbacks.push_negotiation({
+        let r = response.clone();
+        move |update| {
+            if !update.is_empty() {
+                debug!(
+                    "push_negotiation {:?} {:?}",
+                    update[0].src_refname(),
+                    update[0].dst_refname()
+                );
+            }
+            if is_cancelled(&cancel, &r) {
+                return Err(git2::Error::from_str(CANCELLED));
+            }
+            Ok(())
         }
-        Ok(())
     });
     response
 }
 
-pub fn update_remote(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), git2::Error> {
+/// Fetches all remotes. Without prune remote.<name>.prune or
+/// fetch.prune from git config decides and when neither is set,
+/// tracking refs are pruned as before. Pruning only drops tracking
+/// refs which are absent on remote, refs not matched by refspec are kept.
+pub fn update_remote(
+    path: PathBuf,
+    prune: bool,
+    sender: Sender<crate::Event>,
+) -> Result<(), git2::Error> {
     let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
     let repo = git2::Repository::open(path)?;
     let mut errors: HashMap<&str, Vec<anyhow::Error>> = HashMap::new();
 
+    let config = repo.config()?;
     let remotes = repo.remotes()?;
     for remote_name in &remotes {
         let remote_name = remote_name.unwrap();
+        let prune = prune
+            || config
+                .get_bool(&format!("remote.{}.prune", remote_name))
+                .or_else(|_| config.get_bool("fetch.prune"))
+                .unwrap_or(true);
         let sender = sender.clone();
         match make_authorized_remote(
             &repo,
@@ -242,18 +464,21 @@ pub fn update_remote(path: PathBuf, sender: Sender<crate::Event>) -> Result<(),
             sender.clone(),
         ) {
             Ok((mut remote, authorizer)) => {
-                let mut callbacks = authorizer.callbacks();
-                set_remote_callbacks(&mut callbacks);
-                if let Err(err) = remote.prune(Some(callbacks)) {
-                    errors.entry(remote_name).or_default().push(err.into());
-                    continue;
-                }
                 let mut opts = git2::FetchOptions::new();
+                opts.prune(if prune {
+                    git2::FetchPrune::On
+                } else {
+                    git2::FetchPrune::Off
+                });
                 let mut callbacks = authorizer.callbacks();
-                set_remote_callbacks(&mut callbacks);
+                let response = set_remote_callbacks(&mut callbacks, &sender);
                 opts.remote_callbacks(callbacks);
                 let refs: [String; 0] = [];
                 if let Err(err) = remote.fetch(&refs, Some(&mut opts), None) {
+                    let err = fetch_error(&response, err);
+                    if err.message() == CANCELLED {
+                        return Err(err);
+                    }
                     errors.entry(remote_name).or_default().push(err.into());
                     continue;
                 }
@@ -351,7 +576,7 @@ pub fn push(
         }
     });
 
-    let response = set_remote_callbacks(&mut callbacks);
+    let response = set_remote_callbacks(&mut callbacks, &sender);
     opts.remote_callbacks(callbacks);
 
     let result = remote.push(&[refspec], Some(&mut opts));
@@ -384,22 +609,210 @@ pub fn push(
     Ok(())
 }
 
-pub fn pull(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), RemoteResponse> {
+// push without update_tips. error from hooks (in response) is
+// more important than error from result
+fn push_refspecs(
+    remote: &mut git2::Remote,
+    authorizer: &Authorizer,
+    refspecs: &[String],
+    sender: &Sender<crate::Event>,
+) -> Result<(), RemoteResponse> {
+    let mut opts = git2::PushOptions::new();
+    let mut callbacks = authorizer.callbacks();
+    let response = set_remote_callbacks(&mut callbacks, sender);
+    opts.remote_callbacks(callbacks);
+
+    let result = remote.push(refspecs, Some(&mut opts));
+    let mut rr = response.borrow_mut();
+    if let Err(error) = &result {
+        if rr.error.is_none() {
+            rr.error.replace(error.message().to_string());
+        }
+    }
+    if let Some(error) = &rr.error {
+        let mut response_result = RemoteResponse::default();
+        response_result.error.replace(error.clone());
+        if let Some(body) = &rr.body {
+            response_result.body.replace(body.clone());
+        }
+        return Err(response_result);
+    }
+    Ok(())
+}
+
+/// Pushes local tags which are absent on remote. Tags which exist
+/// on remote, but point to another target are not forced and are
+/// returned as skipped. Result is (pushed, skipped).
+pub fn push_tags(
+    path: PathBuf,
+    remote_name: String,
+    sender: Sender<crate::Event>,
+) -> Result<(Vec<String>, Vec<String>), RemoteResponse> {
+    let repo = git2::Repository::open(path.clone())?;
+    let (mut remote, authorizer) = make_authorized_remote(
+        &repo,
+        &remote_name,
+        git2::Direction::Push,
+        Authorizer::default(),
+        sender.clone(),
+    )?;
+    // annotated tags are listed twice: tag object and peeled ^{} commit.
+    // tag object is compared with local ref target
+    let remote_tags: HashMap<String, git2::Oid> = remote
+        .list()?
+        .iter()
+        .filter(|head| head.name().starts_with("refs/tags/") && !head.name().ends_with("^{}"))
+        .map(|head| (head.name().to_string(), head.oid()))
+        .collect();
+
+    let mut pushed = Vec::new();
+    let mut skipped = Vec::new();
+    for reference in repo.references_glob("refs/tags/*")? {
+        let reference = reference?;
+        let (Some(name), Some(oid)) = (reference.name(), reference.target()) else {
+            continue;
+        };
+        let short_name = name.trim_start_matches("refs/tags/").to_string();
+        match remote_tags.get(name) {
+            None => pushed.push(short_name),
+            Some(remote_oid) if *remote_oid != oid => skipped.push(short_name),
+            Some(_) => {}
+        }
+    }
+    if pushed.is_empty() {
+        return Ok((pushed, skipped));
+    }
+    let refspecs: Vec<String> = pushed
+        .iter()
+        .map(|name| format!("refs/tags/{}:refs/tags/{}", name, name))
+        .collect();
+    trace!("push tags. refspecs {:?}", refspecs);
+
+    push_refspecs(&mut remote, &authorizer, &refspecs, &sender)?;
+    Ok((pushed, skipped))
+}
+
+pub fn delete_tag(
+    path: PathBuf,
+    remote_name: String,
+    tag_name: String,
+    sender: Sender<crate::Event>,
+) -> Result<(), RemoteResponse> {
+    let repo = git2::Repository::open(path.clone())?;
+    let refspec = format!(":refs/tags/{}", tag_name);
+    trace!("delete remote tag. refspec {}", refspec);
+
+    let (mut remote, authorizer) = make_authorized_remote(
+        &repo,
+        &remote_name,
+        git2::Direction::Push,
+        Authorizer::default(),
+        sender.clone(),
+    )?;
+    push_refspecs(&mut remote, &authorizer, &[refspec], &sender)?;
+    sender
+        .send_blocking(crate::Event::Toast(format!(
+            "Deleted tag {} on {}",
+            tag_name, remote_name
+        )))
+        .expect("cant send through channel");
+    Ok(())
+}
+
+pub fn delete_branch(
+    path: PathBuf,
+    remote_name: String,
+    branch_name: String,
+    sender: Sender<crate::Event>,
+) -> Result<(), RemoteResponse> {
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path.clone())?;
+
+    let refspec = format!(":refs/heads/{}", branch_name);
+    trace!("delete remote branch. refspec {}", refspec);
+
+    let (mut remote, authorizer) = make_authorized_remote(
+        &repo,
+        &remote_name,
+        git2::Direction::Push,
+        Authorizer::default(),
+        sender.clone(),
+    )?;
+
+    push_refspecs(&mut remote, &authorizer, &[refspec], &sender)?;
+    // push could already update remote tracking ref.
+    // if not - prune it by hands
+    if let Ok(mut tracking) = repo.find_branch(
+        &format!("{}/{}", remote_name, branch_name),
+        git2::BranchType::Remote,
+    ) {
+        tracking.delete()?;
+    }
+    sender
+        .send_blocking(crate::Event::Toast(format!(
+            "Deleted {}/{}",
+            remote_name, branch_name
+        )))
+        .expect("cant send through channel");
+    Ok(())
+}
+
+// conflicts are not an error: rebase stays in progress and is
+// continued or aborted from the banner, as any other rebase
+fn rebase_on_upstream(
+    path: PathBuf,
+    upstream: git2::Oid,
+    sender: Sender<crate::Event>,
+) -> Result<(), RemoteResponse> {
+    if let Err(err) = rebase(path.clone(), upstream, None, sender) {
+        let repo = git2::Repository::open(path)?;
+        if repo.state() == git2::RepositoryState::RebaseMerge && repo.index()?.has_conflicts() {
+            return Ok(());
+        }
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Fetches upstream and merges it. In fast-forward only mode
+/// (or with pull.ff=only in git config) diverged branch is not merged.
+/// In rebase mode (or with pull.rebase=true) diverged local commits
+/// are rebased onto upstream.
+pub fn pull(
+    path: PathBuf,
+    ff_only: bool,
+    rebase: bool,
+    sender: Sender<crate::Event>,
+) -> Result<(), RemoteResponse> {
     let defer = DeferRefresh::new(path.clone(), sender.clone(), true, true);
     let repo = git2::Repository::open(path.clone())?;
 
     let head_ref = repo.head()?;
+    // remote and its branch are taken from branch config, as git does.
+    // name of tracking branch does not tell them: remote name could
+    // contain slashes and upstream branch could be named differently
+    let no_remote = || git2::Error::from_str("No remote to pull from");
+    let local_ref = head_ref.name().ok_or_else(no_remote)?.to_string();
+    let remote_name = repo
+        .branch_upstream_remote(&local_ref)
+        .map_err(|_| no_remote())?
+        .as_str()
+        .ok_or_else(no_remote)?
+        .to_string();
+    let merge_ref = repo
+        .branch_upstream_merge(&local_ref)
+        .map_err(|_| no_remote())?
+        .as_str()
+        .ok_or_else(no_remote)?
+        .to_string();
+    let tracking_ref = repo
+        .branch_upstream_name(&local_ref)
+        .map_err(|_| no_remote())?
+        .as_str()
+        .ok_or_else(no_remote)?
+        .to_string();
     let branch = git2::Branch::wrap(head_ref);
-    let err = "No remote to pull from";
-    let branch_data = BranchData::from_branch(&branch, git2::BranchType::Local)?
-        .ok_or(git2::Error::from_str(err))?;
-
-    let remote_name = branch_data
-        .remote_name
-        .clone()
-        .ok_or(git2::Error::from_str(err))?;
 
-    // let mut remote = repo.find_remote(&remote_name)?;
     let (mut remote, authorizer) = make_authorized_remote(
         &repo,
         &remote_name,
@@ -441,13 +854,39 @@ pub fn pull(path: PathBuf, sender: Sender<crate::Event>) -> Result<(), RemoteRes
         }
     });
 
-    set_remote_callbacks(&mut callbacks);
+    let response = set_remote_callbacks(&mut callbacks, &sender);
     opts.remote_callbacks(callbacks);
 
-    remote.fetch(&[&branch_data.local_name()], Some(&mut opts), None)?;
+    // explicit destination: tracking ref is updated before merge
+    let refspec = format!("+{}:{}", merge_ref, tracking_ref);
+    remote
+        .fetch(&[&refspec], Some(&mut opts), None)
+        .map_err(|e| fetch_error(&response, e))?;
 
     let upstream = branch.upstream()?;
 
+    let config = repo.config()?;
+    let ff_only = ff_only || config.get_string("pull.ff").is_ok_and(|ff| ff == "only");
+    let rebase = rebase || config.get_bool("pull.rebase").unwrap_or(false);
+    // upstream is looked up after fetch, so its target is the fresh one
+    if let (true, Some(local), Some(remote)) = (
+        ff_only || rebase,
+        branch.get().target(),
+        upstream.get().target(),
+    ) {
+        let (ahead, behind) = repo.graph_ahead_behind(local, remote)?;
+        if ahead > 0 && behind > 0 && rebase {
+            return rebase_on_upstream(path, remote, sender);
+        }
+        if ahead > 0 && behind > 0 {
+            Err(git2::Error::from_str(&format!(
+                "Branch and upstream have diverged: {} local and {} upstream commits.\n\
+                 Fast-forward is not possible. Rebase on upstream instead",
+                ahead, behind
+            )))?;
+        }
+    }
+
     let branch_data = BranchData::from_branch(&upstream, git2::BranchType::Remote)
         .unwrap()
         .unwrap();
@@ -508,13 +947,112 @@ pub fn list(path: PathBuf) -> Result<Vec<RemoteDetail>, git2::Error> {
     Ok(remotes)
 }
 
-pub fn add(path: PathBuf, name: String, url: String) -> Result<Option<RemoteDetail>, git2::Error> {
+// host and path (without .git) of remote url.
+// scp like git@host:o/r.git and scheme://user@host:port/o/r.git
+fn host_and_path(url: &str) -> Option<(String, String)> {
+    let (host, path) = if let Some((_, rest)) = url.split_once("://") {
+        rest.split_once('/')?
+    } else {
+        url.split_once(':')?
+    };
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    let path = path.trim_matches('/').trim_end_matches(".git");
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some((host.to_lowercase(), path.to_string()))
+}
+
+/// Browser url for commit on known hosting, e.g.
+/// git@github.com:o/r.git -> https://github.com/o/r/commit/sha
+pub fn commit_web_url(remote_url: &str, oid: git2::Oid) -> Option<String> {
+    let (host, path) = host_and_path(remote_url)?;
+    let commit_path = if host == "github.com" || host == "codeberg.org" || host.contains("gitea") {
+        "commit"
+    } else if host.contains("gitlab") {
+        "-/commit"
+    } else if host == "bitbucket.org" {
+        "commits"
+    } else {
+        return None;
+    };
+    Some(format!("https://{}/{}/{}/{}", host, path, commit_path, oid))
+}
+
+/// Commit url on origin (or the only remote), if hosting is known.
+pub fn commit_url(path: PathBuf, oid: git2::Oid) -> Result<Option<String>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let remote = match repo.find_remote("origin") {
+        Ok(remote) => remote,
+        Err(_) => {
+            let remotes = repo.remotes()?;
+            match remotes.iter().flatten().next() {
+                Some(name) => repo.find_remote(name)?,
+                None => return Ok(None),
+            }
+        }
+    };
+    Ok(remote.url().and_then(|url| commit_web_url(url, oid)))
+}
+
+// url forms which git understands:
+// scheme://host/path, scp like user@host:path and local paths
+pub fn validate_url(url: &str) -> Result<(), git2::Error> {
+    let invalid = |reason: &str| Err(git2::Error::from_str(&format!("{}: {}", reason, url)));
+    if url.trim().is_empty() {
+        return invalid("Url is empty");
+    }
+    if url.chars().any(|c| c.is_whitespace()) {
+        return invalid("Url must not contain spaces");
+    }
+    if let Some((scheme, rest)) = url.split_once("://") {
+        if !["https", "http", "ssh", "git", "file"].contains(&scheme) {
+            return invalid("Unknown protocol");
+        }
+        if rest.is_empty() || (scheme != "file" && rest.starts_with('/')) {
+            return invalid("Host is missing");
+        }
+        return Ok(());
+    }
+    if url.starts_with('/') || url.starts_with('.') {
+        return Ok(());
+    }
+    match url.split_once(':') {
+        Some((host, path)) if !host.is_empty() && !path.is_empty() && !host.contains('/') => Ok(()),
+        _ => invalid("Url must be like https://host/repo.git or git@host:repo.git"),
+    }
+}
+
+pub fn forget_credentials(path: PathBuf, name: String) -> Result<(), git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let remote = repo.find_remote(&name)?;
+    if let Some(url) = remote.url() {
+        credentials::forget(&repo, url);
+    }
+    Ok(())
+}
+
+pub fn add(
+    path: PathBuf,
+    name: String,
+    url: String,
+    sender: Sender<crate::Event>,
+) -> Result<Option<RemoteDetail>, git2::Error> {
+    validate_url(&url)?;
+    let _updater = DeferRefresh::new(path.clone(), sender, true, false);
     let repo = git2::Repository::open(path.clone())?;
     let remote = repo.remote(&name, &url)?;
     Ok(Some(remote.into()))
 }
 
-pub fn delete(path: PathBuf, name: String) -> Result<bool, git2::Error> {
+pub fn delete(
+    path: PathBuf,
+    name: String,
+    sender: Sender<crate::Event>,
+) -> Result<bool, git2::Error> {
+    // remote tracking branches are deleted too
+    let _updater = DeferRefresh::new(path.clone(), sender, true, false);
     let repo = git2::Repository::open(path.clone())?;
     repo.remote_delete(&name)?;
     Ok(true)
@@ -524,16 +1062,20 @@ pub fn edit(
     path: PathBuf,
     name: String,
     remote: RemoteDetail,
+    sender: Sender<crate::Event>,
 ) -> Result<Option<RemoteDetail>, git2::Error> {
     let repo = git2::Repository::open(path.clone())?;
     let git_remote = repo.find_remote(&name)?;
     if let Some(name) = git_remote.name() {
         if name != remote.name {
+            let _updater = DeferRefresh::new(path.clone(), sender, true, false);
             repo.remote_rename(name, &remote.name)?;
             return Ok(Some(repo.find_remote(&remote.name)?.into()));
         }
         if let Some(url) = git_remote.url() {
             if url != remote.url {
+                validate_url(&remote.url)?;
+                let _updater = DeferRefresh::new(path.clone(), sender, true, false);
                 repo.remote_set_url(name, &remote.url)?;
                 return Ok(Some(repo.find_remote(&remote.name)?.into()));
             }
@@ -541,3 +1083,171 @@ pub fn edit(
     }
     Ok(None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_commit_web_url() {
+        let oid = git2::Oid::from_str("4a708b4a708b4a708b4a708b4a708b4a708b4a70").unwrap();
+        for url in [
+            "git@github.com:o/r.git",
+            "https://github.com/o/r.git",
+            "ssh://git@github.com:22/o/r",
+            "https://user@github.com/o/r/",
+        ] {
+            assert_eq!(
+                commit_web_url(url, oid),
+                Some(format!("https://github.com/o/r/commit/{}", oid))
+            );
+        }
+        assert_eq!(
+            commit_web_url("git@gitlab.com:g/sub/r.git", oid),
+            Some(format!("https://gitlab.com/g/sub/r/-/commit/{}", oid))
+        );
+        assert_eq!(commit_web_url("git@example.org:o/r.git", oid), None);
+        assert_eq!(commit_web_url("/srv/git/r.git", oid), None);
+    }
+
+    #[test]
+    fn test_validate_url() {
+        for url in [
+            "https://github.com/o/r.git",
+            "ssh://git@github.com:22/o/r",
+            "file:///srv/git/r.git",
+            "git@github.com:o/r.git",
+            "/srv/git/r.git",
+            "../r.git",
+        ] {
+            assert!(validate_url(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "",
+            "https://github.com/o/r .git",
+            "htps://github.com/o/r.git",
+            "https:///o/r.git",
+            "github.com/o/r.git",
+        ] {
+            assert!(validate_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_add_edit_delete_remote() {
+        let (path, repo) = crate::tests::temp_repo("remotes");
+        let (sender, _receiver) = async_channel::unbounded();
+        assert!(add(
+            path.clone(),
+            String::from("origin"),
+            String::from("github.com/o/r.git"),
+            sender.clone()
+        )
+        .is_err());
+        assert!(repo.find_remote("origin").is_err());
+        drop(repo);
+
+        let remote = add(
+            path.clone(),
+            String::from("origin"),
+            String::from("https://github.com/o/r.git"),
+            sender.clone(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(remote.url, "https://github.com/o/r.git");
+
+        // wrong protocol is fixed in place
+        let fixed = RemoteDetail {
+            url: String::from("git@github.com:o/r.git"),
+            ..remote
+        };
+        edit(path.clone(), String::from("origin"), fixed, sender.clone()).unwrap();
+        let repo = git2::Repository::open(&path).unwrap();
+        assert_eq!(
+            repo.find_remote("origin").unwrap().url(),
+            Some("git@github.com:o/r.git")
+        );
+
+        assert!(delete(path.clone(), String::from("origin"), sender).unwrap());
+        let repo = git2::Repository::open(&path).unwrap();
+        assert!(repo.find_remote("origin").is_err());
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    fn temp_repo_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("stage-pull-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    fn commit_file(repo: &git2::Repository, content: &str) -> git2::Oid {
+        std::fs::write(repo.workdir().unwrap().join("file.txt"), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let me = git2::Signature::now("test", "test@example.org").unwrap();
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &me, &me, content, &tree, &parents)
+            .unwrap()
+    }
+
+    fn push_head(repo: &git2::Repository, remote_name: &str) {
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        repo.find_remote(remote_name)
+            .unwrap()
+            .push(&[&format!("refs/heads/{0}:refs/heads/{0}", branch)], None)
+            .unwrap();
+    }
+
+    // repo cloned from local one, then real remote is added and
+    // branch is pushed there. new commit on real remote must be pulled
+    #[test]
+    fn test_pull_from_added_remote() {
+        let source_path = temp_repo_path("source");
+        let real_path = temp_repo_path("real");
+        let local_path = temp_repo_path("local");
+        let other_path = temp_repo_path("other");
+
+        let source = git2::Repository::init(&source_path).unwrap();
+        commit_file(&source, "initial\n");
+        let local = git2::Repository::clone(source_path.to_str().unwrap(), &local_path).unwrap();
+        let branch = local.head().unwrap().shorthand().unwrap().to_string();
+
+        git2::Repository::init_bare(&real_path).unwrap();
+        local.remote("real", real_path.to_str().unwrap()).unwrap();
+        push_head(&local, "real");
+        local
+            .find_remote("real")
+            .unwrap()
+            .fetch(&[] as &[&str], None, None)
+            .unwrap();
+        local
+            .find_branch(&branch, git2::BranchType::Local)
+            .unwrap()
+            .set_upstream(Some(&format!("real/{}", branch)))
+            .unwrap();
+
+        let other = git2::Repository::clone(real_path.to_str().unwrap(), &other_path).unwrap();
+        let pushed = commit_file(&other, "changed on remote\n");
+        push_head(&other, "origin");
+
+        let (sender, _receiver) = async_channel::unbounded();
+        pull(local_path.clone(), false, false, sender).unwrap();
+
+        let local = git2::Repository::open(&local_path).unwrap();
+        assert_eq!(
+            local
+                .refname_to_id(&format!("refs/remotes/real/{}", branch))
+                .unwrap(),
+            pushed
+        );
+        assert_eq!(local.head().unwrap().target().unwrap(), pushed);
+    }
+}
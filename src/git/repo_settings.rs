@@ -0,0 +1,202 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Per repo settings layer on top of global gio::Settings.
+//! Stored in .git/stage-config in git config format:
+//!
+//! ```text
+//! [stage]
+//!     ignore-whitespace = true
+//!     ticket-regex = "([A-Z]+-[0-9]+)"
+//! ```
+//! When file is present, repo is in "per repo" mode: values from file
+//! override globals and all changes of the keys below go there.
+use crate::gio;
+use crate::gio::prelude::*;
+use git2;
+use log::{debug, info};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const REPO_SETTINGS: &str = "stage-config";
+const SECTION: &str = "stage";
+
+// settings keys which could be overridden per repo
+pub const IGNORE_WHITESPACE_TOKEN: &str = "ignore-whitespace";
+pub const RUN_HOOKS_TOKEN: &str = "run-hooks";
+pub const SIGN_OFF_TOKEN: &str = "sign-off";
+pub const SIGN_COMMITS_TOKEN: &str = "sign-commits";
+pub const PULL_FF_ONLY_TOKEN: &str = "pull-ff-only";
+pub const PULL_REBASE_TOKEN: &str = "pull-rebase";
+pub const PRE_COMMIT_COMMAND_TOKEN: &str = "pre-commit-command";
+pub const COMMIT_TEMPLATE_TOKEN: &str = "commit-template";
+pub const TICKET_REGEX_TOKEN: &str = "ticket-regex";
+pub const SIGNING_FORMAT_TOKEN: &str = "signing-format";
+pub const SIGNING_KEY_TOKEN: &str = "signing-key";
+pub const SSH_KEY_TOKEN: &str = "ssh-key";
+
+pub const BOOL_KEYS: [&str; 6] = [
+    IGNORE_WHITESPACE_TOKEN,
+    RUN_HOOKS_TOKEN,
+    SIGN_OFF_TOKEN,
+    SIGN_COMMITS_TOKEN,
+    PULL_FF_ONLY_TOKEN,
+    PULL_REBASE_TOKEN,
+];
+pub const STRING_KEYS: [&str; 6] = [
+    PRE_COMMIT_COMMAND_TOKEN,
+    COMMIT_TEMPLATE_TOKEN,
+    TICKET_REGEX_TOKEN,
+    SIGNING_FORMAT_TOKEN,
+    SIGNING_KEY_TOKEN,
+    SSH_KEY_TOKEN,
+];
+
+fn config_path(path: &Path) -> Option<PathBuf> {
+    git2::Repository::open(path)
+        .ok()
+        .map(|repo| repo.path().join(REPO_SETTINGS))
+}
+
+fn open_config(path: Option<&PathBuf>) -> Option<git2::Config> {
+    let config_path = config_path(path?)?;
+    if !config_path.exists() {
+        return None;
+    }
+    git2::Config::open(&config_path)
+        .inspect_err(|e| debug!("cant open repo settings {:?} {:?}", config_path, e))
+        .ok()
+}
+
+fn key(name: &str) -> String {
+    format!("{}.{}", SECTION, name)
+}
+
+pub fn is_per_repo(path: Option<&PathBuf>) -> bool {
+    path.and_then(|path| config_path(path))
+        .map(|config_path| config_path.exists())
+        .unwrap_or(false)
+}
+
+/// Switch repo to per repo mode. Current global values are
+/// copied to repo settings, so nothing changes at first.
+pub fn enable(path: PathBuf, settings: &gio::Settings) -> Result<(), git2::Error> {
+    let config_path = config_path(&path).ok_or(git2::Error::from_str("no repo"))?;
+    info!("per repo settings {:?}", config_path);
+    let mut config = git2::Config::open(&config_path)?;
+    for name in BOOL_KEYS {
+        config.set_bool(&key(name), settings.get::<bool>(name))?;
+    }
+    for name in STRING_KEYS {
+        config.set_str(&key(name), &settings.get::<String>(name))?;
+    }
+    Ok(())
+}
+
+/// Back to global settings.
+pub fn disable(path: PathBuf) -> Result<(), git2::Error> {
+    if let Some(config_path) = config_path(&path) {
+        if config_path.exists() {
+            fs::remove_file(&config_path).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_bool(path: Option<&PathBuf>, settings: &gio::Settings, name: &str) -> bool {
+    open_config(path)
+        .and_then(|config| config.get_bool(&key(name)).ok())
+        .unwrap_or_else(|| settings.get::<bool>(name))
+}
+
+pub fn get_string(path: Option<&PathBuf>, settings: &gio::Settings, name: &str) -> String {
+    open_config(path)
+        .and_then(|config| config.get_string(&key(name)).ok())
+        .unwrap_or_else(|| settings.get::<String>(name))
+}
+
+pub fn set_bool(path: Option<&PathBuf>, settings: &gio::Settings, name: &str, value: bool) {
+    if let Some(mut config) = open_config(path) {
+        if config.set_bool(&key(name), value).is_ok() {
+            return;
+        }
+    }
+    settings.set(name, value).expect("cant set settings");
+}
+
+pub fn set_string(path: Option<&PathBuf>, settings: &gio::Settings, name: &str, value: String) {
+    if let Some(mut config) = open_config(path) {
+        if config.set_str(&key(name), &value).is_ok() {
+            return;
+        }
+    }
+    settings.set(name, value).expect("cant set settings");
+}
+
+/// Settings which are read deep inside git ops, where
+/// only repo is at hand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Applied {
+    pub ignore_whitespace: bool,
+    pub run_hooks: bool,
+    pub ssh_key: Option<PathBuf>,
+}
+
+impl Default for Applied {
+    fn default() -> Self {
+        Self {
+            ignore_whitespace: false,
+            run_hooks: true,
+            ssh_key: None,
+        }
+    }
+}
+
+// keyed by .git dir of repo. every tab applies settings of its
+// own repo, so ops in background tabs do not see settings
+// of the selected one
+static APPLIED: Mutex<Option<HashMap<PathBuf, Applied>>> = Mutex::new(None);
+
+/// Settings applied for repo, defaults when nothing is applied yet.
+pub fn applied(repo: &git2::Repository) -> Applied {
+    APPLIED
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|applied| applied.get(repo.path()).cloned())
+        .unwrap_or_default()
+}
+
+fn ssh_key_path(key: &str) -> Option<PathBuf> {
+    let key = key.trim();
+    if key.is_empty() {
+        None
+    } else if let (Some(rest), Ok(home)) = (key.strip_prefix("~/"), std::env::var("HOME")) {
+        Some(PathBuf::from(home).join(rest))
+    } else {
+        Some(PathBuf::from(key))
+    }
+}
+
+/// Loads settings for repo at path: repo layer if present,
+/// globals otherwise. Returns true if diffs must be rebuilt.
+pub fn apply(path: Option<&PathBuf>, settings: &gio::Settings) -> bool {
+    let Some(repo) = path.and_then(|path| git2::Repository::open(path).ok()) else {
+        return false;
+    };
+    let new = Applied {
+        ignore_whitespace: get_bool(path, settings, IGNORE_WHITESPACE_TOKEN),
+        run_hooks: get_bool(path, settings, RUN_HOOKS_TOKEN),
+        ssh_key: ssh_key_path(&get_string(path, settings, SSH_KEY_TOKEN)),
+    };
+    let previous = APPLIED
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(repo.path().to_path_buf(), new.clone())
+        .unwrap_or_default();
+    previous.ignore_whitespace != new.ignore_whitespace
+}
@@ -2,10 +2,17 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::git::DeferRefresh;
+use crate::git::{
+    delete_untracked, make_diff_options, stage_via_apply, undo, untracked_files, DeferRefresh, Hunk,
+};
+use crate::StageOp;
 use async_channel::Sender;
 use git2;
 
+use std::cell::Cell;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
@@ -103,12 +110,241 @@ pub fn stash(
     Ok(Some(list(path, sender)))
 }
 
+// tree with untracked entries only, same as 3rd parent of stash
+// made by git stash -u
+fn untracked_tree(
+    repo: &git2::Repository,
+    path: PathBuf,
+    entries: Vec<PathBuf>,
+) -> Result<git2::Oid, git2::Error> {
+    let workdir = repo.workdir().ok_or(git2::Error::from_str("bare repo"))?;
+    let mut index = git2::Index::new()?;
+    for file in untracked_files(path, entries, false)? {
+        let full_path = workdir.join(&file);
+        let read_error =
+            |e: std::io::Error| git2::Error::from_str(&format!("can't read {:?}: {}", file, e));
+        // symlink is stored as link itself, not as file it points to
+        let metadata = fs::symlink_metadata(&full_path).map_err(read_error)?;
+        let (mode, id) = if metadata.file_type().is_symlink() {
+            let target = fs::read_link(&full_path).map_err(read_error)?;
+            (0o120000, repo.blob(target.as_os_str().as_bytes())?)
+        } else if metadata.permissions().mode() & 0o111 != 0 {
+            (0o100755, repo.blob_path(&full_path)?)
+        } else {
+            (0o100644, repo.blob_path(&full_path)?)
+        };
+        index.add(&git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode,
+            uid: 0,
+            gid: 0,
+            file_size: metadata.len() as u32,
+            id,
+            flags: 0,
+            flags_extended: 0,
+            path: file.to_string_lossy().as_bytes().to_vec(),
+        })?;
+    }
+    index.write_tree_to(repo)
+}
+
+/// Part of working tree which goes to partial stash. It is
+/// selected the same way as for stage/kill at cursor.
+#[derive(Debug, Clone)]
+pub enum StashSelection {
+    // all unstaged changes, changes in file or single hunk
+    Unstaged(Option<PathBuf>, Option<String>),
+    Untracked(Vec<PathBuf>),
+}
+
+/// Stashes selected changes, leaving the rest in working tree.
+/// Stash commits are made by hand the way git stash makes them:
+/// index commit is HEAD itself, so only selected changes go
+/// to stash. Untracked files go to stash only when selected.
+pub fn stash_selected(
+    path: PathBuf,
+    stash_message: String,
+    selection: StashSelection,
+    sender: Sender<crate::Event>,
+) -> Result<Option<Stashes>, git2::Error> {
+    let _defer = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = git2::Repository::open(path.clone())?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    let me = repo.signature()?;
+
+    let head = repo.head()?;
+    let branch = if head.is_branch() {
+        head.shorthand().unwrap_or("(no branch)").to_string()
+    } else {
+        "(no branch)".to_string()
+    };
+    let head_commit = head.peel_to_commit()?;
+    let head_tree = head_commit.tree()?;
+    let summary = format!(
+        "{} {}",
+        &head_commit.id().to_string()[..7],
+        head_commit.summary().unwrap_or("")
+    );
+
+    let work_tree = match &selection {
+        StashSelection::Unstaged(file_path, hunk_header) => {
+            let mut opts = make_diff_options();
+            if let Some(file_path) = file_path {
+                opts.pathspec(file_path);
+            }
+            let git_diff = repo.diff_index_to_workdir(None, Some(&mut opts))?;
+            let hunk_found = Cell::new(false);
+            let mut apply_options = git2::ApplyOptions::new();
+            apply_options.hunk_callback(|odh| -> bool {
+                let (Some(hunk_header), Some(dh)) = (hunk_header, odh) else {
+                    return true;
+                };
+                let found = &Hunk::get_header_from(&dh) == hunk_header;
+                if found {
+                    hunk_found.replace(true);
+                }
+                found
+            });
+            // changes are taken from index, but go on top of HEAD
+            let mut work_index = repo
+                .apply_to_tree(&head_tree, &git_diff, Some(&mut apply_options))
+                .map_err(|e| {
+                    git2::Error::from_str(&format!(
+                        "Can't stash changes: {}. Are there staged changes in the same files?",
+                        e.message()
+                    ))
+                })?;
+            if hunk_header.is_some() && !hunk_found.get() {
+                return Err(git2::Error::from_str("Hunk is not found. Try to refresh."));
+            }
+            repo.find_tree(work_index.write_tree_to(&repo)?)?
+        }
+        StashSelection::Untracked(_) => head_tree.clone(),
+    };
+
+    let index_oid = repo.commit(
+        None,
+        &me,
+        &me,
+        &format!("index on {}: {}", branch, summary),
+        &head_tree,
+        &[&head_commit],
+    )?;
+    let index_commit = repo.find_commit(index_oid)?;
+    let mut parents = vec![head_commit.clone(), index_commit];
+
+    if let StashSelection::Untracked(entries) = &selection {
+        let tree = repo.find_tree(untracked_tree(&repo, path.clone(), entries.clone())?)?;
+        let untracked_oid = repo.commit(
+            None,
+            &me,
+            &me,
+            &format!("untracked files on {}: {}", branch, summary),
+            &tree,
+            &[],
+        )?;
+        parents.push(repo.find_commit(untracked_oid)?);
+    }
+
+    let message = if stash_message.is_empty() {
+        format!("WIP on {}: {}", branch, summary)
+    } else {
+        format!("On {}: {}", branch, stash_message)
+    };
+    let stash_oid = repo.commit(
+        None,
+        &me,
+        &me,
+        &message,
+        &work_tree,
+        &parents.iter().collect::<Vec<&git2::Commit>>(),
+    )?;
+    // stash list is a reflog of refs/stash
+    repo.reference_ensure_log("refs/stash")?;
+    repo.reference("refs/stash", stash_oid, true, &message)?;
+
+    // stash is saved. now selected changes leave workdir
+    match selection {
+        StashSelection::Unstaged(file_path, hunk_header) => {
+            stage_via_apply(
+                path.clone(),
+                file_path,
+                hunk_header,
+                StageOp::Kill,
+                sender.clone(),
+            )?;
+        }
+        StashSelection::Untracked(entries) => {
+            delete_untracked(path.clone(), entries, false, sender.clone())?;
+        }
+    }
+    Ok(Some(list(path, sender)))
+}
+
+// blobs of untracked tree (3rd parent of stash), if stash has one
+fn stashed_untracked(
+    repo: &git2::Repository,
+    stash_oid: git2::Oid,
+    file_path: Option<&PathBuf>,
+) -> Result<Vec<(PathBuf, git2::Oid, i32)>, git2::Error> {
+    let stash_commit = repo.find_commit(stash_oid)?;
+    if stash_commit.parent_count() < 3 {
+        return Ok(Vec::new());
+    }
+    let tree = stash_commit.parent(2)?.tree()?;
+    let mut entries = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob) {
+            let entry_path = PathBuf::from(root).join(entry.name().unwrap_or_default());
+            if file_path.is_none_or(|file_path| file_path == &entry_path) {
+                entries.push((entry_path, entry.id(), entry.filemode()));
+            }
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    Ok(entries)
+}
+
+// same as git stash apply, untracked files are never overwritten
+fn restore_untracked(
+    repo: &git2::Repository,
+    entries: &[(PathBuf, git2::Oid, i32)],
+) -> Result<(), git2::Error> {
+    let workdir = repo.workdir().unwrap_or(repo.path());
+    for (entry_path, oid, filemode) in entries {
+        undo::write_blob(repo, &workdir.join(entry_path), *oid, *filemode as u32)?;
+    }
+    Ok(())
+}
+
+fn stash_oid(repo: &mut git2::Repository, num: StashNum) -> Result<git2::Oid, git2::Error> {
+    let mut stash_oid = None;
+    repo.stash_foreach(|n, _, oid| {
+        if n == num.as_usize() {
+            stash_oid.replace(*oid);
+            return false;
+        }
+        true
+    })?;
+    stash_oid.ok_or(git2::Error::from_str("stash not found"))
+}
+
+/// Applies stash on top of HEAD. When stash conflicts with HEAD,
+/// changes are merged into index with conflicts, same as git
+/// stash apply does, and conflicting files are returned, so they
+/// go through the usual conflict resolution. Untracked files
+/// of stash are restored as well, unless they already exist.
 pub fn apply(
     path: PathBuf,
     num: StashNum,
     file_path: Option<PathBuf>,
     sender: Sender<crate::Event>,
-) -> Result<(), git2::Error> {
+) -> Result<Vec<PathBuf>, git2::Error> {
     let _defer = DeferRefresh::new(path.clone(), sender.clone(), true, true);
 
     let mut repo = git2::Repository::open(path.clone())?;
@@ -116,13 +352,98 @@ pub fn apply(
         .send_blocking(crate::Event::LockMonitors(true))
         .expect("Could not send through channel");
 
-    let mut stash_options = git2::StashApplyOptions::new();
-    if let Some(file_path) = file_path {
-        let mut cb = git2::build::CheckoutBuilder::new();
+    // stash commit has index as its second parent, so mainline 1
+    // compares it against the commit it was made on
+    let stash_oid = stash_oid(&mut repo, num)?;
+    let mut memory_index = {
+        let stash_commit = repo.find_commit(stash_oid)?;
+        let our_commit = repo.head()?.peel_to_commit()?;
+        repo.cherrypick_commit(&stash_commit, &our_commit, 1, None)?
+    };
+    let mut conflicts = Vec::new();
+    for conflict in memory_index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            let conflict_path = PathBuf::from(String::from_utf8_lossy(&entry.path).to_string());
+            if let Some(file_path) = &file_path {
+                if file_path != &conflict_path {
+                    continue;
+                }
+            }
+            conflicts.push(conflict_path);
+        }
+    }
+
+    let mut cb = git2::build::CheckoutBuilder::new();
+    if let Some(file_path) = &file_path {
         cb.path(file_path);
+    };
+    let applied = if memory_index.has_conflicts() {
+        // stash_apply would refuse to apply anything here,
+        // so untracked files are restored by hand
+        let untracked = stashed_untracked(&repo, stash_oid, file_path.as_ref())?;
+        let workdir = repo.workdir().unwrap_or(repo.path()).to_path_buf();
+        if let Some((existing, _, _)) = untracked
+            .iter()
+            .find(|(entry_path, _, _)| fs::symlink_metadata(workdir.join(entry_path)).is_ok())
+        {
+            return Err(git2::Error::from_str(&format!(
+                "Stash can't be applied: untracked {} already exists.",
+                existing.display()
+            )));
+        }
+        cb.allow_conflicts(true).conflict_style_merge(true);
+        repo.checkout_index(Some(&mut memory_index), Some(&mut cb))
+            .and_then(|_| restore_untracked(&repo, &untracked))
+    } else {
+        let mut stash_options = git2::StashApplyOptions::new();
         stash_options.checkout_options(cb);
+        repo.stash_apply(num.as_usize(), Some(&mut stash_options))
     };
-    repo.stash_apply(num.as_usize(), Some(&mut stash_options))?;
+    // libgit2 refuses to overwrite files having local changes
+    // with the same code it uses for merge conflicts
+    applied.map_err(|e| {
+        if e.code() == git2::ErrorCode::Conflict {
+            git2::Error::from_str(&format!(
+                "Stash can't be applied: {}. Commit or stash local changes first.",
+                e.message()
+            ))
+        } else {
+            e
+        }
+    })?;
+    Ok(conflicts)
+}
+
+/// Creates new branch on the commit stash was made on, checks it out
+/// and applies stash there, where it can't conflict. Stash is dropped
+/// afterwards, like git stash branch does.
+pub fn apply_on_branch(
+    path: PathBuf,
+    num: StashNum,
+    branch_name: String,
+    sender: Sender<crate::Event>,
+) -> Result<(), git2::Error> {
+    let _defer = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+
+    let mut repo = git2::Repository::open(path.clone())?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+
+    let stash_oid = stash_oid(&mut repo, num)?;
+    {
+        let base = repo.find_commit(stash_oid)?.parent(0)?;
+        let branch = repo.branch(&branch_name, &base, false)?;
+        let branch_ref = branch.get().name().unwrap_or_default().to_string();
+
+        let mut cb = git2::build::CheckoutBuilder::new();
+        cb.safe();
+        repo.checkout_tree(base.as_object(), Some(&mut cb))?;
+        repo.set_head(&branch_ref)?;
+    }
+    repo.stash_apply(num.as_usize(), None)?;
+    repo.stash_drop(num.as_usize())?;
     Ok(())
 }
 
@@ -132,3 +453,86 @@ pub fn drop(path: PathBuf, stash_data: StashData, sender: Sender<crate::Event>)
         .expect("cant drop stash");
     list(path, sender)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::unstaged_diff;
+    use crate::tests::{commit_files, temp_repo};
+
+    #[test]
+    fn test_stash_hunk_round_trip() {
+        let (path, repo) = temp_repo("stash-hunk");
+        let lines: Vec<String> = (0..20).map(|i| format!("line {}\n", i)).collect();
+        let original = lines.concat();
+        commit_files(&repo, &[("a.txt", &original)], "initial");
+
+        let mut first = lines.clone();
+        first[1] = String::from("first change\n");
+        let mut both = first.clone();
+        both[18] = String::from("second change\n");
+        let mut second = lines.clone();
+        second[18] = String::from("second change\n");
+        fs::write(path.join("a.txt"), both.concat()).unwrap();
+
+        let hunk_header = unstaged_diff(&repo).unwrap().files[0].hunks[0]
+            .header
+            .clone();
+        let (sender, _receiver) = async_channel::unbounded();
+        stash_selected(
+            path.clone(),
+            String::from("first"),
+            StashSelection::Unstaged(Some(PathBuf::from("a.txt")), Some(hunk_header)),
+            sender.clone(),
+        )
+        .unwrap();
+        // only stashed hunk leaves workdir
+        assert_eq!(
+            fs::read_to_string(path.join("a.txt")).unwrap(),
+            second.concat()
+        );
+
+        fs::write(path.join("a.txt"), &original).unwrap();
+        apply(path.clone(), StashNum::new(0), None, sender).unwrap();
+        assert_eq!(
+            fs::read_to_string(path.join("a.txt")).unwrap(),
+            first.concat()
+        );
+    }
+
+    #[test]
+    fn test_apply_with_conflicts() {
+        let (path, mut repo) = temp_repo("stash-conflicts");
+        commit_files(&repo, &[("a.txt", "a\n")], "initial");
+
+        fs::write(path.join("a.txt"), "stashed\n").unwrap();
+        fs::write(path.join("u.txt"), "untracked\n").unwrap();
+        let me = repo.signature().unwrap();
+        repo.stash_save(
+            &me,
+            "conflicting",
+            Some(git2::StashFlags::INCLUDE_UNTRACKED),
+        )
+        .unwrap();
+
+        commit_files(&repo, &[("a.txt", "committed\n")], "conflicting");
+        let (sender, _receiver) = async_channel::unbounded();
+        let conflicts = apply(path.clone(), StashNum::new(0), None, sender).unwrap();
+        assert_eq!(conflicts, vec![PathBuf::from("a.txt")]);
+
+        let mut index = repo.index().unwrap();
+        index.read(true).unwrap();
+        assert!(index.has_conflicts());
+        let conflicted: Vec<Vec<u8>> = index
+            .conflicts()
+            .unwrap()
+            .map(|conflict| conflict.unwrap().our.unwrap().path)
+            .collect();
+        assert_eq!(conflicted, vec![b"a.txt".to_vec()]);
+        assert_eq!(
+            fs::read_to_string(path.join("u.txt")).unwrap(),
+            "untracked\n"
+        );
+        fs::remove_dir_all(path).unwrap();
+    }
+}
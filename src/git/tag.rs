@@ -2,8 +2,9 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::git::commit::{CommitLog, CommitRelation};
+use crate::git::commit::{git_time_dt, CommitLog, CommitRelation};
 use async_channel::Sender;
+use chrono::{DateTime, FixedOffset};
 use git2;
 use log::info;
 use std::path::PathBuf;
@@ -14,6 +15,8 @@ pub struct Tag {
     pub name: String,
     pub commit: CommitLog,
     pub message: String,
+    // tagger date. lightweight tags have commit date here
+    pub dt: DateTime<FixedOffset>,
 }
 
 impl Tag {
@@ -21,11 +24,13 @@ impl Tag {
         let mut encoded = String::from("");
         html_escape::encode_safe_to_string(message, &mut encoded);
         let name = name.replace("refs/tags/", "");
+        let dt = commit.commit_dt;
         Tag {
             oid,
             name,
             commit,
             message: encoded,
+            dt,
         }
     }
 }
@@ -37,22 +42,45 @@ impl Default for Tag {
             name: String::from(""),
             commit: CommitLog::default(),
             message: String::from(""),
+            dt: DateTime::<FixedOffset>::MIN_UTC.into(),
         }
     }
 }
 
 pub const TAG_PAGE_SIZE: usize = 100;
 
+// annotated tag has its own date. lightweight one
+// falls back to date of target commit
+fn tag_dt(repo: &git2::Repository, oid: git2::Oid) -> Option<DateTime<FixedOffset>> {
+    if let Ok(tag) = repo.find_tag(oid) {
+        if let Some(tagger) = tag.tagger() {
+            return Some(git_time_dt(tagger.when()));
+        }
+        return Some(git_time_dt(
+            tag.target().ok()?.peel_to_commit().ok()?.time(),
+        ));
+    }
+    Some(git_time_dt(repo.find_commit(oid).ok()?.time()))
+}
+
+/// Page of tags starting from start_oid (inclusive).
+/// Tags come in repo order or newest first when by_date.
+/// Dates are cheap to get, so whole set is sorted, but
+/// full Tag is built only for requested page.
 pub fn get_tag_list(
     path: PathBuf,
     start_oid: Option<git2::Oid>,
     search_term: Option<String>,
+    by_date: bool,
 ) -> Result<Vec<Tag>, git2::Error> {
-    info!("get_tag_list {:?} {:?}", start_oid, search_term);
+    info!(
+        "get_tag_list {:?} {:?} by date {}",
+        start_oid, search_term, by_date
+    );
     let repo = git2::Repository::open(path.clone())?;
     let mut result = Vec::new();
     let mut cnt = 0;
-    repo.tag_foreach(|oid, name| {
+    let mut visit = |oid: git2::Oid, name: &[u8]| {
         if cnt == 0 {
             if let Some(begin_oid) = start_oid {
                 if oid != begin_oid {
@@ -61,9 +89,11 @@ pub fn get_tag_list(
             }
         }
         let message: String;
+        let mut dt = None;
         let commit = {
             if let Ok(tag) = repo.find_tag(oid) {
                 message = String::from(tag.message().unwrap_or(""));
+                dt = tag.tagger().map(|tagger| git_time_dt(tagger.when()));
                 let ob = tag.target().unwrap();
                 ob.peel_to_commit().unwrap()
             } else {
@@ -82,13 +112,32 @@ pub fn get_tag_list(
             }
         }
         let commit_log = CommitLog::from_log(commit, CommitRelation::None);
-        result.push(Tag::new(oid, tag_name, commit_log, message));
+        let mut tag = Tag::new(oid, tag_name, commit_log, message);
+        if let Some(dt) = dt {
+            tag.dt = dt;
+        }
+        result.push(tag);
         cnt += 1;
         if cnt == TAG_PAGE_SIZE {
             return false;
         }
         true
-    })?;
+    };
+    if by_date {
+        let mut all: Vec<(git2::Oid, Vec<u8>, Option<DateTime<FixedOffset>>)> = Vec::new();
+        repo.tag_foreach(|oid, name| {
+            all.push((oid, name.to_vec(), tag_dt(&repo, oid)));
+            true
+        })?;
+        all.sort_by(|a, b| b.2.cmp(&a.2));
+        for (oid, name, _) in all {
+            if !visit(oid, &name) {
+                break;
+            }
+        }
+    } else {
+        repo.tag_foreach(visit)?;
+    }
     info!("returning result {:?}", cnt);
     Ok(result)
 }
@@ -112,7 +161,11 @@ pub fn create_tag(
     };
     let commit = target.peel_to_commit()?;
     let commit_log = CommitLog::from_log(commit, CommitRelation::None);
-    Ok(Some(Tag::new(created_oid, tag_name, commit_log, message)))
+    let mut tag = Tag::new(created_oid, tag_name, commit_log, message);
+    if let Some(dt) = tag_dt(&repo, created_oid) {
+        tag.dt = dt;
+    }
+    Ok(Some(tag))
 }
 
 pub fn kill_tag(
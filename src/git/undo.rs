@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! One level undo of stage/unstage/kill.
+//! Instead of applying inverse op (hunk headers are already
+//! different after op) the state before op is stored and
+//! restored back: index as a tree, killed files as blobs.
+//! Unlike stash, only files touched by kill are stored, so
+//! other changes are left as they are on undo.
+use crate::git::DeferRefresh;
+use crate::StageOp;
+use async_channel::Sender;
+use git2::{Error, Oid, Repository};
+use log::info;
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum Snapshot {
+    // tree written from index
+    Index(Oid),
+    // killed files in workdir: blob and mode, None if there was no file
+    Workdir(Vec<(PathBuf, Option<(Oid, u32)>)>),
+}
+
+fn to_git_error(e: std::io::Error) -> Error {
+    Error::from_str(&e.to_string())
+}
+
+/// Writes blob to workdir, creating missing dirs. Mode is file mode
+/// from metadata or git filemode of tree entry: only permission bits
+/// and symlink type are taken from it.
+pub fn write_blob(repo: &Repository, full_path: &Path, oid: Oid, mode: u32) -> Result<(), Error> {
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent).map_err(to_git_error)?;
+    }
+    let blob = repo.find_blob(oid)?;
+    if mode & 0o170000 == 0o120000 {
+        if fs::symlink_metadata(full_path).is_ok() {
+            fs::remove_file(full_path).map_err(to_git_error)?;
+        }
+        return std::os::unix::fs::symlink(OsStr::from_bytes(blob.content()), full_path)
+            .map_err(to_git_error);
+    }
+    fs::write(full_path, blob.content()).map_err(to_git_error)?;
+    fs::set_permissions(full_path, fs::Permissions::from_mode(mode & 0o7777)).map_err(to_git_error)
+}
+
+/// State which will be changed by op: workdir files for Kill,
+/// index otherwise. Must be called right before op.
+pub fn snapshot(path: &Path, op: StageOp, files: &[PathBuf]) -> Result<Snapshot, Error> {
+    let repo = Repository::open(path)?;
+    if op != StageOp::Kill {
+        // fails on conflicts, so there is no undo for ops during merge
+        return Ok(Snapshot::Index(repo.index()?.write_tree()?));
+    }
+    let workdir = repo.workdir().ok_or(Error::from_str("bare repo"))?;
+    let mut stored = Vec::new();
+    for file_path in files {
+        let full_path = workdir.join(file_path);
+        let blob = match fs::metadata(&full_path) {
+            Ok(metadata) => Some((repo.blob_path(&full_path)?, metadata.permissions().mode())),
+            Err(_) => None,
+        };
+        stored.push((file_path.clone(), blob));
+    }
+    Ok(Snapshot::Workdir(stored))
+}
+
+pub fn restore(
+    path: PathBuf,
+    snapshot: Snapshot,
+    sender: Sender<crate::Event>,
+) -> Result<(), Error> {
+    info!("undo {:?}", snapshot);
+    let _updater = DeferRefresh::new(path.clone(), sender.clone(), true, true);
+    let repo = Repository::open(path)?;
+    sender
+        .send_blocking(crate::Event::LockMonitors(true))
+        .expect("Could not send through channel");
+    match snapshot {
+        Snapshot::Index(tree_oid) => {
+            let mut index = repo.index()?;
+            index.read_tree(&repo.find_tree(tree_oid)?)?;
+            index.write()
+        }
+        Snapshot::Workdir(files) => {
+            let workdir = repo.workdir().ok_or(Error::from_str("bare repo"))?;
+            for (file_path, blob) in files {
+                let full_path = workdir.join(&file_path);
+                match blob {
+                    Some((oid, mode)) => {
+                        write_blob(&repo, &full_path, oid, mode)?;
+                    }
+                    None if full_path.exists() => {
+                        fs::remove_file(&full_path).map_err(to_git_error)?;
+                    }
+                    None => {}
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{commit_files, temp_repo};
+
+    #[test]
+    fn test_restore_killed_files() {
+        let (path, repo) = temp_repo("undo-kill");
+        commit_files(&repo, &[("a.txt", "a\n")], "initial");
+        fs::write(path.join("a.txt"), "changed\n").unwrap();
+        fs::create_dir_all(path.join("dir")).unwrap();
+        fs::write(path.join("dir/new.txt"), "untracked\n").unwrap();
+        let files = vec![PathBuf::from("a.txt"), PathBuf::from("dir/new.txt")];
+
+        let stored = snapshot(&path, StageOp::Kill, &files).unwrap();
+        fs::write(path.join("a.txt"), "a\n").unwrap();
+        fs::remove_dir_all(path.join("dir")).unwrap();
+
+        let (sender, _receiver) = async_channel::unbounded();
+        restore(path.clone(), stored, sender).unwrap();
+        assert_eq!(fs::read_to_string(path.join("a.txt")).unwrap(), "changed\n");
+        assert_eq!(
+            fs::read_to_string(path.join("dir/new.txt")).unwrap(),
+            "untracked\n"
+        );
+    }
+
+    #[test]
+    fn test_restore_index() {
+        let (path, repo) = temp_repo("undo-index");
+        commit_files(&repo, &[("a.txt", "a\n")], "initial");
+        fs::write(path.join("a.txt"), "staged\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        let staged_tree = index.write_tree().unwrap();
+
+        let stored = snapshot(&path, StageOp::Unstage, &[]).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.reset(head.as_object(), git2::ResetType::Mixed, None)
+            .unwrap();
+
+        let (sender, _receiver) = async_channel::unbounded();
+        restore(path.clone(), stored, sender).unwrap();
+        let repo = Repository::open(&path).unwrap();
+        assert_eq!(repo.index().unwrap().write_tree().unwrap(), staged_tree);
+    }
+}
@@ -0,0 +1,161 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use git2;
+use log::info;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct WorktreeInfo {
+    // None for main worktree
+    pub name: Option<String>,
+    pub path: PathBuf,
+    pub branch: Option<String>,
+    pub is_current: bool,
+    // worktree dir could be removed without git worktree remove
+    pub exists: bool,
+    pub is_locked: bool,
+}
+
+// worktrees are listed and added from main repo
+// even if current one is linked worktree itself
+fn main_repo(path: &Path) -> Result<(git2::Repository, Option<PathBuf>), git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let current = repo.workdir().map(|p| p.to_path_buf());
+    if repo.is_worktree() {
+        return Ok((git2::Repository::open(repo.commondir())?, current));
+    }
+    Ok((repo, current))
+}
+
+fn branch_of(repo: &git2::Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if head.is_branch() {
+        head.shorthand().map(|s| s.to_string())
+    } else {
+        head.target().map(|oid| oid.to_string()[..7].to_string())
+    }
+}
+
+fn same_path(a: &Path, b: Option<&PathBuf>) -> bool {
+    let Some(b) = b else {
+        return false;
+    };
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+pub fn list(path: PathBuf) -> Result<Vec<WorktreeInfo>, git2::Error> {
+    let (repo, current) = main_repo(&path)?;
+    let mut result = Vec::new();
+    if let Some(workdir) = repo.workdir() {
+        result.push(WorktreeInfo {
+            name: None,
+            path: workdir.to_path_buf(),
+            branch: branch_of(&repo),
+            is_current: same_path(workdir, current.as_ref()),
+            exists: true,
+            is_locked: false,
+        });
+    }
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        let exists = worktree.validate().is_ok();
+        let branch = if exists {
+            git2::Repository::open_from_worktree(&worktree)
+                .ok()
+                .and_then(|wt_repo| branch_of(&wt_repo))
+        } else {
+            None
+        };
+        result.push(WorktreeInfo {
+            name: Some(name.to_string()),
+            path: worktree.path().to_path_buf(),
+            branch,
+            is_current: same_path(worktree.path(), current.as_ref()),
+            exists,
+            is_locked: !matches!(worktree.is_locked(), Ok(git2::WorktreeLockStatus::Unlocked)),
+        });
+    }
+    Ok(result)
+}
+
+/// Suggested worktree dir for branch: sibling of workdir, ../repo-branch
+pub fn default_path(path: &Path, branch_name: &str) -> Result<PathBuf, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or(git2::Error::from_str("bare repository has no workdir"))?;
+    let repo_dir = workdir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let parent = workdir.parent().unwrap_or(workdir);
+    Ok(parent.join(format!("{}-{}", repo_dir, branch_name.replace('/', "-"))))
+}
+
+/// Checks out local branch in new worktree at worktree_path.
+pub fn add(
+    path: PathBuf,
+    branch_name: String,
+    worktree_path: PathBuf,
+) -> Result<WorktreeInfo, git2::Error> {
+    info!("add worktree {:?} for {}", worktree_path, branch_name);
+    let (repo, _) = main_repo(&path)?;
+    let name = worktree_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or(git2::Error::from_str("wrong worktree path"))?
+        .to_string();
+    let branch = repo.find_branch(&branch_name, git2::BranchType::Local)?;
+    let mut opts = git2::WorktreeAddOptions::new();
+    opts.reference(Some(branch.get()));
+    let worktree = repo.worktree(&name, &worktree_path, Some(&opts))?;
+    Ok(WorktreeInfo {
+        name: Some(name),
+        path: worktree.path().to_path_buf(),
+        branch: Some(branch_name),
+        is_current: false,
+        exists: true,
+        is_locked: false,
+    })
+}
+
+/// Removes git metadata of worktree which dir is gone.
+pub fn prune(path: PathBuf, name: String) -> Result<(), git2::Error> {
+    info!("prune worktree {}", name);
+    let (repo, _) = main_repo(&path)?;
+    let worktree = repo.find_worktree(&name)?;
+    if worktree.validate().is_ok() {
+        return Err(git2::Error::from_str(&format!(
+            "Worktree {} still exists at {:?}",
+            name,
+            worktree.path()
+        )));
+    }
+    worktree.prune(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_repo;
+
+    #[test]
+    fn test_default_path() {
+        let (path, repo) = temp_repo("worktree-default");
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        let repo_dir = workdir.file_name().unwrap().to_string_lossy().to_string();
+        // status keeps path of .git dir
+        let default = default_path(repo.path(), "feature/x").unwrap();
+        assert_eq!(default.parent(), workdir.parent());
+        assert_eq!(
+            default.file_name().unwrap().to_string_lossy(),
+            format!("{}-feature-x", repo_dir)
+        );
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Named actions of key handlers with their accelerators.
+//! Defaults below could be overridden in "keybindings" setting,
+//! e.g. {'status.stage': 'j Return', 'branches.kill': '<shift>d'}.
+//! Several accelerators for action are separated with space,
+//! same as in ShortcutsShortcut.
+use gtk4::prelude::*;
+use gtk4::{gdk, gio, EventControllerKey};
+use log::{debug, error};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub const KEYBINDINGS_TOKEN: &str = "keybindings";
+
+// order matters when user binds same key to several actions:
+// first one wins
+const DEFAULTS: [(&str, &str); 72] = [
+    ("status.expand", "Tab space"),
+    ("status.stage_all", "<ctrl>s"),
+    ("status.unstage_all", "<ctrl>u"),
+    ("status.stage", "s a Return"),
+    ("status.unstage", "u r"),
+    ("status.kill", "k Delete BackSpace"),
+    ("status.undo", "<ctrl>z"),
+    ("status.ignore", "i"),
+    ("status.blame", "<ctrl>b"),
+    ("status.blame_file", "<ctrl><shift>b"),
+    ("status.show_file", "v"),
+    ("status.bisect_good", "<shift>g"),
+    ("status.bisect_bad", "<shift>b"),
+    ("status.mergetool", "m"),
+    ("status.next_conflict", "n"),
+    ("status.prev_conflict", "<shift>n"),
+    ("status.squash", "<shift>s"),
+    ("status.abort", "<shift>a"),
+    ("status.branch_switcher", "<shift>c"),
+    ("status.branch_at_head", "<shift>h"),
+    ("status.commit", "c"),
+    ("status.inline_commit", "<ctrl>Return <ctrl>KP_Enter"),
+    ("status.push", "p"),
+    ("status.pull", "f"),
+    ("status.pull_rebase", "<shift>f"),
+    ("status.branches", "b"),
+    ("status.reflog", "<shift>l"),
+    ("status.log", "l"),
+    ("status.refresh", "g"),
+    ("status.paste_path", "<ctrl>v"),
+    ("status.open", "<ctrl>o"),
+    ("status.repo_popup", "o"),
+    ("status.stashes", "z"),
+    ("status.worktrees", "w"),
+    ("status.dump", "<ctrl>d"),
+    ("status.debug", "d"),
+    ("status.zoom_in", "<ctrl>equal"),
+    ("status.zoom_out", "<ctrl>minus"),
+    ("status.next_file", "bracketright"),
+    ("status.prev_file", "bracketleft"),
+    ("status.expand_all", "plus KP_Add"),
+    ("status.expand_all_global", "<alt>plus <alt>KP_Add"),
+    ("status.collapse_all", "minus KP_Subtract"),
+    ("status.collapse_all_global", "<alt>minus <alt>KP_Subtract"),
+    ("status.editor", "e"),
+    ("status.tags", "t"),
+    ("status.copy_hunk", "y"),
+    ("status.search", "<ctrl>f slash"),
+    ("status.next_match", "<ctrl>g"),
+    ("status.prev_match", "<ctrl><shift>g"),
+    ("status.copy_hunk_with_prefixes", "<shift>y"),
+    ("status.apply_patch", "<ctrl><shift>a"),
+    ("status.export_patch", "<ctrl>e"),
+    ("branches.create", "c n"),
+    ("branches.kill", "k"),
+    ("branches.merge", "m"),
+    ("branches.rebase", "r"),
+    ("branches.merge_base", "b"),
+    ("branches.log", "l"),
+    ("branches.cherry_pick", "a"),
+    ("branches.pull", "p"),
+    ("branches.rename", "F2"),
+    ("branches.upstream", "t"),
+    ("branches.worktree", "w"),
+    ("branches.copy", "y"),
+    ("branches.update_remote", "u"),
+    ("branches.search", "s"),
+    ("tags.search", "s"),
+    ("tags.create", "c n"),
+    ("tags.kill", "k d"),
+    ("tags.push_all", "<shift>p"),
+    ("tags.push", "p"),
+];
+
+struct Binding {
+    name: &'static str,
+    accelerator: String,
+    keys: Vec<(gdk::Key, gdk::ModifierType)>,
+}
+
+thread_local! {
+    static KEYMAP: RefCell<Vec<Binding>> = RefCell::new(build(&HashMap::new()));
+}
+
+fn parse(accelerator: &str) -> Option<Vec<(gdk::Key, gdk::ModifierType)>> {
+    accelerator
+        .split_whitespace()
+        .map(gtk4::accelerator_parse)
+        .collect()
+}
+
+fn build(overrides: &HashMap<String, String>) -> Vec<Binding> {
+    DEFAULTS
+        .iter()
+        .map(|(name, default)| {
+            let accelerator = match overrides.get(*name) {
+                Some(user) => match parse(user) {
+                    Some(keys) if !keys.is_empty() => {
+                        debug!("keybinding {} {}", name, user);
+                        return Binding {
+                            name,
+                            accelerator: user.clone(),
+                            keys,
+                        };
+                    }
+                    _ => {
+                        error!("wrong accelerator for {}: {:?}", name, user);
+                        default
+                    }
+                },
+                None => default,
+            };
+            Binding {
+                name,
+                accelerator: accelerator.to_string(),
+                keys: parse(accelerator).expect("default accelerators are valid"),
+            }
+        })
+        .collect()
+}
+
+pub fn load(settings: &gio::Settings) {
+    let overrides = settings.get::<HashMap<String, String>>(KEYBINDINGS_TOKEN);
+    for name in overrides.keys() {
+        if !DEFAULTS.iter().any(|(default, _)| default == name) {
+            error!("unknown action in keybindings {}", name);
+        }
+    }
+    KEYMAP.replace(build(&overrides));
+}
+
+/// Action in scope (status, branches, tags) for current key event
+/// of controller. Returns name without scope, e.g. "stage".
+/// Key event does the matching, so layouts where accelerator
+/// requires shift (e.g. plus) and caps lock are handled like in
+/// gtk shortcuts.
+pub fn action(controller: &EventControllerKey, scope: &str) -> Option<&'static str> {
+    let event = controller
+        .current_event()?
+        .downcast::<gdk::KeyEvent>()
+        .ok()?;
+    KEYMAP.with_borrow(|keymap| {
+        keymap
+            .iter()
+            .filter_map(|binding| {
+                let name = binding.name.strip_prefix(scope)?.strip_prefix('.')?;
+                Some((name, binding))
+            })
+            .find(|(_, binding)| {
+                binding
+                    .keys
+                    .iter()
+                    .any(|(key, modifier)| event.matches(*key, *modifier) == gdk::KeyMatch::Exact)
+            })
+            .map(|(name, _)| name)
+    })
+}
+
+/// Current accelerators for action, for shortcuts window.
+pub fn accelerator(name: &str) -> String {
+    KEYMAP.with_borrow(|keymap| {
+        keymap
+            .iter()
+            .find(|binding| binding.name == name)
+            .map(|binding| binding.accelerator.clone())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_overrides() {
+        let mut overrides = HashMap::new();
+        overrides.insert(String::from("status.stage"), String::from("j <ctrl>Return"));
+        overrides.insert(String::from("status.commit"), String::from("<nonsense"));
+        let keymap = build(&overrides);
+        assert_eq!(keymap.len(), DEFAULTS.len());
+        let stage = keymap.iter().find(|b| b.name == "status.stage").unwrap();
+        assert_eq!(stage.accelerator, "j <ctrl>Return");
+        assert_eq!(
+            stage.keys,
+            vec![
+                (gdk::Key::j, gdk::ModifierType::empty()),
+                (gdk::Key::Return, gdk::ModifierType::CONTROL_MASK)
+            ]
+        );
+        // wrong accelerator falls back to default
+        let commit = keymap.iter().find(|b| b.name == "status.commit").unwrap();
+        assert_eq!(commit.accelerator, "c");
+    }
+}
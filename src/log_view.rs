@@ -2,8 +2,9 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::dialogs::{alert, DangerDialog, YES};
-use crate::git::{commit, git_log};
+use crate::commit_view::{copy_commit_url, copy_oid};
+use crate::dialogs::{alert, confirm_dialog_factory, DangerDialog, PROCEED, YES};
+use crate::git::{commit, format_patch, git_log};
 use crate::{CurrentWindow, DARK_CLASS, LIGHT_CLASS};
 use async_channel::Sender;
 use core::time::Duration;
@@ -12,9 +13,9 @@ use glib::Object;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{
-    gdk, gio, glib, pango, Box, Button, EventControllerKey, GestureClick, Image, Label, ListItem,
-    ListView, Orientation, PositionType, ScrolledWindow, SearchBar, SearchEntry,
-    SignalListItemFactory, SingleSelection, Widget,
+    gdk, gio, glib, pango, Box, Button, EventControllerKey, FileDialog, GestureClick, Image, Label,
+    ListItem, ListView, Orientation, PositionType, ScrolledWindow, SearchBar, SearchEntry,
+    SignalListItemFactory, SingleSelection, TextView, Widget, WrapMode,
 };
 use libadwaita::prelude::*;
 use libadwaita::{HeaderBar, StyleManager, ToolbarView, Window};
@@ -40,6 +41,9 @@ mod commit_item {
     pub struct CommitItem {
         pub commit: RefCell<commit::CommitLog>,
 
+        #[property(get = Self::get_graph)]
+        pub graph: String,
+
         #[property(get = Self::get_author)]
         pub author: String,
 
@@ -74,6 +78,9 @@ mod commit_item {
                 self.commit.borrow().oid
             )
         }
+        pub fn get_graph(&self) -> String {
+            format!("<tt>{}</tt>", self.commit.borrow().graph)
+        }
         pub fn get_source(&self) -> String {
             match self.commit.borrow().from {
                 commit::CommitRelation::None => "".to_string(),
@@ -130,6 +137,8 @@ mod commit_list {
         pub list: RefCell<Vec<super::CommitItem>>,
         pub original_list: RefCell<Vec<super::commit::CommitLog>>,
         pub search_term: RefCell<(String, usize)>,
+        // state of commit graph between pages
+        pub lanes: RefCell<Vec<Option<git2::Oid>>>,
 
         // does not used for now
         #[property(get, set)]
@@ -195,7 +204,7 @@ impl CommitList {
                 if term.is_empty() {
                     None
                 } else {
-                    // search pull commits 1 by 1. inc counter
+                    // search pull commits by SEARCH_PAGE_SIZE. inc counter
                     // to stop that iteration when page size is reached
                     self.imp()
                         .search_term
@@ -233,37 +242,38 @@ impl CommitList {
                 }
                 let mut added = 0;
                 let mut last_added_oid: Option<Oid> = None;
-                for item in commits
-                    .into_iter()
-                    .inspect(|commit| {
-                        if search_term.is_none() {
-                            commit_list
-                                .imp()
-                                .original_list
-                                .borrow_mut()
-                                .push(commit.clone());
-                        }
-                    })
-                    .map(CommitItem::new)
-                {
-                    if append_to_existing {
-                        if let Some(oid) = start_oid {
-                            if item.imp().commit.borrow().oid == oid {
-                                continue;
-                            }
-                        }
+                for mut commit in commits {
+                    if append_to_existing && Some(commit.oid) == start_oid {
+                        continue;
                     }
-                    last_added_oid.replace(item.imp().commit.borrow().oid);
-                    commit_list.imp().list.borrow_mut().push(item);
+                    if search_term.is_none() {
+                        // graph makes sense only for the whole history
+                        commit.graph = git_log::graph_row(
+                            &mut commit_list.imp().lanes.borrow_mut(),
+                            commit.oid,
+                            &commit.parents,
+                        );
+                        commit_list
+                            .imp()
+                            .original_list
+                            .borrow_mut()
+                            .push(commit.clone());
+                    }
+                    last_added_oid.replace(commit.oid);
+                    commit_list
+                        .imp()
+                        .list
+                        .borrow_mut()
+                        .push(CommitItem::new(commit));
                     added += 1;
                 }
                 if added > 0 {
                     commit_list.items_changed(if list_le > 0 { list_le } else { 0 }, 0, added);
-                    // search will return commits 1 by 1
+                    // search will return commits by small pages
                     // it need to stop somehow
                     if search_term.is_some()
                         && last_added_oid.is_some()
-                        && term_count < git_log::COMMIT_PAGE_SIZE
+                        && term_count < git_log::COMMIT_PAGE_SIZE / git_log::SEARCH_PAGE_SIZE
                     {
                         trace!(
                             "go next loop with start >>>>>>>>   oid {:?}",
@@ -314,6 +324,163 @@ impl CommitList {
         oid
     }
 
+    /// Patches for mailing list from selected commit up to
+    /// the top one in log, one file per commit.
+    pub fn format_patch(
+        &self,
+        repo_path: PathBuf,
+        window: &impl IsA<Widget>,
+        sender: Sender<crate::Event>,
+    ) {
+        let first = self.get_selected_oid();
+        let Some(last) = self.imp().original_list.borrow().first().map(|c| c.oid) else {
+            return;
+        };
+        glib::spawn_future_local({
+            let window = window.clone();
+            async move {
+                let dialog = FileDialog::builder().title("Folder for patches").build();
+                let parent = window.root().and_downcast::<gtk4::Window>();
+                let Some(dir) = dialog
+                    .select_folder_future(parent.as_ref())
+                    .await
+                    .ok()
+                    .and_then(|file| file.path())
+                else {
+                    return;
+                };
+                let written = gio::spawn_blocking(move || {
+                    format_patch::format_patch(repo_path, first, last, &dir)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(Vec::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    Vec::new()
+                });
+                if let Some(first_file) = written.first() {
+                    sender
+                        .send_blocking(crate::Event::Toast(format!(
+                            "{} patches saved to {}",
+                            written.len(),
+                            first_file.parent().unwrap_or(first_file).display()
+                        )))
+                        .expect("cant send through channel");
+                }
+            }
+        });
+    }
+
+    pub fn reword(
+        &self,
+        repo_path: PathBuf,
+        window: &impl IsA<Widget>,
+        sender: Sender<crate::Event>,
+    ) {
+        let oid = self.get_selected_oid();
+        glib::spawn_future_local({
+            let window = window.clone();
+            let commit_list = self.clone();
+            async move {
+                let is_pushed = gio::spawn_blocking({
+                    let path = repo_path.clone();
+                    move || commit::is_pushed(path, oid)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(true)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    true
+                });
+                if is_pushed {
+                    alert(format!(
+                        "Commit {} is already pushed to upstream. Rewording it will rewrite published history",
+                        oid
+                    ))
+                    .present(Some(&window));
+                    return;
+                }
+                let message = gio::spawn_blocking({
+                    let path = repo_path.clone();
+                    move || commit::get_raw_message(path, oid)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(String::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    String::new()
+                });
+                if message.is_empty() {
+                    return;
+                }
+                let txt = TextView::builder()
+                    .margin_start(12)
+                    .margin_end(12)
+                    .margin_top(12)
+                    .margin_bottom(12)
+                    .wrap_mode(WrapMode::Word)
+                    .build();
+                txt.buffer().set_text(message.trim_end());
+                let scroll = ScrolledWindow::builder()
+                    .vexpand(true)
+                    .hexpand(true)
+                    .min_content_width(480)
+                    .min_content_height(320)
+                    .child(&txt)
+                    .build();
+                let dialog = confirm_dialog_factory(Some(&scroll), "Reword commit", "Reword");
+                dialog.connect_realize({
+                    let txt = txt.clone();
+                    move |_| {
+                        txt.grab_focus();
+                    }
+                });
+                if dialog.choose_future(&window).await != PROCEED {
+                    return;
+                }
+                let buffer = txt.buffer();
+                let new_message = buffer
+                    .text(&buffer.start_iter(), &buffer.end_iter(), true)
+                    .to_string();
+                if new_message.trim_end() == message.trim_end() {
+                    return;
+                }
+                let result = gio::spawn_blocking({
+                    let path = repo_path.clone();
+                    move || commit::reword(path, oid, new_message, sender)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(())
+                });
+                match result {
+                    Ok(()) => {
+                        // all commits on top of reworded one got new oids
+                        let le = commit_list.imp().list.take().len();
+                        commit_list.imp().original_list.borrow_mut().clear();
+                        commit_list.imp().lanes.borrow_mut().clear();
+                        commit_list.imp().search_term.take();
+                        commit_list.items_changed(0, le as u32, 0);
+                        commit_list.get_commits_inside(repo_path, None, &window);
+                    }
+                    Err(e) => {
+                        alert(e).present(Some(&window));
+                    }
+                }
+            }
+        });
+    }
+
     pub fn reset_hard(
         &self,
         repo_path: PathBuf,
@@ -411,6 +578,12 @@ pub fn item_factory(sender: Sender<crate::Event>) -> SignalListItemFactory {
         });
         oid_label.add_controller(gesture_controller);
 
+        let graph_label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .xalign(0.0)
+            .build();
+
         let source = Image::new();
 
         let author_label = Label::builder()
@@ -458,6 +631,7 @@ pub fn item_factory(sender: Sender<crate::Event>) -> SignalListItemFactory {
             .focusable(true)
             .build();
 
+        bx.append(&graph_label);
         bx.append(&oid_label);
         bx.append(&source);
         bx.append(&author_label);
@@ -473,6 +647,8 @@ pub fn item_factory(sender: Sender<crate::Event>) -> SignalListItemFactory {
         list_item.set_focusable(true);
 
         let item = list_item.property_expression("item");
+        item.chain_property::<CommitItem>("graph")
+            .bind(&graph_label, "label", Widget::NONE);
         item.chain_property::<CommitItem>("oid")
             .bind(&oid_label, "label", Widget::NONE);
         item.chain_property::<CommitItem>("source")
@@ -580,7 +756,7 @@ pub fn headerbar_factory(
     let commit_list = get_commit_list(list_view);
 
     let search = SearchBar::builder()
-        .tooltip_text("search commits")
+        .tooltip_text("search commits by message, author name/email or oid")
         .search_mode_enabled(true)
         .visible(true)
         .show_close_button(false)
@@ -644,7 +820,7 @@ pub fn headerbar_factory(
     let cherry_pick_btn = Button::builder()
         .icon_name("emblem-shared-symbolic")
         .can_shrink(true)
-        .tooltip_text("Cherry-pick")
+        .tooltip_text("Cherry-pick (a)")
         .sensitive(true)
         .use_underline(true)
         .build();
@@ -665,7 +841,7 @@ pub fn headerbar_factory(
     let revert_btn = Button::builder()
         .icon_name("edit-undo-symbolic")
         .can_shrink(true)
-        .tooltip_text("Revert")
+        .tooltip_text("Revert (r)")
         .sensitive(true)
         .use_underline(true)
         .build();
@@ -674,7 +850,7 @@ pub fn headerbar_factory(
         let commit_list = commit_list.clone();
         move |_btn| {
             sender
-                .send_blocking(crate::Event::Apply(crate::ApplyOp::CherryPick(
+                .send_blocking(crate::Event::Apply(crate::ApplyOp::Revert(
                     commit_list.get_selected_oid(),
                     None,
                     None,
@@ -697,11 +873,45 @@ pub fn headerbar_factory(
     reset_btn.connect_clicked({
         let window = window.clone();
         let repo_path = repo_path.clone();
+        let commit_list = commit_list.clone();
+        let sender = sender.clone();
         move |_| {
             commit_list.reset_hard(repo_path.clone(), &window, sender.clone());
         }
     });
     hb.pack_end(&reset_btn);
+
+    let format_patch_btn = Button::builder()
+        .icon_name("mail-send-symbolic")
+        .can_focus(false)
+        .tooltip_text("Format patches from selected commit up to the top one (f)")
+        .can_shrink(true)
+        .build();
+    format_patch_btn.connect_clicked({
+        let window = window.clone();
+        let repo_path = repo_path.clone();
+        let commit_list = commit_list.clone();
+        let sender = sender.clone();
+        move |_| {
+            commit_list.format_patch(repo_path.clone(), &window, sender.clone());
+        }
+    });
+    hb.pack_end(&format_patch_btn);
+
+    let reword_btn = Button::builder()
+        .icon_name("document-edit-symbolic")
+        .can_focus(false)
+        .tooltip_text("Reword commit message (e)")
+        .can_shrink(true)
+        .build();
+    reword_btn.connect_clicked({
+        let window = window.clone();
+        let repo_path = repo_path.clone();
+        move |_| {
+            commit_list.reword(repo_path.clone(), &window, sender.clone());
+        }
+    });
+    hb.pack_end(&reword_btn);
     hb
 }
 
@@ -780,6 +990,35 @@ pub fn show_log_window(
                     trace!("enter search");
                     search_entry.grab_focus();
                 }
+                (gdk::Key::y, _) => {
+                    copy_oid(
+                        get_commit_list(&list_view).get_selected_oid(),
+                        &window,
+                        main_sender.clone(),
+                    );
+                }
+                (gdk::Key::Y, gdk::ModifierType::SHIFT_MASK) => {
+                    copy_commit_url(
+                        repo_path.clone(),
+                        get_commit_list(&list_view).get_selected_oid(),
+                        &window,
+                        main_sender.clone(),
+                    );
+                }
+                (gdk::Key::e, _) => {
+                    get_commit_list(&list_view).reword(
+                        repo_path.clone(),
+                        &window,
+                        main_sender.clone(),
+                    );
+                }
+                (gdk::Key::f, _) => {
+                    get_commit_list(&list_view).format_patch(
+                        repo_path.clone(),
+                        &window,
+                        main_sender.clone(),
+                    );
+                }
                 (gdk::Key::x, _) => {
                     get_commit_list(&list_view).reset_hard(
                         repo_path.clone(),
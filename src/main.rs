@@ -3,12 +3,18 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 mod external;
+mod keymap;
 mod status_view;
 mod syntax;
 use status_view::{
     context::StatusRenderContext,
     headerbar::factory as headerbar_factory,
-    headerbar::{HbUpdateData, Scheme, SCHEME_TOKEN},
+    headerbar::{
+        commit_signing_dialog, pre_commit_command_dialog, HbUpdateData, Scheme, CONFIRM_KILL_TOKEN,
+        CONFIRM_QUIT_DRAFT_TOKEN, CONFIRM_QUIT_TOKEN, DIFF_COLORS_TOKEN, IGNORE_WHITESPACE_TOKEN,
+        PULL_FF_ONLY_TOKEN, PULL_REBASE_TOKEN, RUN_HOOKS_TOKEN, SCHEME_TOKEN,
+        SYNTAX_HIGHLIGHTING_TOKEN,
+    },
     remotes::auth,
     stage_view::factory as stage_factory,
     Status,
@@ -27,36 +33,48 @@ mod stashes_view;
 use stashes_view::factory as stashes_view_factory;
 
 mod commit_view;
-use commit_view::show_commit_window;
+use commit_view::{show_commit_window, CommitWindowOptions};
+
+mod blame_view;
+use blame_view::show_blame_window;
+
+mod reflog_view;
+use reflog_view::show_reflog_window;
+
+mod file_view;
+use file_view::show_file_window;
 
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Condvar, Mutex};
 mod git;
 use git::{
-    branch, commit, get_current_repo_status, get_directories, reset_hard, stage_untracked,
-    stage_via_apply,
+    branch, commit, delete_untracked, discard_file, discover_workdir, get_current_repo_status,
+    get_directories, repo_settings, reset_hard, stage_all, stage_untracked, stage_via_apply,
     stash::{StashNum, Stashes},
-    Diff, DiffKind, File, Head, Hunk, HunkLineNo, Line, LineKind, State, MARKER_OURS,
-    MARKER_THEIRS,
+    unstage_all, untracked_files, Diff, DiffKind, File, Head, Hunk, HunkLineNo, Line, LineKind,
+    State, MARKER_OURS, MARKER_THEIRS,
 };
-use git2::Oid;
+use git2::{Oid, RepositoryState};
 mod dialogs;
-use dialogs::alert;
+use dialogs::{alert, confirm_dialog_factory, DangerDialog, PROCEED, YES};
 
 mod tests;
 use gdk::Display;
 use gtk4::prelude::*;
 use gtk4::{
     gdk, gio, glib, style_context_add_provider_for_display,
-    style_context_remove_provider_for_display, Box as Gtk4Box, CssProvider, Orientation,
-    ScrolledWindow, STYLE_PROVIDER_PRIORITY_USER,
+    style_context_remove_provider_for_display, Align, Box as Gtk4Box, Button, CssProvider,
+    DropTarget, Entry, EventControllerKey, FileDialog, ListBox, Orientation, ScrolledWindow,
+    SearchBar, SearchEntry, SelectionMode, Widget, STYLE_PROVIDER_PRIORITY_USER,
 };
 use libadwaita::prelude::*;
 use libadwaita::{
-    Application, ApplicationWindow, Banner, OverlaySplitView, StyleManager, Toast, ToastOverlay,
-    ToolbarStyle, ToolbarView, Window,
+    Application, ApplicationWindow, Banner, OverlaySplitView, StyleManager, SwitchRow, TabBar,
+    TabPage, TabView, Toast, ToastOverlay, ToolbarStyle, ToolbarView, Window,
 };
 
 use log::{info, trace};
@@ -78,6 +96,11 @@ pub struct LoginPassword {
     password: String,
     cancel: bool,
     pending: bool,
+    // ssh key path, when passphrase is asked instead of login
+    key: Option<String>,
+    // opt in credentials caching from auth dialog
+    remember: bool,
+    save_password: bool,
 }
 
 impl Default for LoginPassword {
@@ -87,6 +110,9 @@ impl Default for LoginPassword {
             password: String::from(""),
             cancel: false,
             pending: true,
+            key: None,
+            remember: false,
+            save_password: false,
         }
     }
 }
@@ -107,6 +133,33 @@ pub enum ApplyOp {
     Stash(Oid, StashNum, Option<PathBuf>, Option<String>),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchOp {
+    Open,
+    // term is edited
+    Changed,
+    Next,
+    Previous,
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InlineCommitOp {
+    Open,
+    Commit(String),
+    // commit is created, message is not needed anymore
+    Done,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BisectOp {
+    Start,
+    Good,
+    Bad,
+    Reset,
+}
+
 #[derive(Debug, Clone)]
 pub struct BlameLine {
     pub file_path: PathBuf,
@@ -119,6 +172,9 @@ pub enum Event {
     Debug,
     Dump,
     OpenRepo(PathBuf),
+    // dropped or pasted path, which is not checked yet
+    OpenPath(PathBuf),
+    PastePath,
     CurrentRepo(PathBuf),
     Conflicted(Option<Diff>, Option<State>),
     Unstaged(Option<Diff>),
@@ -127,19 +183,38 @@ pub enum Event {
     Head(Option<Head>),
     Upstream(Option<Head>),
     UpstreamProgress,
+    // objects received/sent of total during fetch or push
+    RemoteProgress(usize, usize),
+    // remote operation is started. setting flag stops it.
+    // None when operation could not be stopped anymore
+    RemoteCancel(Option<Arc<AtomicBool>>),
+    // current step, total steps, subject. None when rebase is over
+    RebaseProgress(Option<(usize, usize, String)>),
     State(State),
     OpenFileDialog,
+    InitRepo,
+    // window got focus or tab is selected. global settings
+    // could be changed in another tab meanwhile
+    WindowActive,
+    // tab is closed. its workspace stops
+    CloseTab,
     RepoPopup,
     Expand(i32, i32),
     Cursor(i32, i32),
     Stage(StageOp),
+    StageAll(StageOp),
     Commit,
     Push,
     Pull,
+    PullRebase,
     ShowBranches,
+    BranchSwitcher,
+    BranchAtHead,
     Branches(Vec<branch::BranchData>),
     Log(Option<Oid>, Option<String>),
     ShowOid(Oid, Option<StashNum>, Option<BlameLine>),
+    // oid, base. changes of oid since base instead of its parent
+    ShowOidFromBase(Oid, Oid),
     ShowTextOid(String),
     TextViewResize(i32),
     Toast(String),
@@ -147,6 +222,9 @@ pub enum Event {
     Stashes(Stashes),
     Refresh,
     RemotesDialog,
+    WorktreesDialog,
+    Reflog,
+    Bisect(BisectOp),
     Zoom(bool),
     ResetHard(Option<Oid>),
     CommitDiff(commit::CommitDiff),
@@ -158,9 +236,84 @@ pub enum Event {
     Focus,
     UserInputRequired(Arc<(Mutex<LoginPassword>, Condvar)>),
     Blame,
+    BlameFile,
+    // file under cursor at HEAD or at commit of window
+    ShowFile,
+    ShowFileAt(Oid, PathBuf),
+    Squash,
+    // abort merge/rebase/cherry-pick/revert in progress
+    Abort,
+    Clean,
+    // add untracked file under cursor to .gitignore
+    Ignore,
+    ChooseConflictSide(bool),
+    NextConflict(bool),
+    OpenMergetool,
+    IgnoreWhitespace(bool),
+    FileChanges(PathBuf, Option<File>, Option<File>),
+    SyntaxHighlighting(bool),
+    ConfirmKill(bool),
+    ExpandAll(bool, bool),
+    NextFile(bool),
+    CopyChangesAsJson,
+    // with +/- prefixes or not
+    CopyHunk(bool),
+    Search(SearchOp),
+    // commit message in bar below status, without dialog
+    InlineCommit(InlineCommitOp),
+    ApplyPatch,
+    ExportPatch,
+    Undo,
+    RunHooks(bool),
+    PullFfOnly(bool),
+    PullRebaseMode(bool),
+    PerRepoSettings(bool),
+    PreCommitCommand,
+    CommitSigning,
 }
 
 fn main() -> glib::ExitCode {
+    // stage diff-json [path] prints staged and unstaged
+    // changes as json, for external tools
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "diff-json" {
+        let path = args
+            .get(2)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().expect("cant get current dir"));
+        return match git::json::repo_changes_to_json(path) {
+            Ok(json) => {
+                println!("{}", json);
+                glib::ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{}", err.message());
+                glib::ExitCode::FAILURE
+            }
+        };
+    }
+
+    // stage --status [path] prints status of repo, like
+    // git status --porcelain does, and exits
+    if args.len() > 1 && args[1] == "--status" {
+        let path = args
+            .get(2)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().expect("cant get current dir"));
+        return match git::porcelain::repo_status(path) {
+            Ok(status) => {
+                print!("{}", status);
+                glib::ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{}", err.message());
+                glib::ExitCode::FAILURE
+            }
+        };
+    }
+
+    env_logger::builder().format_timestamp(None).init();
+
     let app = Application::builder()
         .application_id(APP_ID)
         .flags(gio::ApplicationFlags::HANDLES_OPEN)
@@ -194,8 +347,23 @@ fn main() -> glib::ExitCode {
         let initial_path = initial_path.clone();
         move |running_app| {
             let windows = running_app.windows();
+            let path = initial_path.take();
             if windows.is_empty() {
-                run_app(running_app, &initial_path.borrow());
+                run_app(
+                    running_app,
+                    path.or_else(|| {
+                        let last_path = get_settings().get::<String>("lastpath");
+                        if !last_path.is_empty() {
+                            Some(last_path.into())
+                        } else {
+                            None
+                        }
+                    }),
+                );
+            } else if let Some(path) = path {
+                // app is already running: repo from command line
+                // gets its own tab
+                open_in_window(running_app, path);
             } else {
                 windows[0].present();
             }
@@ -204,6 +372,90 @@ fn main() -> glib::ExitCode {
     app.run()
 }
 
+// folder inside existing repo is not initialized:
+// user is offered to open that repo instead
+fn init_repo(window: &ApplicationWindow, sender: async_channel::Sender<Event>) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let dialog = FileDialog::builder().title("New repository").build();
+            let Some(path) = dialog
+                .select_folder_future(Some(&window))
+                .await
+                .ok()
+                .and_then(|file| file.path())
+            else {
+                return;
+            };
+            let existing = gio::spawn_blocking({
+                let path = path.clone();
+                move || discover_workdir(path)
+            })
+            .await
+            .ok()
+            .and_then(|workdir| workdir.ok());
+            if let Some(workdir) = existing {
+                let dialog = confirm_dialog_factory(
+                    None::<&Widget>,
+                    &format!("{} is already a git repository", workdir.display()),
+                    "Open",
+                );
+                if dialog.choose_future(&window).await == PROCEED {
+                    sender
+                        .send_blocking(Event::OpenRepo(workdir))
+                        .expect("Could not send through channel");
+                }
+                return;
+            }
+            let lb = ListBox::builder()
+                .selection_mode(SelectionMode::None)
+                .css_classes(vec![String::from("boxed-list")])
+                .build();
+            let bare = SwitchRow::builder()
+                .title("Bare repository")
+                .subtitle("Without working directory. It could not be opened in Stage")
+                .build();
+            lb.append(&bare);
+            let initial_commit = SwitchRow::builder()
+                .title("Create initial empty commit")
+                .active(true)
+                .build();
+            lb.append(&initial_commit);
+            let dialog = confirm_dialog_factory(
+                Some(&lb),
+                &format!("Init repository in {}", path.display()),
+                "Create",
+            );
+            if dialog.choose_future(&window).await != PROCEED {
+                return;
+            }
+            let bare = bare.is_active();
+            let initial_commit = initial_commit.is_active();
+            let created = gio::spawn_blocking(move || git::init(path, bare, initial_commit))
+                .await
+                .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+            match created {
+                Ok(path) if bare => {
+                    sender
+                        .send_blocking(Event::Toast(format!(
+                            "Bare repository is created in {}",
+                            path.display()
+                        )))
+                        .expect("Could not send through channel");
+                }
+                Ok(path) => {
+                    sender
+                        .send_blocking(Event::OpenRepo(path))
+                        .expect("Could not send through channel");
+                }
+                Err(e) => {
+                    alert(e).present(Some(&window));
+                }
+            }
+        }
+    });
+}
+
 pub fn get_settings() -> gio::Settings {
     if let Some(system_schema_source) = gio::SettingsSchemaSource::default() {
         if let Some(schema) = system_schema_source.lookup(APP_ID, false) {
@@ -219,12 +471,108 @@ pub fn get_settings() -> gio::Settings {
     gio::Settings::new_full(&schema, None::<&gio::SettingsBackend>, None)
 }
 
-fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
-    env_logger::builder().format_timestamp(None).init();
+// dropped folder or pasted path could be anywhere inside repo
+fn open_path(path: PathBuf, window: &ApplicationWindow, sender: async_channel::Sender<Event>) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let workdir = gio::spawn_blocking({
+                let path = path.clone();
+                move || discover_workdir(path)
+            })
+            .await
+            .unwrap_or_else(|e| {
+                alert(format!("{:?}", e)).present(Some(&window));
+                Err(git2::Error::from_str("cant discover repository"))
+            });
+            match workdir {
+                Ok(workdir) => {
+                    sender
+                        .send_blocking(Event::OpenRepo(workdir))
+                        .expect("Could not send through channel");
+                }
+                Err(_) => {
+                    alert(format!("{} is not inside a git repository", path.display()))
+                        .present(Some(&window));
+                }
+            }
+        }
+    });
+}
 
-    let (sender, receiver) = async_channel::unbounded();
-    let monitors = Rc::new(RefCell::<Vec<gio::FileMonitor>>::new(Vec::new()));
+const DIRTY_MARKER: &str = "*";
 
+// repo is known by tab title and by window title of selected
+// tab, e.g. in window switcher. uncommitted work is marked
+// like unsaved file in editors
+fn update_tab_title(page: &TabPage, status: &Status) {
+    if let Some(path) = &status.path {
+        let mut title = path.to_str().unwrap_or_default().replace(".git/", "");
+        if status.has_staged() || status.has_unstaged() {
+            title.insert_str(0, DIRTY_MARKER);
+        }
+        page.set_title(&title);
+    }
+}
+
+// repo comes from command line while the app is running.
+// tab which already shows repo is reused
+fn open_in_window(app: &Application, path: PathBuf) {
+    let workdir = discover_workdir(path.clone()).unwrap_or(path);
+    let target = workdir.to_str().unwrap_or_default().to_variant();
+    if let Some(window) = app
+        .windows()
+        .into_iter()
+        .find(|w| w.activate_action("win.open_tab", Some(&target)).is_ok())
+    {
+        window.present();
+        return;
+    }
+    run_app(app, Some(workdir));
+}
+
+// each tab is separate workspace with its own Status,
+// file monitors and event loop
+struct Workspace {
+    page: TabPage,
+    sender: async_channel::Sender<Event>,
+    repo_state: Rc<Cell<RepositoryState>>,
+    // inline commit message, which is lost on close
+    commit_entry: Entry,
+}
+
+type Workspaces = Rc<RefCell<Vec<Workspace>>>;
+
+// why workspace should not be closed silently: unfinished
+// merge/rebase or message typed in inline commit bar
+fn close_warning(workspace: &Workspace, settings: &gio::Settings) -> Option<String> {
+    let repo_state = workspace.repo_state.get();
+    if repo_state != RepositoryState::Clean && settings.get::<bool>(CONFIRM_QUIT_TOKEN) {
+        return Some(format!(
+            "Repository is in the middle of operation: {:?}.",
+            repo_state
+        ));
+    }
+    if !workspace.commit_entry.text().is_empty() && settings.get::<bool>(CONFIRM_QUIT_DRAFT_TOKEN) {
+        return Some(String::from("Commit message is not committed yet."));
+    }
+    None
+}
+
+// window wide actions go to workspace of selected tab
+fn send_to_selected(tab_view: &TabView, workspaces: &Workspaces, event: Event) {
+    let Some(page) = tab_view.selected_page() else {
+        return;
+    };
+    if let Some(workspace) = workspaces.borrow().iter().find(|w| w.page == page) {
+        workspace
+            .sender
+            .send_blocking(event)
+            .expect("Could not send through channel");
+    }
+}
+
+fn run_app(app: &Application, initial_path: Option<PathBuf>) {
     let settings = get_settings();
 
     let font_size = settings.get::<i32>("zoom");
@@ -235,25 +583,19 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
     ));
     let display = Display::default().expect("cant get dispay");
     style_context_add_provider_for_display(&display, &provider, STYLE_PROVIDER_PRIORITY_USER);
-    let font_size_provider = RefCell::new(provider);
+    let font_size_provider = Rc::new(RefCell::new(provider));
+
+    syntax::set_enabled(settings.get::<bool>(SYNTAX_HIGHLIGHTING_TOKEN));
+    keymap::load(&settings);
+    settings.connect_changed(Some(keymap::KEYBINDINGS_TOKEN), |settings, _| {
+        keymap::load(settings);
+    });
 
     let scheme = settings.get::<String>(SCHEME_TOKEN);
     if !scheme.is_empty() {
         StyleManager::default().set_color_scheme(Scheme::new(scheme).scheme_name());
     }
 
-    let mut status = Status::new(
-        initial_path.clone().or_else(|| {
-            let last_path = settings.get::<String>("lastpath");
-            if !last_path.is_empty() {
-                Some(last_path.into())
-            } else {
-                None
-            }
-        }),
-        sender.clone(),
-    );
-
     let application_window = ApplicationWindow::builder().application(app).build();
 
     settings
@@ -269,31 +611,260 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
         .bind("is-fullscreen", &application_window, "fullscreened")
         .build();
 
+    let tab_view = TabView::new();
+    let tab_bar = TabBar::builder().view(&tab_view).autohide(true).build();
+    let workspaces: Workspaces = Rc::new(RefCell::new(Vec::new()));
+
     let action_close = gio::SimpleAction::new("close", None);
     action_close.connect_activate({
-        let window = application_window.clone();
+        let tab_view = tab_view.clone();
         move |_, _| {
-            window.close();
+            if let Some(page) = tab_view.selected_page() {
+                tab_view.close_page(&page);
+            }
         }
     });
     application_window.add_action(&action_close);
 
     let action_open = gio::SimpleAction::new("open", Some(glib::VariantTy::STRING));
     action_open.connect_activate({
-        let sender = sender.clone();
+        let tab_view = tab_view.clone();
+        let workspaces = workspaces.clone();
         move |_, chosen_path| {
             if let Some(path) = chosen_path {
                 let path: String = path.get().expect("cant get path from gvariant");
-                sender
-                    .send_blocking(Event::OpenRepo(path.into()))
-                    .expect("Could not send through channel");
+                send_to_selected(&tab_view, &workspaces, Event::OpenRepo(path.into()));
             }
         }
     });
 
     application_window.add_action(&action_open);
 
+    let action_init = gio::SimpleAction::new("init", None);
+    action_init.connect_activate({
+        let tab_view = tab_view.clone();
+        let workspaces = workspaces.clone();
+        move |_, _| {
+            send_to_selected(&tab_view, &workspaces, Event::InitRepo);
+        }
+    });
+    application_window.add_action(&action_init);
+
+    let action_new_tab = gio::SimpleAction::new("new_tab", None);
+    action_new_tab.connect_activate({
+        let window = application_window.clone();
+        let tab_view = tab_view.clone();
+        let workspaces = workspaces.clone();
+        let settings = settings.clone();
+        let font_size_provider = font_size_provider.clone();
+        move |_, _| {
+            open_tab(
+                &window,
+                &tab_view,
+                &workspaces,
+                &settings,
+                font_size_provider.clone(),
+                None,
+            );
+        }
+    });
+    application_window.add_action(&action_new_tab);
+
+    let action_open_tab = gio::SimpleAction::new("open_tab", Some(glib::VariantTy::STRING));
+    action_open_tab.connect_activate({
+        let window = application_window.clone();
+        let tab_view = tab_view.clone();
+        let workspaces = workspaces.clone();
+        let settings = settings.clone();
+        let font_size_provider = font_size_provider.clone();
+        move |_, chosen_path| {
+            let Some(path) = chosen_path.and_then(|path| path.get::<String>()) else {
+                return;
+            };
+            let existing = workspaces
+                .borrow()
+                .iter()
+                .map(|workspace| workspace.page.clone())
+                .find(|page| {
+                    let title = page.title();
+                    title.strip_prefix(DIRTY_MARKER).unwrap_or(&title) == path
+                });
+            if let Some(page) = existing {
+                tab_view.set_selected_page(&page);
+                return;
+            }
+            open_tab(
+                &window,
+                &tab_view,
+                &workspaces,
+                &settings,
+                font_size_provider.clone(),
+                Some(path.into()),
+            );
+        }
+    });
+    application_window.add_action(&action_open_tab);
+
+    application_window.connect_is_active_notify({
+        let tab_view = tab_view.clone();
+        let workspaces = workspaces.clone();
+        move |window| {
+            if window.is_active() {
+                send_to_selected(&tab_view, &workspaces, Event::WindowActive);
+            }
+        }
+    });
+
+    tab_view.connect_selected_page_notify({
+        let window = application_window.clone();
+        let workspaces = workspaces.clone();
+        move |tab_view| {
+            if let Some(page) = tab_view.selected_page() {
+                window.set_title(Some(&page.title()));
+            }
+            send_to_selected(tab_view, &workspaces, Event::WindowActive);
+        }
+    });
+
+    let drop_target = DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+    drop_target.connect_drop({
+        let tab_view = tab_view.clone();
+        let workspaces = workspaces.clone();
+        move |_, value, _, _| {
+            let Ok(files) = value.get::<gdk::FileList>() else {
+                return false;
+            };
+            let Some(path) = files.files().first().and_then(|f| f.path()) else {
+                return false;
+            };
+            send_to_selected(&tab_view, &workspaces, Event::OpenPath(path));
+            true
+        }
+    });
+    application_window.add_controller(drop_target);
+
     app.set_accels_for_action("win.close", &["<Ctrl>W"]);
+    app.set_accels_for_action("win.new_tab", &["<Ctrl>T"]);
+
+    // git keeps all changes, but unfinished merge/rebase
+    // could surprise user when the app is opened next time
+    tab_view.connect_close_page({
+        let window = application_window.clone();
+        let workspaces = workspaces.clone();
+        let settings = settings.clone();
+        move |tab_view, page| {
+            let warning = workspaces
+                .borrow()
+                .iter()
+                .find(|workspace| workspace.page == *page)
+                .and_then(|workspace| close_warning(workspace, &settings));
+            let Some(warning) = warning else {
+                tab_view.close_page_finish(page, true);
+                return glib::Propagation::Stop;
+            };
+            glib::spawn_future_local({
+                let window = window.clone();
+                let tab_view = tab_view.clone();
+                let page = page.clone();
+                async move {
+                    let response = alert(DangerDialog(
+                        String::from("Close"),
+                        format!("{} Close anyway?", warning),
+                    ))
+                    .choose_future(&window)
+                    .await;
+                    tab_view.close_page_finish(&page, response == YES);
+                }
+            });
+            glib::Propagation::Stop
+        }
+    });
+    tab_view.connect_page_detached({
+        let window = application_window.clone();
+        let workspaces = workspaces.clone();
+        move |tab_view, page, _| {
+            workspaces.borrow_mut().retain(|workspace| {
+                if workspace.page != *page {
+                    return true;
+                }
+                workspace
+                    .sender
+                    .send_blocking(Event::CloseTab)
+                    .expect("Could not send through channel");
+                false
+            });
+            if tab_view.n_pages() == 0 {
+                window.close();
+            }
+        }
+    });
+    application_window.connect_close_request({
+        let workspaces = workspaces.clone();
+        let settings = settings.clone();
+        let quit_confirmed = Rc::new(Cell::new(false));
+        move |window| {
+            if quit_confirmed.get() {
+                return glib::Propagation::Proceed;
+            }
+            let warning = workspaces
+                .borrow()
+                .iter()
+                .find_map(|workspace| close_warning(workspace, &settings));
+            let Some(warning) = warning else {
+                return glib::Propagation::Proceed;
+            };
+            glib::spawn_future_local({
+                let window = window.clone();
+                let quit_confirmed = quit_confirmed.clone();
+                async move {
+                    let response = alert(DangerDialog(
+                        String::from("Quit"),
+                        format!("{} Quit anyway?", warning),
+                    ))
+                    .choose_future(&window)
+                    .await;
+                    if response == YES {
+                        quit_confirmed.replace(true);
+                        window.close();
+                    }
+                }
+            });
+            glib::Propagation::Stop
+        }
+    });
+
+    let content = Gtk4Box::builder()
+        .orientation(Orientation::Vertical)
+        .build();
+    content.append(&tab_bar);
+    content.append(&tab_view);
+    application_window.set_content(Some(&content));
+
+    open_tab(
+        &application_window,
+        &tab_view,
+        &workspaces,
+        &settings,
+        font_size_provider,
+        initial_path,
+    );
+    application_window.present();
+}
+
+fn open_tab(
+    window: &ApplicationWindow,
+    tab_view: &TabView,
+    workspaces: &Workspaces,
+    settings: &gio::Settings,
+    font_size_provider: Rc<RefCell<CssProvider>>,
+    initial_path: Option<PathBuf>,
+) {
+    let (sender, receiver) = async_channel::unbounded();
+    let monitors = Rc::new(RefCell::<Vec<gio::FileMonitor>>::new(Vec::new()));
+    let application_window = window.clone();
+    let settings = settings.clone();
+
+    let mut status = Status::new(initial_path, sender.clone());
 
     let (hb, hb_updater) = headerbar_factory(
         sender.clone(),
@@ -301,7 +872,28 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
         &application_window.clone(),
     );
 
-    let txt = stage_factory(sender.clone(), "status_view");
+    let txt = stage_factory(
+        sender.clone(),
+        "status_view",
+        &settings.get::<HashMap<String, String>>(DIFF_COLORS_TOKEN),
+    );
+    let diff_colors_handler_id = settings.connect_changed(Some(DIFF_COLORS_TOKEN), {
+        let txt = txt.downgrade();
+        move |settings, _| {
+            if let Some(txt) = txt.upgrade() {
+                txt.set_diff_colors(&settings.get::<HashMap<String, String>>(DIFF_COLORS_TOKEN));
+            }
+        }
+    });
+    let diff_colors_handler_id = RefCell::new(Some(diff_colors_handler_id));
+    txt.connect_destroy({
+        let settings = settings.clone();
+        move |_| {
+            if let Some(handler_id) = diff_colors_handler_id.take() {
+                settings.disconnect(handler_id);
+            }
+        }
+    });
 
     let scroll = ScrolledWindow::builder()
         .vexpand(true)
@@ -325,8 +917,150 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
     let banner_button = gizmo.last_child().unwrap();
     let banner_button_handler_id = banner.connect_button_clicked(|_| {});
     let banner_button_clicked = Rc::new(RefCell::new(Some(banner_button_handler_id)));
+    let side_buttons = Gtk4Box::builder()
+        .orientation(Orientation::Horizontal)
+        .halign(Align::End)
+        .spacing(6)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_end(12)
+        .visible(false)
+        .build();
+    for (label, tooltip, ours) in [
+        (
+            "Take ours",
+            "Resolve conflicted file with our version",
+            true,
+        ),
+        (
+            "Take theirs",
+            "Resolve conflicted file with their version",
+            false,
+        ),
+    ] {
+        let btn = Button::builder()
+            .label(label)
+            .tooltip_text(tooltip)
+            .can_focus(false)
+            .build();
+        btn.connect_clicked({
+            let sender = sender.clone();
+            move |_| {
+                sender
+                    .send_blocking(Event::ChooseConflictSide(ours))
+                    .expect("cant send through channel");
+            }
+        });
+        side_buttons.append(&btn);
+    }
+    let mergetool_btn = Button::builder()
+        .label("Merge tool")
+        .tooltip_text("Resolve conflicted file in merge tool (m)")
+        .can_focus(false)
+        .build();
+    mergetool_btn.connect_clicked({
+        let sender = sender.clone();
+        move |_| {
+            sender
+                .send_blocking(Event::OpenMergetool)
+                .expect("cant send through channel");
+        }
+    });
+    side_buttons.append(&mergetool_btn);
+    let search_entry = SearchEntry::builder()
+        .placeholder_text("Search in diffs")
+        .width_chars(40)
+        .build();
+    let search_bar = SearchBar::builder()
+        .show_close_button(true)
+        .child(&search_entry)
+        .build();
+    search_bar.connect_entry(&search_entry);
+    search_entry.connect_search_changed({
+        let sender = sender.clone();
+        move |_| {
+            sender
+                .send_blocking(Event::Search(SearchOp::Changed))
+                .expect("Could not send through channel");
+        }
+    });
+    search_entry.connect_activate({
+        let sender = sender.clone();
+        move |_| {
+            sender
+                .send_blocking(Event::Search(SearchOp::Next))
+                .expect("Could not send through channel");
+        }
+    });
+    let search_controller = EventControllerKey::new();
+    search_controller.connect_key_pressed({
+        let sender = sender.clone();
+        move |_, key, _, modifier| {
+            if (key == gdk::Key::Return || key == gdk::Key::KP_Enter)
+                && modifier == gdk::ModifierType::SHIFT_MASK
+            {
+                sender
+                    .send_blocking(Event::Search(SearchOp::Previous))
+                    .expect("Could not send through channel");
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        }
+    });
+    search_entry.add_controller(search_controller);
+    search_bar.connect_search_mode_enabled_notify({
+        let sender = sender.clone();
+        move |search_bar| {
+            if !search_bar.is_search_mode() {
+                sender
+                    .send_blocking(Event::Search(SearchOp::Close))
+                    .expect("Could not send through channel");
+            }
+        }
+    });
+
+    let commit_entry = Entry::builder()
+        .placeholder_text("Commit message. Ctrl+Enter to commit, Escape to hide")
+        .hexpand(true)
+        .build();
+    let commit_bar = Gtk4Box::builder()
+        .orientation(Orientation::Horizontal)
+        .margin_top(6)
+        .margin_bottom(6)
+        .margin_start(12)
+        .margin_end(12)
+        .visible(false)
+        .build();
+    commit_bar.append(&commit_entry);
+    let commit_controller = EventControllerKey::new();
+    commit_controller.connect_key_pressed({
+        let sender = sender.clone();
+        let commit_entry = commit_entry.clone();
+        move |_, key, _, modifier| match (key, modifier) {
+            (gdk::Key::Return | gdk::Key::KP_Enter, gdk::ModifierType::CONTROL_MASK) => {
+                sender
+                    .send_blocking(Event::InlineCommit(InlineCommitOp::Commit(
+                        commit_entry.text().to_string(),
+                    )))
+                    .expect("Could not send through channel");
+                glib::Propagation::Stop
+            }
+            (gdk::Key::Escape, _) => {
+                sender
+                    .send_blocking(Event::InlineCommit(InlineCommitOp::Close))
+                    .expect("Could not send through channel");
+                glib::Propagation::Stop
+            }
+            _ => glib::Propagation::Proceed,
+        }
+    });
+    commit_entry.add_controller(commit_controller);
+
     bx.append(&banner);
+    bx.append(&side_buttons);
+    bx.append(&search_bar);
     bx.append(&scroll);
+    bx.append(&commit_bar);
 
     let toast_lock: Rc<Cell<bool>> = Rc::new(Cell::new(false));
 
@@ -345,11 +1079,27 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
         .build();
     tb.add_top_bar(&hb);
 
-    application_window.set_content(Some(&tb));
+    let page = tab_view.append(&tb);
+    page.set_title("Stage");
+    page.connect_title_notify({
+        let window = window.clone();
+        move |page| {
+            if page.is_selected() {
+                window.set_title(Some(&page.title()));
+            }
+        }
+    });
+    let repo_state = Rc::new(Cell::new(RepositoryState::Clean));
+    workspaces.borrow_mut().push(Workspace {
+        page: page.clone(),
+        sender: sender.clone(),
+        repo_state: repo_state.clone(),
+        commit_entry: commit_entry.clone(),
+    });
+    tab_view.set_selected_page(&page);
 
     let mut stage_set = false;
-    status.get_status();
-    application_window.present();
+    status.open_status(&application_window);
 
     let window_stack: Rc<RefCell<Vec<Window>>> = Rc::new(RefCell::new(Vec::new()));
 
@@ -371,15 +1121,66 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     }
                     status.update_path(path, monitors.clone(), true, &settings);
                     txt.grab_focus();
-                    status.get_status();
+                    status.open_status(&application_window);
                 }
                 Event::Focus => {
                     info!("focus");
                     txt.grab_focus();
                 }
+                Event::OpenPath(path) => {
+                    info!("info.open path {:?}", path);
+                    open_path(path, &application_window, sender.clone());
+                }
+                Event::PastePath => {
+                    info!("info.paste path");
+                    glib::spawn_future_local({
+                        let window = application_window.clone();
+                        let sender = sender.clone();
+                        async move {
+                            let text = window
+                                .display()
+                                .clipboard()
+                                .read_text_future()
+                                .await
+                                .ok()
+                                .flatten()
+                                .map(|t| t.trim().to_string())
+                                .unwrap_or_default();
+                            if text.is_empty() {
+                                return;
+                            }
+                            // file managers copy paths as uris
+                            let path = gio::File::for_commandline_arg(&text)
+                                .path()
+                                .unwrap_or_else(|| PathBuf::from(&text));
+                            sender
+                                .send_blocking(Event::OpenPath(path))
+                                .expect("Could not send through channel");
+                        }
+                    });
+                }
                 Event::OpenFileDialog => {
                     hb_updater(HbUpdateData::RepoOpen);
                 }
+                Event::InitRepo => {
+                    info!("main. init repo");
+                    init_repo(&application_window, sender.clone());
+                }
+                Event::CloseTab => {
+                    info!("main. close tab");
+                    for monitor in monitors.borrow_mut().drain(..) {
+                        monitor.cancel();
+                    }
+                    break;
+                }
+                Event::WindowActive => {
+                    info!("main. window active");
+                    if status.path.is_some()
+                        && repo_settings::apply(status.path.as_ref(), &settings)
+                    {
+                        status.get_status();
+                    }
+                }
                 Event::RepoPopup => {
                     hb_updater(HbUpdateData::RepoPopup);
                 }
@@ -392,9 +1193,11 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     }
                     hb_updater(HbUpdateData::Path(path.clone()));
                     status.update_path(path, monitors.clone(), false, &settings);
+                    update_tab_title(&page, &status);
                 }
                 Event::State(state) => {
                     info!("main. state");
+                    repo_state.replace(state.state);
                     status.update_state(state, &txt, &mut ctx);
                 }
                 Event::OpenEditor => {
@@ -417,7 +1220,38 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                         alert(String::from("No changes were staged. Stage by hitting 's'"))
                             .present(Some(&txt));
                     } else {
-                        status.commit(&application_window);
+                        status.commit(&settings, &application_window);
+                    }
+                }
+                Event::InlineCommit(op) => {
+                    info!("main. inline commit {:?}", op);
+                    match op {
+                        InlineCommitOp::Open => {
+                            if !status.has_staged() {
+                                alert(String::from("No changes were staged. Stage by hitting 's'"))
+                                    .present(Some(&txt));
+                            } else {
+                                commit_bar.set_visible(true);
+                                commit_entry.grab_focus();
+                            }
+                        }
+                        InlineCommitOp::Commit(_) if !status.has_staged() => {
+                            alert(String::from("No changes were staged. Stage by hitting 's'"))
+                                .present(Some(&txt));
+                        }
+                        InlineCommitOp::Commit(message) => {
+                            // message stays in bar until commit is done
+                            commit_bar.set_visible(false);
+                            txt.grab_focus();
+                            status.commit_inline(message, &settings, &application_window);
+                        }
+                        InlineCommitOp::Done => {
+                            commit_entry.set_text("");
+                        }
+                        InlineCommitOp::Close => {
+                            commit_bar.set_visible(false);
+                            txt.grab_focus();
+                        }
                     }
                 }
                 Event::Untracked(untracked) => {
@@ -432,7 +1266,12 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                 Event::Pull => {
                     info!("main.pull");
                     hb_updater(HbUpdateData::Pull);
-                    status.pull(&application_window);
+                    status.pull(false, &settings, &application_window);
+                }
+                Event::PullRebase => {
+                    info!("main.pull rebase");
+                    hb_updater(HbUpdateData::Pull);
+                    status.pull(true, &settings, &application_window);
                 }
                 Event::Branches(branches) => {
                     info!("main. branches");
@@ -527,6 +1366,43 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     });
                     window_stack.borrow_mut().push(log_window);
                 }
+                Event::BranchSwitcher => {
+                    info!("main.branch switcher");
+                    status.show_branch_switcher(&txt, &application_window);
+                }
+                Event::BranchAtHead => {
+                    info!("main.branch at head");
+                    status.create_branch_at_head(&application_window);
+                }
+                Event::Bisect(op) => {
+                    info!("main.bisect {:?}", op);
+                    status.bisect(op, &application_window);
+                }
+                Event::Reflog => {
+                    info!("main.reflog");
+                    let current_window = if let Some(stacked_window) = window_stack.borrow().last()
+                    {
+                        CurrentWindow::Window(stacked_window.clone())
+                    } else {
+                        CurrentWindow::ApplicationWindow(application_window.clone())
+                    };
+                    let reflog_window = show_reflog_window(
+                        status.path.clone().expect("no path"),
+                        current_window,
+                        sender.clone(),
+                    );
+                    reflog_window.connect_close_request({
+                        let window_stack = window_stack.clone();
+                        move |_| {
+                            info!(
+                                "popping stack while close reflog {:?}",
+                                window_stack.borrow_mut().pop()
+                            );
+                            glib::signal::Propagation::Proceed
+                        }
+                    });
+                    window_stack.borrow_mut().push(reflog_window);
+                }
                 Event::Head(h) => {
                     info!("main. head");
                     if let Some(upstream) = &status.upstream {
@@ -542,6 +1418,34 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     info!("main. UpstreamProgress");
                     hb_updater(HbUpdateData::Upstream);
                 }
+                Event::RemoteProgress(done, total) => {
+                    trace!("main. remote progress {}/{}", done, total);
+                    hb_updater(HbUpdateData::RemoteProgress(done, total));
+                }
+                Event::RemoteCancel(cancel) => {
+                    info!("main. remote cancel");
+                    hb_updater(HbUpdateData::RemoteCancel(cancel));
+                }
+                Event::RebaseProgress(progress) => {
+                    info!("main. rebase progress {:?}", progress);
+                    if let Some((current, total, subject)) = progress {
+                        banner.set_title(&format!(
+                            "Rebasing {}/{}: {}",
+                            current,
+                            total,
+                            html_escape::encode_text(&subject)
+                        ));
+                        banner.set_css_classes(if StyleManager::default().is_dark() {
+                            &[DARK_CLASS]
+                        } else {
+                            &[LIGHT_CLASS]
+                        });
+                        banner.set_button_label(None);
+                        banner.set_revealed(true);
+                    } else {
+                        banner.set_revealed(false);
+                    }
+                }
                 Event::Upstream(h) => {
                     info!("main. upstream");
                     hb_updater(HbUpdateData::Upstream);
@@ -561,6 +1465,7 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                         &banner,
                         &banner_button,
                         banner_button_clicked.clone(),
+                        side_buttons.upcast_ref::<gtk4::Widget>(),
                         &mut ctx,
                     );
                 }
@@ -568,15 +1473,38 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     info!("Staged");
                     hb_updater(HbUpdateData::Staged(odiff.is_some()));
                     status.update_staged(odiff, &txt, &mut ctx);
+                    update_tab_title(&page, &status);
                 }
                 Event::Unstaged(odiff) => {
                     info!("Unstaged");
                     status.update_unstaged(odiff, &txt, &mut ctx);
+                    update_tab_title(&page, &status);
+                }
+                Event::FileChanges(file_path, staged, unstaged) => {
+                    info!("File changes {:?}", file_path);
+                    status.update_file(file_path, staged, unstaged, &txt, &mut ctx);
+                    update_tab_title(&page, &status);
                 }
                 Event::Expand(offset, line_no) => {
                     trace!("Expand");
                     status.expand(&txt, line_no, offset, &mut ctx);
                 }
+                Event::NextFile(forward) => {
+                    trace!("NextFile");
+                    status.next_file(&txt, forward);
+                }
+                Event::ExpandAll(expand, global) => {
+                    trace!("ExpandAll {} {}", expand, global);
+                    status.expand_all(&txt, expand, global, &mut ctx);
+                }
+                Event::OpenMergetool => {
+                    info!("main.open mergetool");
+                    status.open_mergetool(&application_window);
+                }
+                Event::NextConflict(forward) => {
+                    trace!("NextConflict");
+                    status.next_conflict(&txt, forward, &mut ctx);
+                }
                 Event::Cursor(offset, line_no) => {
                     trace!("Cursor");
                     status.cursor(&txt, line_no, offset, &mut ctx);
@@ -585,9 +1513,56 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     info!("Stage {:?}", stage_op);
                     status.stage_op(stage_op, &application_window, &settings);
                 }
+                Event::StageAll(stage_op) => {
+                    info!("StageAll {:?}", stage_op);
+                    status.stage_all(stage_op, &application_window);
+                }
                 Event::TextViewResize(w) => {
                     info!("TextViewResize {}", w);
                 }
+                Event::Search(op) => {
+                    info!("main. search {:?}", op);
+                    match op {
+                        SearchOp::Open => {
+                            search_bar.set_search_mode(true);
+                            search_entry.grab_focus();
+                        }
+                        SearchOp::Close => {
+                            status.search(&txt, op, "", &mut ctx);
+                            txt.grab_focus();
+                        }
+                        _ => {
+                            status.search(&txt, op, &search_entry.text(), &mut ctx);
+                        }
+                    }
+                }
+                Event::CopyHunk(with_prefixes) => {
+                    info!("main. copy hunk {}", with_prefixes);
+                    status.copy_hunk(with_prefixes, &application_window);
+                }
+                Event::ApplyPatch => {
+                    info!("main. apply patch");
+                    status.apply_patch(&application_window);
+                }
+                Event::ExportPatch => {
+                    info!("main. export patch");
+                    status.export_patch(&application_window);
+                }
+                Event::Undo => {
+                    info!("main. undo");
+                    status.undo(&application_window);
+                }
+                Event::CopyChangesAsJson => {
+                    info!("main. copy changes as json");
+                    let json = git::json::changes_to_json(
+                        status.staged.as_ref(),
+                        status.unstaged.as_ref(),
+                    );
+                    application_window.display().clipboard().set_text(&json);
+                    sender
+                        .send_blocking(Event::Toast(String::from("Changes copied as JSON")))
+                        .expect("cant send through channel");
+                }
                 Event::Toast(title) => {
                     info!("Toast {:?}", toast_lock);
                     if !toast_lock.get() {
@@ -659,6 +1634,92 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     };
                     status.blame(current_window);
                 }
+                Event::ChooseConflictSide(ours) => {
+                    info!("main.choose conflict side {:?}", ours);
+                    status.choose_conflict_side_of_file(ours, &application_window);
+                }
+                Event::Ignore => {
+                    info!("main.ignore");
+                    status.ignore_file(&application_window);
+                }
+                Event::Clean => {
+                    info!("main.clean");
+                    status.clean(&application_window);
+                }
+                Event::Squash => {
+                    info!("main.squash");
+                    status.squash(&settings, &application_window);
+                }
+                Event::Abort => {
+                    info!("main.abort");
+                    status.abort(&application_window);
+                }
+                Event::BlameFile => {
+                    info!("blame file");
+                    if let Some((_, Some(file_path), _)) = status.selected() {
+                        let current_window =
+                            if let Some(stacked_window) = window_stack.borrow().last() {
+                                CurrentWindow::Window(stacked_window.clone())
+                            } else {
+                                CurrentWindow::ApplicationWindow(application_window.clone())
+                            };
+                        let blame_window = show_blame_window(
+                            status.path.clone().expect("no path"),
+                            file_path,
+                            current_window,
+                            sender.clone(),
+                        );
+                        blame_window.connect_close_request({
+                            let window_stack = window_stack.clone();
+                            move |_| {
+                                info!(
+                                    "popping stack while close blame {:?}",
+                                    window_stack.borrow_mut().pop()
+                                );
+                                glib::signal::Propagation::Proceed
+                            }
+                        });
+                        window_stack.borrow_mut().push(blame_window);
+                    }
+                }
+                Event::ShowFile => {
+                    info!("main. show file");
+                    if let (Some((_, Some(file_path), _)), Some(head)) =
+                        (status.selected(), &status.head)
+                    {
+                        sender
+                            .send_blocking(Event::ShowFileAt(head.oid, file_path))
+                            .expect("Could not send through channel");
+                    }
+                }
+                Event::ShowFileAt(oid, file_path) => {
+                    info!("main. show file {:?} at {:?}", file_path, oid);
+                    let current_window = if let Some(stacked_window) = window_stack.borrow().last()
+                    {
+                        CurrentWindow::Window(stacked_window.clone())
+                    } else {
+                        CurrentWindow::ApplicationWindow(application_window.clone())
+                    };
+                    let file_window = show_file_window(
+                        status.path.clone().expect("no path"),
+                        oid,
+                        file_path,
+                        current_window,
+                        &settings.get::<HashMap<String, String>>(DIFF_COLORS_TOKEN),
+                        sender.clone(),
+                    );
+                    file_window.connect_close_request({
+                        let window_stack = window_stack.clone();
+                        move |_| {
+                            info!(
+                                "popping stack while close file {:?}",
+                                window_stack.borrow_mut().pop()
+                            );
+                            glib::signal::Propagation::Proceed
+                        }
+                    });
+                    window_stack.borrow_mut().push(file_window);
+                }
                 Event::ShowTextOid(short_sha) => {
                     info!("main.show text oid {:?}", txt);
                     glib::spawn_future_local({
@@ -669,6 +1730,8 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                             } else {
                                 CurrentWindow::ApplicationWindow(application_window.clone())
                             };
+                        let diff_colors =
+                            settings.get::<HashMap<String, String>>(DIFF_COLORS_TOKEN);
                         let sender = sender.clone();
                         let window_stack = window_stack.clone();
                         async move {
@@ -683,8 +1746,10 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                                     let commit_window = show_commit_window(
                                         path,
                                         oid,
-                                        None,
-                                        None,
+                                        CommitWindowOptions {
+                                            diff_colors,
+                                            ..Default::default()
+                                        },
                                         current_window,
                                         sender.clone(),
                                     );
@@ -728,8 +1793,43 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     let commit_window = show_commit_window(
                         status.path.clone().expect("no path"),
                         oid,
-                        onum,
-                        blame_line,
+                        CommitWindowOptions {
+                            stash_num: onum,
+                            blame_line,
+                            diff_colors: settings.get::<HashMap<String, String>>(DIFF_COLORS_TOKEN),
+                            ..Default::default()
+                        },
+                        current_window,
+                        sender.clone(),
+                    );
+                    commit_window.connect_close_request({
+                        let window_stack = window_stack.clone();
+                        move |_| {
+                            info!(
+                                "popping stack while close commit {:?}",
+                                window_stack.borrow_mut().pop()
+                            );
+                            glib::signal::Propagation::Proceed
+                        }
+                    });
+                    window_stack.borrow_mut().push(commit_window);
+                }
+                Event::ShowOidFromBase(oid, base) => {
+                    info!("main.show oid {:?} from base {:?}", oid, base);
+                    let current_window = if let Some(stacked_window) = window_stack.borrow().last()
+                    {
+                        CurrentWindow::Window(stacked_window.clone())
+                    } else {
+                        CurrentWindow::ApplicationWindow(application_window.clone())
+                    };
+                    let commit_window = show_commit_window(
+                        status.path.clone().expect("no path"),
+                        oid,
+                        CommitWindowOptions {
+                            base: Some(base),
+                            diff_colors: settings.get::<HashMap<String, String>>(DIFF_COLORS_TOKEN),
+                            ..Default::default()
+                        },
                         current_window,
                         sender.clone(),
                     );
@@ -753,12 +1853,96 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
                     info!("main. refresh");
                     status.get_status();
                 }
+                Event::IgnoreWhitespace(ignore) => {
+                    info!("main. ignore whitespace {}", ignore);
+                    repo_settings::set_bool(
+                        status.path.as_ref(),
+                        &settings,
+                        IGNORE_WHITESPACE_TOKEN,
+                        ignore,
+                    );
+                    repo_settings::apply(status.path.as_ref(), &settings);
+                    status.get_status();
+                }
+                Event::SyntaxHighlighting(enabled) => {
+                    info!("main. syntax highlighting {}", enabled);
+                    settings
+                        .set(SYNTAX_HIGHLIGHTING_TOKEN, enabled)
+                        .expect("cant set settings");
+                    syntax::set_enabled(enabled);
+                    status.get_status();
+                }
+                Event::ConfirmKill(confirm) => {
+                    info!("main. confirm kill {}", confirm);
+                    settings
+                        .set(CONFIRM_KILL_TOKEN, confirm)
+                        .expect("cant set settings");
+                }
+                Event::RunHooks(run) => {
+                    info!("main. run hooks {}", run);
+                    repo_settings::set_bool(status.path.as_ref(), &settings, RUN_HOOKS_TOKEN, run);
+                    repo_settings::apply(status.path.as_ref(), &settings);
+                }
+                Event::PullFfOnly(ff_only) => {
+                    info!("main. pull ff only {}", ff_only);
+                    repo_settings::set_bool(
+                        status.path.as_ref(),
+                        &settings,
+                        PULL_FF_ONLY_TOKEN,
+                        ff_only,
+                    );
+                }
+                Event::PullRebaseMode(rebase) => {
+                    info!("main. pull rebase mode {}", rebase);
+                    repo_settings::set_bool(
+                        status.path.as_ref(),
+                        &settings,
+                        PULL_REBASE_TOKEN,
+                        rebase,
+                    );
+                }
+                Event::PerRepoSettings(per_repo) => {
+                    info!("main. per repo settings {}", per_repo);
+                    if let Some(path) = status.path.clone() {
+                        let result = if per_repo {
+                            repo_settings::enable(path.clone(), &settings)
+                        } else {
+                            repo_settings::disable(path.clone())
+                        };
+                        if let Err(err) = result {
+                            alert(err).present(Some(&application_window));
+                        }
+                        repo_settings::apply(Some(&path), &settings);
+                        hb_updater(HbUpdateData::Path(path));
+                        status.get_status();
+                    }
+                }
+                Event::CommitSigning => {
+                    info!("main. commit signing");
+                    commit_signing_dialog(
+                        &application_window,
+                        settings.clone(),
+                        status.path.clone(),
+                    );
+                }
+                Event::PreCommitCommand => {
+                    info!("main. pre-commit command");
+                    pre_commit_command_dialog(
+                        &application_window,
+                        settings.clone(),
+                        status.path.clone(),
+                    );
+                }
                 Event::CommitDiff(_d) => {
                     panic!("got oid diff in another receiver");
                 }
                 Event::RemotesDialog => {
                     info!("main. remotes dialog");
-                    status.show_remotes_dialog(&application_window);
+                    status.show_remotes_dialog(&application_window, &settings);
+                }
+                Event::WorktreesDialog => {
+                    info!("main. worktrees dialog");
+                    status.show_worktrees_dialog(&application_window);
                 }
                 Event::LockMonitors(lock) => {
                     info!("main. lock monitors {}", lock);
@@ -790,5 +1974,8 @@ fn run_app(app: &Application, initial_path: &Option<PathBuf>) {
             };
             hb_updater(HbUpdateData::Context(ctx));
         }
+        // ops which are still running in closed tab
+        // report to its channel
+        while receiver.recv().await.is_ok() {}
     });
 }
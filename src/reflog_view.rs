@@ -0,0 +1,378 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::commit_view::copy_oid;
+use crate::dialogs::{alert, DangerDialog, YES};
+use crate::git::git_log::{reflog, ReflogEntry};
+use crate::{reset_hard, CurrentWindow, DARK_CLASS, LIGHT_CLASS};
+use async_channel::Sender;
+use glib::Object;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{
+    gdk, gio, glib, pango, Box, Button, EventControllerKey, Label, ListItem, ListView, Orientation,
+    ScrolledWindow, SignalListItemFactory, SingleSelection, Widget,
+};
+use libadwaita::prelude::*;
+use libadwaita::{HeaderBar, StyleManager, ToolbarView, Window};
+use log::trace;
+use std::path::PathBuf;
+
+glib::wrapper! {
+    pub struct ReflogItem(ObjectSubclass<reflog_item::ReflogItem>);
+}
+
+mod reflog_item {
+    use crate::git::git_log::ReflogEntry;
+    use glib::Properties;
+    use gtk4::glib;
+    use gtk4::prelude::*;
+    use gtk4::subclass::prelude::*;
+    use std::cell::RefCell;
+
+    #[derive(Properties, Default)]
+    #[properties(wrapper_type = super::ReflogItem)]
+    pub struct ReflogItem {
+        pub entry: RefCell<Option<ReflogEntry>>,
+
+        #[property(get = Self::get_selector)]
+        pub selector: String,
+
+        #[property(get = Self::get_oids)]
+        pub oids: String,
+
+        #[property(get = Self::get_message)]
+        pub message: String,
+
+        #[property(get = Self::get_dt)]
+        pub dt: String,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ReflogItem {
+        const NAME: &'static str = "StageReflogItem";
+        type Type = super::ReflogItem;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for ReflogItem {}
+
+    fn short(oid: git2::Oid) -> String {
+        oid.to_string().get(..8).unwrap_or("").to_string()
+    }
+
+    impl ReflogItem {
+        pub fn get_selector(&self) -> String {
+            self.entry
+                .borrow()
+                .as_ref()
+                .map(|e| format!("HEAD@{{{}}}", e.num))
+                .unwrap_or_default()
+        }
+        pub fn get_oids(&self) -> String {
+            match self.entry.borrow().as_ref() {
+                // first entry in reflog has no previous oid
+                Some(e) if e.old_oid.is_zero() => {
+                    format!("<span color=\"#1C71D8\">{}</span>", short(e.new_oid))
+                }
+                Some(e) => format!(
+                    "{} → <span color=\"#1C71D8\">{}</span>",
+                    short(e.old_oid),
+                    short(e.new_oid)
+                ),
+                None => String::new(),
+            }
+        }
+        pub fn get_message(&self) -> String {
+            self.entry
+                .borrow()
+                .as_ref()
+                .map(|e| e.message.clone())
+                .unwrap_or_default()
+        }
+        pub fn get_dt(&self) -> String {
+            self.entry
+                .borrow()
+                .as_ref()
+                .map(|e| e.dt.to_string())
+                .unwrap_or_default()
+        }
+    }
+}
+
+impl ReflogItem {
+    pub fn new(entry: ReflogEntry) -> Self {
+        let ob = Object::builder::<ReflogItem>().build();
+        ob.imp().entry.replace(Some(entry));
+        ob
+    }
+
+    pub fn oid(&self) -> Option<git2::Oid> {
+        self.imp().entry.borrow().as_ref().map(|e| e.new_oid)
+    }
+}
+
+pub fn item_factory() -> SignalListItemFactory {
+    let factory = SignalListItemFactory::new();
+    factory.connect_setup(move |_, list_item| {
+        let selector_label = Label::builder()
+            .label("")
+            .width_chars(10)
+            .xalign(0.0)
+            .css_classes(["dim-label"])
+            .build();
+
+        let oids_label = Label::builder()
+            .label("")
+            .use_markup(true)
+            .width_chars(20)
+            .max_width_chars(20)
+            .xalign(0.0)
+            .build();
+
+        let message_label = Label::builder()
+            .label("")
+            .lines(1)
+            .single_line_mode(true)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+
+        let dt_label = Label::builder()
+            .label("")
+            .xalign(1.0)
+            .css_classes(["dim-label"])
+            .build();
+
+        let bx = Box::builder()
+            .orientation(Orientation::Horizontal)
+            .margin_top(0)
+            .margin_bottom(0)
+            .margin_start(2)
+            .margin_end(2)
+            .spacing(12)
+            .can_focus(true)
+            .focusable(true)
+            .build();
+
+        bx.append(&selector_label);
+        bx.append(&oids_label);
+        bx.append(&message_label);
+        bx.append(&dt_label);
+
+        let list_item = list_item
+            .downcast_ref::<ListItem>()
+            .expect("Needs to be ListItem");
+        list_item.set_child(Some(&bx));
+        list_item.set_selectable(true);
+        list_item.set_activatable(true);
+        list_item.set_focusable(true);
+
+        let item = list_item.property_expression("item");
+        item.chain_property::<ReflogItem>("selector")
+            .bind(&selector_label, "label", Widget::NONE);
+        item.chain_property::<ReflogItem>("oids")
+            .bind(&oids_label, "label", Widget::NONE);
+        item.chain_property::<ReflogItem>("message")
+            .bind(&message_label, "label", Widget::NONE);
+        item.chain_property::<ReflogItem>("dt")
+            .bind(&dt_label, "label", Widget::NONE);
+    });
+    factory
+}
+
+pub fn listview_factory(sender: Sender<crate::Event>) -> ListView {
+    let store = gio::ListStore::new::<ReflogItem>();
+    let selection_model = SingleSelection::new(Some(store));
+
+    let factory = item_factory();
+    let mut classes = glib::collections::strv::StrV::new();
+    classes.extend_from_slice(if StyleManager::default().is_dark() {
+        &[DARK_CLASS]
+    } else {
+        &[LIGHT_CLASS]
+    });
+    let list_view = ListView::builder()
+        .model(&selection_model)
+        .factory(&factory)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .css_classes(classes)
+        .build();
+    list_view.connect_activate(move |list_view, pos| {
+        let selection_model = list_view.model().unwrap();
+        if let Some(item) = selection_model.item(pos) {
+            let reflog_item = item.downcast_ref::<ReflogItem>().unwrap();
+            if let Some(oid) = reflog_item.oid() {
+                sender
+                    .send_blocking(crate::Event::ShowOid(oid, None, None))
+                    .expect("cant send through channel");
+            }
+        }
+    });
+    list_view
+}
+
+fn selected_oid(list_view: &ListView) -> Option<git2::Oid> {
+    let selection_model = list_view.model().unwrap();
+    let selection_model = selection_model.downcast_ref::<SingleSelection>().unwrap();
+    selection_model
+        .selected_item()
+        .and_then(|item| item.downcast_ref::<ReflogItem>().and_then(|i| i.oid()))
+}
+
+fn load_reflog(repo_path: PathBuf, list_view: &ListView, window: &Window) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        let list_view = list_view.clone();
+        async move {
+            let entries = gio::spawn_blocking(move || reflog(repo_path))
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(Vec::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    Vec::new()
+                });
+            let selection_model = list_view.model().unwrap();
+            let selection_model = selection_model.downcast_ref::<SingleSelection>().unwrap();
+            let store = selection_model.model().unwrap();
+            let store = store.downcast_ref::<gio::ListStore>().unwrap();
+            let items: Vec<ReflogItem> = entries.into_iter().map(ReflogItem::new).collect();
+            store.remove_all();
+            store.extend_from_slice(&items);
+        }
+    });
+}
+
+// reset moves HEAD, so reflog gets new entry on top
+fn reset_to_selected(
+    repo_path: PathBuf,
+    list_view: &ListView,
+    window: &Window,
+    sender: Sender<crate::Event>,
+) {
+    let Some(oid) = selected_oid(list_view) else {
+        return;
+    };
+    glib::spawn_future_local({
+        let window = window.clone();
+        let list_view = list_view.clone();
+        async move {
+            let response = alert(DangerDialog(
+                String::from("Reset"),
+                format!("Hard reset to {}", oid),
+            ))
+            .choose_future(&window)
+            .await;
+            if response != YES {
+                return;
+            }
+            let result = gio::spawn_blocking({
+                let path = repo_path.clone();
+                move || reset_hard(path, Some(oid), sender)
+            })
+            .await
+            .unwrap_or_else(|e| {
+                alert(format!("{:?}", e)).present(Some(&window));
+                Ok(false)
+            })
+            .unwrap_or_else(|e| {
+                alert(e).present(Some(&window));
+                false
+            });
+            if result {
+                load_reflog(repo_path, &list_view, &window);
+            }
+        }
+    });
+}
+
+pub fn show_reflog_window(
+    repo_path: PathBuf,
+    app_window: CurrentWindow,
+    main_sender: Sender<crate::Event>,
+) -> Window {
+    let mut builder = Window::builder().default_width(1280).default_height(960);
+    match app_window {
+        CurrentWindow::Window(w) => {
+            builder = builder.transient_for(&w);
+        }
+        CurrentWindow::ApplicationWindow(w) => {
+            builder = builder.transient_for(&w);
+        }
+    }
+    let window = builder.build();
+    let list_view = listview_factory(main_sender.clone());
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_child(Some(&list_view));
+
+    let tb = ToolbarView::builder().content(&scroll).build();
+    let hb = HeaderBar::new();
+    let title = Label::builder()
+        .label("Reflog")
+        .single_line_mode(true)
+        .build();
+    hb.set_title_widget(Some(&title));
+
+    let reset_btn = Button::builder()
+        .icon_name("edit-undo-symbolic")
+        .can_focus(false)
+        .tooltip_text("Hard reset to selected entry (x)")
+        .can_shrink(true)
+        .build();
+    reset_btn.connect_clicked({
+        let window = window.clone();
+        let list_view = list_view.clone();
+        let repo_path = repo_path.clone();
+        let sender = main_sender.clone();
+        move |_| {
+            reset_to_selected(repo_path.clone(), &list_view, &window, sender.clone());
+        }
+    });
+    hb.pack_end(&reset_btn);
+    tb.add_top_bar(&hb);
+    window.set_content(Some(&tb));
+
+    let event_controller = EventControllerKey::new();
+    event_controller.connect_key_pressed({
+        let window = window.clone();
+        let list_view = list_view.clone();
+        let repo_path = repo_path.clone();
+        move |_, key, _, modifier| {
+            match (key, modifier) {
+                (gdk::Key::w, gdk::ModifierType::CONTROL_MASK) => {
+                    window.close();
+                }
+                (gdk::Key::Escape, _) => {
+                    window.close();
+                }
+                (gdk::Key::x, _) => {
+                    reset_to_selected(repo_path.clone(), &list_view, &window, main_sender.clone());
+                }
+                (gdk::Key::y, _) => {
+                    if let Some(oid) = selected_oid(&list_view) {
+                        copy_oid(oid, &window, main_sender.clone());
+                    }
+                }
+                (key, modifier) => {
+                    trace!("key pressed {:?} {:?}", key, modifier);
+                }
+            }
+            glib::Propagation::Proceed
+        }
+    });
+    window.add_controller(event_controller);
+    window.present();
+    list_view.grab_focus();
+
+    load_reflog(repo_path, &list_view, &window);
+    window
+}
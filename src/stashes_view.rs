@@ -147,27 +147,52 @@ impl OidRow {
             let row = self.clone();
 
             async move {
-                let lbl = {
-                    let stash = row.imp().stash.borrow();
-                    Label::new(Some(&format!("Apply stash {}", stash.title)))
-                };
-                let dialog = confirm_dialog_factory(Some(&lbl), "Apply", "Apply");
+                let heading = format!("Apply stash {}", row.imp().stash.borrow().title);
+                let lb = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let branch = EntryRow::builder()
+                    .title("Apply on new branch made from stash base (optional):")
+                    .css_classes(vec!["input_field"])
+                    .show_apply_button(false)
+                    .build();
+                lb.append(&branch);
+                let dialog = confirm_dialog_factory(Some(&lb), &heading, "Apply");
                 let result = dialog.choose_future(&window).await;
-                if result == PROCEED {
-                    gio::spawn_blocking({
-                        let stash = row.imp().stash.borrow().clone();
-                        let sender = sender.clone();
-                        move || stash::apply(path, stash.num, None, sender)
-                    })
-                    .await
-                    .unwrap()
-                    .unwrap_or_else(|e| {
-                        alert(e).present(Some(&window));
-                    });
-                    sender
-                        .send_blocking(Event::StashesPanel)
-                        .expect("cant send through channel");
+                if result != PROCEED {
+                    return;
                 }
+                let stash = row.imp().stash.borrow().clone();
+                let branch_name = branch.text().trim().to_string();
+                let applied = gio::spawn_blocking({
+                    let sender = sender.clone();
+                    move || {
+                        if branch_name.is_empty() {
+                            stash::apply(path, stash.num, None, sender)
+                        } else {
+                            stash::apply_on_branch(path, stash.num, branch_name, sender)
+                                .map(|_| Vec::new())
+                        }
+                    }
+                })
+                .await
+                .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+                match applied {
+                    Ok(conflicts) if !conflicts.is_empty() => {
+                        sender
+                            .send_blocking(Event::Toast(format!(
+                                "Stash applied with conflicts in {} files",
+                                conflicts.len()
+                            )))
+                            .expect("cant send through channel");
+                    }
+                    Ok(_) => {}
+                    Err(e) => alert(e).present(Some(&window)),
+                }
+                sender
+                    .send_blocking(Event::StashesPanel)
+                    .expect("cant send through channel");
             }
         });
     }
@@ -184,6 +209,7 @@ pub fn add_stash(
     window: &ApplicationWindow,
     stashes_box: &ListBox,
     selected: Selected,
+    selection: Option<stash::StashSelection>,
     sender: Sender<Event>,
 ) {
     glib::spawn_future_local({
@@ -248,6 +274,41 @@ pub fn add_stash(
                     lb.append(&file_chooser);
                 }
             }
+            let partial: Rc<RefCell<Option<stash::StashSelection>>> = Rc::new(RefCell::new(None));
+            if let Some(selection) = selection {
+                let title = match &selection {
+                    stash::StashSelection::Unstaged(_, Some(header)) => {
+                        format!("Only changes in hunk {}", header)
+                    }
+                    stash::StashSelection::Unstaged(Some(path), None) => {
+                        format!("Only unstaged changes in file {}", path.display())
+                    }
+                    stash::StashSelection::Unstaged(None, None) => {
+                        String::from("Only unstaged changes")
+                    }
+                    stash::StashSelection::Untracked(entries) => match &entries[..] {
+                        [path] => format!("Only untracked {}", path.display()),
+                        entries => format!("Only {} untracked entries", entries.len()),
+                    },
+                };
+                let partial_chooser = SwitchRow::builder()
+                    .title(title)
+                    .subtitle("the rest of changes stays in working tree")
+                    .css_classes(vec!["input_field"])
+                    .active(false)
+                    .build();
+                partial_chooser.connect_active_notify({
+                    let partial = partial.clone();
+                    move |row| {
+                        if row.is_active() {
+                            partial.replace(Some(selection.clone()));
+                        } else {
+                            partial.take();
+                        }
+                    }
+                });
+                lb.append(&partial_chooser);
+            }
 
             dialog.set_extra_child(Some(&lb));
             dialog.add_responses(&[(CANCEL, "Cancel"), (PROCEED, title)]);
@@ -280,7 +341,13 @@ pub fn add_stash(
             let result = gio::spawn_blocking({
                 let sender = sender.clone();
                 let file_path = file_path.borrow().clone();
-                move || stash::stash(path, stash_message, stash_staged, file_path.clone(), sender)
+                let partial = partial.borrow().clone();
+                move || {
+                    if let Some(selection) = partial {
+                        return stash::stash_selected(path, stash_message, selection, sender);
+                    }
+                    stash::stash(path, stash_message, stash_staged, file_path.clone(), sender)
+                }
             })
             .await
             .unwrap_or_else(|e| {
@@ -370,8 +437,16 @@ pub fn factory(window: &ApplicationWindow, status: &Status) -> (ToolbarView, imp
         let path = status.path.clone().expect("no path");
         let lb = lb.clone();
         let selected = status.selected().clone();
+        let selection = status.stash_selection();
         move |_| {
-            add_stash(path.clone(), &window, &lb, selected.clone(), sender.clone());
+            add_stash(
+                path.clone(),
+                &window,
+                &lb,
+                selected.clone(),
+                selection.clone(),
+                sender.clone(),
+            );
         }
     });
     apply.connect_clicked({
@@ -412,6 +487,7 @@ pub fn factory(window: &ApplicationWindow, status: &Status) -> (ToolbarView, imp
         let window = window.clone();
         let path = status.path.clone().expect("no path");
         let selected = status.selected().clone();
+        let selection = status.stash_selection();
         move |_, key, _, modifier| {
             match (key, modifier) {
                 (gdk::Key::Escape, _) => {
@@ -437,6 +513,7 @@ pub fn factory(window: &ApplicationWindow, status: &Status) -> (ToolbarView, imp
                         &window,
                         &lb.clone(),
                         selected.clone(),
+                        selection.clone(),
                         sender.clone(),
                     );
                 }
@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod branch_switcher;
 pub mod commit;
 pub mod context;
 pub mod headerbar;
@@ -9,12 +10,19 @@ pub mod monitor;
 pub mod op;
 pub mod remotes;
 pub mod render;
+pub mod search;
 pub mod stage_view;
 pub mod tags;
+pub mod worktrees;
 
-use crate::dialogs::{alert, DangerDialog, YES};
+use crate::dialogs::{
+    alert, confirm_dialog_factory, ConfirmDialog, DangerDialog, DangerWithOptions, PROCEED, YES,
+};
 use crate::git::{
-    abort_rebase, blame, branch::BranchData, continue_rebase, merge, remote, stash, HunkLineNo,
+    abort_rebase, add_to_gitignore, bisect, blame, branch::BranchData, clean_candidates,
+    commit as git_commit, continue_rebase, count_changes, delete_untracked, ignore_patterns, merge,
+    patch, remote, repo_settings, stash, undo, untracked_files, untracked_preview, HunkLineNo,
+    MANY_CHANGES,
 };
 
 use git2::RepositoryState;
@@ -31,10 +39,11 @@ use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+use crate::external;
 use crate::status_view::view::View;
 use crate::{
     get_current_repo_status, BlameLine, CurrentWindow, Diff, DiffKind, Event, File as GitFile,
-    Head, Selected, State, StatusRenderContext, DARK_CLASS, LIGHT_CLASS,
+    Head, LineKind, Selected, State, StatusRenderContext, DARK_CLASS, LIGHT_CLASS, MARKER_OURS,
 };
 use async_channel::Sender;
 
@@ -42,11 +51,31 @@ use gio::FileMonitor;
 
 use glib::signal::SignalHandlerId;
 use gtk4::prelude::*;
-use gtk4::{gio, glib, Align, Button, FileDialog, Widget, Window as GTKWindow};
+use gtk4::{
+    gio, glib, Align, Button, CheckButton, FileDialog, FileFilter, ListBox, ScrolledWindow,
+    SelectionMode, Widget, Window as GTKWindow,
+};
 use libadwaita::prelude::*;
-use libadwaita::{ApplicationWindow, Banner, ButtonContent, StatusPage, StyleManager};
+use libadwaita::{
+    ApplicationWindow, Banner, ButtonContent, EntryRow, SpinRow, StatusPage, StyleManager,
+    SwitchRow,
+};
 use log::{debug, trace};
 
+fn abort_state(
+    state: RepositoryState,
+    path: PathBuf,
+    sender: Sender<Event>,
+) -> Result<(), git2::Error> {
+    match state {
+        RepositoryState::RebaseMerge => abort_rebase(path, sender),
+        RepositoryState::CherryPickSequence | RepositoryState::RevertSequence => {
+            git_commit::abort_cherry_pick(path, sender)
+        }
+        _ => merge::abort(path, sender),
+    }
+}
+
 impl State {
     pub fn title_for_proceed_banner(&self) -> String {
         match self.state {
@@ -57,6 +86,15 @@ impl State {
             ),
             RepositoryState::CherryPick => format!("Commit to finish cherry-pick {}", self.subject),
             RepositoryState::Revert => format!("Commit to finish revert {}", self.subject),
+            RepositoryState::CherryPickSequence => {
+                format!("Continue cherry-pick sequence from {}", self.subject)
+            }
+            RepositoryState::RevertSequence => {
+                format!("Continue revert sequence from {}", self.subject)
+            }
+            RepositoryState::RebaseMerge => {
+                format!("Continue rebase from {}", self.subject)
+            }
             _ => "".to_string(),
         }
     }
@@ -66,9 +104,19 @@ impl State {
             RepositoryState::Merge => {
                 format!("{} merging branch {}", start, self.subject)
             }
-            RepositoryState::CherryPick => {
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
                 format!("{} cherry picking {}", start, self.subject)
             }
+            RepositoryState::Revert | RepositoryState::RevertSequence => {
+                format!("{} reverting {}", start, self.subject)
+            }
+            RepositoryState::Rebase
+            | RepositoryState::RebaseMerge
+            | RepositoryState::RebaseInteractive => {
+                format!("{} rebasing {}", start, self.subject)
+            }
+            // e.g. stash applied with conflicts
+            RepositoryState::Clean => format!("{} applying changes", start),
             _ => "".to_string(),
         }
     }
@@ -142,7 +190,13 @@ pub struct Status {
 
     pub monitor_global_lock: Rc<RefCell<bool>>,
     pub monitor_lock: Rc<RefCell<HashSet<PathBuf>>>,
+    pub monitor_events: Rc<Cell<u32>>,
     pub last_op: Cell<Option<LastOp>>,
+    // file path and hunk header under cursor when last op was made.
+    // indexes in LastOp are shifted, when other files come and go
+    pub last_op_target: RefCell<Option<(PathBuf, Option<String>)>>,
+    // state before last stage/unstage/kill, to undo it
+    pub undo_snapshot: Rc<RefCell<Option<undo::Snapshot>>>,
     pub cursor_position: Cell<CursorPosition>,
 }
 
@@ -165,7 +219,10 @@ impl Status {
             // TODO! replace with Cell
             monitor_global_lock: Rc::new(RefCell::new(false)),
             monitor_lock: Rc::new(RefCell::new(HashSet::new())),
+            monitor_events: Rc::new(Cell::new(0)),
             last_op: Cell::new(None),
+            last_op_target: RefCell::new(None),
+            undo_snapshot: Rc::new(RefCell::new(None)),
             cursor_position: Cell::new(CursorPosition::None),
         }
     }
@@ -247,19 +304,28 @@ impl Status {
             // investigated path
             // assert!(path.ends_with(".git/"));
             if self.path.is_none() || path != self.path.clone().unwrap() {
-                let mut paths = settings.get::<Vec<String>>("paths");
                 let str_path = String::from(path.to_str().unwrap()).replace(".git/", "");
                 settings
                     .set("lastpath", str_path.clone())
                     .expect("cant set lastpath");
-                if !paths.contains(&str_path) {
-                    paths.push(str_path.clone());
-                    settings.set("paths", paths).expect("cant set settings");
-                }
+                let paths = headerbar::recent_paths(
+                    settings.get::<Vec<String>>(headerbar::PATHS_TOKEN),
+                    Some(&str_path),
+                );
+                settings
+                    .set(headerbar::PATHS_TOKEN, paths)
+                    .expect("cant set settings");
                 self.setup_monitors(monitors, PathBuf::from(str_path));
             }
         }
         self.path.replace(path.clone());
+        if !user_action {
+            // per repo layer overrides globals. status is already
+            // requested with previous repo diff options
+            if repo_settings::apply(Some(&path), settings) {
+                self.get_status();
+            }
+        }
     }
 
     pub fn update_stashes(&mut self, stashes: stash::Stashes) {
@@ -302,6 +368,59 @@ impl Status {
         });
     }
 
+    pub fn bisect(&self, op: crate::BisectOp, window: &ApplicationWindow) {
+        glib::spawn_future_local({
+            let sender = self.sender.clone();
+            let path = self.path.clone().unwrap();
+            let window = window.clone();
+            async move {
+                let step = gio::spawn_blocking({
+                    let sender = sender.clone();
+                    move || match op {
+                        crate::BisectOp::Start => {
+                            bisect::start(path, sender).map(|_| bisect::BisectStep::NeedMore)
+                        }
+                        crate::BisectOp::Good => bisect::mark(path, true, sender),
+                        crate::BisectOp::Bad => bisect::mark(path, false, sender),
+                        crate::BisectOp::Reset => {
+                            bisect::reset(path, sender).map(|_| bisect::BisectStep::NeedMore)
+                        }
+                    }
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(bisect::BisectStep::NeedMore)
+                });
+                let message = match step {
+                    Ok(bisect::BisectStep::Found(oid)) => {
+                        sender
+                            .send_blocking(crate::Event::ShowOid(oid, None, None))
+                            .expect("cant send through channel");
+                        format!("First bad commit {}", oid)
+                    }
+                    Ok(bisect::BisectStep::Next(oid, left)) => {
+                        format!("Checked out {}. {} revisions left", oid, left)
+                    }
+                    Ok(bisect::BisectStep::NeedMore) => match op {
+                        crate::BisectOp::Start => {
+                            String::from("Bisect started. Mark HEAD good (G) or bad (B)")
+                        }
+                        crate::BisectOp::Reset => String::from("Bisect reset"),
+                        _ => String::from("Mark other commit good or bad"),
+                    },
+                    Err(e) => {
+                        alert(e).present(Some(&window));
+                        return;
+                    }
+                };
+                sender
+                    .send_blocking(crate::Event::Toast(message))
+                    .expect("cant send through channel");
+            }
+        });
+    }
+
     pub fn get_status(&self) {
         gio::spawn_blocking({
             let path = self.path.clone();
@@ -313,6 +432,42 @@ impl Status {
         });
     }
 
+    // same as get_status, but asks user before
+    // rendering repos with enormous amount of changes
+    pub fn open_status(&self, window: &ApplicationWindow) {
+        glib::spawn_future_local({
+            let path = self.path.clone();
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                if let Some(path) = path.clone() {
+                    let count = gio::spawn_blocking(move || count_changes(path))
+                        .await
+                        .unwrap_or(Ok(0))
+                        .unwrap_or(0);
+                    if count > MANY_CHANGES {
+                        let response = alert(ConfirmDialog(
+                            String::from("Too many changes"),
+                            format!(
+                                "There are {} changed files in repository. Rendering them could take a long time. Proceed?",
+                                count
+                            ),
+                        ))
+                        .choose_future(&window)
+                        .await;
+                        if response != YES {
+                            return;
+                        }
+                    }
+                }
+                gio::spawn_blocking(move || {
+                    let lookup_result = get_current_repo_status(path, sender);
+                    debug!("repo lookup result {:?}", lookup_result);
+                });
+            }
+        });
+    }
+
     pub fn get_empty_view(&self) -> impl IsA<Widget> {
         let button_content = ButtonContent::builder()
             .icon_name("document-open-symbolic")
@@ -352,7 +507,12 @@ impl Status {
             .build()
     }
 
-    pub fn pull(&self, window: &ApplicationWindow) {
+    /// Rebase is forced by rebase param, otherwise it is taken from settings.
+    pub fn pull(&self, rebase: bool, settings: &gio::Settings, window: &ApplicationWindow) {
+        let ff_only =
+            repo_settings::get_bool(self.path.as_ref(), settings, headerbar::PULL_FF_ONLY_TOKEN);
+        let rebase = rebase
+            || repo_settings::get_bool(self.path.as_ref(), settings, headerbar::PULL_REBASE_TOKEN);
         glib::spawn_future_local({
             let path = self.path.clone().expect("no path");
             let sender = self.sender.clone();
@@ -360,7 +520,7 @@ impl Status {
             async move {
                 gio::spawn_blocking({
                     let sender = sender.clone();
-                    move || remote::pull(path, sender)
+                    move || remote::pull(path, ff_only, rebase, sender)
                 })
                 .await
                 .unwrap_or_else(|e| {
@@ -381,28 +541,418 @@ impl Status {
         });
     }
 
+    /// Choose .patch/.diff file and apply it to workdir
+    /// and optionally to index.
+    pub fn apply_patch(&self, window: &ApplicationWindow) {
+        glib::spawn_future_local({
+            let path = self.path.clone().expect("no path");
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                let filter = FileFilter::new();
+                filter.set_name(Some("Patches"));
+                filter.add_suffix("patch");
+                filter.add_suffix("diff");
+                let filters = gio::ListStore::new::<FileFilter>();
+                filters.append(&filter);
+                let dialog = FileDialog::builder()
+                    .title("Apply patch")
+                    .filters(&filters)
+                    .build();
+                let Some(patch_path) = dialog
+                    .open_future(Some(&window))
+                    .await
+                    .ok()
+                    .and_then(|file| file.path())
+                else {
+                    return;
+                };
+                let stage = CheckButton::builder()
+                    .label("Stage applied changes")
+                    .build();
+                let dialog = confirm_dialog_factory(
+                    Some(&stage),
+                    &format!("Apply {}", patch_path.display()),
+                    "Apply",
+                );
+                if dialog.choose_future(&window).await != PROCEED {
+                    return;
+                }
+                let stage = stage.is_active();
+                gio::spawn_blocking(move || patch::apply_patch(path, patch_path, stage, sender))
+                    .await
+                    .unwrap_or_else(|e| {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        Ok(())
+                    })
+                    .unwrap_or_else(|e| {
+                        alert(e).present(Some(&window));
+                    });
+            }
+        });
+    }
+
+    /// git clean. Deletion is irreversible, so whole list of
+    /// files is shown before anything is deleted.
+    pub fn clean(&self, window: &ApplicationWindow) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        glib::spawn_future_local({
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                let lb = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let dirs = SwitchRow::builder()
+                    .title("Untracked directories")
+                    .subtitle("git clean -d")
+                    .css_classes(vec!["input_field"])
+                    .build();
+                lb.append(&dirs);
+                let ignored = SwitchRow::builder()
+                    .title("Ignored files")
+                    .subtitle("git clean -x")
+                    .css_classes(vec!["input_field"])
+                    .build();
+                lb.append(&ignored);
+                let dialog = confirm_dialog_factory(Some(&lb), "Clean working tree", "Show files");
+                if dialog.choose_future(&window).await != PROCEED {
+                    return;
+                }
+                let (dirs, ignored) = (dirs.is_active(), ignored.is_active());
+                // same files as kill of untracked entries would delete
+                let (entries, files) = gio::spawn_blocking({
+                    let path = path.clone();
+                    move || {
+                        let entries = clean_candidates(path.clone(), dirs, ignored)?;
+                        let files = untracked_files(path, entries.clone(), ignored)?;
+                        Ok::<_, git2::Error>((entries, files))
+                    }
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok((Vec::new(), Vec::new()))
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    (Vec::new(), Vec::new())
+                });
+                if files.is_empty() {
+                    sender
+                        .send_blocking(Event::Toast(String::from("Nothing to clean")))
+                        .expect("Could not send through channel");
+                    return;
+                }
+                let list: Vec<String> = files
+                    .iter()
+                    .map(|file| file.to_string_lossy().to_string())
+                    .collect();
+                let label = gtk4::Label::builder()
+                    .label(list.join("\n"))
+                    .xalign(0.0)
+                    .yalign(0.0)
+                    .selectable(true)
+                    .css_classes(["monospace"])
+                    .build();
+                let scroll = ScrolledWindow::builder()
+                    .min_content_height(320)
+                    .child(&label)
+                    .build();
+                let response = alert(DangerWithOptions(
+                    "Delete files?".to_string(),
+                    format!("{} files will be deleted from disk", files.len()),
+                    scroll.upcast::<Widget>(),
+                ))
+                .choose_future(&window)
+                .await;
+                if response != YES {
+                    return;
+                }
+                let deleted = gio::spawn_blocking({
+                    let sender = sender.clone();
+                    move || delete_untracked(path, entries, ignored, sender)
+                })
+                .await
+                .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+                match deleted {
+                    Ok(deleted) => {
+                        sender
+                            .send_blocking(Event::Toast(format!("Deleted {} files", deleted)))
+                            .expect("Could not send through channel");
+                    }
+                    Err(e) => alert(e).present(Some(&window)),
+                }
+            }
+        });
+    }
+
+    /// Adds untracked file under cursor to .gitignore,
+    /// as is or by suggested glob.
+    pub fn ignore_file(&self, window: &ApplicationWindow) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let file_idx = match self.cursor_position.get() {
+            CursorPosition::CursorFile(DiffKind::Untracked, file_idx)
+            | CursorPosition::CursorHunk(DiffKind::Untracked, file_idx, _)
+            | CursorPosition::CursorLine(DiffKind::Untracked, file_idx, _, _) => file_idx,
+            _ => return,
+        };
+        let Some(file) = self.untracked.as_ref().and_then(|u| u.files.get(file_idx)) else {
+            return;
+        };
+        let file_path = file.path.clone();
+        glib::spawn_future_local({
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                let patterns = ignore_patterns(&file_path);
+                let lb = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let input = EntryRow::builder()
+                    .title("Pattern to add to .gitignore:")
+                    .show_apply_button(false)
+                    .css_classes(vec!["input_field"])
+                    .text(&patterns[0])
+                    .build();
+                lb.append(&input);
+                if let Some(glob) = patterns.get(1) {
+                    let by_glob = SwitchRow::builder()
+                        .title(format!("Ignore all {} files", glob))
+                        .css_classes(vec!["input_field"])
+                        .build();
+                    by_glob.connect_active_notify({
+                        let input = input.clone();
+                        let patterns = patterns.clone();
+                        move |row| {
+                            input.set_text(&patterns[if row.is_active() { 1 } else { 0 }]);
+                        }
+                    });
+                    lb.append(&by_glob);
+                }
+                let dialog = confirm_dialog_factory(
+                    Some(&lb),
+                    &format!("Ignore {}", file_path.display()),
+                    "Ignore",
+                );
+                if dialog.choose_future(&window).await != PROCEED {
+                    return;
+                }
+                let pattern = input.text().trim().to_string();
+                if pattern.is_empty() {
+                    return;
+                }
+                gio::spawn_blocking(move || add_to_gitignore(path, file_path, pattern, sender))
+                    .await
+                    .unwrap_or_else(|e| {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        Ok(())
+                    })
+                    .unwrap_or_else(|e| {
+                        alert(e).present(Some(&window));
+                    });
+            }
+        });
+    }
+
+    fn alert_conflicts(&self, window: &ApplicationWindow) -> bool {
+        if let Some(conflicted) = &self.conflicted {
+            let files: Vec<String> = conflicted
+                .files
+                .iter()
+                .map(|file| html_escape::encode_text(&file.path.to_string_lossy()).to_string())
+                .collect();
+            alert(format!(
+                "Resolve conflicts before commit. Conflicted files:\n{}",
+                files.join("\n")
+            ))
+            .present(Some(window));
+            return true;
+        }
+        false
+    }
+
     pub fn commit(
         &self,
+        settings: &gio::Settings,
         window: &ApplicationWindow, // &impl IsA<Gtk4Window>,
     ) {
-        let mut amend_message: Option<String> = None;
+        if self.alert_conflicts(window) {
+            return;
+        }
+        let mut mode = commit::CommitMode::New;
         if let Some(head) = &self.head {
             if let Some(upstream) = &self.upstream {
                 if head.oid != upstream.oid {
-                    amend_message.replace(head.raw_message.clone());
+                    mode = commit::CommitMode::Amend(head.raw_message.clone());
                 }
             } else {
-                amend_message.replace(head.raw_message.clone());
+                mode = commit::CommitMode::Amend(head.raw_message.clone());
             }
         }
         commit::commit(
             self.path.clone(),
-            amend_message,
+            mode,
+            self.head
+                .as_ref()
+                .and_then(|head| head.branch.as_ref())
+                .map(|branch| branch.name.to_string()),
+            settings.clone(),
             window,
             self.sender.clone(),
         );
     }
 
+    /// Commit with message typed in bar below status. Sign off
+    /// and signing are taken from settings, as dialog has them.
+    pub fn commit_inline(
+        &self,
+        message: String,
+        settings: &gio::Settings,
+        window: &ApplicationWindow,
+    ) {
+        if self.alert_conflicts(window) {
+            return;
+        }
+        commit::commit_inline(
+            self.path.clone(),
+            message,
+            settings.clone(),
+            window,
+            self.sender.clone(),
+        );
+    }
+
+    /// Abort merge, rebase, cherry-pick or revert in progress.
+    /// Conflict banner does the same, but when all conflicts are
+    /// resolved banner only proposes to finish.
+    pub fn abort(&self, window: &ApplicationWindow) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        if !(state.need_final_commit()
+            || state.need_rebase_continue()
+            || state.need_sequence_continue())
+        {
+            return;
+        }
+        let state = state.state;
+        let heading = match state {
+            RepositoryState::Merge => "Abort merge?",
+            RepositoryState::RebaseMerge => "Abort rebase?",
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                "Abort cherry-pick?"
+            }
+            _ => "Abort revert?",
+        };
+        glib::spawn_future_local({
+            let path = self.path.clone().expect("no path");
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                let dialog = confirm_dialog_factory(None::<&Widget>, heading, "Abort");
+                if dialog.choose_future(&window).await != PROCEED {
+                    return;
+                }
+                gio::spawn_blocking(move || abort_state(state, path, sender))
+                    .await
+                    .unwrap_or_else(|e| {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        Ok(())
+                    })
+                    .unwrap_or_else(|e| {
+                        alert(e).present(Some(&window));
+                    });
+            }
+        });
+    }
+
+    pub fn squash(&self, settings: &gio::Settings, window: &ApplicationWindow) {
+        let path = self.path.clone().expect("no path");
+        let branch_name = self
+            .head
+            .as_ref()
+            .and_then(|head| head.branch.as_ref())
+            .map(|branch| branch.name.to_string());
+        glib::spawn_future_local({
+            let sender = self.sender.clone();
+            let settings = settings.clone();
+            let window = window.clone();
+            async move {
+                let (depth, not_pushed) = gio::spawn_blocking({
+                    let path = path.clone();
+                    move || git_commit::squash_limits(path)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok((0, None))
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    (0, None)
+                });
+                if depth < 2 {
+                    alert(String::from("Not enough commits to squash")).present(Some(&window));
+                    return;
+                }
+                let list_box = ListBox::builder()
+                    .selection_mode(SelectionMode::None)
+                    .css_classes(vec![String::from("boxed-list")])
+                    .build();
+                let count_row = SpinRow::with_range(2.0, depth as f64, 1.0);
+                count_row.set_title("number of last commits");
+                count_row.set_value(not_pushed.unwrap_or(2).clamp(2, depth) as f64);
+                list_box.append(&count_row);
+                let dialog = confirm_dialog_factory(Some(&list_box), "Squash commits", "Squash");
+                if dialog.choose_future(&window).await != PROCEED {
+                    return;
+                }
+                let count = count_row.value() as usize;
+                if let Some(not_pushed) = not_pushed {
+                    if count > not_pushed {
+                        alert(format!(
+                            "Only {} commits are not pushed to upstream. Squashing {} commits will rewrite published history",
+                            not_pushed, count
+                        ))
+                        .present(Some(&window));
+                        return;
+                    }
+                }
+                let message = gio::spawn_blocking({
+                    let path = path.clone();
+                    move || git_commit::squash_message(path, count)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(String::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    String::new()
+                });
+                if message.is_empty() {
+                    return;
+                }
+                commit::commit(
+                    Some(path),
+                    commit::CommitMode::Squash(count, message),
+                    branch_name,
+                    settings,
+                    &window,
+                    sender,
+                );
+            }
+        });
+    }
+
     pub fn update_head<'a>(
         &'a mut self,
         mut head: Option<Head>,
@@ -514,11 +1064,68 @@ impl Status {
             }
         }
         self.untracked = untracked;
+        // views are transferred. files which stay expanded
+        // need their content again
+        self.preview_untracked(|file| file.view.is_expanded());
         if self.untracked.is_some() || render_required {
             self.render(txt, Some(DiffKind::Untracked), context);
         }
     }
 
+    fn preview_untracked(&mut self, filter: impl Fn(&GitFile) -> bool) {
+        let (Some(path), Some(untracked)) = (&self.path, &mut self.untracked) else {
+            return;
+        };
+        for file in untracked.files.iter_mut() {
+            if file.hunks.is_empty() && filter(file) {
+                untracked_preview(path, file);
+            }
+        }
+    }
+
+    // file under cursor in conflicted, or the only one conflicted file
+    fn conflicted_file_at_cursor(&self) -> Option<PathBuf> {
+        let conflicted = self.conflicted.as_ref()?;
+        match self.cursor_position.get() {
+            CursorPosition::CursorFile(DiffKind::Conflicted, file_idx)
+            | CursorPosition::CursorHunk(DiffKind::Conflicted, file_idx, _)
+            | CursorPosition::CursorLine(DiffKind::Conflicted, file_idx, _, _) => {
+                Some(conflicted.files[file_idx].path.clone())
+            }
+            _ if conflicted.files.len() == 1 => Some(conflicted.files[0].path.clone()),
+            _ => {
+                self.sender
+                    .send_blocking(Event::Toast(String::from("Put cursor on conflicted file")))
+                    .expect("cant send through channel");
+                None
+            }
+        }
+    }
+
+    pub fn choose_conflict_side_of_file(&self, ours: bool, window: &impl IsA<Widget>) {
+        let Some(file_path) = self.conflicted_file_at_cursor() else {
+            return;
+        };
+        glib::spawn_future_local({
+            let path = self.path.clone().expect("no path");
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                gio::spawn_blocking(move || {
+                    merge::choose_conflict_side_of_file(path, file_path, ours, sender)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                });
+            }
+        });
+    }
+
     pub fn update_conflicted<'a>(
         &'a mut self,
         diff: Option<Diff>,
@@ -529,8 +1136,12 @@ impl Status {
         banner: &Banner,
         banner_button: &Widget,
         banner_button_clicked: Rc<RefCell<Option<SignalHandlerId>>>,
+        side_buttons: &Widget,
         context: &mut StatusRenderContext<'a>,
     ) {
+        // take ours/theirs for whole file
+        side_buttons.set_visible(diff.is_some());
+        let entering_conflicts = self.conflicted.is_none() && diff.is_some();
         if let Some(state) = state {
             if let Some(current_state) = &self.state {
                 state.enrich_view(current_state, &txt.buffer(), context)
@@ -557,17 +1168,21 @@ impl Status {
                 if banner.is_revealed() {
                     banner.set_revealed(false);
                 }
-                if state.need_final_commit() || state.need_rebase_continue() {
+                if state.need_final_commit()
+                    || state.need_rebase_continue()
+                    || state.need_sequence_continue()
+                {
                     banner.set_title(&state.title_for_proceed_banner());
                     banner.set_css_classes(if StyleManager::default().is_dark() {
                         &[DARK_CLASS, "success"]
                     } else {
                         &[LIGHT_CLASS, "success"]
                     });
-                    banner.set_button_label(if state.need_final_commit() {
-                        Some("Commit")
-                    } else {
-                        Some("Continue")
+                    banner.set_button_label(match state.state {
+                        RepositoryState::Merge => Some("Finish merge"),
+                        RepositoryState::CherryPick => Some("Finish cherry-pick"),
+                        RepositoryState::Revert => Some("Finish revert"),
+                        _ => Some("Continue"),
                     });
                     banner_button.set_css_classes(&["suggested-action"]);
                     banner.set_revealed(true);
@@ -581,6 +1196,18 @@ impl Status {
                         let banner = banner.clone();
                         let state = state.state;
                         move |_| {
+                            if matches!(
+                                state,
+                                RepositoryState::Merge
+                                    | RepositoryState::CherryPick
+                                    | RepositoryState::Revert
+                            ) {
+                                // commit dialog is prefilled with MERGE_MSG
+                                sender
+                                    .send_blocking(Event::Commit)
+                                    .expect("Could not send through channel");
+                                return;
+                            }
                             let sender = sender.clone();
                             let path = path.clone();
                             let window = window.clone();
@@ -589,14 +1216,13 @@ impl Status {
                                 async move {
                                     gio::spawn_blocking({
                                         move || match state {
-                                            RepositoryState::Merge => merge::final_merge_commit(
-                                                path.clone().unwrap(),
-                                                sender,
-                                            ),
                                             RepositoryState::RebaseMerge => {
                                                 continue_rebase(path.clone().unwrap(), sender)
                                             }
-                                            _ => merge::final_commit(path.clone().unwrap(), sender),
+                                            _ => git_commit::continue_cherry_pick(
+                                                path.clone().unwrap(),
+                                                sender,
+                                            ),
                                         }
                                     })
                                     .await
@@ -636,12 +1262,7 @@ impl Status {
                         gio::spawn_blocking({
                             let sender = sender.clone();
                             let path = path.clone();
-                            move || match state {
-                                RepositoryState::RebaseMerge => {
-                                    abort_rebase(path.expect("no path"), sender)
-                                }
-                                _ => merge::abort(path.expect("no path"), sender),
-                            }
+                            move || abort_state(state, path.expect("no path"), sender)
                         });
                     }
                 });
@@ -652,6 +1273,16 @@ impl Status {
         if self.conflicted.is_some() || render_required {
             self.render(txt, Some(DiffKind::Conflicted), context);
         }
+        // merge/rebase/cherry-pick just stopped on conflicts: go to the
+        // first one. but not if user is reading some hunk right now
+        if entering_conflicts
+            && !matches!(
+                self.cursor_position.get(),
+                CursorPosition::CursorHunk(..) | CursorPosition::CursorLine(..)
+            )
+        {
+            self.next_conflict(txt, true, context);
+        }
     }
 
     pub fn update_staged<'a>(
@@ -710,6 +1341,52 @@ impl Status {
         }
     }
 
+    /// Patch changes of single file into rendered staged/unstaged.
+    /// If file appears or disappears in any of them, order of files
+    /// and headerbar state could change - whole status is requested.
+    pub fn update_file<'a>(
+        &'a mut self,
+        file_path: PathBuf,
+        staged: Option<GitFile>,
+        unstaged: Option<GitFile>,
+        txt: &StageView,
+        context: &mut StatusRenderContext<'a>,
+    ) {
+        let has_file = |diff: &Option<Diff>| {
+            diff.as_ref()
+                .map(|diff| diff.files.iter().any(|f| f.path == file_path))
+                .unwrap_or(false)
+        };
+        if has_file(&self.staged) != staged.is_some()
+            || has_file(&self.unstaged) != unstaged.is_some()
+        {
+            debug!("set of changed files is changed. full refresh");
+            self.get_status();
+            return;
+        }
+        let patched = |diff: &Option<Diff>, file: GitFile| {
+            diff.as_ref().map(|diff| {
+                let mut new = Diff::new(diff.kind);
+                for f in &diff.files {
+                    if f.path == file.path {
+                        new.push_file(file.clone());
+                    } else {
+                        new.push_file(f.clone());
+                    }
+                }
+                new
+            })
+        };
+        if let Some(file) = staged {
+            let diff = patched(&self.staged, file);
+            self.update_staged(diff, txt, context);
+        }
+        if let Some(file) = unstaged {
+            let diff = patched(&self.unstaged, file);
+            self.update_unstaged(diff, txt, context);
+        }
+    }
+
     /// cursor does not change structure, but changes highlights
     /// it will collect highlights in context. no need further render
     pub fn cursor<'a>(
@@ -758,6 +1435,121 @@ impl Status {
     //     }
     // }
 
+    pub fn open_mergetool(&self, window: &impl IsA<Widget>) {
+        let Some(file_path) = self.conflicted_file_at_cursor() else {
+            return;
+        };
+        glib::spawn_future_local({
+            let path = self.path.clone().expect("no path");
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                let result = gio::spawn_blocking(move || {
+                    external::try_open_mergetool(path, file_path, sender)
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+                if let Err(message) = result {
+                    alert(message).present(Some(&window));
+                }
+            }
+        });
+    }
+
+    /// moves cursor to next/previous conflict (its ours marker).
+    /// walks conflicts of current file, then goes to next conflicted file.
+    /// collapsed files and hunks get expanded.
+    pub fn next_conflict<'a>(
+        &'a self,
+        txt: &StageView,
+        forward: bool,
+        context: &mut StatusRenderContext<'a>,
+    ) {
+        let Some(conflicted) = &self.conflicted else {
+            return;
+        };
+        let markers: Vec<(usize, usize, usize)> = conflicted
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(f, file)| {
+                file.hunks.iter().enumerate().flat_map(move |(h, hunk)| {
+                    hunk.lines
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, line)| {
+                            line.kind == LineKind::ConflictMarker(String::from(MARKER_OURS))
+                        })
+                        .map(move |(l, _)| (f, h, l))
+                })
+            })
+            .collect();
+        if markers.is_empty() {
+            return;
+        }
+        let current = match self.cursor_position.get() {
+            CursorPosition::CursorFile(DiffKind::Conflicted, f) => Some((f, 0, 0)),
+            CursorPosition::CursorHunk(DiffKind::Conflicted, f, h) => Some((f, h, 0)),
+            CursorPosition::CursorLine(DiffKind::Conflicted, f, h, l) => Some((f, h, l)),
+            _ => None,
+        };
+        let target = match (current, forward) {
+            (Some(current), true) => markers
+                .iter()
+                .find(|m| **m > current)
+                .unwrap_or(&markers[0]),
+            (Some(current), false) => markers
+                .iter()
+                .rev()
+                .find(|m| **m < current)
+                .unwrap_or(&markers[markers.len() - 1]),
+            (None, true) => &markers[0],
+            (None, false) => &markers[markers.len() - 1],
+        };
+        let (f, h, l) = *target;
+        let file = &conflicted.files[f];
+        let hunk = &file.hunks[h];
+        if !file.view.is_expanded() || !hunk.view.is_expanded() {
+            file.view.expand(true);
+            hunk.view.expand(true);
+            self.render(txt, Some(DiffKind::Conflicted), context);
+        }
+        let buffer = txt.buffer();
+        if let Some(mut iter) = buffer.iter_at_line(hunk.lines[l].view.line_no.get()) {
+            buffer.place_cursor(&iter);
+            txt.scroll_to_iter(&mut iter, 0.0, false, 0.0, 0.0);
+        }
+    }
+
+    /// Cursor to the header of next/previous file in any section
+    pub fn next_file(&self, txt: &StageView, forward: bool) {
+        let buffer = txt.buffer();
+        let current = buffer.iter_at_offset(buffer.cursor_position()).line();
+        let mut lines: Vec<i32> = [
+            &self.conflicted,
+            &self.unstaged,
+            &self.untracked,
+            &self.staged,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|diff| diff.view.is_rendered())
+        .flat_map(|diff| &diff.files)
+        .filter(|file| file.view.is_rendered())
+        .map(|file| file.view.line_no.get())
+        .collect();
+        lines.sort();
+        let target = if forward {
+            lines.into_iter().find(|line| *line > current)
+        } else {
+            lines.into_iter().rev().find(|line| *line < current)
+        };
+        if let Some(mut iter) = target.and_then(|line| buffer.iter_at_line(line)) {
+            buffer.place_cursor(&iter);
+            txt.scroll_to_iter(&mut iter, 0.0, false, 0.0, 0.0);
+        }
+    }
+
     pub fn expand<'a>(
         &'a mut self,
         txt: &StageView,
@@ -765,6 +1557,17 @@ impl Status {
         _offset: i32,
         context: &mut StatusRenderContext<'a>,
     ) {
+        let whole_diff = self
+            .untracked
+            .as_ref()
+            .is_some_and(|untracked| untracked.view.is_rendered_in(line_no));
+        self.preview_untracked(|file| whole_diff || file.view.is_rendered_in(line_no));
+        if let Some(untracked) = &self.untracked {
+            if untracked.expand(line_no, context).is_some() {
+                self.render(txt, Some(DiffKind::Untracked), context);
+                return;
+            }
+        }
         if let Some(conflicted) = &self.conflicted {
             if conflicted.expand(line_no, context).is_some() {
                 self.render(txt, Some(DiffKind::Conflicted), context);
@@ -785,6 +1588,46 @@ impl Status {
         }
     }
 
+    /// Expand or collapse all files in diff under cursor,
+    /// or in all diffs when global
+    pub fn expand_all<'a>(
+        &'a mut self,
+        txt: &StageView,
+        expand: bool,
+        global: bool,
+        context: &mut StatusRenderContext<'a>,
+    ) {
+        let cursor_kind = match self.cursor_position.get() {
+            CursorPosition::CursorDiff(kind)
+            | CursorPosition::CursorFile(kind, _)
+            | CursorPosition::CursorHunk(kind, _, _)
+            | CursorPosition::CursorLine(kind, _, _, _) => Some(kind),
+            CursorPosition::None => None,
+        };
+        if !global && cursor_kind.is_none() {
+            return;
+        }
+        let mut changed = false;
+        for diff in [&self.conflicted, &self.unstaged, &self.staged]
+            .into_iter()
+            .flatten()
+        {
+            if !global && Some(diff.kind) != cursor_kind {
+                continue;
+            }
+            for file in &diff.files {
+                if file.view.is_expanded() != expand {
+                    // expand toggles the view
+                    file.expand(file.view.line_no.get(), context);
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            self.render(txt, cursor_kind, context);
+        }
+    }
+
     pub fn render<'a>(
         &'a self,
         txt: &StageView,
@@ -850,6 +1693,13 @@ impl Status {
         false
     }
 
+    pub fn has_unstaged(&self) -> bool {
+        if let Some(unstaged) = &self.unstaged {
+            return !unstaged.files.is_empty();
+        }
+        false
+    }
+
     pub fn head_oid(&self) -> crate::Oid {
         self.head.as_ref().unwrap().oid
     }
@@ -863,6 +1713,116 @@ impl Status {
         }
     }
 
+    /// Copies hunk under cursor (or all hunks of file under cursor)
+    /// without line numbers.
+    pub fn copy_hunk(&self, with_prefixes: bool, window: &ApplicationWindow) {
+        let (kind, file_idx, hunk_idx) = match self.cursor_position.get() {
+            CursorPosition::CursorFile(kind, file_idx) => (kind, file_idx, None),
+            CursorPosition::CursorHunk(kind, file_idx, hunk_idx)
+            | CursorPosition::CursorLine(kind, file_idx, hunk_idx, _) => {
+                (kind, file_idx, Some(hunk_idx))
+            }
+            _ => return,
+        };
+        let Some(diff) = [&self.staged, &self.unstaged, &self.conflicted]
+            .into_iter()
+            .flatten()
+            .find(|diff| diff.kind == kind)
+        else {
+            return;
+        };
+        let Some(file) = diff.files.get(file_idx) else {
+            return;
+        };
+        let text = match hunk_idx {
+            Some(hunk_idx) => file
+                .hunks
+                .get(hunk_idx)
+                .map(|hunk| hunk.text(with_prefixes))
+                .unwrap_or_default(),
+            None => file
+                .hunks
+                .iter()
+                .map(|hunk| hunk.text(with_prefixes))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        if text.is_empty() {
+            return;
+        }
+        window.display().clipboard().set_text(&text);
+        self.sender
+            .send_blocking(crate::Event::Toast(String::from("Copied")))
+            .expect("Could not send through channel");
+    }
+
+    /// Save diff, file or hunk under cursor as unified diff.
+    pub fn export_patch(&self, window: &ApplicationWindow) {
+        let kind = match self.cursor_position.get() {
+            CursorPosition::CursorDiff(kind)
+            | CursorPosition::CursorFile(kind, _)
+            | CursorPosition::CursorHunk(kind, _, _)
+            | CursorPosition::CursorLine(kind, _, _, _) => kind,
+            CursorPosition::None => return,
+        };
+        let Some(diff) = [&self.untracked, &self.unstaged, &self.staged]
+            .into_iter()
+            .flatten()
+            .find(|diff| diff.kind == kind)
+        else {
+            return;
+        };
+        let (patch, name) = match self.cursor_position.get() {
+            CursorPosition::CursorFile(_, file_idx) => {
+                let file = &diff.files[file_idx];
+                (file.to_patch(None), file.path.clone())
+            }
+            CursorPosition::CursorHunk(_, file_idx, hunk_idx)
+            | CursorPosition::CursorLine(_, file_idx, hunk_idx, _) => {
+                let file = &diff.files[file_idx];
+                (file.to_patch(Some(hunk_idx)), file.path.clone())
+            }
+            _ => (
+                diff.files
+                    .iter()
+                    .map(|file| file.to_patch(None))
+                    .collect::<String>(),
+                PathBuf::from("changes"),
+            ),
+        };
+        let name = format!(
+            "{}.patch",
+            name.file_name().unwrap_or_default().to_string_lossy()
+        );
+        glib::spawn_future_local({
+            let sender = self.sender.clone();
+            let window = window.clone();
+            async move {
+                let dialog = FileDialog::builder()
+                    .title("Export patch")
+                    .initial_name(name)
+                    .build();
+                let Some(patch_path) = dialog
+                    .save_future(Some(&window))
+                    .await
+                    .ok()
+                    .and_then(|file| file.path())
+                else {
+                    return;
+                };
+                match std::fs::write(&patch_path, patch) {
+                    Ok(()) => sender
+                        .send_blocking(crate::Event::Toast(format!(
+                            "Saved {}",
+                            patch_path.display()
+                        )))
+                        .expect("Could not send through channel"),
+                    Err(e) => alert(e.to_string()).present(Some(&window)),
+                }
+            }
+        });
+    }
+
     pub fn blame(&self, app_window: CurrentWindow) {
         let mut line_no: Option<HunkLineNo> = None;
         let mut ofile_path: Option<PathBuf> = None;
@@ -953,7 +1913,7 @@ impl Status {
             },
             CursorPosition::CursorFile(kind, fileno) => match kind {
                 DiffKind::Staged => {
-                    let diff = self.unstaged.as_ref().unwrap();
+                    let diff = self.staged.as_ref().unwrap();
                     let file = &diff.files[fileno];
                     return Some((kind, Some(file.path.clone()), None));
                 }
@@ -0,0 +1,294 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::stage_view::StageView;
+use super::Status;
+use crate::dialogs::{alert, confirm_dialog_factory, PROCEED};
+use crate::git::branch::{self, BranchData};
+use git2::BranchType;
+use gtk4::prelude::*;
+use gtk4::{
+    gdk, gio, glib, Box, EventControllerKey, Label, ListBox, ListBoxRow, Orientation, PolicyType,
+    Popover, ScrolledWindow, SearchEntry, SelectionMode, TextWindowType,
+};
+use libadwaita::prelude::*;
+use libadwaita::{ApplicationWindow, EntryRow};
+use std::cell::Cell;
+use std::rc::Rc;
+
+fn visible_rows(list_box: &ListBox) -> Vec<ListBoxRow> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while let Some(row) = list_box.row_at_index(i) {
+        if row.is_child_visible() {
+            rows.push(row);
+        }
+        i += 1;
+    }
+    rows
+}
+
+// up/down in search entry moves selection, so focus never leaves entry
+fn move_selection(list_box: &ListBox, forward: bool) {
+    let rows = visible_rows(list_box);
+    if rows.is_empty() {
+        return;
+    }
+    let pos = list_box
+        .selected_row()
+        .and_then(|selected| rows.iter().position(|row| *row == selected));
+    let new_pos = match (pos, forward) {
+        (None, _) => 0,
+        (Some(pos), true) => (pos + 1).min(rows.len() - 1),
+        (Some(pos), false) => pos.saturating_sub(1),
+    };
+    list_box.select_row(Some(&rows[new_pos]));
+}
+
+impl Status {
+    /// Small popover at cursor with local branches. Type to filter,
+    /// Enter to checkout.
+    pub fn show_branch_switcher(&self, txt: &StageView, window: &ApplicationWindow) {
+        let path = self.path.clone().unwrap();
+        let sender = self.sender.clone();
+        let branches = self.branches.clone();
+        let txt = txt.clone();
+        let window = window.clone();
+        glib::spawn_future_local(async move {
+            let branches = match branches {
+                Some(branches) => branches,
+                None => gio::spawn_blocking({
+                    let path = path.clone();
+                    move || branch::get_branches(path)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(Vec::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    Vec::new()
+                }),
+            };
+            let branches: Rc<Vec<BranchData>> = Rc::new(
+                branches
+                    .into_iter()
+                    .filter(|bd| bd.branch_type == BranchType::Local)
+                    .collect(),
+            );
+
+            let search = SearchEntry::builder()
+                .placeholder_text("Switch to branch")
+                .build();
+            let list_box = ListBox::builder()
+                .selection_mode(SelectionMode::Single)
+                .css_classes(["navigation-sidebar"])
+                .build();
+            for branch_data in branches.iter() {
+                let label = Label::builder()
+                    .label(branch_data.name.to_str())
+                    .xalign(0.0)
+                    .build();
+                if branch_data.is_head {
+                    label.add_css_class("dim-label");
+                }
+                list_box.append(&label);
+            }
+            list_box.set_filter_func({
+                let branches = branches.clone();
+                let search = search.clone();
+                move |row| {
+                    let term = search.text();
+                    branches
+                        .get(row.index() as usize)
+                        .map(|bd| bd.matches(&term))
+                        .unwrap_or(false)
+                }
+            });
+            let scroll = ScrolledWindow::builder()
+                .hscrollbar_policy(PolicyType::Never)
+                .propagate_natural_height(true)
+                .max_content_height(360)
+                .min_content_width(320)
+                .child(&list_box)
+                .build();
+            let bx = Box::builder()
+                .orientation(Orientation::Vertical)
+                .spacing(6)
+                .build();
+            bx.append(&search);
+            bx.append(&scroll);
+
+            let popover = Popover::builder().child(&bx).build();
+            popover.set_parent(&txt);
+            // point to cursor
+            let buffer = txt.buffer();
+            let iter = buffer.iter_at_offset(buffer.cursor_position());
+            let location = txt.iter_location(&iter);
+            let (x, y) =
+                txt.buffer_to_window_coords(TextWindowType::Widget, location.x(), location.y());
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(
+                x,
+                y,
+                location.width().max(1),
+                location.height(),
+            )));
+            popover.connect_closed({
+                let txt = txt.clone();
+                move |popover| {
+                    popover.unparent();
+                    txt.grab_focus();
+                }
+            });
+
+            search.connect_search_changed({
+                let list_box = list_box.clone();
+                move |_| {
+                    list_box.invalidate_filter();
+                    if let Some(first) = visible_rows(&list_box).first() {
+                        list_box.select_row(Some(first));
+                    }
+                }
+            });
+            let key_controller = EventControllerKey::new();
+            key_controller.connect_key_pressed({
+                let list_box = list_box.clone();
+                move |_, key, _, _| match key {
+                    gdk::Key::Down => {
+                        move_selection(&list_box, true);
+                        glib::Propagation::Stop
+                    }
+                    gdk::Key::Up => {
+                        move_selection(&list_box, false);
+                        glib::Propagation::Stop
+                    }
+                    _ => glib::Propagation::Proceed,
+                }
+            });
+            search.add_controller(key_controller);
+            search.connect_activate({
+                let list_box = list_box.clone();
+                move |_| {
+                    let row = list_box
+                        .selected_row()
+                        .filter(|row| row.is_child_visible())
+                        .or_else(|| visible_rows(&list_box).into_iter().next());
+                    if let Some(row) = row {
+                        row.emit_activate();
+                    }
+                }
+            });
+            list_box.connect_row_activated({
+                let popover = popover.clone();
+                move |_, row| {
+                    popover.popdown();
+                    let Some(branch_data) = branches.get(row.index() as usize).cloned() else {
+                        return;
+                    };
+                    if branch_data.is_head {
+                        return;
+                    }
+                    glib::spawn_future_local({
+                        let path = path.clone();
+                        let sender = sender.clone();
+                        let window = window.clone();
+                        async move {
+                            gio::spawn_blocking(move || {
+                                branch::checkout_branch(path, branch_data, sender)
+                            })
+                            .await
+                            .unwrap_or_else(|e| {
+                                alert(format!("{:?}", e)).present(Some(&window));
+                                Ok(None)
+                            })
+                            .unwrap_or_else(|e| {
+                                alert(e).present(Some(&window));
+                                None
+                            });
+                        }
+                    });
+                }
+            });
+            if let Some(first) = visible_rows(&list_box).first() {
+                list_box.select_row(Some(first));
+            }
+            popover.popup();
+            search.grab_focus();
+        });
+    }
+
+    /// Detached HEAD has no branch to come back to. New branch
+    /// is made right at HEAD and checked out, so work goes on there.
+    pub fn create_branch_at_head(&self, window: &ApplicationWindow) {
+        let Some(head) = &self.head else {
+            return;
+        };
+        if let Some(branch_data) = &head.branch {
+            self.sender
+                .send_blocking(crate::Event::Toast(format!(
+                    "HEAD is not detached. Current branch is {}",
+                    branch_data.name.to_str()
+                )))
+                .expect("cant send through channel");
+            return;
+        }
+        let path = self.path.clone().unwrap();
+        let sender = self.sender.clone();
+        let window = window.clone();
+        let branch_data = BranchData {
+            oid: head.oid,
+            ..Default::default()
+        };
+        let title = format!(
+            "Create branch at detached head {}",
+            &head.oid.to_string()[..7]
+        );
+        glib::spawn_future_local(async move {
+            let lb = ListBox::builder()
+                .selection_mode(SelectionMode::None)
+                .css_classes(vec![String::from("boxed-list")])
+                .build();
+            let input = EntryRow::builder()
+                .title("New branch name:")
+                .show_apply_button(false)
+                .css_classes(vec!["input_field"])
+                .build();
+            lb.append(&input);
+            let dialog = confirm_dialog_factory(Some(&lb), &title, "Create");
+            dialog.connect_realize({
+                let input = input.clone();
+                move |_| {
+                    input.grab_focus();
+                }
+            });
+            let enter_pressed = Rc::new(Cell::new(false));
+            input.connect_entry_activated({
+                let enter_pressed = enter_pressed.clone();
+                let dialog = dialog.clone();
+                move |_entry| {
+                    enter_pressed.replace(true);
+                    dialog.close();
+                }
+            });
+            let response = dialog.choose_future(&window).await;
+            let new_branch_name = input.text().trim().to_string();
+            if !(PROCEED == response || enter_pressed.get()) || new_branch_name.is_empty() {
+                return;
+            }
+            gio::spawn_blocking(move || {
+                branch::create_branch(path, new_branch_name, true, branch_data, sender)
+            })
+            .await
+            .unwrap_or_else(|e| {
+                alert(format!("{:?}", e)).present(Some(&window));
+                Ok(None)
+            })
+            .unwrap_or_else(|e| {
+                alert(e).present(Some(&window));
+                None
+            });
+        });
+    }
+}
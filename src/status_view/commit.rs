@@ -3,11 +3,18 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::dialogs::{alert, confirm_dialog_factory, PROCEED};
-use crate::{git::commit as git_commit, Event, DARK_CLASS, LIGHT_CLASS};
+use crate::status_view::headerbar::{
+    PRE_COMMIT_COMMAND_TOKEN, SIGNING_FORMAT_TOKEN, SIGNING_KEY_TOKEN, SIGN_COMMITS_TOKEN,
+};
+use crate::{
+    git::commit as git_commit, git::hooks, git::repo_settings, Event, InlineCommitOp, DARK_CLASS,
+    LIGHT_CLASS,
+};
 use async_channel::Sender;
 use gtk4::prelude::*;
 use gtk4::{
-    gio, glib, Box, Button, ListBox, Orientation, ScrolledWindow, SelectionMode, TextView, WrapMode,
+    gio, glib, pango, Box, Button, Label, ListBox, Orientation, ScrolledWindow, SelectionMode,
+    TextBuffer, TextTag, TextView, Widget, WrapMode,
 };
 use libadwaita::prelude::*;
 use libadwaita::{ApplicationWindow, EntryRow, StyleManager, SwitchRow};
@@ -15,17 +22,231 @@ use std::cell::Cell;
 use std::path::PathBuf;
 use std::rc::Rc;
 
+pub use crate::git::repo_settings::{COMMIT_TEMPLATE_TOKEN, SIGN_OFF_TOKEN, TICKET_REGEX_TOKEN};
+
+// git convention: short subject, body wrapped at 72
+pub const SUBJECT_LIMIT: i32 = 50;
+pub const LINE_LIMIT: i32 = 72;
+const OVERFLOW_TAG: &str = "overflow";
+
+fn update_subject_hint(hint: &Label, subject: &str) {
+    let len = subject.chars().count() as i32;
+    hint.set_label(&format!("subject: {} chars", len));
+    hint.remove_css_class("warning");
+    hint.remove_css_class("error");
+    if len > LINE_LIMIT {
+        hint.add_css_class("error");
+    } else if len > SUBJECT_LIMIT {
+        hint.add_css_class("warning");
+    }
+}
+
+// chars past the guide are underlined, like misspelled words
+fn mark_overflow(buffer: &TextBuffer) {
+    buffer.remove_tag_by_name(OVERFLOW_TAG, &buffer.start_iter(), &buffer.end_iter());
+    for line in 0..buffer.line_count() {
+        let Some(mut start) = buffer.iter_at_line(line) else {
+            continue;
+        };
+        let mut end = start;
+        if end.ends_line() {
+            continue;
+        }
+        end.forward_to_line_end();
+        if end.line_offset() <= LINE_LIMIT {
+            continue;
+        }
+        start.set_line_offset(LINE_LIMIT);
+        buffer.apply_tag_by_name(OVERFLOW_TAG, &start, &end);
+    }
+}
+
+fn commit_signing(
+    path: Option<&PathBuf>,
+    settings: &gio::Settings,
+) -> Option<git_commit::CommitSigning> {
+    if !repo_settings::get_bool(path, settings, SIGN_COMMITS_TOKEN) {
+        return None;
+    }
+    let key = repo_settings::get_string(path, settings, SIGNING_KEY_TOKEN);
+    match &repo_settings::get_string(path, settings, SIGNING_FORMAT_TOKEN)[..] {
+        "ssh" => Some(git_commit::CommitSigning::Ssh(key)),
+        _ => Some(git_commit::CommitSigning::Gpg(key)),
+    }
+}
+
+/// Same hooks and settings as in commit dialog, but message is
+/// already typed. Bar with message is reopened if commit fails.
+pub fn commit_inline(
+    path: Option<PathBuf>,
+    message: String,
+    settings: gio::Settings,
+    window: &ApplicationWindow,
+    sender: Sender<Event>,
+) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let reopen = || {
+                sender
+                    .send_blocking(Event::InlineCommit(InlineCommitOp::Open))
+                    .expect("Could not send through channel");
+            };
+            let hooks_result = gio::spawn_blocking({
+                let path = path.clone();
+                let pre_commit_command =
+                    repo_settings::get_string(path.as_ref(), &settings, PRE_COMMIT_COMMAND_TOKEN);
+                let signed_off = repo_settings::get_bool(path.as_ref(), &settings, SIGN_OFF_TOKEN);
+                move || {
+                    let path = path.expect("no path");
+                    hooks::pre_commit(path.clone(), pre_commit_command)?;
+                    let message = if signed_off {
+                        git_commit::sign_off_message(path.clone(), message)
+                            .map_err(|e| e.message().to_string())?
+                    } else {
+                        message
+                    };
+                    hooks::commit_msg(path, message)
+                }
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            let message = match hooks_result {
+                Ok(message) => message,
+                Err(output) => {
+                    alert(output).present(Some(&window));
+                    reopen();
+                    return;
+                }
+            };
+            let signing = commit_signing(path.as_ref(), &settings);
+            let created = gio::spawn_blocking({
+                let sender = sender.clone();
+                move || {
+                    git_commit::create(
+                        path.expect("no path"),
+                        message,
+                        false,
+                        None,
+                        signing,
+                        sender,
+                    )
+                }
+            })
+            .await
+            .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))));
+            match created {
+                Ok(()) => {
+                    sender
+                        .send_blocking(Event::InlineCommit(InlineCommitOp::Done))
+                        .expect("Could not send through channel");
+                }
+                Err(e) => {
+                    alert(e).present(Some(&window));
+                    reopen();
+                }
+            }
+        }
+    });
+}
+
+/// What commit dialog is opened for.
+pub enum CommitMode {
+    New,
+    // amend is offered with HEAD message
+    Amend(String),
+    // count of last commits to squash into new one and their messages.
+    // HEAD is reset to them only when commit is confirmed
+    Squash(usize, String),
+}
+
 pub fn commit(
     path: Option<PathBuf>,
-    amend_message: Option<String>,
+    mode: CommitMode,
+    branch_name: Option<String>,
+    settings: gio::Settings,
     window: &ApplicationWindow,
     sender: Sender<Event>,
 ) {
+    let (amend_message, message, squash) = match mode {
+        CommitMode::New => (None, None, None),
+        CommitMode::Amend(amend_message) => (Some(amend_message), None, None),
+        CommitMode::Squash(count, message) => (None, Some(message), Some(count)),
+    };
     glib::spawn_future_local({
         let window = window.clone();
         let sender = sender.clone();
         let path = path.clone();
         async move {
+            let hooks_result = gio::spawn_blocking({
+                let path = path.clone();
+                let pre_commit_command =
+                    repo_settings::get_string(path.as_ref(), &settings, PRE_COMMIT_COMMAND_TOKEN);
+                move || hooks::pre_commit(path.expect("no path"), pre_commit_command)
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            if let Err(output) = hooks_result {
+                alert(output).present(Some(&window));
+                return;
+            }
+
+            let template = gio::spawn_blocking({
+                let path = path.clone();
+                let template_path =
+                    repo_settings::get_string(path.as_ref(), &settings, COMMIT_TEMPLATE_TOKEN);
+                move || git_commit::get_commit_template(path.expect("no path"), template_path)
+            })
+            .await
+            .unwrap_or_else(|e| {
+                alert(format!("{:?}", e)).present(Some(&window));
+                Ok(None)
+            })
+            .unwrap_or_else(|e| {
+                alert(e).present(Some(&window));
+                None
+            });
+            let merge_message = gio::spawn_blocking({
+                let path = path.clone();
+                move || git_commit::merge_message(path.expect("no path"))
+            })
+            .await
+            .unwrap_or_else(|e| {
+                alert(format!("{:?}", e)).present(Some(&window));
+                Ok(None)
+            })
+            .unwrap_or_else(|e| {
+                alert(e).present(Some(&window));
+                None
+            });
+            let mut prefill = template.unwrap_or_default();
+            if let Some(message) = message.or(merge_message) {
+                // e.g. messages of squashed commits or MERGE_MSG
+                prefill = message;
+            } else if let Some(ticket) = branch_name.and_then(|name| {
+                git_commit::ticket_from_branch(
+                    &name,
+                    &repo_settings::get_string(path.as_ref(), &settings, TICKET_REGEX_TOKEN),
+                )
+            }) {
+                if !prefill.starts_with(&ticket) {
+                    prefill = format!("{} {}", ticket, prefill);
+                }
+            }
+
+            // author of HEAD could be changed when amending
+            let head_author = if amend_message.is_some() {
+                gio::spawn_blocking({
+                    let path = path.clone();
+                    move || git_commit::head_author(path.expect("no path"))
+                })
+                .await
+                .unwrap_or_else(|e| Err(git2::Error::from_str(&format!("{:?}", e))))
+                .ok()
+            } else {
+                None
+            };
+
             let list_box = ListBox::builder()
                 .selection_mode(SelectionMode::None)
                 .css_classes(vec![String::from("boxed-list")])
@@ -51,10 +272,55 @@ pub fn commit(
                 .active(false)
                 .build();
 
+            let sign_off_switch = SwitchRow::builder()
+                .title("sign off")
+                .css_classes(vec!["input_field"])
+                .active(repo_settings::get_bool(
+                    path.as_ref(),
+                    &settings,
+                    SIGN_OFF_TOKEN,
+                ))
+                .build();
+
+            let author_name = EntryRow::builder()
+                .title("author name")
+                .show_apply_button(false)
+                .css_classes(vec!["input_field"])
+                .visible(false)
+                .build();
+            let author_email = EntryRow::builder()
+                .title("author email")
+                .show_apply_button(false)
+                .css_classes(vec!["input_field"])
+                .visible(false)
+                .build();
+            let author_date = EntryRow::builder()
+                .title("author date")
+                .show_apply_button(false)
+                .css_classes(vec!["input_field"])
+                .visible(false)
+                .build();
+            if let Some((_, author)) = &head_author {
+                author_name.set_text(&author.name);
+                author_email.set_text(&author.email);
+                author_date.set_text(
+                    &author
+                        .date
+                        .format(git_commit::AUTHOR_DATE_FORMAT)
+                        .to_string(),
+                );
+            }
+
             list_box.append(&commit_message);
             if amend_message.is_some() {
                 list_box.append(&amend_switch);
+                if head_author.is_some() {
+                    list_box.append(&author_name);
+                    list_box.append(&author_email);
+                    list_box.append(&author_date);
+                }
             }
+            list_box.append(&sign_off_switch);
             let mut classes = glib::collections::strv::StrV::new();
             classes.extend_from_slice(if StyleManager::default().is_dark() {
                 &[DARK_CLASS]
@@ -69,6 +335,39 @@ pub fn commit(
                 .css_classes(classes)
                 .wrap_mode(WrapMode::Word)
                 .build();
+            txt.set_monospace(true);
+            let overflow_tag = TextTag::builder()
+                .name(OVERFLOW_TAG)
+                .underline(pango::Underline::Error)
+                .build();
+            txt.buffer().tag_table().add(&overflow_tag);
+            let subject_hint = Label::builder()
+                .xalign(1.0)
+                .margin_end(12)
+                .margin_bottom(6)
+                .css_classes(["dim-label", "caption"])
+                .build();
+            update_subject_hint(&subject_hint, "");
+            commit_message.connect_changed({
+                let subject_hint = subject_hint.clone();
+                move |entry| {
+                    update_subject_hint(&subject_hint, &entry.text());
+                }
+            });
+            txt.buffer().connect_changed({
+                let subject_hint = subject_hint.clone();
+                move |buffer| {
+                    let mut subject_end = buffer.start_iter();
+                    if !subject_end.ends_line() {
+                        subject_end.forward_to_line_end();
+                    }
+                    update_subject_hint(
+                        &subject_hint,
+                        &buffer.text(&buffer.start_iter(), &subject_end, true),
+                    );
+                    mark_overflow(buffer);
+                }
+            });
             let scroll = ScrolledWindow::builder()
                 .vexpand(true)
                 .vexpand_set(true)
@@ -96,12 +395,34 @@ pub fn commit(
                 }
             });
 
+            amend_switch.connect_active_notify({
+                let author_name = author_name.clone();
+                let author_email = author_email.clone();
+                let author_date = author_date.clone();
+                move |switch| {
+                    author_name.set_visible(switch.is_active());
+                    author_email.set_visible(switch.is_active());
+                    author_date.set_visible(switch.is_active());
+                }
+            });
+
             amend_switch.connect_active_notify({
                 let txt = txt.clone();
                 let scroll = scroll.clone();
                 let entry = commit_message.clone();
                 let amend_inserted = Cell::new(false);
+                let prefill = prefill.clone();
                 move |_| {
+                    // amend message is preferred over untouched template
+                    if !prefill.is_empty() && !amend_inserted.get() {
+                        if entry.text() == prefill {
+                            entry.set_text("");
+                        }
+                        let buffer = txt.buffer();
+                        if buffer.text(&buffer.start_iter(), &buffer.end_iter(), true) == prefill {
+                            buffer.set_text("");
+                        }
+                    }
                     if !scroll.get_visible() {
                         // force text view
                         // on any toggle
@@ -143,14 +464,35 @@ pub fn commit(
                 .build();
 
             text_view_box.append(&scroll);
+            text_view_box.append(&subject_hint);
             text_view_box.append(&list_box);
 
             let dialog = confirm_dialog_factory(Some(&text_view_box), "Commit", "Commit");
 
+            if prefill.contains('\n') {
+                // multiline template goes straight to text view
+                txt.buffer().set_text(&prefill);
+                commit_message.set_visible(false);
+                scroll.set_visible(true);
+            } else {
+                commit_message.set_text(&prefill);
+            }
+
             dialog.connect_realize({
                 let commit_message = commit_message.clone();
+                let txt = txt.clone();
+                let scroll = scroll.clone();
                 move |_| {
-                    commit_message.grab_focus();
+                    if scroll.get_visible() {
+                        txt.grab_focus();
+                        // cursor at the end of subject line
+                        let mut iter = txt.buffer().start_iter();
+                        iter.forward_to_line_end();
+                        txt.buffer().place_cursor(&iter);
+                    } else {
+                        commit_message.grab_focus();
+                        commit_message.set_position(-1);
+                    }
                 }
             });
 
@@ -170,24 +512,109 @@ pub fn commit(
                 return;
             }
 
-            gio::spawn_blocking({
-                // let message = format!("{}", input.text());
-                let message = {
-                    if scroll.get_visible() {
-                        let buffer = txt.buffer();
-                        let start_iter = buffer.iter_at_offset(0);
-                        let eof_iter = buffer.end_iter();
-                        buffer
-                            .text(&start_iter, &eof_iter, true)
-                            .to_string()
-                            .to_string()
+            // let message = format!("{}", input.text());
+            let message = {
+                if scroll.get_visible() {
+                    let buffer = txt.buffer();
+                    let start_iter = buffer.iter_at_offset(0);
+                    let eof_iter = buffer.end_iter();
+                    buffer
+                        .text(&start_iter, &eof_iter, true)
+                        .to_string()
+                        .to_string()
+                } else {
+                    commit_message.text().to_string()
+                }
+            };
+            let signed_off = sign_off_switch.is_active();
+            if signed_off != repo_settings::get_bool(path.as_ref(), &settings, SIGN_OFF_TOKEN) {
+                repo_settings::set_bool(path.as_ref(), &settings, SIGN_OFF_TOKEN, signed_off);
+            }
+            let hooks_result = gio::spawn_blocking({
+                let path = path.clone();
+                move || {
+                    let path = path.expect("no path");
+                    let message = if signed_off {
+                        git_commit::sign_off_message(path.clone(), message)
+                            .map_err(|e| e.message().to_string())?
                     } else {
-                        commit_message.text().to_string()
-                    }
-                };
+                        message
+                    };
+                    hooks::commit_msg(path, message)
+                }
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            let message = match hooks_result {
+                Ok(message) => message,
+                Err(output) => {
+                    alert(output).present(Some(&window));
+                    return;
+                }
+            };
 
-                let amend = amend_switch.is_active();
-                move || git_commit::create(path.expect("no path"), message, amend, sender)
+            let signing = commit_signing(path.as_ref(), &settings);
+            let amend = amend_switch.is_active();
+            let author = match &head_author {
+                Some((oid, head_author)) if amend => {
+                    let date = match git_commit::Author::parse_date(&author_date.text()) {
+                        Ok(date) => date,
+                        Err(e) => {
+                            alert(e).present(Some(&window));
+                            return;
+                        }
+                    };
+                    let author = git_commit::Author {
+                        name: author_name.text().trim().to_string(),
+                        email: author_email.text().trim().to_string(),
+                        date,
+                    };
+                    if author != *head_author {
+                        let pushed = gio::spawn_blocking({
+                            let path = path.clone();
+                            let oid = *oid;
+                            move || git_commit::is_pushed(path.expect("no path"), oid)
+                        })
+                        .await
+                        .unwrap_or(Ok(false))
+                        .unwrap_or(false);
+                        if pushed {
+                            let dialog = confirm_dialog_factory(
+                                None::<&Widget>,
+                                "Commit is already pushed. Changing author rewrites published history",
+                                "Amend",
+                            );
+                            if dialog.choose_future(&window).await != PROCEED {
+                                return;
+                            }
+                        }
+                    }
+                    // amend keeps original author, as git commit --amend does
+                    Some(author)
+                }
+                _ => None,
+            };
+            gio::spawn_blocking({
+                move || {
+                    let path = path.expect("no path");
+                    let Some(count) = squash else {
+                        return git_commit::create(path, message, amend, author, signing, sender);
+                    };
+                    let head = git_commit::squash(path.clone(), count, sender.clone())?;
+                    git_commit::create(
+                        path.clone(),
+                        message,
+                        amend,
+                        author,
+                        signing,
+                        sender.clone(),
+                    )
+                    .or_else(|e| {
+                        // squashed commits must stay on branch
+                        git_commit::undo_squash(path, head, sender)?;
+                        Err(e)
+                    })
+                }
             })
             .await
             .unwrap_or_else(|e| {
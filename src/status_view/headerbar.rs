@@ -2,19 +2,26 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::dialogs::{confirm_dialog_factory, PROCEED};
+use crate::git::repo_settings;
+use crate::keymap;
 use crate::status_view::context::StatusRenderContext;
 use async_channel::Sender;
 use gtk4::{
-    gio, Align, Box, Button, FileDialog, Label, MenuButton, Orientation, PopoverMenu,
-    ShortcutsGroup, ShortcutsSection, ShortcutsShortcut, ShortcutsWindow, Spinner, ToggleButton,
-    Widget,
+    gio, glib, Align, Box, Button, FileDialog, Image, Label, ListBox, MenuButton, Orientation,
+    PopoverMenu, SelectionMode, ShortcutsGroup, ShortcutsSection, ShortcutsShortcut,
+    ShortcutsWindow, Spinner, StringList, ToggleButton, Widget,
 };
 use libadwaita::prelude::*;
 use libadwaita::{
-    AboutDialog, ApplicationWindow, ButtonContent, ColorScheme, HeaderBar, SplitButton,
-    StyleManager, Window,
+    AboutDialog, ApplicationWindow, ButtonContent, ColorScheme, ComboRow, EntryRow, HeaderBar,
+    SplitButton, StyleManager, SwitchRow, Window,
 };
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 pub enum HbUpdateData<'a> {
     Path(PathBuf),
@@ -25,6 +32,8 @@ pub enum HbUpdateData<'a> {
     Pull,
     Push,
     Upstream,
+    RemoteProgress(usize, usize),
+    RemoteCancel(Option<Arc<AtomicBool>>),
     Context(StatusRenderContext<'a>),
 }
 use crate::git::DiffKind;
@@ -60,8 +69,39 @@ impl Scheme {
 }
 
 pub const CUSTOM_ATTR: &str = "custom";
+pub use crate::git::repo_settings::{
+    IGNORE_WHITESPACE_TOKEN, PRE_COMMIT_COMMAND_TOKEN, PULL_FF_ONLY_TOKEN, PULL_REBASE_TOKEN,
+    RUN_HOOKS_TOKEN, SIGNING_FORMAT_TOKEN, SIGNING_KEY_TOKEN, SIGN_COMMITS_TOKEN, SSH_KEY_TOKEN,
+};
 pub const SCHEME_TOKEN: &str = "scheme";
+pub const DIFF_COLORS_TOKEN: &str = "diff-colors";
 pub const ZOOM_TOKEN: &str = "zoom";
+pub const SYNTAX_HIGHLIGHTING_TOKEN: &str = "syntax-highlighting";
+pub const CONFIRM_KILL_TOKEN: &str = "confirm-kill";
+pub const CONFIRM_QUIT_TOKEN: &str = "confirm-quit";
+pub const CONFIRM_QUIT_DRAFT_TOKEN: &str = "confirm-quit-draft";
+pub const SIGNING_FORMATS: [&str; 2] = ["openpgp", "ssh"];
+pub const PATHS_TOKEN: &str = "paths";
+pub const MAX_RECENT_PATHS: usize = 10;
+
+/// Most recent first, without duplicates and without
+/// paths which are not repos anymore.
+pub fn recent_paths(paths: Vec<String>, current: Option<&str>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for path in current.map(|c| c.to_string()).into_iter().chain(paths) {
+        let trimmed = path.trim_end_matches('/');
+        if result.iter().any(|p| p.trim_end_matches('/') == trimmed) {
+            continue;
+        }
+        // worktrees have .git file instead of dir
+        if !Path::new(&path).join(".git").exists() {
+            continue;
+        }
+        result.push(path);
+    }
+    result.truncate(MAX_RECENT_PATHS);
+    result
+}
 
 pub fn scheme_selector(stored_scheme: Scheme, sender: Sender<crate::Event>) -> Box {
     let scheme_selector = Box::builder()
@@ -186,11 +226,180 @@ pub fn zoom(
     bx
 }
 
+pub fn pre_commit_command_dialog(
+    window: &ApplicationWindow,
+    settings: gio::Settings,
+    path: Option<PathBuf>,
+) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let lb = ListBox::builder()
+                .selection_mode(SelectionMode::None)
+                .css_classes(vec![String::from("boxed-list")])
+                .build();
+            let input = EntryRow::builder()
+                .title("Command to run before commit (empty to disable):")
+                .show_apply_button(false)
+                .css_classes(vec!["input_field"])
+                .build();
+            input.set_text(&repo_settings::get_string(
+                path.as_ref(),
+                &settings,
+                PRE_COMMIT_COMMAND_TOKEN,
+            ));
+            lb.append(&input);
+            let dialog = confirm_dialog_factory(Some(&lb), "Pre-commit command", "Save");
+            dialog.connect_realize({
+                let input = input.clone();
+                move |_| {
+                    input.grab_focus();
+                }
+            });
+
+            let enter_pressed = Rc::new(Cell::new(false));
+            input.connect_entry_activated({
+                let enter_pressed = enter_pressed.clone();
+                let dialog = dialog.clone();
+                move |_entry| {
+                    // someone pressed enter
+                    enter_pressed.replace(true);
+                    dialog.close();
+                }
+            });
+
+            let response = dialog.choose_future(&window).await;
+            if !(PROCEED == response || enter_pressed.get()) {
+                return;
+            }
+            repo_settings::set_string(
+                path.as_ref(),
+                &settings,
+                PRE_COMMIT_COMMAND_TOKEN,
+                input.text().trim().to_string(),
+            );
+        }
+    });
+}
+
+pub fn commit_signing_dialog(
+    window: &ApplicationWindow,
+    settings: gio::Settings,
+    path: Option<PathBuf>,
+) {
+    glib::spawn_future_local({
+        let window = window.clone();
+        async move {
+            let lb = ListBox::builder()
+                .selection_mode(SelectionMode::None)
+                .css_classes(vec![String::from("boxed-list")])
+                .build();
+            let sign_switch = SwitchRow::builder()
+                .title("Sign commits")
+                .css_classes(vec!["input_field"])
+                .active(repo_settings::get_bool(
+                    path.as_ref(),
+                    &settings,
+                    SIGN_COMMITS_TOKEN,
+                ))
+                .build();
+            let format = repo_settings::get_string(path.as_ref(), &settings, SIGNING_FORMAT_TOKEN);
+            let format_combo = ComboRow::builder()
+                .title("Format")
+                .model(&StringList::new(&SIGNING_FORMATS))
+                .selected(
+                    SIGNING_FORMATS
+                        .iter()
+                        .position(|f| *f == format)
+                        .unwrap_or(0) as u32,
+                )
+                .css_classes(vec!["input_field"])
+                .build();
+            let key_input = EntryRow::builder()
+                .title("Key id or ssh key path (empty for user.signingkey):")
+                .show_apply_button(false)
+                .css_classes(vec!["input_field"])
+                .build();
+            key_input.set_text(&repo_settings::get_string(
+                path.as_ref(),
+                &settings,
+                SIGNING_KEY_TOKEN,
+            ));
+            lb.append(&sign_switch);
+            lb.append(&format_combo);
+            lb.append(&key_input);
+            let dialog = confirm_dialog_factory(Some(&lb), "Commit signing", "Save");
+            if PROCEED != dialog.choose_future(&window).await {
+                return;
+            }
+            repo_settings::set_bool(
+                path.as_ref(),
+                &settings,
+                SIGN_COMMITS_TOKEN,
+                sign_switch.is_active(),
+            );
+            repo_settings::set_string(
+                path.as_ref(),
+                &settings,
+                SIGNING_FORMAT_TOKEN,
+                SIGNING_FORMATS[format_combo.selected() as usize % SIGNING_FORMATS.len()]
+                    .to_string(),
+            );
+            repo_settings::set_string(
+                path.as_ref(),
+                &settings,
+                SIGNING_KEY_TOKEN,
+                key_input.text().trim().to_string(),
+            );
+        }
+    });
+}
+
+// sync stateful menu items with per repo settings
+fn update_menu_state(
+    ag: &gio::SimpleActionGroup,
+    settings: &gio::Settings,
+    path: Option<&PathBuf>,
+) {
+    let states = [
+        (
+            "ignore_whitespace",
+            repo_settings::get_bool(path, settings, IGNORE_WHITESPACE_TOKEN),
+        ),
+        (
+            "run_hooks",
+            repo_settings::get_bool(path, settings, RUN_HOOKS_TOKEN),
+        ),
+        (
+            "pull_ff_only",
+            repo_settings::get_bool(path, settings, PULL_FF_ONLY_TOKEN),
+        ),
+        (
+            "pull_rebase",
+            repo_settings::get_bool(path, settings, PULL_REBASE_TOKEN),
+        ),
+        ("per_repo", repo_settings::is_per_repo(path)),
+    ];
+    for (name, state) in states {
+        if let Some(action) = ag.lookup_action(name) {
+            if let Some(action) = action.downcast_ref::<gio::SimpleAction>() {
+                action.set_state(&state.to_variant());
+            }
+        }
+    }
+}
+
 pub fn burger_menu(
     stored_scheme: Scheme,
+    ignore_whitespace: bool,
+    syntax_highlighting: bool,
+    run_hooks: bool,
+    pull_ff_only: bool,
+    pull_rebase: bool,
+    confirm_kill: bool,
     window: &ApplicationWindow,
     sender: Sender<crate::Event>,
-) -> MenuButton {
+) -> (MenuButton, gio::SimpleActionGroup) {
     let menu_model = gio::Menu::new();
 
     let scheme_model = gio::Menu::new();
@@ -207,6 +416,55 @@ pub fn burger_menu(
     zoom_model.insert_item(0, &zoom_item);
     menu_model.append_section(None, &zoom_model);
 
+    let whitespace_item =
+        gio::MenuItem::new(Some("Ignore whitespace"), Some("menu.ignore_whitespace"));
+    menu_model.append_item(&whitespace_item);
+
+    let syntax_item = gio::MenuItem::new(
+        Some("Syntax highlighting"),
+        Some("menu.syntax_highlighting"),
+    );
+    menu_model.append_item(&syntax_item);
+
+    let json_item = gio::MenuItem::new(Some("Copy changes as JSON"), Some("menu.copy_json"));
+    menu_model.append_item(&json_item);
+
+    let hooks_item = gio::MenuItem::new(Some("Run git hooks"), Some("menu.run_hooks"));
+    menu_model.append_item(&hooks_item);
+
+    let confirm_kill_item = gio::MenuItem::new(
+        Some("Confirm discarding changes"),
+        Some("menu.confirm_kill"),
+    );
+    menu_model.append_item(&confirm_kill_item);
+
+    let pull_ff_only_item =
+        gio::MenuItem::new(Some("Fast-forward only pull"), Some("menu.pull_ff_only"));
+    menu_model.append_item(&pull_ff_only_item);
+
+    let pull_rebase_item = gio::MenuItem::new(Some("Pull with rebase"), Some("menu.pull_rebase"));
+    menu_model.append_item(&pull_rebase_item);
+
+    let signing_item = gio::MenuItem::new(Some("Commit signing"), Some("menu.commit_signing"));
+    menu_model.append_item(&signing_item);
+
+    let per_repo_item =
+        gio::MenuItem::new(Some("Settings for this repo only"), Some("menu.per_repo"));
+    menu_model.append_item(&per_repo_item);
+
+    let pre_commit_item =
+        gio::MenuItem::new(Some("Pre-commit command"), Some("menu.pre_commit_command"));
+    menu_model.append_item(&pre_commit_item);
+
+    let clean_item = gio::MenuItem::new(Some("Clean working tree"), Some("menu.clean"));
+    menu_model.append_item(&clean_item);
+
+    let bisect_start_item = gio::MenuItem::new(Some("Start bisect"), Some("menu.bisect_start"));
+    menu_model.append_item(&bisect_start_item);
+
+    let bisect_reset_item = gio::MenuItem::new(Some("Reset bisect"), Some("menu.bisect_reset"));
+    menu_model.append_item(&bisect_reset_item);
+
     let shortcuts_item = gio::MenuItem::new(Some("Shortcuts"), Some("menu.shortcuts"));
     menu_model.append_item(&shortcuts_item);
 
@@ -223,6 +481,192 @@ pub fn burger_menu(
 
     let ag = gio::SimpleActionGroup::new();
 
+    let whitespace_action =
+        gio::SimpleAction::new_stateful("ignore_whitespace", None, &ignore_whitespace.to_variant());
+    whitespace_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let ignore = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(false);
+            action.set_state(&ignore.to_variant());
+            sender
+                .send_blocking(crate::Event::IgnoreWhitespace(ignore))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&whitespace_action);
+
+    let syntax_action = gio::SimpleAction::new_stateful(
+        "syntax_highlighting",
+        None,
+        &syntax_highlighting.to_variant(),
+    );
+    syntax_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let enabled = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(true);
+            action.set_state(&enabled.to_variant());
+            sender
+                .send_blocking(crate::Event::SyntaxHighlighting(enabled))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&syntax_action);
+
+    let json_action = gio::SimpleAction::new("copy_json", None);
+    json_action.connect_activate({
+        let sender = sender.clone();
+        move |_, _| {
+            sender
+                .send_blocking(crate::Event::CopyChangesAsJson)
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&json_action);
+
+    let hooks_action = gio::SimpleAction::new_stateful("run_hooks", None, &run_hooks.to_variant());
+    hooks_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let run = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(true);
+            action.set_state(&run.to_variant());
+            sender
+                .send_blocking(crate::Event::RunHooks(run))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&hooks_action);
+
+    let confirm_kill_action =
+        gio::SimpleAction::new_stateful("confirm_kill", None, &confirm_kill.to_variant());
+    confirm_kill_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let confirm = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(true);
+            action.set_state(&confirm.to_variant());
+            sender
+                .send_blocking(crate::Event::ConfirmKill(confirm))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&confirm_kill_action);
+
+    let pull_ff_only_action =
+        gio::SimpleAction::new_stateful("pull_ff_only", None, &pull_ff_only.to_variant());
+    pull_ff_only_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let ff_only = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(false);
+            action.set_state(&ff_only.to_variant());
+            sender
+                .send_blocking(crate::Event::PullFfOnly(ff_only))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&pull_ff_only_action);
+
+    let pull_rebase_action =
+        gio::SimpleAction::new_stateful("pull_rebase", None, &pull_rebase.to_variant());
+    pull_rebase_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let rebase = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(false);
+            action.set_state(&rebase.to_variant());
+            sender
+                .send_blocking(crate::Event::PullRebaseMode(rebase))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&pull_rebase_action);
+
+    let per_repo_action = gio::SimpleAction::new_stateful("per_repo", None, &false.to_variant());
+    per_repo_action.connect_activate({
+        let sender = sender.clone();
+        move |action, _| {
+            let per_repo = !action
+                .state()
+                .and_then(|state| state.get::<bool>())
+                .unwrap_or(false);
+            action.set_state(&per_repo.to_variant());
+            sender
+                .send_blocking(crate::Event::PerRepoSettings(per_repo))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&per_repo_action);
+
+    let signing_action = gio::SimpleAction::new("commit_signing", None);
+    signing_action.connect_activate({
+        let sender = sender.clone();
+        move |_, _| {
+            sender
+                .send_blocking(crate::Event::CommitSigning)
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&signing_action);
+
+    let pre_commit_action = gio::SimpleAction::new("pre_commit_command", None);
+    pre_commit_action.connect_activate({
+        let sender = sender.clone();
+        move |_, _| {
+            sender
+                .send_blocking(crate::Event::PreCommitCommand)
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&pre_commit_action);
+
+    let clean_action = gio::SimpleAction::new("clean", None);
+    clean_action.connect_activate({
+        let sender = sender.clone();
+        move |_, _| {
+            sender
+                .send_blocking(crate::Event::Clean)
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&clean_action);
+
+    let bisect_start_action = gio::SimpleAction::new("bisect_start", None);
+    bisect_start_action.connect_activate({
+        let sender = sender.clone();
+        move |_, _| {
+            sender
+                .send_blocking(crate::Event::Bisect(crate::BisectOp::Start))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&bisect_start_action);
+
+    let bisect_reset_action = gio::SimpleAction::new("bisect_reset", None);
+    bisect_reset_action.connect_activate({
+        let sender = sender.clone();
+        move |_, _| {
+            sender
+                .send_blocking(crate::Event::Bisect(crate::BisectOp::Reset))
+                .expect("cant send through channel");
+        }
+    });
+    ag.add_action(&bisect_reset_action);
+
     let shortcuts_action = gio::SimpleAction::new("shortcuts", None);
     shortcuts_action.connect_activate({
         let window = window.clone();
@@ -257,10 +701,13 @@ pub fn burger_menu(
 
     popover_menu.insert_action_group("menu", Some(&ag));
 
-    MenuButton::builder()
-        .popover(&popover_menu)
-        .icon_name("open-menu-symbolic")
-        .build()
+    (
+        MenuButton::builder()
+            .popover(&popover_menu)
+            .icon_name("open-menu-symbolic")
+            .build(),
+        ag,
+    )
 }
 
 pub fn factory(
@@ -373,10 +820,31 @@ pub fn factory(
         }
     });
 
+    // flag of running fetch/push. cancel button is shown
+    // only while pull or push button is busy
+    let remote_cancel: Rc<RefCell<Option<Arc<AtomicBool>>>> = Rc::new(RefCell::new(None));
+    let cancel_btn = Button::builder()
+        .label("Cancel")
+        .use_underline(true)
+        .can_focus(false)
+        .tooltip_text("Cancel remote operation")
+        .icon_name("process-stop-symbolic")
+        .visible(false)
+        .build();
+    cancel_btn.connect_clicked({
+        let remote_cancel = remote_cancel.clone();
+        move |btn| {
+            if let Some(cancel) = remote_cancel.take() {
+                cancel.store(true, Ordering::Relaxed);
+            }
+            btn.set_visible(false);
+        }
+    });
+
     let pull_btn = Button::builder()
         .label("Pull")
         .use_underline(true)
-        .tooltip_text("Pull (f)")
+        .tooltip_text("Pull (f), with rebase (Shift+F)")
         .icon_name("document-save-symbolic")
         .sensitive(false)
         .width_request(38)
@@ -412,9 +880,13 @@ pub fn factory(
     });
 
     let repo_menu = gio::Menu::new();
-    for path in settings.get::<Vec<String>>("paths").iter() {
+    for path in recent_paths(settings.get::<Vec<String>>(PATHS_TOKEN), None).iter() {
         repo_menu.append(Some(path), Some(&format!("win.open::{}", path)));
     }
+    let init_section = gio::Menu::new();
+    init_section.append(Some("New repository"), Some("win.init"));
+    init_section.append(Some("New tab"), Some("win.new_tab"));
+    repo_menu.append_section(None, &init_section);
     let repo_popover = PopoverMenu::from_model(Some(&repo_menu));
 
     let repo_opener = ButtonContent::builder()
@@ -476,16 +948,46 @@ pub fn factory(
                 .expect("cant send through channel");
         }
     });
+    let (burger_menu_btn, menu_actions) = burger_menu(
+        Scheme::new(settings.get::<String>(SCHEME_TOKEN)),
+        settings.get::<bool>(IGNORE_WHITESPACE_TOKEN),
+        settings.get::<bool>(SYNTAX_HIGHLIGHTING_TOKEN),
+        settings.get::<bool>(RUN_HOOKS_TOKEN),
+        settings.get::<bool>(PULL_FF_ONLY_TOKEN),
+        settings.get::<bool>(PULL_REBASE_TOKEN),
+        settings.get::<bool>(CONFIRM_KILL_TOKEN),
+        window,
+        sender.clone(),
+    );
+    // "do not ask again" in kill dialog turns confirmation off too
+    settings.connect_changed(Some(CONFIRM_KILL_TOKEN), {
+        let menu_actions = menu_actions.downgrade();
+        move |settings, key| {
+            let Some(action) = menu_actions
+                .upgrade()
+                .and_then(|ag| ag.lookup_action("confirm_kill"))
+                .and_downcast::<gio::SimpleAction>()
+            else {
+                return;
+            };
+            action.set_state(&settings.boolean(key).to_variant());
+        }
+    });
+
     let updater = {
+        let settings = settings.clone();
         let stashes_btn = stashes_btn.clone();
         let refresh_btn = refresh_btn.clone();
         let branches_btn = branches_btn.clone();
         let reset_btn = reset_btn.clone();
         let repo_opener = repo_opener.clone();
+        let init_section = init_section.clone();
         let commit_btn = commit_btn.clone();
         let push_btn = push_btn.clone();
         let log_btn = log_btn.clone();
         let pull_btn = pull_btn.clone();
+        let cancel_btn = cancel_btn.clone();
+        let remote_cancel = remote_cancel.clone();
         let stage_btn = stage_btn.clone();
         let unstage_btn = unstage_btn.clone();
         let kill_btn = kill_btn.clone();
@@ -493,6 +995,7 @@ pub fn factory(
         let repo_selector = repo_selector.clone();
         move |data: HbUpdateData| match data {
             HbUpdateData::Path(path) => {
+                update_menu_state(&menu_actions, &settings, Some(&path));
                 stashes_btn.set_sensitive(true);
                 refresh_btn.set_sensitive(true);
                 branches_btn.set_sensitive(true);
@@ -511,25 +1014,13 @@ pub fn factory(
                 repo_opener_label
                     .set_markup(&format!("<span weight=\"normal\">{}</span>", clean_path));
                 repo_opener_label.set_visible(true);
-                let mut path_exists = false;
-                for i in 0..repo_menu.n_items() {
-                    let iter = repo_menu.iterate_item_attributes(i);
-                    while let Some(attr) = iter.next() {
-                        if attr.0 == "target"
-                            && clean_path
-                                == attr.1.get::<String>().expect("cant get path from gvariant")
-                        {
-                            path_exists = true;
-                            break;
-                        }
-                    }
-                }
-                if !path_exists {
-                    repo_menu.append(
-                        Some(&clean_path),
-                        Some(&format!("win.open::{}", clean_path)),
-                    );
+                repo_menu.remove_all();
+                for path in
+                    recent_paths(settings.get::<Vec<String>>(PATHS_TOKEN), Some(&clean_path))
+                {
+                    repo_menu.append(Some(&path), Some(&format!("win.open::{}", path)));
                 }
+                repo_menu.append_section(None, &init_section);
             }
             HbUpdateData::Staged(is_staged) => {
                 commit_btn.set_sensitive(is_staged);
@@ -557,12 +1048,58 @@ pub fn factory(
                     _ => {}
                 }
             }
+            HbUpdateData::RemoteProgress(done, total) => {
+                // only button which is busy with spinner shows progress
+                for btn in [&pull_btn, &push_btn] {
+                    let Some(child) = btn.child() else {
+                        continue;
+                    };
+                    let text = format!("{}/{}", done, total);
+                    if let Some(progress) = child.downcast_ref::<Box>() {
+                        if let Some(label) = progress.last_child() {
+                            label.downcast_ref::<Label>().unwrap().set_label(&text);
+                        }
+                    } else if let Ok(spinner) = child.downcast::<Spinner>() {
+                        btn.set_child(None::<&Widget>);
+                        let progress = Box::builder().spacing(4).build();
+                        progress.append(&spinner);
+                        progress.append(&Label::new(Some(&text)));
+                        btn.set_child(Some(&progress));
+                    } else {
+                        continue;
+                    }
+                    btn.set_tooltip_text(Some(&format!(
+                        "{}% ({} of {} objects)",
+                        done * 100 / total.max(1),
+                        done,
+                        total
+                    )));
+                }
+            }
+            HbUpdateData::RemoteCancel(None) => {
+                remote_cancel.take();
+                cancel_btn.set_visible(false);
+            }
+            HbUpdateData::RemoteCancel(Some(cancel)) => {
+                // remote ops from other views do not make buttons busy
+                let busy = [&pull_btn, &push_btn]
+                    .iter()
+                    .any(|btn| btn.child().is_some_and(|child| !child.is::<Image>()));
+                if busy {
+                    remote_cancel.replace(Some(cancel));
+                    cancel_btn.set_visible(true);
+                }
+            }
             HbUpdateData::Upstream => {
+                remote_cancel.take();
+                cancel_btn.set_visible(false);
                 pull_btn.set_child(None::<&Widget>);
                 pull_btn.set_icon_name("document-save-symbolic");
+                pull_btn.set_tooltip_text(Some("Pull (f), with rebase (Shift+F)"));
                 pull_btn.set_sensitive(true);
                 push_btn.set_child(None::<&Widget>);
                 push_btn.set_icon_name("send-to-symbolic");
+                push_btn.set_tooltip_text(Some("Push (P)"));
                 push_btn.set_sensitive(true);
             }
             HbUpdateData::Context(ctx) => {
@@ -640,11 +1177,7 @@ pub fn factory(
 
     hb.set_title_widget(Some(&repo_selector));
 
-    hb.pack_end(&burger_menu(
-        Scheme::new(settings.get::<String>(SCHEME_TOKEN)),
-        window,
-        sender,
-    ));
+    hb.pack_end(&burger_menu_btn);
     hb.pack_end(&commit_btn);
     hb.pack_end(&stage_btn);
     hb.pack_end(&unstage_btn);
@@ -653,6 +1186,7 @@ pub fn factory(
     hb.pack_end(&branches_btn);
     hb.pack_end(&push_btn);
     hb.pack_end(&pull_btn);
+    hb.pack_end(&cancel_btn);
     hb.pack_end(&log_btn);
     hb.pack_end(&reset_btn);
     hb.pack_end(&refresh_btn);
@@ -669,84 +1203,253 @@ pub fn show_shortcuts_view(app_window: &ApplicationWindow, sender: Sender<crate:
     let stage_group = ShortcutsGroup::builder().title("Staging").build();
     let stage_shortcut = ShortcutsShortcut::builder()
         .title("Stage")
-        .accelerator("s Return")
+        .accelerator(keymap::accelerator("status.stage"))
         .build();
     stage_group.add_shortcut(&stage_shortcut);
     let unstage_shortcut = ShortcutsShortcut::builder()
         .title("Unstage")
-        .accelerator("u")
+        .accelerator(keymap::accelerator("status.unstage"))
         .build();
     stage_group.add_shortcut(&unstage_shortcut);
+    let stage_all_shortcut = ShortcutsShortcut::builder()
+        .title("Stage all changes")
+        .subtitle("Conflicted files are skipped")
+        .accelerator(keymap::accelerator("status.stage_all"))
+        .build();
+    stage_group.add_shortcut(&stage_all_shortcut);
+    let unstage_all_shortcut = ShortcutsShortcut::builder()
+        .title("Unstage all changes")
+        .accelerator(keymap::accelerator("status.unstage_all"))
+        .build();
+    stage_group.add_shortcut(&unstage_all_shortcut);
     let expand_shortcut = ShortcutsShortcut::builder()
         .title("Expand/Collapse block")
-        .accelerator("Tab space")
+        .accelerator(keymap::accelerator("status.expand"))
         .build();
     stage_group.add_shortcut(&expand_shortcut);
+    let expand_all_shortcut = ShortcutsShortcut::builder()
+        .title("Expand/Collapse all files")
+        .subtitle("In current section. With Alt - in all sections")
+        .accelerator(format!(
+            "{} {}",
+            keymap::accelerator("status.expand_all"),
+            keymap::accelerator("status.collapse_all")
+        ))
+        .build();
+    stage_group.add_shortcut(&expand_all_shortcut);
+    let next_file_shortcut = ShortcutsShortcut::builder()
+        .title("Next/Previous file")
+        .accelerator(format!(
+            "{} {}",
+            keymap::accelerator("status.next_file"),
+            keymap::accelerator("status.prev_file")
+        ))
+        .build();
+    stage_group.add_shortcut(&next_file_shortcut);
 
     let kill_shortcut = ShortcutsShortcut::builder()
         .title("Kill")
         .subtitle("Discard changes for file or hunk")
-        .accelerator("k")
+        .accelerator(keymap::accelerator("status.kill"))
         .build();
     stage_group.add_shortcut(&kill_shortcut);
+    let undo_shortcut = ShortcutsShortcut::builder()
+        .title("Undo")
+        .subtitle("Revert last stage, unstage or kill")
+        .accelerator(keymap::accelerator("status.undo"))
+        .build();
+    stage_group.add_shortcut(&undo_shortcut);
+    let ignore_shortcut = ShortcutsShortcut::builder()
+        .title("Ignore untracked file")
+        .subtitle("Adds it to .gitignore")
+        .accelerator(keymap::accelerator("status.ignore"))
+        .build();
+    stage_group.add_shortcut(&ignore_shortcut);
     let commit_shortcut = ShortcutsShortcut::builder()
         .title("Commit")
-        .accelerator("c")
+        .accelerator(keymap::accelerator("status.commit"))
         .build();
     stage_group.add_shortcut(&commit_shortcut);
+    let inline_commit_shortcut = ShortcutsShortcut::builder()
+        .title("Commit from status")
+        .subtitle("Message in bottom bar. Ctrl+Enter commits, Escape hides it")
+        .accelerator(keymap::accelerator("status.inline_commit"))
+        .build();
+    stage_group.add_shortcut(&inline_commit_shortcut);
+    let next_conflict_shortcut = ShortcutsShortcut::builder()
+        .title("Next conflict")
+        .accelerator(keymap::accelerator("status.next_conflict"))
+        .build();
+    stage_group.add_shortcut(&next_conflict_shortcut);
+    let prev_conflict_shortcut = ShortcutsShortcut::builder()
+        .title("Previous conflict")
+        .accelerator(keymap::accelerator("status.prev_conflict"))
+        .build();
+    stage_group.add_shortcut(&prev_conflict_shortcut);
+    let mergetool_shortcut = ShortcutsShortcut::builder()
+        .title("Open conflicted file in merge tool")
+        .accelerator(keymap::accelerator("status.mergetool"))
+        .build();
+    stage_group.add_shortcut(&mergetool_shortcut);
+    let squash_shortcut = ShortcutsShortcut::builder()
+        .title("Squash last commits")
+        .accelerator(keymap::accelerator("status.squash"))
+        .build();
+    stage_group.add_shortcut(&squash_shortcut);
+    let abort_shortcut = ShortcutsShortcut::builder()
+        .title("Abort merge, rebase, cherry-pick or revert")
+        .accelerator(keymap::accelerator("status.abort"))
+        .build();
+    stage_group.add_shortcut(&abort_shortcut);
+    let copy_hunk_shortcut = ShortcutsShortcut::builder()
+        .title("Copy hunk text")
+        .subtitle("Without line numbers. With Shift - with +/- prefixes")
+        .accelerator(format!(
+            "{} {}",
+            keymap::accelerator("status.copy_hunk"),
+            keymap::accelerator("status.copy_hunk_with_prefixes")
+        ))
+        .build();
+    stage_group.add_shortcut(&copy_hunk_shortcut);
+    let apply_patch_shortcut = ShortcutsShortcut::builder()
+        .title("Apply patch file")
+        .accelerator(keymap::accelerator("status.apply_patch"))
+        .build();
+    stage_group.add_shortcut(&apply_patch_shortcut);
+    let export_patch_shortcut = ShortcutsShortcut::builder()
+        .title("Export patch file")
+        .subtitle("Diff, file or hunk under cursor")
+        .accelerator(keymap::accelerator("status.export_patch"))
+        .build();
+    stage_group.add_shortcut(&export_patch_shortcut);
+    let search_shortcut = ShortcutsShortcut::builder()
+        .title("Search in diffs")
+        .subtitle("Enter/Shift+Enter in search entry - next/previous match")
+        .accelerator(keymap::accelerator("status.search"))
+        .build();
+    stage_group.add_shortcut(&search_shortcut);
+    let next_match_shortcut = ShortcutsShortcut::builder()
+        .title("Next/Previous match")
+        .accelerator(format!(
+            "{} {}",
+            keymap::accelerator("status.next_match"),
+            keymap::accelerator("status.prev_match")
+        ))
+        .build();
+    stage_group.add_shortcut(&next_match_shortcut);
     status_section.add_group(&stage_group);
 
     let window_group = ShortcutsGroup::builder().title("Other windows").build();
     let branches_shortcut = ShortcutsShortcut::builder()
         .title("Branches")
-        .accelerator("b")
+        .accelerator(keymap::accelerator("status.branches"))
         .build();
     window_group.add_shortcut(&branches_shortcut);
+    let switcher_shortcut = ShortcutsShortcut::builder()
+        .title("Quick branch switcher")
+        .accelerator(keymap::accelerator("status.branch_switcher"))
+        .build();
+    window_group.add_shortcut(&switcher_shortcut);
+    let branch_at_head_shortcut = ShortcutsShortcut::builder()
+        .title("Create branch at detached head")
+        .accelerator(keymap::accelerator("status.branch_at_head"))
+        .build();
+    window_group.add_shortcut(&branch_at_head_shortcut);
     let log_shortcut = ShortcutsShortcut::builder()
         .title("Logs")
-        .accelerator("l")
+        .accelerator(keymap::accelerator("status.log"))
         .build();
     window_group.add_shortcut(&log_shortcut);
+    let reflog_shortcut = ShortcutsShortcut::builder()
+        .title("Reflog")
+        .subtitle("Hard reset to any previous HEAD")
+        .accelerator(keymap::accelerator("status.reflog"))
+        .build();
+    window_group.add_shortcut(&reflog_shortcut);
     let stashes_shortcut = ShortcutsShortcut::builder()
         .title("Stashes")
-        .accelerator("z")
+        .accelerator(keymap::accelerator("status.stashes"))
         .build();
     window_group.add_shortcut(&stashes_shortcut);
+    let worktrees_shortcut = ShortcutsShortcut::builder()
+        .title("Worktrees")
+        .accelerator(keymap::accelerator("status.worktrees"))
+        .build();
+    window_group.add_shortcut(&worktrees_shortcut);
+    let show_file_shortcut = ShortcutsShortcut::builder()
+        .title("File at HEAD")
+        .subtitle("Read-only content of committed file")
+        .accelerator(keymap::accelerator("status.show_file"))
+        .build();
+    window_group.add_shortcut(&show_file_shortcut);
     let close_shortcut = ShortcutsShortcut::builder()
-        .title("Close window")
+        .title("Close tab or window")
         .accelerator("<ctrl>w Escape")
         .build();
     window_group.add_shortcut(&close_shortcut);
+    let new_tab_shortcut = ShortcutsShortcut::builder()
+        .title("New tab")
+        .subtitle("Another repository in the same window")
+        .accelerator("<ctrl>t")
+        .build();
+    window_group.add_shortcut(&new_tab_shortcut);
 
     status_section.add_group(&window_group);
 
     let other_group = ShortcutsGroup::builder().title("Other commands").build();
     let push_shortcut = ShortcutsShortcut::builder()
         .title("Push")
-        .accelerator("p")
+        .accelerator(keymap::accelerator("status.push"))
         .build();
     other_group.add_shortcut(&push_shortcut);
     let pull_shortcut = ShortcutsShortcut::builder()
         .title("Pull")
-        .accelerator("f")
+        .accelerator(keymap::accelerator("status.pull"))
         .build();
     other_group.add_shortcut(&pull_shortcut);
+    let pull_rebase_shortcut = ShortcutsShortcut::builder()
+        .title("Pull with rebase")
+        .subtitle("Local commits are rebased onto fetched upstream")
+        .accelerator(keymap::accelerator("status.pull_rebase"))
+        .build();
+    other_group.add_shortcut(&pull_rebase_shortcut);
     let repo_shortcut = ShortcutsShortcut::builder()
         .title("Previous repo chooser")
-        .accelerator("o")
+        .accelerator(keymap::accelerator("status.repo_popup"))
         .build();
     other_group.add_shortcut(&repo_shortcut);
     let file_shortcut = ShortcutsShortcut::builder()
         .title("Open repository")
-        .accelerator("<ctrl>o")
+        .accelerator(keymap::accelerator("status.open"))
         .build();
     other_group.add_shortcut(&file_shortcut);
+    let paste_shortcut = ShortcutsShortcut::builder()
+        .title("Open repository from path in clipboard")
+        .accelerator(keymap::accelerator("status.paste_path"))
+        .build();
+    other_group.add_shortcut(&paste_shortcut);
 
     let blame_shortcut = ShortcutsShortcut::builder()
         .title("Blame")
-        .accelerator("<ctrl>b")
+        .accelerator(keymap::accelerator("status.blame"))
         .build();
     other_group.add_shortcut(&blame_shortcut);
+    let blame_file_shortcut = ShortcutsShortcut::builder()
+        .title("Blame whole file")
+        .accelerator(keymap::accelerator("status.blame_file"))
+        .build();
+    other_group.add_shortcut(&blame_file_shortcut);
+
+    let bisect_good_shortcut = ShortcutsShortcut::builder()
+        .title("Bisect: mark HEAD good")
+        .accelerator(keymap::accelerator("status.bisect_good"))
+        .build();
+    other_group.add_shortcut(&bisect_good_shortcut);
+    let bisect_bad_shortcut = ShortcutsShortcut::builder()
+        .title("Bisect: mark HEAD bad")
+        .accelerator(keymap::accelerator("status.bisect_bad"))
+        .build();
+    other_group.add_shortcut(&bisect_bad_shortcut);
 
     status_section.add_group(&other_group);
     shortcuts_window.add_section(&status_section);
@@ -758,47 +1461,78 @@ pub fn show_shortcuts_view(app_window: &ApplicationWindow, sender: Sender<crate:
     let commands_group = ShortcutsGroup::builder().title("Branches commands").build();
     let create_shortcut = ShortcutsShortcut::builder()
         .title("Create branch")
-        .accelerator("c n")
+        .accelerator(keymap::accelerator("branches.create"))
         .build();
     commands_group.add_shortcut(&create_shortcut);
     let kill_shortcut = ShortcutsShortcut::builder()
         .title("Delete branch")
-        .subtitle("ATTENTION - no confirmation")
-        .accelerator("k")
+        .subtitle("Not merged branches require force. Remote branches are deleted on remote")
+        .accelerator(keymap::accelerator("branches.kill"))
         .build();
     commands_group.add_shortcut(&kill_shortcut);
     let merge_shortcut = ShortcutsShortcut::builder()
         .title("Merge branch")
-        .accelerator("m")
+        .accelerator(keymap::accelerator("branches.merge"))
         .build();
     commands_group.add_shortcut(&merge_shortcut);
     let rebase_shortcut = ShortcutsShortcut::builder()
         .title("Rebase on branch")
-        .accelerator("r")
+        .accelerator(keymap::accelerator("branches.rebase"))
         .build();
     commands_group.add_shortcut(&rebase_shortcut);
+    let checkout_pull_shortcut = ShortcutsShortcut::builder()
+        .title("Checkout and pull")
+        .accelerator(keymap::accelerator("branches.pull"))
+        .build();
+    commands_group.add_shortcut(&checkout_pull_shortcut);
+    let rename_shortcut = ShortcutsShortcut::builder()
+        .title("Rename branch")
+        .accelerator(keymap::accelerator("branches.rename"))
+        .build();
+    commands_group.add_shortcut(&rename_shortcut);
+    let copy_shortcut = ShortcutsShortcut::builder()
+        .title("Copy branch name")
+        .accelerator(keymap::accelerator("branches.copy"))
+        .build();
+    commands_group.add_shortcut(&copy_shortcut);
+    let upstream_shortcut = ShortcutsShortcut::builder()
+        .title("Set upstream (tracking) branch")
+        .accelerator(keymap::accelerator("branches.upstream"))
+        .build();
+    commands_group.add_shortcut(&upstream_shortcut);
+    let worktree_shortcut = ShortcutsShortcut::builder()
+        .title("Create worktree for branch")
+        .accelerator(keymap::accelerator("branches.worktree"))
+        .build();
+    commands_group.add_shortcut(&worktree_shortcut);
+
+    let merge_base_shortcut = ShortcutsShortcut::builder()
+        .title("Show merge base with HEAD")
+        .accelerator(keymap::accelerator("branches.merge_base"))
+        .build();
+    commands_group.add_shortcut(&merge_base_shortcut);
 
     let branch_log_shortcut = ShortcutsShortcut::builder()
         .title("Log for branch")
-        .accelerator("l")
+        .accelerator(keymap::accelerator("branches.log"))
         .build();
     commands_group.add_shortcut(&branch_log_shortcut);
 
     let apply_shortcut = ShortcutsShortcut::builder()
         .title("Cherry pick")
-        .accelerator("a")
+        .accelerator(keymap::accelerator("branches.cherry_pick"))
         .build();
     commands_group.add_shortcut(&apply_shortcut);
 
     let update_shortcut = ShortcutsShortcut::builder()
         .title("Update remotes")
-        .accelerator("u")
+        .accelerator(keymap::accelerator("branches.update_remote"))
         .build();
     commands_group.add_shortcut(&update_shortcut);
 
     let search_shortcut = ShortcutsShortcut::builder()
         .title("Search branches")
-        .accelerator("s")
+        .accelerator(keymap::accelerator("branches.search"))
         .build();
     commands_group.add_shortcut(&search_shortcut);
 
@@ -830,6 +1564,21 @@ pub fn show_shortcuts_view(app_window: &ApplicationWindow, sender: Sender<crate:
         .accelerator("r")
         .build();
     log_commands_group.add_shortcut(&revert_shortcut);
+    let reword_shortcut = ShortcutsShortcut::builder()
+        .title("Reword commit message")
+        .accelerator("e")
+        .build();
+    log_commands_group.add_shortcut(&reword_shortcut);
+    let copy_sha_shortcut = ShortcutsShortcut::builder()
+        .title("Copy commit sha")
+        .accelerator("y")
+        .build();
+    log_commands_group.add_shortcut(&copy_sha_shortcut);
+    let copy_url_shortcut = ShortcutsShortcut::builder()
+        .title("Copy commit web url")
+        .accelerator("<shift>y")
+        .build();
+    log_commands_group.add_shortcut(&copy_url_shortcut);
 
     log_section.add_group(&log_commands_group);
     shortcuts_window.add_section(&log_section);
@@ -851,12 +1600,28 @@ pub fn show_shortcuts_view(app_window: &ApplicationWindow, sender: Sender<crate:
         .accelerator("r")
         .build();
     commit_commands_group.add_shortcut(&revert_shortcut);
+    let copy_sha_shortcut = ShortcutsShortcut::builder()
+        .title("Copy commit sha")
+        .accelerator("y")
+        .build();
+    commit_commands_group.add_shortcut(&copy_sha_shortcut);
+    let copy_url_shortcut = ShortcutsShortcut::builder()
+        .title("Copy commit web url")
+        .accelerator("<shift>y")
+        .build();
+    commit_commands_group.add_shortcut(&copy_url_shortcut);
 
     let commit_blame_shortcut = ShortcutsShortcut::builder()
         .title("Blame")
         .accelerator("<ctrl>b")
         .build();
     commit_commands_group.add_shortcut(&commit_blame_shortcut);
+    let commit_show_file_shortcut = ShortcutsShortcut::builder()
+        .title("File at this commit")
+        .subtitle("Also in blame window. r in file window restores it")
+        .accelerator(keymap::accelerator("status.show_file"))
+        .build();
+    commit_commands_group.add_shortcut(&commit_show_file_shortcut);
 
     commit_section.add_group(&commit_commands_group);
     shortcuts_window.add_section(&commit_section);
@@ -8,10 +8,15 @@ use gio::{Cancellable, File, FileMonitor, FileMonitorEvent, FileMonitorFlags};
 use gtk4::prelude::*;
 use gtk4::{gio, glib};
 use log::trace;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::path::PathBuf;
 use std::rc::Rc;
 
+// refresh comes only after this period passes without any
+// file event. e.g. cargo build will produce single refresh
+// after the build, instead of refresh on each written file
+const QUIET_PERIOD: Duration = Duration::from_millis(300);
+
 impl Status {
     pub fn lock_monitors(&mut self, lock: bool) {
         self.monitor_global_lock.replace(lock);
@@ -22,6 +27,7 @@ impl Status {
             let sender = self.sender.clone();
             let lock = self.monitor_lock.clone();
             let global_lock = self.monitor_global_lock.clone();
+            let events = self.monitor_events.clone();
             async move {
                 let mut directories = gio::spawn_blocking({
                     let path = path.clone();
@@ -54,6 +60,7 @@ impl Status {
                         let sender = sender.clone();
                         let lock = lock.clone();
                         let global_lock = global_lock.clone();
+                        let events = events.clone();
                         move |_monitor, file, _other_file, event| {
                             // TODO get from SELF.settings
                             if *global_lock.borrow() {
@@ -63,7 +70,9 @@ impl Status {
                             let patterns_to_exclude: Vec<&str> =
                                 vec!["/.#", "/mout", "flycheck_", "/sed", ".goutputstream"];
                             match event {
-                                FileMonitorEvent::Changed | FileMonitorEvent::Created | FileMonitorEvent::Deleted => {
+                                FileMonitorEvent::Changed
+                                | FileMonitorEvent::Created
+                                | FileMonitorEvent::Deleted => {
                                     // ChangesDoneHint is not fired for small changes :(
                                     let Some(fp) = file.path() else {
                                         return;
@@ -72,10 +81,7 @@ impl Status {
                                         return;
                                     };
                                     let Some(str_file_path) = str_file_path.strip_prefix(
-                                        &path
-                                            .to_str()
-                                            .unwrap()
-                                            .replace("./git/", "")
+                                        &path.to_str().unwrap().replace("./git/", ""),
                                     ) else {
                                         return;
                                     };
@@ -87,55 +93,62 @@ impl Status {
                                         }
                                     }
                                     let file_path: PathBuf = str_file_path.into();
-                                    if lock.borrow().contains(&file_path) {
-                                        trace!("NO WAY: monitor locked");
-                                        return;
-                                    }
-                                    lock.borrow_mut().insert(file_path.clone());
-                                    let current_lock_len = lock.borrow().len();
-                                    glib::source::timeout_add_local(
-                                        Duration::from_millis(300),
-                                        {
-                                            let lock = lock.clone();
-                                            let sender = sender.clone();
-                                            let path = path.clone();
-                                            move || {
-                                                let future_lock_len = lock.borrow().len();
-                                                if future_lock_len != current_lock_len {
-                                                    trace!("^^^^^^^^something added to lock!.... NO WAY!!!!");
-                                                    return glib::ControlFlow::Break;
-                                                }
-                                                if future_lock_len > 1 {
-                                                    // if multiple files are changed during 300 msec
-                                                    // period - just refresh whole status
-                                                    gio::spawn_blocking({
-                                                        let path = path.clone();
-                                                        let sender = sender.clone();
-                                                        move || crate::get_current_repo_status(Some(path), sender)
-                                                    });
-                                                } else {
-                                                    // track just 1 file!
-                                                    gio::spawn_blocking({
-                                                        let path = path.clone();
-                                                        let sender = sender.clone();
-                                                        let file_path = lock.borrow().iter().next().unwrap().clone();
-                                                        move || track_changes(path.clone(), file_path, sender.clone())
-                                                    });
-
-                                                }
-                                                trace!("........ cleanup lock");
-                                                lock.borrow_mut().clear();
-                                                glib::ControlFlow::Break
+                                    lock.borrow_mut().insert(file_path);
+                                    // any event, even for the same file, postpones refresh
+                                    let current_event = events.get().wrapping_add(1);
+                                    events.set(current_event);
+                                    glib::source::timeout_add_local(QUIET_PERIOD, {
+                                        let lock = lock.clone();
+                                        let events = events.clone();
+                                        let sender = sender.clone();
+                                        let path = path.clone();
+                                        move || {
+                                            if events.get() != current_event {
+                                                trace!("^^^^^^^^more events came. wait for quiet");
+                                                return glib::ControlFlow::Break;
+                                            }
+                                            let future_lock_len = lock.borrow().len();
+                                            if future_lock_len > 1 {
+                                                // if multiple files are changed during
+                                                // burst - just refresh whole status
+                                                gio::spawn_blocking({
+                                                    let path = path.clone();
+                                                    let sender = sender.clone();
+                                                    move || {
+                                                        crate::get_current_repo_status(
+                                                            Some(path),
+                                                            sender,
+                                                        )
+                                                    }
+                                                });
+                                            } else {
+                                                // track just 1 file!
+                                                gio::spawn_blocking({
+                                                    let path = path.clone();
+                                                    let sender = sender.clone();
+                                                    let file_path = lock
+                                                        .borrow()
+                                                        .iter()
+                                                        .next()
+                                                        .unwrap()
+                                                        .clone();
+                                                    move || {
+                                                        track_changes(
+                                                            path.clone(),
+                                                            file_path,
+                                                            sender.clone(),
+                                                        )
+                                                    }
+                                                });
                                             }
-                                        },
-                                    );
+                                            trace!("........ cleanup lock");
+                                            lock.borrow_mut().clear();
+                                            glib::ControlFlow::Break
+                                        }
+                                    });
                                 }
                                 _ => {
-                                    trace!(
-                                        "file event in monitor {:?} {:?}",
-                                        event,
-                                        file.path()
-                                    );
+                                    trace!("file event in monitor {:?} {:?}", event, file.path());
                                 }
                             }
                         }
@@ -2,15 +2,20 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use super::headerbar::CONFIRM_KILL_TOKEN;
 use super::{CursorPosition, Status};
-use crate::dialogs::{alert, ConfirmWithOptions, DangerDialog, YES};
-use crate::git::{commit, merge, stash};
+use crate::dialogs::{alert, ConfirmWithOptions, DangerWithOptions, YES};
+use crate::git::{commit, merge, stash, undo};
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::{stage_untracked, stage_via_apply, ApplyOp, DiffKind, Event, StageOp};
+use crate::{
+    delete_untracked, discard_file, stage_all, stage_untracked, stage_via_apply, unstage_all,
+    untracked_files, ApplyOp, Diff, DiffKind, Event, File, Hunk, StageOp,
+};
 
+use git2::DiffLineType;
 use gtk4::prelude::*;
 use gtk4::{gio, glib, ListBox, SelectionMode, TextBuffer, TextIter, Widget};
 use libadwaita::prelude::*;
@@ -98,7 +103,13 @@ impl CursorPosition {
                     return (Some(untracked.kind), None, None);
                 }
             }
-            (Self::CursorFile(DiffKind::Untracked, file_idx), StageOp::Stage | StageOp::Kill) => {
+            // preview of untracked file could be staged or killed only as a whole
+            (
+                Self::CursorFile(DiffKind::Untracked, file_idx)
+                | Self::CursorHunk(DiffKind::Untracked, file_idx, _)
+                | Self::CursorLine(DiffKind::Untracked, file_idx, _, _),
+                StageOp::Stage | StageOp::Kill,
+            ) => {
                 if let Some(untracked) = &status.untracked {
                     let file = &untracked.files[*file_idx];
                     return (Some(untracked.kind), Some(file.path.clone()), None);
@@ -121,7 +132,140 @@ impl CursorPosition {
     }
 }
 
+// op goes on without undo, if state could not be stored
+fn take_snapshot(path: &Path, op: StageOp, files: &[PathBuf]) -> Option<undo::Snapshot> {
+    undo::snapshot(path, op, files)
+        .inspect_err(|e| debug!("no undo for {:?}: {:?}", op, e))
+        .ok()
+}
+
 impl Status {
+    /// Restores index or killed files as they were
+    /// before last stage/unstage/kill.
+    pub fn undo(&self, window: &ApplicationWindow) {
+        let Some(snapshot) = self.undo_snapshot.take() else {
+            self.sender
+                .send_blocking(Event::Toast(String::from("Nothing to undo")))
+                .expect("Could not send through channel");
+            return;
+        };
+        self.last_op.take();
+        self.last_op_target.replace(None);
+        glib::spawn_future_local({
+            let window = window.clone();
+            let path = self.path.clone().expect("no path");
+            let sender = self.sender.clone();
+            async move {
+                gio::spawn_blocking(move || undo::restore(path, snapshot, sender))
+                    .await
+                    .unwrap_or_else(|e| {
+                        alert(format!("{:?}", e)).present(Some(&window));
+                        Ok(())
+                    })
+                    .unwrap_or_else(|e| {
+                        alert(e).present(Some(&window));
+                    });
+            }
+        });
+    }
+
+    // what will be lost on kill of unstaged changes: whole diff,
+    // file or hunk
+    fn kill_message(&self, file_path: Option<&PathBuf>, hunk_header: Option<&String>) -> String {
+        let Some(unstaged) = &self.unstaged else {
+            return String::new();
+        };
+        let hunks: Vec<&Hunk> = unstaged
+            .files
+            .iter()
+            .filter(|file| file_path.is_none_or(|path| &file.path == path))
+            .flat_map(|file| file.hunks.iter())
+            .filter(|hunk| hunk_header.is_none_or(|header| &hunk.header == header))
+            .collect();
+        let lines = hunks
+            .iter()
+            .flat_map(|hunk| hunk.lines.iter())
+            .filter(|line| matches!(line.origin, DiffLineType::Addition | DiffLineType::Deletion))
+            .count();
+        let mut message = format!(
+            "{} changed lines in {} hunks will be discarded",
+            lines,
+            hunks.len()
+        );
+        if let Some(file_path) = file_path {
+            message.push_str(&format!(
+                "\nFile: {}",
+                html_escape::encode_text(&file_path.to_string_lossy())
+            ));
+        }
+        message
+    }
+
+    /// Whole repo at once, wherever cursor is.
+    pub fn stage_all(&mut self, op: StageOp, window: &ApplicationWindow) {
+        if op == StageOp::Kill {
+            return;
+        }
+        self.last_op.replace(Some(LastOp {
+            op,
+            cursor_position: self.cursor_position.get(),
+            desired_diff_kind: None,
+        }));
+        self.last_op_target.replace(None);
+        glib::spawn_future_local({
+            let window = window.clone();
+            let path = self.path.clone().expect("no path");
+            let sender = self.sender.clone();
+            let undo_snapshot = self.undo_snapshot.clone();
+            async move {
+                let snapshot = gio::spawn_blocking(move || {
+                    let snapshot = take_snapshot(&path, op, &[]);
+                    match op {
+                        StageOp::Unstage => unstage_all(path, sender),
+                        _ => stage_all(path, sender),
+                    }
+                    .map(|_| snapshot)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(None)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    None
+                });
+                undo_snapshot.replace(snapshot);
+            }
+        });
+    }
+
+    /// Changes at cursor for partial stash. Selection is the
+    /// same as for kill: stash takes what kill would discard.
+    pub fn stash_selection(&self) -> Option<stash::StashSelection> {
+        let (diff_kind, file_path, hunk_header) = self
+            .cursor_position
+            .get()
+            .resolve_stage_op(self, &StageOp::Kill);
+        match diff_kind? {
+            DiffKind::Unstaged => Some(stash::StashSelection::Unstaged(file_path, hunk_header)),
+            DiffKind::Untracked => {
+                let entries = match file_path {
+                    Some(file_path) => vec![file_path],
+                    None => self
+                        .untracked
+                        .as_ref()?
+                        .files
+                        .iter()
+                        .map(|file| file.path.clone())
+                        .collect(),
+                };
+                Some(stash::StashSelection::Untracked(entries))
+            }
+            _ => None,
+        }
+    }
+
     pub fn stage_op(
         &mut self,
         op: StageOp,
@@ -136,28 +280,36 @@ impl Status {
             cursor_position: self.cursor_position.get(),
             desired_diff_kind: None,
         });
+        self.last_op_target
+            .replace(file_path.clone().map(|path| (path, hunk_header.clone())));
 
         match diff_kind {
             Some(DiffKind::Untracked) => match op {
                 StageOp::Stage => {
                     self.last_op.replace(current_op);
                     glib::spawn_future_local({
-                        let path = self.path.clone();
+                        let path = self.path.clone().expect("no path");
                         let sender = self.sender.clone();
                         let file_path = file_path.clone();
                         let window = window.clone();
+                        let undo_snapshot = self.undo_snapshot.clone();
                         async move {
-                            gio::spawn_blocking({
-                                move || stage_untracked(path.expect("no path"), file_path, sender)
+                            let snapshot = gio::spawn_blocking({
+                                move || {
+                                    let snapshot = take_snapshot(&path, op, &[]);
+                                    stage_untracked(path, file_path, sender).map(|_| snapshot)
+                                }
                             })
                             .await
                             .unwrap_or_else(|e| {
                                 alert(format!("{:?}", e)).present(Some(&window));
-                                Ok(())
+                                Ok(None)
                             })
                             .unwrap_or_else(|e| {
                                 alert(e).present(Some(&window));
+                                None
                             });
+                            undo_snapshot.replace(snapshot);
                         }
                     });
                 }
@@ -165,48 +317,137 @@ impl Status {
                     self.last_op.replace(current_op);
                     glib::spawn_future_local({
                         let window = window.clone();
-                        let path = self.path.clone();
+                        let path = self.path.clone().expect("no path");
                         let gio_settings = gio_settings.clone();
                         let sender = self.sender.clone();
                         let untracked = self.untracked.clone();
-                        let mut ignored = Vec::new();
-                        let mut message = "This will hide all untracked files!".to_string();
+                        let undo_snapshot = self.undo_snapshot.clone();
+                        let mut entries = Vec::new();
                         if let Some(file_path) = &file_path {
-                            let str_path = file_path.to_str().expect("wrong path");
-                            ignored.push(str_path.to_string());
-                            message = file_path.to_str().expect("wrong path").to_string();
+                            entries.push(file_path.clone());
                         } else if let Some(untracked) = &untracked {
                             for file in &untracked.files {
-                                let str_path = file.path.to_str().expect("wrong path");
-                                ignored.push(str_path.to_string());
+                                entries.push(file.path.clone());
                             }
                         }
-
-                        let mut settings =
-                            gio_settings.get::<HashMap<String, Vec<String>>>("ignored");
                         async move {
-                            let response =
-                                alert(DangerDialog("Hide Untracked files?".to_string(), message))
-                                    .choose_future(&window)
-                                    .await;
+                            let count = |include_ignored: bool| {
+                                let path = path.clone();
+                                let entries = entries.clone();
+                                gio::spawn_blocking(move || {
+                                    untracked_files(path, entries, include_ignored)
+                                })
+                            };
+                            let files = count(false).await.unwrap_or(Ok(Vec::new()));
+                            let all_files = count(true).await.unwrap_or(Ok(Vec::new()));
+                            let (files, all_files) = match (files, all_files) {
+                                (Ok(files), Ok(all_files)) => (files.len(), all_files.len()),
+                                (Err(e), _) | (_, Err(e)) => {
+                                    alert(e).present(Some(&window));
+                                    return;
+                                }
+                            };
+                            let mut message = format!("{} untracked files", files);
+                            if all_files > files {
+                                message.push_str(&format!(
+                                    "\n{} ignored files inside are kept",
+                                    all_files - files
+                                ));
+                            }
+                            let list_box = ListBox::builder()
+                                .selection_mode(SelectionMode::None)
+                                .css_classes(vec![String::from("boxed-list")])
+                                .build();
+                            // deleting from disk can't be undone, so it
+                            // must be chosen explicitly
+                            let hide = SwitchRow::builder()
+                                .title("Only hide from the list, keep files")
+                                .subtitle("switch off to delete files from disk")
+                                .css_classes(vec!["input_field"])
+                                .active(true)
+                                .build();
+                            list_box.append(&hide);
+                            let with_ignored = SwitchRow::builder()
+                                .title("Delete ignored files too")
+                                .css_classes(vec!["input_field"])
+                                .active(false)
+                                .visible(all_files > files)
+                                .sensitive(false)
+                                .build();
+                            list_box.append(&with_ignored);
+                            hide.connect_active_notify({
+                                let with_ignored = with_ignored.clone();
+                                move |row| {
+                                    with_ignored.set_sensitive(!row.is_active());
+                                }
+                            });
+                            let response = alert(DangerWithOptions(
+                                "Discard untracked files?".to_string(),
+                                message,
+                                list_box.upcast::<Widget>(),
+                            ))
+                            .choose_future(&window)
+                            .await;
                             if response != YES {
                                 return;
                             }
-                            let repo_path = path.expect("no path");
-                            let repo_path = repo_path.to_str().expect("wrong path");
-                            if let Some(stored) = settings.get_mut(repo_path) {
-                                stored.append(&mut ignored);
-                                trace!("added ignore {:?}", settings);
-                            } else {
-                                settings.insert(repo_path.to_string(), ignored);
-                                trace!("first ignored file {:?}", settings);
+                            if hide.is_active() {
+                                let mut settings =
+                                    gio_settings.get::<HashMap<String, Vec<String>>>("ignored");
+                                let mut ignored: Vec<String> = entries
+                                    .iter()
+                                    .map(|p| p.to_str().expect("wrong path").to_string())
+                                    .collect();
+                                let repo_path = path.to_str().expect("wrong path");
+                                if let Some(stored) = settings.get_mut(repo_path) {
+                                    stored.append(&mut ignored);
+                                    trace!("added ignore {:?}", settings);
+                                } else {
+                                    settings.insert(repo_path.to_string(), ignored);
+                                    trace!("first ignored file {:?}", settings);
+                                }
+                                gio_settings
+                                    .set("ignored", settings)
+                                    .expect("cant set settings");
+                                sender
+                                    .send_blocking(Event::Untracked(untracked))
+                                    .expect("Could not send through channel");
+                                return;
+                            }
+                            let include_ignored = with_ignored.is_active();
+                            // deleted files are stored as blobs, so undo
+                            // brings them back
+                            let result = gio::spawn_blocking({
+                                let sender = sender.clone();
+                                move || {
+                                    let files = untracked_files(
+                                        path.clone(),
+                                        entries.clone(),
+                                        include_ignored,
+                                    )?;
+                                    let snapshot = take_snapshot(&path, op, &files);
+                                    delete_untracked(path, entries, include_ignored, sender)
+                                        .map(|deleted| (deleted, snapshot))
+                                }
+                            })
+                            .await;
+                            match result {
+                                Ok(Ok((deleted, snapshot))) => {
+                                    undo_snapshot.replace(snapshot);
+                                    sender
+                                        .send_blocking(Event::Toast(format!(
+                                            "Deleted {} files",
+                                            deleted
+                                        )))
+                                        .expect("Could not send through channel");
+                                }
+                                Ok(Err(e)) => {
+                                    alert(e).present(Some(&window));
+                                }
+                                Err(e) => {
+                                    alert(format!("{:?}", e)).present(Some(&window));
+                                }
                             }
-                            gio_settings
-                                .set("ignored", settings)
-                                .expect("cant set settings");
-                            sender
-                                .send_blocking(Event::Untracked(untracked))
-                                .expect("Could not send through channel");
                         }
                     });
                 }
@@ -216,30 +457,79 @@ impl Status {
             },
             Some(DiffKind::Staged) | Some(DiffKind::Unstaged) => {
                 self.last_op.replace(current_op);
+                // files which content is lost on kill
+                let killed: Vec<PathBuf> = match (&file_path, &self.unstaged) {
+                    (Some(file_path), _) => vec![file_path.clone()],
+                    (None, Some(unstaged)) => {
+                        unstaged.files.iter().map(|f| f.path.clone()).collect()
+                    }
+                    (None, None) => Vec::new(),
+                };
+                let confirmation =
+                    if op == StageOp::Kill && gio_settings.get::<bool>(CONFIRM_KILL_TOKEN) {
+                        Some(self.kill_message(file_path.as_ref(), hunk_header.as_ref()))
+                    } else {
+                        None
+                    };
                 glib::spawn_future_local({
                     let window = window.clone();
-                    let path = self.path.clone();
+                    let path = self.path.clone().expect("no path");
                     let sender = self.sender.clone();
+                    let undo_snapshot = self.undo_snapshot.clone();
+                    let gio_settings = gio_settings.clone();
                     async move {
-                        gio::spawn_blocking({
+                        if let Some(message) = confirmation {
+                            let list_box = ListBox::builder()
+                                .selection_mode(SelectionMode::None)
+                                .css_classes(vec![String::from("boxed-list")])
+                                .build();
+                            let dont_ask = SwitchRow::builder()
+                                .title("Do not ask again")
+                                .css_classes(vec!["input_field"])
+                                .active(false)
+                                .build();
+                            list_box.append(&dont_ask);
+                            let response = alert(DangerWithOptions(
+                                "Discard changes?".to_string(),
+                                message,
+                                list_box.upcast::<Widget>(),
+                            ))
+                            .choose_future(&window)
+                            .await;
+                            if response != YES {
+                                return;
+                            }
+                            if dont_ask.is_active() {
+                                gio_settings
+                                    .set(CONFIRM_KILL_TOKEN, false)
+                                    .expect("cant set settings");
+                            }
+                        }
+                        let snapshot = gio::spawn_blocking({
                             move || {
-                                stage_via_apply(
-                                    path.expect("no path"),
-                                    file_path,
-                                    hunk_header,
-                                    op,
-                                    sender,
-                                )
+                                let snapshot = take_snapshot(&path, op, &killed);
+                                match (op, file_path, hunk_header) {
+                                    // whole file is checked out from index
+                                    (StageOp::Kill, Some(file_path), None) => {
+                                        discard_file(path, file_path, sender)
+                                    }
+                                    (op, file_path, hunk_header) => {
+                                        stage_via_apply(path, file_path, hunk_header, op, sender)
+                                    }
+                                }
+                                .map(|_| snapshot)
                             }
                         })
                         .await
                         .unwrap_or_else(|e| {
                             alert(format!("{:?}", e)).present(Some(&window));
-                            Ok(())
+                            Ok(None)
                         })
                         .unwrap_or_else(|e| {
                             alert(e).present(Some(&window));
+                            None
                         });
+                        undo_snapshot.replace(snapshot);
                     }
                 });
             }
@@ -362,6 +652,43 @@ impl Status {
                             true,
                         ),
                     };
+                // dry run before real op. if it fails, just go without preview
+                let conflicts = gio::spawn_blocking({
+                    let path = path.clone();
+                    let ofile_path = ofile_path.clone();
+                    move || {
+                        commit::apply_op_preview(
+                            path,
+                            oid,
+                            revert,
+                            if stash { 1 } else { 0 },
+                            ofile_path,
+                        )
+                    }
+                })
+                .await
+                .ok()
+                .and_then(|result| {
+                    result
+                        .inspect_err(|e| debug!("apply op preview error {:?}", e))
+                        .ok()
+                });
+                let body = match conflicts {
+                    Some(conflicts) if conflicts.is_empty() => {
+                        format!("{}\nNo conflicts expected", body)
+                    }
+                    Some(conflicts) => format!(
+                        "{}\n<span color=\"#ff0000\">Conflicts expected in:</span>\n{}",
+                        body,
+                        conflicts
+                            .iter()
+                            .map(|p| html_escape::encode_text(&p.to_string_lossy()).to_string())
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    ),
+                    None => body,
+                };
+
                 let no_commit = SwitchRow::builder()
                     .title("Only apply changes without commit")
                     .css_classes(vec!["input_field"])
@@ -443,7 +770,18 @@ impl Status {
                             );
                         }
                         match op {
-                            ApplyOp::Stash(_, num, _, _) => stash::apply(path, num, None, sender),
+                            ApplyOp::Stash(_, num, _, _) => {
+                                let conflicts = stash::apply(path, num, None, sender.clone())?;
+                                if !conflicts.is_empty() {
+                                    sender
+                                        .send_blocking(Event::Toast(format!(
+                                            "Stash applied with conflicts in {} files",
+                                            conflicts.len()
+                                        )))
+                                        .expect("cant send through channel");
+                                }
+                                Ok(())
+                            }
                             _ => commit::apply(path, oid, revert, None, no_commit, sender),
                         }
                         // if use_hunk {
@@ -556,12 +894,9 @@ impl Status {
                         .filter_map(|d| d.as_ref())
                     {
                         if diff.kind == render_diff_kind {
-                            for i in (0..file_idx + 1).rev() {
-                                if let Some(file) = diff.files.get(i) {
-                                    iter.set_line(file.view.line_no.get());
-                                    self.last_op.take();
-                                    break;
-                                }
+                            if let Some(file) = self.file_after_op(diff, *file_idx) {
+                                iter.set_line(file.view.line_no.get());
+                                self.last_op.take();
                             }
                         }
                     }
@@ -588,21 +923,14 @@ impl Status {
                         .filter_map(|d| d.as_ref())
                     {
                         if diff.kind == render_diff_kind {
-                            'found: for i in (0..file_idx + 1).rev() {
-                                if let Some(file) = diff.files.get(i) {
-                                    if file.view.is_expanded() {
-                                        for j in (0..hunk_ids + 1).rev() {
-                                            if let Some(hunk) = file.hunks.get(j) {
-                                                iter.set_line(hunk.view.line_no.get());
-                                                self.last_op.take();
-                                                break 'found;
-                                            }
-                                        }
+                            if let Some(file) = self.file_after_op(diff, *file_idx) {
+                                iter.set_line(file.view.line_no.get());
+                                if file.view.is_expanded() {
+                                    if let Some(hunk) = self.hunk_after_op(file, *hunk_ids) {
+                                        iter.set_line(hunk.view.line_no.get());
                                     }
-                                    iter.set_line(file.view.line_no.get());
-                                    self.last_op.take();
-                                    break;
                                 }
+                                self.last_op.take();
                             }
                         }
                     }
@@ -647,6 +975,33 @@ impl Status {
         iter
     }
 
+    /// Same file as was under cursor during op. If it is gone,
+    /// the one which follows it (files are sorted by path).
+    /// Without known path - by index, as it was before op.
+    fn file_after_op<'a>(&self, diff: &'a Diff, file_idx: usize) -> Option<&'a File> {
+        if let Some((path, _)) = &*self.last_op_target.borrow() {
+            return diff
+                .files
+                .iter()
+                .find(|f| &f.path >= path)
+                .or(diff.files.last());
+        }
+        (0..file_idx + 1).rev().find_map(|i| diff.files.get(i))
+    }
+
+    /// Same hunk by header (e.g. it was not touched by op),
+    /// otherwise the one which took place of staged hunk
+    fn hunk_after_op<'a>(&self, file: &'a File, hunk_idx: usize) -> Option<&'a Hunk> {
+        if let Some((path, Some(header))) = &*self.last_op_target.borrow() {
+            if &file.path == path {
+                if let Some(hunk) = file.hunks.iter().find(|h| &h.header == header) {
+                    return Some(hunk);
+                }
+            }
+        }
+        (0..hunk_idx + 1).rev().find_map(|j| file.hunks.get(j))
+    }
+
     fn put_cursor_on_opposite_diff(&self, render_diff_kind: DiffKind, iter: &mut TextIter) {
         // ONLY IF LAST_OP WAS NOT DROPPED BY PREVIOUS LOOP
         if let Some(op) = self.last_op.get() {
@@ -678,3 +1033,85 @@ impl Status {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, headers: &[&str]) -> File {
+        let mut file = File::new(DiffKind::Unstaged);
+        file.path = PathBuf::from(path);
+        for header in headers {
+            let mut hunk = Hunk::new(DiffKind::Unstaged);
+            hunk.header = header.to_string();
+            file.hunks.push(hunk);
+        }
+        file
+    }
+
+    #[test]
+    fn test_file_after_op() {
+        let (sender, _receiver) = async_channel::unbounded();
+        let status = Status::new(None, sender);
+        let mut diff = Diff::new(DiffKind::Unstaged);
+        diff.files.push(file("a.rs", &[]));
+        diff.files.push(file("c.rs", &[]));
+
+        // without target - by index
+        assert_eq!(
+            status.file_after_op(&diff, 0).unwrap().path,
+            Path::new("a.rs")
+        );
+        assert_eq!(
+            status.file_after_op(&diff, 5).unwrap().path,
+            Path::new("c.rs")
+        );
+
+        // file is gone. next one by path
+        status
+            .last_op_target
+            .replace(Some((PathBuf::from("b.rs"), None)));
+        assert_eq!(
+            status.file_after_op(&diff, 0).unwrap().path,
+            Path::new("c.rs")
+        );
+        status
+            .last_op_target
+            .replace(Some((PathBuf::from("d.rs"), None)));
+        assert_eq!(
+            status.file_after_op(&diff, 0).unwrap().path,
+            Path::new("c.rs")
+        );
+    }
+
+    #[test]
+    fn test_hunk_after_op() {
+        let (sender, _receiver) = async_channel::unbounded();
+        let status = Status::new(None, sender);
+        let file = file("a.rs", &["@@ -1,2 +1,2 @@", "@@ -10,2 +10,2 @@"]);
+
+        // same hunk, though its index is changed
+        status.last_op_target.replace(Some((
+            PathBuf::from("a.rs"),
+            Some(String::from("@@ -10,2 +10,2 @@")),
+        )));
+        assert_eq!(
+            status.hunk_after_op(&file, 0).unwrap().header,
+            "@@ -10,2 +10,2 @@"
+        );
+
+        // staged hunk is gone. one which took its place
+        status.last_op_target.replace(Some((
+            PathBuf::from("a.rs"),
+            Some(String::from("@@ -5,2 +5,2 @@")),
+        )));
+        assert_eq!(
+            status.hunk_after_op(&file, 0).unwrap().header,
+            "@@ -1,2 +1,2 @@"
+        );
+        assert_eq!(
+            status.hunk_after_op(&file, 3).unwrap().header,
+            "@@ -10,2 +10,2 @@"
+        );
+    }
+}
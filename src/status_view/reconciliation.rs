@@ -7,7 +7,6 @@ use crate::{Diff, File, Head, Hunk, Line, State};
 
 use gtk4::TextBuffer;
 use log::trace;
-use std::collections::HashSet;
 
 impl Hunk {
     // Hunk
@@ -23,9 +22,28 @@ impl Hunk {
         if self.header != rendered.header {
             self.view.dirty(true);
         }
+        let same_folds = self.folds.len() == rendered.folds.len()
+            && self
+                .folds
+                .iter()
+                .zip(rendered.folds.iter())
+                .all(|(new, old)| new.start == old.start && new.len == old.len);
+        if same_folds {
+            for (new, old) in self.folds.iter().zip(rendered.folds.iter()) {
+                new.folded.replace(old.folded.get());
+            }
+        }
         if !self.view.is_expanded() {
             return;
         }
+        if !same_folds {
+            // lines do not match their rendered views
+            // anymore. they will be rendered from scratch
+            rendered.lines.iter().for_each(|line| {
+                line.erase(buffer, context);
+            });
+            return;
+        }
         let mut last_rendered = 0;
         self.lines
             .iter()
@@ -290,29 +308,22 @@ impl Diff {
             self.files.len(),
             rendered.files.len(),
         );
-        let mut replaces_by_new = HashSet::new();
-        for file in &self.files {
-            for of in &rendered.files {
-                if file.path == of.path {
-                    file.enrich_view(of, buffer, context);
-                    replaces_by_new.insert(file.path.clone());
-                }
+        // rendered files are walked top to bottom: erase and
+        // enrich both shift lines below them. file keeps its
+        // view (e.g. expanded) while it is in diff. rename
+        // changes rendered title, so it is new file
+        for of in &rendered.files {
+            if let Some(file) = self
+                .files
+                .iter()
+                .find(|f| f.path == of.path && f.old_path == of.old_path)
+            {
+                file.enrich_view(of, buffer, context);
+            } else {
+                trace!("erase stale file {:?}", of.path);
+                of.erase(buffer, context);
             }
         }
-        // erase all stale views
-        trace!(
-            "before erasing files. replaced by new {:?} for total files count: {:?}",
-            replaces_by_new,
-            rendered.files.len()
-        );
-        rendered
-            .files
-            .iter()
-            .filter(|f| !replaces_by_new.contains(&f.path))
-            .for_each(|f| {
-                trace!("context on final lines of diff render view {:?}", context);
-                f.erase(buffer, context)
-            });
     }
 }
 
@@ -4,15 +4,17 @@
 
 use super::Status;
 use crate::dialogs::{alert, confirm_dialog_factory, PROCEED};
-use crate::git::remote;
+use crate::git::{remote, repo_settings};
+use crate::status_view::headerbar::SSH_KEY_TOKEN;
 use gtk4::{gio, glib, Button, ListBox, SelectionMode, StringList};
 use libadwaita::prelude::*;
 use libadwaita::{
-    ApplicationWindow, ComboRow, EntryRow, PasswordEntryRow, PreferencesDialog, PreferencesGroup,
-    PreferencesPage, SwitchRow,
+    ActionRow, ApplicationWindow, ComboRow, EntryRow, PasswordEntryRow, PreferencesDialog,
+    PreferencesGroup, PreferencesPage, SwitchRow,
 };
 
-use crate::LoginPassword;
+use crate::{Event, LoginPassword};
+use async_channel::Sender;
 use std::cell::{Cell, RefCell};
 use std::path::Path;
 use std::rc::Rc;
@@ -24,6 +26,7 @@ impl remote::RemoteDetail {
         page: &PreferencesPage,
         path: &Path,
         window: &ApplicationWindow,
+        sender: Sender<Event>,
     ) -> PreferencesGroup {
         let remote_name = Rc::new(RefCell::new(self.name.clone()));
 
@@ -38,6 +41,7 @@ impl remote::RemoteDetail {
             let group = group.clone();
             let page = page.clone();
             let remote_name = remote_name.clone();
+            let sender = sender.clone();
             move |_| {
                 glib::spawn_future_local({
                     let path = path.clone();
@@ -45,10 +49,11 @@ impl remote::RemoteDetail {
                     let group = group.clone();
                     let page = page.clone();
                     let remote_name = remote_name.clone();
+                    let sender = sender.clone();
                     async move {
                         let remote_name = (*(remote_name.borrow())).clone();
                         let deleted = gio::spawn_blocking(move || {
-                            remote::delete(path.to_path_buf(), remote_name)
+                            remote::delete(path.to_path_buf(), remote_name, sender)
                         })
                         .await
                         .unwrap_or_else(|e| {
@@ -77,6 +82,7 @@ impl remote::RemoteDetail {
             let window = window.clone();
             let remote_name = remote_name.clone();
             let group = group.clone();
+            let sender = sender.clone();
             move |row| {
                 let mut remote = remote.clone();
                 remote.name = row.text().to_string();
@@ -85,11 +91,12 @@ impl remote::RemoteDetail {
                     let window = window.clone();
                     let remote_name = remote_name.clone();
                     let group = group.clone();
+                    let sender = sender.clone();
                     async move {
                         let remote_to_edit = (*(remote_name.borrow())).clone();
                         let new_remote = gio::spawn_blocking({
                             let path = path.clone();
-                            move || remote::edit(path.to_path_buf(), remote_to_edit, remote)
+                            move || remote::edit(path.to_path_buf(), remote_to_edit, remote, sender)
                         })
                         .await
                         .unwrap_or_else(|e| {
@@ -127,11 +134,12 @@ impl remote::RemoteDetail {
                     let path = path.clone();
                     let window = window.clone();
                     let remote_name = remote_name.clone();
+                    let sender = sender.clone();
                     async move {
                         gio::spawn_blocking({
                             let path = path.clone();
                             let remote_to_edit = (*(remote_name.borrow())).clone();
-                            move || remote::edit(path.to_path_buf(), remote_to_edit, edited)
+                            move || remote::edit(path.to_path_buf(), remote_to_edit, edited, sender)
                         })
                         .await
                         .unwrap_or_else(|e| {
@@ -147,6 +155,44 @@ impl remote::RemoteDetail {
             }
         });
         group.add(&row);
+
+        let forget_button = Button::builder()
+            .label("Forget")
+            .valign(gtk4::Align::Center)
+            .build();
+        let credentials_row = ActionRow::builder()
+            .title("Saved credentials")
+            .subtitle("User name and password from keyring")
+            .build();
+        credentials_row.add_suffix(&forget_button);
+        forget_button.connect_clicked({
+            let path = path.to_path_buf();
+            let window = window.clone();
+            let remote_name = remote_name.clone();
+            move |button| {
+                glib::spawn_future_local({
+                    let path = path.clone();
+                    let window = window.clone();
+                    let button = button.clone();
+                    let remote_name = (*(remote_name.borrow())).clone();
+                    async move {
+                        let forgotten = gio::spawn_blocking(move || {
+                            remote::forget_credentials(path, remote_name)
+                        })
+                        .await
+                        .unwrap_or_else(|e| {
+                            alert(format!("{:?}", e)).present(Some(&window));
+                            Ok(())
+                        });
+                        match forgotten {
+                            Ok(()) => button.set_sensitive(false),
+                            Err(e) => alert(e).present(Some(&window)),
+                        }
+                    }
+                });
+            }
+        });
+        group.add(&credentials_row);
         group
     }
 }
@@ -155,6 +201,7 @@ fn remote_adding(
     page: &PreferencesPage,
     path: &Path,
     window: &ApplicationWindow,
+    sender: Sender<Event>,
 ) -> PreferencesGroup {
     let add_button = Button::builder().icon_name("list-add-symbolic").build();
     let adding = PreferencesGroup::builder()
@@ -185,10 +232,12 @@ fn remote_adding(
                     let window = window.clone();
                     let page = page.clone();
                     let adding = adding.clone();
+                    let sender = sender.clone();
                     async move {
                         let remote = gio::spawn_blocking({
                             let path = path.clone();
-                            move || remote::add(path, name.to_string(), url.to_string())
+                            let sender = sender.clone();
+                            move || remote::add(path, name.to_string(), url.to_string(), sender)
                         })
                         .await
                         .unwrap_or_else(|e| {
@@ -201,9 +250,9 @@ fn remote_adding(
                         });
                         if let Some(remote) = remote {
                             page.remove(&adding);
-                            let group = remote.render(&page, &path, &window);
+                            let group = remote.render(&page, &path, &window, sender.clone());
                             page.add(&group);
-                            page.add(&remote_adding(&page, &path, &window));
+                            page.add(&remote_adding(&page, &path, &window, sender));
                         }
                     }
                 });
@@ -382,9 +431,11 @@ impl Status {
         None
     }
 
-    pub fn show_remotes_dialog(&self, window: &ApplicationWindow) {
+    pub fn show_remotes_dialog(&self, window: &ApplicationWindow, settings: &gio::Settings) {
         let window = window.clone();
         let path = self.path.clone().unwrap();
+        let sender = self.sender.clone();
+        let settings = settings.clone();
         glib::spawn_future_local({
             async move {
                 let remotes = gio::spawn_blocking({
@@ -411,12 +462,13 @@ impl Status {
                     .title("Remotes")
                     .icon_name("network-server-symbolic")
                     .build();
+                page.add(&ssh_key_group(&path, &settings));
                 for remote in &remotes {
-                    let group = remote.render(&page, &path, &window);
+                    let group = remote.render(&page, &path, &window, sender.clone());
                     page.add(&group);
                 }
 
-                let adding = remote_adding(&page, &path, &window);
+                let adding = remote_adding(&page, &path, &window, sender);
 
                 page.add(&adding);
                 dialog.add(&page);
@@ -426,6 +478,34 @@ impl Status {
     }
 }
 
+// agent is tried first. key from settings is used when agent fails
+fn ssh_key_group(path: &Path, settings: &gio::Settings) -> PreferencesGroup {
+    let path = Some(path.to_path_buf());
+    let group = PreferencesGroup::builder()
+        .title("SSH")
+        .description("Private key is used when ssh agent fails to authorize")
+        .build();
+    let row = EntryRow::builder()
+        .title("Private key path")
+        .text(repo_settings::get_string(
+            path.as_ref(),
+            settings,
+            SSH_KEY_TOKEN,
+        ))
+        .show_apply_button(true)
+        .build();
+    row.connect_apply({
+        let settings = settings.clone();
+        move |row| {
+            let key = row.text().trim().to_string();
+            repo_settings::set_string(path.as_ref(), &settings, SSH_KEY_TOKEN, key);
+            repo_settings::apply(path.as_ref(), &settings);
+        }
+    });
+    group.add(&row);
+    group
+}
+
 pub fn auth(auth_request: Arc<(Mutex<LoginPassword>, Condvar)>, window: &impl IsA<gtk4::Widget>) {
     let lb = ListBox::builder()
         .selection_mode(SelectionMode::None)
@@ -441,10 +521,36 @@ pub fn auth(auth_request: Arc<(Mutex<LoginPassword>, Condvar)>, window: &impl Is
         .css_classes(vec!["input_field"])
         .build();
 
-    lb.append(&user_name);
-    lb.append(&password);
+    let remember = SwitchRow::builder()
+        .title("Remember user name")
+        .active(false)
+        .build();
+    let save_password = SwitchRow::builder()
+        .title("Save password in keyring")
+        .active(false)
+        .build();
+    let (key, login) = {
+        let login_pass = auth_request.0.lock().unwrap();
+        (login_pass.key.clone(), login_pass.login.clone())
+    };
+    let heading = if let Some(key) = key {
+        // only passphrase for ssh key is required
+        password.set_title(&format!("Passphrase for {}:", key));
+        lb.append(&password);
+        "Passphrase required"
+    } else {
+        if !login.is_empty() {
+            user_name.set_text(&login);
+            remember.set_active(true);
+        }
+        lb.append(&user_name);
+        lb.append(&password);
+        lb.append(&remember);
+        lb.append(&save_password);
+        "Login required"
+    };
 
-    let dialog = confirm_dialog_factory(Some(&lb), "Login required", "Proceed");
+    let dialog = confirm_dialog_factory(Some(&lb), heading, "Proceed");
     glib::spawn_future_local({
         let window = window.clone();
         async move {
@@ -454,6 +560,8 @@ pub fn auth(auth_request: Arc<(Mutex<LoginPassword>, Condvar)>, window: &impl Is
             let mut login_pass = auth_request.0.lock().unwrap();
             login_pass.login = login.into();
             login_pass.password = password.into();
+            login_pass.remember = remember.is_active();
+            login_pass.save_password = save_password.is_active();
             login_pass.pending = false;
             if result != PROCEED {
                 login_pass.cancel = true;
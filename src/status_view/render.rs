@@ -2,10 +2,12 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::git::ContextFold;
 use crate::status_view::stage_view::cursor_to_line_offset;
 use crate::status_view::tags;
 use crate::status_view::view::{View, ViewState};
 use crate::status_view::Label;
+use crate::syntax;
 use crate::{
     Diff,
     DiffKind,
@@ -322,6 +324,9 @@ pub trait ViewContainer {
                 }
             });
         } else if v.is_expanded() && v.is_rendered() {
+            if self.expand_inside(line_no) {
+                return Some(line_no);
+            }
             // go deeper for self.children
             for child in self.get_children() {
                 found_line = child.expand(line_no, context);
@@ -341,6 +346,12 @@ pub trait ViewContainer {
         false
     }
 
+    // expands/collapses something within self, which is not
+    // a child, e.g. folded context lines in hunk
+    fn expand_inside(&self, _line_no: i32) -> bool {
+        false
+    }
+
     fn erase(&self, buffer: &TextBuffer, context: &mut StatusRenderContext) {
         // CAUTION. ATTENTION. IMPORTANT
         // !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
@@ -487,9 +498,6 @@ impl ViewContainer for Diff {
 
     // Diff
     fn expand(&self, line_no: i32, context: &mut StatusRenderContext) -> Option<i32> {
-        if self.kind == DiffKind::Untracked {
-            return None;
-        }
         let mut result: Option<i32> = None;
         let expand_all = self.get_view().is_rendered_in(line_no);
         if expand_all {
@@ -576,6 +584,9 @@ impl ViewContainer for File {
         if self.status == git2::Delta::Deleted {
             buffer.insert(iter, "- ");
         }
+        if let Some(old_path) = &self.old_path {
+            buffer.insert(iter, &format!("{} → ", old_path.to_str().unwrap()));
+        }
         buffer.insert(iter, self.path.to_str().unwrap());
     }
 
@@ -654,6 +665,10 @@ impl ViewContainer for Hunk {
         buffer: &TextBuffer,
         _context: &mut StatusRenderContext<'_>,
     ) {
+        if self.is_mode_change() {
+            buffer.insert(iter, &self.header);
+            return;
+        }
         let parts: Vec<&str> = self.header.split("@@").collect();
         let scope = parts.last().unwrap();
         buffer.insert(iter, "Line ");
@@ -668,18 +683,56 @@ impl ViewContainer for Hunk {
     }
 
     fn get_children(&self) -> Vec<&dyn ViewContainer> {
+        // folded away lines are still children while rendered:
+        // they have to be deleted from buffer
         self.lines
             .iter()
-            .filter(|l| {
+            .enumerate()
+            .filter(|(i, l)| {
                 !matches!(
                     l.origin,
                     DiffLineType::FileHeader | DiffLineType::HunkHeader
-                )
+                ) && (l.view.is_rendered() || !self.is_folded_away(*i))
             })
-            .map(|vh| vh as &dyn ViewContainer)
+            .map(|(_, vh)| vh as &dyn ViewContainer)
             .collect()
     }
 
+    // Hunk
+    // lines are passed with their index in hunk, not in children:
+    // cursor position points into hunk.lines
+    fn search_cursor_position<'a>(
+        &'a self,
+        line_no: i32,
+        parent_index: usize,
+        context: &mut StatusRenderContext<'a>,
+    ) -> bool {
+        if self.view.is_rendered_in(line_no) {
+            self.fill_selected(context, parent_index);
+            return true;
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            if line.search_cursor_position(line_no, i, context) {
+                self.fill_selected(context, parent_index);
+                return true;
+            }
+        }
+        false
+    }
+
+    // Hunk
+    fn expand_inside(&self, line_no: i32) -> bool {
+        if let Some(fold) = self
+            .folds
+            .iter()
+            .find(|f| self.lines[f.start].view.is_rendered_in(line_no))
+        {
+            self.set_folded(fold, !fold.folded.get());
+            return true;
+        }
+        false
+    }
+
     // Hunk
     fn prepare_context<'a>(&'a self, ctx: &mut StatusRenderContext<'a>, _line_no: Option<i32>) {
         ctx.current_hunk = Some(self);
@@ -769,7 +822,13 @@ impl ViewContainer for Line {
                 .map(|num| num.as_i32())
                 .unwrap_or(self.old_line_no.map(|num| num.as_i32()).unwrap_or(0));
 
+            let folded = ctx
+                .current_hunk
+                .is_some_and(|hunk| hunk.folded_at(self).is_some());
             let line_no_text = match self.origin {
+                _ if folded => String::new(),
+                // "\ No newline at end of file" has no number in file
+                _ if self.kind == LineKind::NoNewlineAtEof => String::new(),
                 DiffLineType::Deletion => match line_no {
                     0..10 => "-".to_string(),
                     10..100 => " -".to_string(),
@@ -874,7 +933,15 @@ impl ViewContainer for Line {
         buffer: &TextBuffer,
         context: &mut StatusRenderContext<'_>,
     ) {
-        let content = self.content(context.current_hunk.unwrap());
+        let hunk = context.current_hunk.unwrap();
+        if let Some(folded) = hunk.folded_at(self) {
+            buffer.insert(
+                iter,
+                &format!("{}… {} unchanged lines …", LINENO_MARGIN, folded),
+            );
+            return;
+        }
+        let content = self.content(hunk);
         if content.is_empty() {
             // when add or delete single line, mark it somehow to be visible
             match (self.origin, context.previous_line.map(|l| l.origin)) {
@@ -889,7 +956,15 @@ impl ViewContainer for Line {
         } else {
             // MARGIN FOR LINENO
             buffer.insert(iter, LINENO_MARGIN);
-            buffer.insert(iter, content);
+            if content.contains('\r') {
+                // stray CR (file with mixed eols, or eol filter was not
+                // applied) is a line break for TextBuffer. it will shift
+                // all lines below against their numbers in gutter.
+                // same length replacement keeps char offsets for highlights
+                buffer.insert(iter, &content.replace('\r', " "));
+            } else {
+                buffer.insert(iter, content);
+            }
         }
     }
 
@@ -903,6 +978,23 @@ impl ViewContainer for Line {
         let (mut start_iter, end_iter) = self.start_end_iters(buffer, self.view.line_no.get());
         let start_offset = start_iter.offset();
         let hunk = context.current_hunk.unwrap();
+        if hunk.folded_at(self).is_some() {
+            // fold marker has no syntax or spaces to highlight
+            match tag_changes {
+                TagChanges::Render => self.add_tag(buffer, self.choose_tag().0, None),
+                TagChanges::BecomeActive(is_active) => {
+                    self.remove_tag(buffer, self.choose_tag().0);
+                    self.remove_tag(buffer, self.choose_tag().enhance().0);
+                    if is_active {
+                        self.add_tag(buffer, self.choose_tag().enhance().0, None);
+                    } else {
+                        self.add_tag(buffer, self.choose_tag().0, None);
+                    }
+                }
+                TagChanges::BecomeCurrent(_) => {}
+            }
+            return;
+        }
         match tag_changes {
             TagChanges::Render => {
                 // highlight spaces
@@ -972,33 +1064,28 @@ impl ViewContainer for Line {
             TagChanges::BecomeActive(is_active) => {
                 self.remove_tag(buffer, self.choose_tag().0);
                 self.remove_tag(buffer, self.choose_tag().enhance().0);
-                self.remove_tag(buffer, self.choose_syntax_tag().0);
-                self.remove_tag(buffer, self.choose_syntax_tag().enhance().0);
-                self.remove_tag(buffer, self.choose_syntax_1_tag().0);
-                self.remove_tag(buffer, self.choose_syntax_1_tag().enhance().0);
-
-                if is_active {
-                    self.fill_syntax_tags(
-                        self.choose_syntax_tag().enhance().0,
-                        &hunk.keyword_ranges,
-                        buffer,
-                        start_offset,
-                    );
-                    self.fill_syntax_tags(
-                        self.choose_syntax_1_tag().enhance().0,
-                        &hunk.identifier_ranges,
-                        buffer,
-                        start_offset,
-                    );
-                } else {
+                // nothing to remove or fill when highlighting is off
+                if syntax::is_enabled() {
+                    self.remove_tag(buffer, self.choose_syntax_tag().0);
+                    self.remove_tag(buffer, self.choose_syntax_tag().enhance().0);
+                    self.remove_tag(buffer, self.choose_syntax_1_tag().0);
+                    self.remove_tag(buffer, self.choose_syntax_1_tag().enhance().0);
+                    let (keyword_tag, identifier_tag) = if is_active {
+                        (
+                            self.choose_syntax_tag().enhance(),
+                            self.choose_syntax_1_tag().enhance(),
+                        )
+                    } else {
+                        (self.choose_syntax_tag(), self.choose_syntax_1_tag())
+                    };
                     self.fill_syntax_tags(
-                        self.choose_syntax_tag().0,
+                        keyword_tag.0,
                         &hunk.keyword_ranges,
                         buffer,
                         start_offset,
                     );
                     self.fill_syntax_tags(
-                        self.choose_syntax_1_tag().0,
+                        identifier_tag.0,
                         &hunk.identifier_ranges,
                         buffer,
                         start_offset,
@@ -1077,30 +1164,33 @@ impl ViewContainer for Head {
         buffer: &TextBuffer,
         _context: &mut StatusRenderContext<'_>,
     ) {
-        let title = {
-            if let Some(branch_data) = &self.branch {
-                branch_data.name.to_string()
-            } else {
-                "Detached head".to_string()
-            }
-        };
         let short = self.oid.to_string()[..7].to_string();
         let color = if StyleManager::default().is_dark() {
             "#839daf"
         } else {
             "#4a708b"
         };
+        // detached head is easy to miss, while commits made
+        // there are easy to lose
+        let title = if let Some(branch_data) = &self.branch {
+            format!(
+                "<span color=\"{}\">{}</span>",
+                color,
+                html_escape::encode_text(&branch_data.name.to_string())
+            )
+        } else {
+            "<span color=\"#ff0000\" weight=\"bold\">Detached head</span>".to_string()
+        };
         buffer.insert_markup(
             iter,
             &format!(
-                "{} <span color=\"#1C71D8\">{}</span> <span color=\"{}\">{}</span> {}",
+                "{} <span color=\"#1C71D8\">{}</span> {} {}",
                 if !self.is_upstream {
                     "Head:     "
                 } else {
                     "Upstream: "
                 },
                 short,
-                color,
                 title,
                 self.log_message
             ),
@@ -1166,6 +1256,9 @@ impl ViewContainer for State {
             }
             RepositoryState::Bisect => {
                 buffer.insert_markup(iter, "<span color=\"#ff0000\">Bisect</span>");
+                if !self.subject.is_empty() {
+                    buffer.insert(iter, &format!(" {}", self.subject));
+                }
             }
             RepositoryState::Rebase => {
                 buffer.insert_markup(iter, "<span color=\"#ff0000\">Rebase</span>");
@@ -1197,6 +1290,39 @@ impl Diff {
     }
 }
 
+impl Hunk {
+    // marker line is rewritten in place. hidden lines are
+    // deleted or inserted on next render
+    fn set_folded(&self, fold: &ContextFold, folded: bool) {
+        fold.folded.replace(folded);
+        let marker = &self.lines[fold.start].view;
+        marker.dirty(true);
+        marker.transfer(true);
+        for line in &self.lines[fold.start + 1..fold.start + fold.len] {
+            if folded {
+                line.view.squash(true);
+            } else {
+                line.view.squash(false);
+                line.view.render(false);
+            }
+        }
+        self.view.child_dirty(true);
+    }
+
+    /// Unfolds context which hides line, e.g. to put cursor on it.
+    pub fn reveal(&self, line_idx: usize) -> bool {
+        if let Some(fold) = self
+            .folds
+            .iter()
+            .find(|f| f.folded.get() && line_idx >= f.start && line_idx < f.start + f.len)
+        {
+            self.set_folded(fold, false);
+            return true;
+        }
+        false
+    }
+}
+
 impl Line {
     fn choose_tag(&self) -> tags::Tag {
         match self.origin {
@@ -1227,6 +1353,9 @@ impl Line {
         buffer: &TextBuffer,
         start_offset: i32,
     ) {
+        if ranges.is_empty() {
+            return;
+        }
         for (start, end) in self.byte_indexes_to_char_indexes(ranges) {
             // MARGIN FOR LINENO
             let line_no_margin = 4;
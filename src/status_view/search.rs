@@ -0,0 +1,155 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::stage_view::StageView;
+use super::{CursorPosition, Status};
+use crate::status_view::context::StatusRenderContext;
+use crate::status_view::tags;
+use crate::{DiffKind, SearchOp};
+use gtk4::prelude::*;
+use gtk4::TextBuffer;
+
+// (diff, file, hunk, line) in the order diffs are rendered
+type Match = (usize, usize, usize, usize);
+
+fn diff_order(kind: DiffKind) -> usize {
+    match kind {
+        DiffKind::Conflicted => 0,
+        DiffKind::Unstaged => 1,
+        DiffKind::Staged => 2,
+        _ => 3,
+    }
+}
+
+fn clear_highlights(buffer: &TextBuffer) {
+    let (start, end) = buffer.bounds();
+    buffer.remove_tag_by_name(tags::SEARCH, &start, &end);
+}
+
+// all occurrences of term in rendered line
+fn highlight_line(buffer: &TextBuffer, line_no: i32, term: &str) {
+    let Some(start) = buffer.iter_at_line(line_no) else {
+        return;
+    };
+    let mut end = start;
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+    let text = buffer.text(&start, &end, true).to_lowercase();
+    let term_chars = term.chars().count() as i32;
+    for (idx, _) in text.match_indices(term) {
+        let offset = text[..idx].chars().count() as i32;
+        let mut from = start;
+        from.forward_chars(offset);
+        let mut to = from;
+        to.forward_chars(term_chars);
+        buffer.apply_tag_by_name(tags::SEARCH, &from, &to);
+    }
+}
+
+impl Status {
+    fn diff_by_order(&self, order: usize) -> Option<&crate::Diff> {
+        [&self.conflicted, &self.unstaged, &self.staged]
+            .into_iter()
+            .flatten()
+            .find(|diff| diff_order(diff.kind) == order)
+    }
+
+    fn search_matches(&self, term: &str) -> Vec<Match> {
+        [&self.conflicted, &self.unstaged, &self.staged]
+            .into_iter()
+            .flatten()
+            .flat_map(|diff| {
+                let d = diff_order(diff.kind);
+                diff.files.iter().enumerate().flat_map(move |(f, file)| {
+                    file.hunks.iter().enumerate().flat_map(move |(h, hunk)| {
+                        hunk.lines
+                            .iter()
+                            .enumerate()
+                            .filter(move |(_, line)| {
+                                line.content(hunk).to_lowercase().contains(term)
+                            })
+                            .map(move |(l, _)| (d, f, h, l))
+                    })
+                })
+            })
+            .collect()
+    }
+
+    fn highlight_matches(&self, buffer: &TextBuffer, matches: &[Match], term: &str) {
+        clear_highlights(buffer);
+        for (d, f, h, l) in matches {
+            let Some(diff) = self.diff_by_order(*d) else {
+                continue;
+            };
+            let file = &diff.files[*f];
+            let hunk = &file.hunks[*h];
+            let line = &hunk.lines[*l];
+            if file.view.is_expanded() && hunk.view.is_expanded() && line.view.is_rendered() {
+                highlight_line(buffer, line.view.line_no.get(), term);
+            }
+        }
+    }
+
+    /// Moves cursor to next/previous line in staged/unstaged/conflicted
+    /// diffs containing term (case insensitive) and highlights all
+    /// visible matches. Collapsed file with match is expanded.
+    pub fn search<'a>(
+        &'a self,
+        txt: &StageView,
+        op: SearchOp,
+        term: &str,
+        context: &mut StatusRenderContext<'a>,
+    ) {
+        let buffer = txt.buffer();
+        let term = term.to_lowercase();
+        if op == SearchOp::Close || term.is_empty() {
+            clear_highlights(&buffer);
+            return;
+        }
+        let matches = self.search_matches(&term);
+        if matches.is_empty() {
+            clear_highlights(&buffer);
+            return;
+        }
+        let current = match self.cursor_position.get() {
+            CursorPosition::CursorDiff(kind) => Some((diff_order(kind), 0, 0, 0)),
+            CursorPosition::CursorFile(kind, f) => Some((diff_order(kind), f, 0, 0)),
+            CursorPosition::CursorHunk(kind, f, h) => Some((diff_order(kind), f, h, 0)),
+            CursorPosition::CursorLine(kind, f, h, l) => Some((diff_order(kind), f, h, l)),
+            CursorPosition::None => None,
+        };
+        let target = match (current, op) {
+            // while typing stay on current line if it still matches
+            (Some(current), SearchOp::Changed) => matches.iter().find(|m| **m >= current),
+            (Some(current), SearchOp::Next) => matches.iter().find(|m| **m > current),
+            (Some(current), SearchOp::Previous) => matches.iter().rev().find(|m| **m < current),
+            _ => None,
+        };
+        let target = match (target, op) {
+            (Some(target), _) => *target,
+            (None, SearchOp::Previous) => matches[matches.len() - 1],
+            (None, _) => matches[0],
+        };
+        let (d, f, h, l) = target;
+        let Some(diff) = self.diff_by_order(d) else {
+            return;
+        };
+        let file = &diff.files[f];
+        let hunk = &file.hunks[h];
+        if !file.view.is_expanded() || !hunk.view.is_expanded() {
+            file.view.expand(true);
+            hunk.view.expand(true);
+            hunk.reveal(l);
+            self.render(txt, Some(diff.kind), context);
+        } else if hunk.reveal(l) {
+            self.render(txt, Some(diff.kind), context);
+        }
+        self.highlight_matches(&buffer, &matches, &term);
+        if let Some(mut iter) = buffer.iter_at_line(hunk.lines[l].view.line_no.get()) {
+            buffer.place_cursor(&iter);
+            txt.scroll_to_iter(&mut iter, 0.0, false, 0.0, 0.0);
+        }
+    }
+}
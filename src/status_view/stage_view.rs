@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::keymap;
 use crate::status_view::context::StatusRenderContext;
 use crate::status_view::tags;
 use crate::{DARK_CLASS, LIGHT_CLASS};
@@ -12,13 +13,14 @@ use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{
     gdk, glib, pango::Underline, EventControllerKey, EventControllerMotion, EventSequenceState,
-    GestureClick, GestureDrag, MovementStep, TextBuffer, TextIter, TextTag, TextView,
+    GestureClick, GestureDrag, MovementStep, TextBuffer, TextIter, TextTag, TextTagTable, TextView,
     TextWindowType, Widget,
 };
 use libadwaita::StyleManager;
 use log::trace;
 
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 
 glib::wrapper! {
@@ -29,6 +31,7 @@ glib::wrapper! {
 
 mod stage_view_internal {
 
+    use crate::status_view::tags;
     use crate::LineKind;
     use git2::DiffLineType;
     use gtk4::prelude::*;
@@ -39,13 +42,13 @@ mod stage_view_internal {
     use std::str::FromStr;
 
     // #cce0f8/23374f - 204/255 224/255 248/255  35 55 79
-    const LIGHT_CURSOR: gdk::RGBA = gdk::RGBA::new(0.80, 0.878, 0.972, 1.0);
+    pub const LIGHT_CURSOR: gdk::RGBA = gdk::RGBA::new(0.80, 0.878, 0.972, 1.0);
 
     // super bright!
     // const DARK_CURSOR: gdk::RGBA = gdk::RGBA::new(0.101, 0.294, 0.526, 1.0);
     // also bright
     // const DARK_CURSOR: gdk::RGBA = gdk::RGBA::new(0.166, 0.329, 0.525, 1.0);
-    const DARK_CURSOR: gdk::RGBA = gdk::RGBA::new(0.094, 0.257, 0.454, 1.0);
+    pub const DARK_CURSOR: gdk::RGBA = gdk::RGBA::new(0.094, 0.257, 0.454, 1.0);
 
     const DARK_BG_FILL: gdk::RGBA = gdk::RGBA::new(0.139, 0.139, 0.139, 1.0);
     const LIGHT_BG_FILL: gdk::RGBA = gdk::RGBA::new(1.0, 1.0, 1.0, 1.0);
@@ -72,6 +75,8 @@ mod stage_view_internal {
         // TODO! put it here!
         pub is_dark: Cell<bool>,
         pub is_dark_set: Cell<bool>,
+
+        pub color_tags: RefCell<Vec<tags::ColorTag>>,
         // #[property(get, set)]
         // pub current_line: RefCell<i32>,
     }
@@ -315,6 +320,19 @@ impl StageView {
         self.imp().is_dark_set.replace(true);
     }
 
+    /// User palette is applied to existing tags, so already rendered
+    /// text changes colors without rerender.
+    pub fn set_diff_colors(&self, diff_colors: &HashMap<String, String>) {
+        self.imp()
+            .color_tags
+            .replace(palette(diff_colors).color_tags());
+        toggle_colors(
+            &self.buffer().tag_table(),
+            &self.imp().color_tags.borrow(),
+            StyleManager::default().is_dark(),
+        );
+    }
+
     pub fn set_cursor_highlight(&self, value: bool) {
         self.imp().show_cursor.replace(value);
     }
@@ -388,7 +406,35 @@ impl EmptyLayoutManager {
     }
 }
 
-pub fn factory(sndr: Sender<crate::Event>, name: &str) -> StageView {
+// lowest contrast of default colors over cursor highlight is
+// dark red (~1.4). anything lower is hard to read
+const MIN_CURSOR_CONTRAST: f32 = 1.3;
+
+fn palette(diff_colors: &HashMap<String, String>) -> tags::Palette {
+    tags::Palette::new(diff_colors, |hex, is_dark| {
+        let cursor = if is_dark {
+            stage_view_internal::DARK_CURSOR
+        } else {
+            stage_view_internal::LIGHT_CURSOR
+        };
+        tags::Color::contrast(hex, (cursor.red(), cursor.green(), cursor.blue()))
+            >= MIN_CURSOR_CONTRAST
+    })
+}
+
+fn toggle_colors(table: &TextTagTable, color_tags: &[tags::ColorTag], is_dark: bool) {
+    for color_tag in color_tags {
+        if let Some(tag) = table.lookup(color_tag.0 .0) {
+            color_tag.toggle(&tag, is_dark);
+        }
+    }
+}
+
+pub fn factory(
+    sndr: Sender<crate::Event>,
+    name: &str,
+    diff_colors: &HashMap<String, String>,
+) -> StageView {
     let manager = StyleManager::default();
     let is_dark = manager.is_dark();
 
@@ -410,117 +456,15 @@ pub fn factory(sndr: Sender<crate::Event>, name: &str) -> StageView {
     let buffer = txt.buffer();
     let table = buffer.tag_table();
 
-    // ----------------- COLORS -------------------------------------
-    // Dark theme - first color on tuple. Light - last one.
-    let green = tags::Color(("#4a8e09".to_string(), "#10ac64".to_string())); //
-    let red = tags::Color(("#a51d2d".to_string(), "#c01c28".to_string()));
-    // in terms of dark theme: white  - is white font on black backgroud. grey is near white.
-    // in terms of light theme: black - is black font on white color. greay is near black
-    let grey = tags::Color(("#999999".to_string(), "#555555".to_string()));
-
-    let diff_color = tags::Color(("#a78a44".to_string(), "#8b6508".to_string()));
-    let conflict_color = tags::Color(("#ff0000".to_string(), "#ff0000".to_string()));
-
-    // ----------------- COLORS -------------------------------------
-
-    let diff = tags::ColorTag((tags::DIFF, diff_color));
-    let diff_tag = diff.create(&table, is_dark);
+    let color_tags = palette(diff_colors).color_tags();
+    for color_tag in &color_tags {
+        color_tag.create(&table, is_dark);
+    }
+    txt.imp().color_tags.replace(color_tags);
+    let diff_tag = table.lookup(tags::DIFF).unwrap();
     diff_tag.set_weight(700);
     diff_tag.set_pixels_above_lines(32);
 
-    let conflict_marker = tags::ColorTag((tags::CONFLICT_MARKER, conflict_color.clone()));
-    let conflict_marker_tag = conflict_marker.create(&table, is_dark);
-
-    let spaces_added = tags::ColorTag((tags::SPACES_ADDED, green.clone()));
-    let spaces_added_tag = spaces_added.create(&table, is_dark);
-
-    let spaces_removed = tags::ColorTag((tags::SPACES_REMOVED, red.clone()));
-    let spaces_removed_tag = spaces_removed.create(&table, is_dark);
-
-    let added = tags::ColorTag((tags::ADDED, green.clone()));
-    let added_tag = added.create(&table, is_dark);
-
-    let enhanced_added = tags::ColorTag((
-        tags::ENHANCED_ADDED,
-        green.from_hsl(tags::HslAdjustment::Enhance),
-    ));
-    let enhanced_added_tag = enhanced_added.create(&table, is_dark);
-
-    let removed = tags::ColorTag((tags::REMOVED, red.clone()));
-    let removed_tag = removed.create(&table, is_dark);
-
-    let enhanced_removed = tags::ColorTag((
-        tags::ENHANCED_REMOVED,
-        red.from_hsl(tags::HslAdjustment::Enhance),
-    ));
-    let enhanced_removed_tag = enhanced_removed.create(&table, is_dark);
-
-    let context = tags::ColorTag((tags::CONTEXT, grey.clone()));
-    let context_tag = context.create(&table, is_dark);
-
-    let enhanced_context = tags::ColorTag((tags::ENHANCED_CONTEXT, grey.darken(Some(0.2)))); //grey.from_hsl(tags::HslAdjustment::Enhance)));
-    let enhanced_context_tag = enhanced_context.create(&table, is_dark);
-
-    let syntax = tags::ColorTag((tags::SYNTAX, grey.darken(Some(0.3)))); //grey.from_hsl(tags::HslAdjustment::Up(false))));
-    let syntax_tag = syntax.create(&table, is_dark);
-
-    let enhanced_syntax = tags::ColorTag((tags::ENHANCED_SYNTAX, grey.darken(Some(0.4)))); // grey.from_hsl(tags::HslAdjustment::Up(true))
-    let enhanced_syntax_tag = enhanced_syntax.create(&table, is_dark);
-
-    let syntax_1 = tags::ColorTag((tags::SYNTAX_1, grey.darken(Some(-0.2)))); //grey.from_hsl(tags::HslAdjustment::Down(false))));
-    let syntax_1_tag = syntax_1.create(&table, is_dark);
-
-    let enhanced_syntax_1 = tags::ColorTag((tags::ENHANCED_SYNTAX_1, grey.darken(Some(-0.2)))); //grey.from_hsl(tags::HslAdjustment::Down(true))));
-    let enhanced_syntax_1_tag = enhanced_syntax_1.create(&table, is_dark);
-
-    let syntax_added = tags::ColorTag((
-        tags::SYNTAX_ADDED,
-        green.from_hsl(tags::HslAdjustment::Up(false)),
-    ));
-    let syntax_added_tag = syntax_added.create(&table, is_dark);
-
-    let enhanced_syntax_added = tags::ColorTag((
-        tags::ENHANCED_SYNTAX_ADDED,
-        green.from_hsl(tags::HslAdjustment::Up(true)),
-    ));
-    let enhanced_syntax_added_tag = enhanced_syntax_added.create(&table, is_dark);
-
-    let syntax_removed = tags::ColorTag((
-        tags::SYNTAX_REMOVED,
-        red.from_hsl(tags::HslAdjustment::Up(false)),
-    ));
-    let syntax_removed_tag = syntax_removed.create(&table, is_dark);
-
-    let enhanced_syntax_removed = tags::ColorTag((
-        tags::ENHANCED_SYNTAX_REMOVED,
-        red.from_hsl(tags::HslAdjustment::Up(true)),
-    ));
-    let enhanced_syntax_removed_tag = enhanced_syntax_removed.create(&table, is_dark);
-
-    let syntax_1_added = tags::ColorTag((
-        tags::SYNTAX_1_ADDED,
-        green.from_hsl(tags::HslAdjustment::Down(false)),
-    )); //magenta_color.clone()
-    let syntax_1_added_tag = syntax_1_added.create(&table, is_dark);
-
-    let syntax_1_removed = tags::ColorTag((
-        tags::SYNTAX_1_REMOVED,
-        red.from_hsl(tags::HslAdjustment::Down(false)),
-    )); // yellow_color.clone()
-    let syntax_1_removed_tag = syntax_1_removed.create(&table, is_dark);
-
-    let enhanced_syntax_1_added = tags::ColorTag((
-        tags::ENHANCED_SYNTAX_1_ADDED,
-        green.from_hsl(tags::HslAdjustment::Down(true)),
-    )); //magenta_color
-    let enhanced_syntax_1_added_tag = enhanced_syntax_1_added.create(&table, is_dark);
-
-    let enhanced_syntax_1_removed = tags::ColorTag((
-        tags::ENHANCED_SYNTAX_1_REMOVED,
-        red.from_hsl(tags::HslAdjustment::Down(true)),
-    )); // yellow_color
-    let enhanced_syntax_1_removed_tag = enhanced_syntax_1_removed.create(&table, is_dark);
-
     let pointer = tags::Tag(tags::POINTER).create(&table);
     let staged = tags::Tag(tags::STAGED).create(&table);
     let unstaged = tags::Tag(tags::UNSTAGED).create(&table);
@@ -534,6 +478,10 @@ pub fn factory(sndr: Sender<crate::Event>, name: &str) -> StageView {
     let underline = tags::Tag(tags::UNDERLINE).create(&table);
     underline.set_underline(Underline::Single);
 
+    let search = tags::Tag(tags::SEARCH).create(&table);
+    // translucent, so it is visible in both themes and over cursor
+    search.set_background(Some("rgba(246, 211, 45, 0.5)"));
+
     tags::Tag(tags::OURS).create(&table);
     tags::Tag(tags::THEIRS).create(&table);
 
@@ -556,39 +504,11 @@ pub fn factory(sndr: Sender<crate::Event>, name: &str) -> StageView {
                 txt.add_css_class(LIGHT_CLASS);
             }
             txt.set_background();
-
-            diff.toggle(&diff_tag, is_dark);
-            conflict_marker.toggle(&conflict_marker_tag, is_dark);
-
-            spaces_added.toggle(&spaces_added_tag, is_dark);
-            spaces_removed.toggle(&spaces_removed_tag, is_dark);
-
-            added.toggle(&added_tag, is_dark);
-            enhanced_added.toggle(&enhanced_added_tag, is_dark);
-
-            removed.toggle(&removed_tag, is_dark);
-            enhanced_removed.toggle(&enhanced_removed_tag, is_dark);
-
-            context.toggle(&context_tag, is_dark);
-            enhanced_context.toggle(&enhanced_context_tag, is_dark);
-
-            syntax.toggle(&syntax_tag, is_dark);
-            enhanced_syntax.toggle(&enhanced_syntax_tag, is_dark);
-
-            syntax_1.toggle(&syntax_1_tag, is_dark);
-            enhanced_syntax_1.toggle(&enhanced_syntax_1_tag, is_dark);
-
-            syntax_added.toggle(&syntax_added_tag, is_dark);
-            enhanced_syntax_added.toggle(&enhanced_syntax_added_tag, is_dark);
-
-            syntax_removed.toggle(&syntax_removed_tag, is_dark);
-            enhanced_syntax_removed.toggle(&enhanced_syntax_removed_tag, is_dark);
-
-            syntax_1_added.toggle(&syntax_1_added_tag, is_dark);
-            enhanced_syntax_1_added.toggle(&enhanced_syntax_1_added_tag, is_dark);
-
-            syntax_1_removed.toggle(&syntax_1_removed_tag, is_dark);
-            enhanced_syntax_1_removed.toggle(&enhanced_syntax_1_removed_tag, is_dark);
+            toggle_colors(
+                &txt.buffer().tag_table(),
+                &txt.imp().color_tags.borrow(),
+                is_dark,
+            );
         }
     });
 
@@ -597,15 +517,23 @@ pub fn factory(sndr: Sender<crate::Event>, name: &str) -> StageView {
         let buffer = buffer.clone();
         let sndr = sndr.clone();
         let oid = oid.clone();
-        move |_, key, _, modifier| {
-            match (key, modifier) {
-                (gdk::Key::Tab | gdk::Key::space, _) => {
+        move |controller, key, _, modifier| {
+            match keymap::action(controller, "status") {
+                Some("expand") => {
                     let iter = buffer.iter_at_offset(buffer.cursor_position());
                     sndr.send_blocking(crate::Event::Expand(iter.offset(), iter.line()))
                         .expect("Could not send through channel");
                     return glib::Propagation::Stop;
                 }
-                (gdk::Key::s | gdk::Key::a | gdk::Key::Return, _) => {
+                Some("stage_all") => {
+                    sndr.send_blocking(crate::Event::StageAll(crate::StageOp::Stage))
+                        .expect("Could not send through channel");
+                }
+                Some("unstage_all") => {
+                    sndr.send_blocking(crate::Event::StageAll(crate::StageOp::Unstage))
+                        .expect("Could not send through channel");
+                }
+                Some("stage") => {
                     if key == gdk::Key::Return {
                         let pos = buffer.cursor_position();
                         let iter = buffer.iter_at_offset(pos);
@@ -619,89 +547,210 @@ pub fn factory(sndr: Sender<crate::Event>, name: &str) -> StageView {
                     sndr.send_blocking(crate::Event::Stage(crate::StageOp::Stage))
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::u | gdk::Key::r, _) => {
+                Some("unstage") => {
                     sndr.send_blocking(crate::Event::Stage(crate::StageOp::Unstage))
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::k | gdk::Key::Delete | gdk::Key::BackSpace, _) => {
+                Some("kill") => {
                     sndr.send_blocking(crate::Event::Stage(crate::StageOp::Kill))
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::b, gdk::ModifierType::CONTROL_MASK) => {
+                Some("blame") => {
                     sndr.send_blocking(crate::Event::Blame)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::c, gdk::ModifierType::CONTROL_MASK) => {
-                    // for ctrl-c
+                Some("blame_file") => {
+                    sndr.send_blocking(crate::Event::BlameFile)
+                        .expect("Could not send through channel");
+                }
+                Some("ignore") => {
+                    sndr.send_blocking(crate::Event::Ignore)
+                        .expect("Could not send through channel");
+                }
+                Some("show_file") => {
+                    sndr.send_blocking(crate::Event::ShowFile)
+                        .expect("Could not send through channel");
+                }
+                Some("bisect_good") => {
+                    sndr.send_blocking(crate::Event::Bisect(crate::BisectOp::Good))
+                        .expect("Could not send through channel");
+                }
+                Some("bisect_bad") => {
+                    sndr.send_blocking(crate::Event::Bisect(crate::BisectOp::Bad))
+                        .expect("Could not send through channel");
+                }
+                Some("mergetool") => {
+                    sndr.send_blocking(crate::Event::OpenMergetool)
+                        .expect("Could not send through channel");
+                }
+                Some("next_conflict") => {
+                    sndr.send_blocking(crate::Event::NextConflict(true))
+                        .expect("Could not send through channel");
                 }
-                (gdk::Key::c, _) => {
+                Some("prev_conflict") => {
+                    sndr.send_blocking(crate::Event::NextConflict(false))
+                        .expect("Could not send through channel");
+                }
+                Some("squash") => {
+                    sndr.send_blocking(crate::Event::Squash)
+                        .expect("Could not send through channel");
+                }
+                Some("abort") => {
+                    sndr.send_blocking(crate::Event::Abort)
+                        .expect("Could not send through channel");
+                }
+                Some("branch_switcher") => {
+                    sndr.send_blocking(crate::Event::BranchSwitcher)
+                        .expect("Could not send through channel");
+                }
+                Some("branch_at_head") => {
+                    sndr.send_blocking(crate::Event::BranchAtHead)
+                        .expect("Could not send through channel");
+                }
+                Some("commit") => {
                     sndr.send_blocking(crate::Event::Commit)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::p, _) => {
+                Some("inline_commit") => {
+                    sndr.send_blocking(crate::Event::InlineCommit(crate::InlineCommitOp::Open))
+                        .expect("Could not send through channel");
+                }
+                Some("push") => {
                     sndr.send_blocking(crate::Event::Push)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::f, _) => {
+                Some("pull") => {
                     sndr.send_blocking(crate::Event::Pull)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::b, _) => {
+                Some("pull_rebase") => {
+                    sndr.send_blocking(crate::Event::PullRebase)
+                        .expect("Could not send through channel");
+                }
+                Some("branches") => {
                     sndr.send_blocking(crate::Event::ShowBranches)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::l, _) => {
+                Some("reflog") => {
+                    sndr.send_blocking(crate::Event::Reflog)
+                        .expect("Could not send through channel");
+                }
+                Some("log") => {
                     sndr.send_blocking(crate::Event::Log(None, None))
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::g, _) => {
+                Some("refresh") => {
                     sndr.send_blocking(crate::Event::Refresh)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::o, gdk::ModifierType::CONTROL_MASK) => {
+                Some("paste_path") => {
+                    sndr.send_blocking(crate::Event::PastePath)
+                        .expect("Could not send through channel");
+                }
+                Some("open") => {
                     sndr.send_blocking(crate::Event::OpenFileDialog)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::o, _) => {
+                Some("repo_popup") => {
                     sndr.send_blocking(crate::Event::RepoPopup)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::z, _) => {
+                Some("stashes") => {
                     sndr.send_blocking(crate::Event::StashesPanel)
                         .expect("cant send through channel");
                 }
-                (gdk::Key::d, gdk::ModifierType::CONTROL_MASK) => {
+                Some("worktrees") => {
+                    sndr.send_blocking(crate::Event::WorktreesDialog)
+                        .expect("Could not send through channel");
+                }
+                Some("dump") => {
                     let _iter = buffer.iter_at_offset(buffer.cursor_position());
                     sndr.send_blocking(crate::Event::Dump)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::d, _) => {
+                Some("debug") => {
                     let _iter = buffer.iter_at_offset(buffer.cursor_position());
                     sndr.send_blocking(crate::Event::Debug)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::equal, gdk::ModifierType::CONTROL_MASK) => {
+                Some("zoom_in") => {
                     sndr.send_blocking(crate::Event::Zoom(true))
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::minus, gdk::ModifierType::CONTROL_MASK) => {
+                Some("zoom_out") => {
                     sndr.send_blocking(crate::Event::Zoom(false))
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::e, _) => {
+                Some("next_file") => {
+                    sndr.send_blocking(crate::Event::NextFile(true))
+                        .expect("Could not send through channel");
+                }
+                Some("prev_file") => {
+                    sndr.send_blocking(crate::Event::NextFile(false))
+                        .expect("Could not send through channel");
+                }
+                // global - in all sections
+                Some(action @ ("expand_all" | "expand_all_global")) => {
+                    let global = action == "expand_all_global";
+                    sndr.send_blocking(crate::Event::ExpandAll(true, global))
+                        .expect("Could not send through channel");
+                }
+                Some(action @ ("collapse_all" | "collapse_all_global")) => {
+                    let global = action == "collapse_all_global";
+                    sndr.send_blocking(crate::Event::ExpandAll(false, global))
+                        .expect("Could not send through channel");
+                }
+                Some("editor") => {
                     sndr.send_blocking(crate::Event::OpenEditor)
                         .expect("Could not send through channel");
                 }
-                (gdk::Key::t, _) => {
+                Some("copy_hunk") => {
+                    sndr.send_blocking(crate::Event::CopyHunk(false))
+                        .expect("Could not send through channel");
+                }
+                Some("copy_hunk_with_prefixes") => {
+                    sndr.send_blocking(crate::Event::CopyHunk(true))
+                        .expect("Could not send through channel");
+                }
+                Some("apply_patch") => {
+                    sndr.send_blocking(crate::Event::ApplyPatch)
+                        .expect("Could not send through channel");
+                }
+                Some("export_patch") => {
+                    sndr.send_blocking(crate::Event::ExportPatch)
+                        .expect("Could not send through channel");
+                }
+                Some("undo") => {
+                    sndr.send_blocking(crate::Event::Undo)
+                        .expect("Could not send through channel");
+                }
+                Some("search") => {
+                    sndr.send_blocking(crate::Event::Search(crate::SearchOp::Open))
+                        .expect("Could not send through channel");
+                }
+                Some("next_match") => {
+                    sndr.send_blocking(crate::Event::Search(crate::SearchOp::Next))
+                        .expect("Could not send through channel");
+                }
+                Some("prev_match") => {
+                    sndr.send_blocking(crate::Event::Search(crate::SearchOp::Previous))
+                        .expect("Could not send through channel");
+                }
+                Some("tags") => {
                     sndr.send_blocking(crate::Event::Tags(None))
                         .expect("Could not send through channel");
                 }
-                (_, gdk::ModifierType::LOCK_MASK) => {
+                None if modifier == gdk::ModifierType::LOCK_MASK => {
                     sndr.send_blocking(crate::Event::Toast(String::from("CapsLock pressed")))
                         .expect("Could not send through channel");
                 }
-                (key, modifier) => {
-                    trace!("key press in status view {:?} {:?}", key.name(), modifier);
+                action => {
+                    trace!(
+                        "key press in status view {:?} {:?} {:?}",
+                        action,
+                        key.name(),
+                        modifier
+                    );
                 }
             }
             glib::Propagation::Proceed
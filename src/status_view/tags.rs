@@ -6,7 +6,9 @@ use crate::status_view::view::View;
 use core::fmt::{Binary, Formatter, Result};
 use gtk4::prelude::*;
 use gtk4::{TextTag, TextTagTable};
+use log::error;
 use palette::{rgb::Rgb, FromColor, Hsl, RgbHue};
+use std::collections::HashMap;
 
 pub const POINTER: &str = "pointer";
 pub const STAGED: &str = "staged";
@@ -45,6 +47,10 @@ pub const THEIRS: &str = "theirs";
 pub const CONTEXT: &str = "context";
 pub const ENHANCED_CONTEXT: &str = "enhancedContext";
 
+// search matches are highlighted directly in buffer,
+// without View, so it is not in TEXT_TAGS
+pub const SEARCH: &str = "search";
+
 pub const SYNTAX: &str = "syntax";
 pub const SYNTAX_1: &str = "syntax1";
 pub const ENHANCED_SYNTAX: &str = "enhancedSyntax";
@@ -171,6 +177,127 @@ impl Color {
     }
 }
 
+impl Color {
+    pub fn is_hex(hex: &str) -> bool {
+        hex.len() == 7 && hex.starts_with('#') && hex[1..].chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    /// WCAG contrast ratio, 1.0 - same luminance, 21.0 - black on white.
+    pub fn contrast(hex: &str, background: (f32, f32, f32)) -> f32 {
+        let luminance = |r: f32, g: f32, b: f32| {
+            let linear = |c: f32| {
+                if c <= 0.03928 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            };
+            0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+        };
+        let rgb = Self::hex_to_rgb(hex);
+        let l1 = luminance(rgb.red, rgb.green, rgb.blue);
+        let l2 = luminance(background.0, background.1, background.2);
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+}
+
+/// Base diff colors. All other colors (syntax, enhanced)
+/// are derived from these ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub added: Color,
+    pub removed: Color,
+    pub context: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            // Dark theme - first color on tuple. Light - last one.
+            added: Color(("#4a8e09".to_string(), "#10ac64".to_string())),
+            removed: Color(("#a51d2d".to_string(), "#c01c28".to_string())),
+            // in terms of dark theme: white  - is white font on black backgroud. grey is near white.
+            // in terms of light theme: black - is black font on white color. greay is near black
+            context: Color(("#999999".to_string(), "#555555".to_string())),
+        }
+    }
+}
+
+impl Palette {
+    /// Overrides are keyed as added-dark, added-light, removed-dark etc.
+    /// Color which is not readable (according to readable(hex, is_dark))
+    /// is ignored and default one is used.
+    pub fn new(overrides: &HashMap<String, String>, readable: impl Fn(&str, bool) -> bool) -> Self {
+        let default = Self::default();
+        let pick = |name: &str, default: &Color| {
+            let choose = |theme: &str, default: &str, is_dark: bool| {
+                let key = format!("{}-{}", name, theme);
+                match overrides.get(&key) {
+                    Some(hex) if Color::is_hex(hex) && readable(hex, is_dark) => hex.clone(),
+                    Some(hex) => {
+                        error!("diff color {} {} is wrong or unreadable", key, hex);
+                        default.to_string()
+                    }
+                    None => default.to_string(),
+                }
+            };
+            Color((
+                choose("dark", &default.0 .0, true),
+                choose("light", &default.0 .1, false),
+            ))
+        };
+        Self {
+            added: pick("added", &default.added),
+            removed: pick("removed", &default.removed),
+            context: pick("context", &default.context),
+        }
+    }
+
+    pub fn color_tags(&self) -> Vec<ColorTag> {
+        let green = &self.added;
+        let red = &self.removed;
+        let grey = &self.context;
+        let diff_color = Color(("#a78a44".to_string(), "#8b6508".to_string()));
+        let conflict_color = Color(("#ff0000".to_string(), "#ff0000".to_string()));
+        vec![
+            ColorTag((DIFF, diff_color)),
+            ColorTag((CONFLICT_MARKER, conflict_color)),
+            ColorTag((SPACES_ADDED, green.clone())),
+            ColorTag((SPACES_REMOVED, red.clone())),
+            ColorTag((ADDED, green.clone())),
+            ColorTag((ENHANCED_ADDED, green.from_hsl(HslAdjustment::Enhance))),
+            ColorTag((REMOVED, red.clone())),
+            ColorTag((ENHANCED_REMOVED, red.from_hsl(HslAdjustment::Enhance))),
+            ColorTag((CONTEXT, grey.clone())),
+            ColorTag((ENHANCED_CONTEXT, grey.darken(Some(0.2)))), //grey.from_hsl(HslAdjustment::Enhance)));
+            ColorTag((SYNTAX, grey.darken(Some(0.3)))), //grey.from_hsl(HslAdjustment::Up(false))));
+            ColorTag((ENHANCED_SYNTAX, grey.darken(Some(0.4)))), // grey.from_hsl(HslAdjustment::Up(true))
+            ColorTag((SYNTAX_1, grey.darken(Some(-0.2)))), //grey.from_hsl(HslAdjustment::Down(false))));
+            ColorTag((ENHANCED_SYNTAX_1, grey.darken(Some(-0.2)))), //grey.from_hsl(HslAdjustment::Down(true))));
+            ColorTag((SYNTAX_ADDED, green.from_hsl(HslAdjustment::Up(false)))),
+            ColorTag((
+                ENHANCED_SYNTAX_ADDED,
+                green.from_hsl(HslAdjustment::Up(true)),
+            )),
+            ColorTag((SYNTAX_REMOVED, red.from_hsl(HslAdjustment::Up(false)))),
+            ColorTag((
+                ENHANCED_SYNTAX_REMOVED,
+                red.from_hsl(HslAdjustment::Up(true)),
+            )),
+            ColorTag((SYNTAX_1_ADDED, green.from_hsl(HslAdjustment::Down(false)))), //magenta_color.clone()
+            ColorTag((SYNTAX_1_REMOVED, red.from_hsl(HslAdjustment::Down(false)))), // yellow_color.clone()
+            ColorTag((
+                ENHANCED_SYNTAX_1_ADDED,
+                green.from_hsl(HslAdjustment::Down(true)),
+            )), //magenta_color
+            ColorTag((
+                ENHANCED_SYNTAX_1_REMOVED,
+                red.from_hsl(HslAdjustment::Down(true)),
+            )), // yellow_color
+        ]
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct Tag(pub &'static str);
 
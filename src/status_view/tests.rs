@@ -504,6 +504,38 @@ fn test_expand_line() {
     }
 }
 
+#[gtk4::test]
+fn test_enrich_keeps_expanded_files() {
+    let buffer = initialize();
+    let stage = StageView::new();
+    stage.set_buffer(Some(&buffer));
+    let mut context = StatusRenderContext::new(&stage);
+
+    let rendered = create_diff();
+    let mut iter = buffer.iter_at_offset(0);
+    rendered.render(&buffer, &mut iter, &mut context);
+    for f in [0, 2] {
+        let line_no = rendered.files[f].view.line_no.get();
+        rendered.files[f].expand(line_no, &mut context).unwrap();
+        let mut iter = buffer.iter_at_offset(0);
+        rendered.render(&buffer, &mut iter, &mut context);
+    }
+
+    // refresh brings same files
+    let new = create_diff();
+    new.enrich_view(&rendered, &buffer, &mut context);
+    assert!(new.files[0].view.is_expanded());
+    assert!(!new.files[1].view.is_expanded());
+    assert!(new.files[2].view.is_expanded());
+
+    let mut iter = buffer.iter_at_offset(0);
+    new.render(&buffer, &mut iter, &mut context);
+    assert!(new.files[0].view.is_expanded());
+    assert!(new.files[2].view.is_expanded());
+    assert!(new.files[2].hunks[0].view.is_rendered());
+    assert!(!new.files[1].hunks[0].view.is_rendered());
+}
+
 #[gtk4::test]
 fn test_reconciliation_new() {
     let buffer = initialize();
@@ -799,6 +831,25 @@ fn test_tags() {
     assert!(!view.tag_indexes.get().is_added(&tag3));
 }
 
+#[test]
+fn test_palette() {
+    let mut overrides = std::collections::HashMap::new();
+    overrides.insert(String::from("added-dark"), String::from("#00ff00"));
+    overrides.insert(String::from("removed-light"), String::from("ff0000"));
+    // same as light cursor highlight
+    overrides.insert(String::from("context-light"), String::from("#cce0f8"));
+    let cursor = (0.80, 0.878, 0.972);
+    let palette = tags::Palette::new(&overrides, |hex, _| {
+        tags::Color::contrast(hex, cursor) >= 1.3
+    });
+    let default = tags::Palette::default();
+    assert_eq!(palette.added.0 .0, "#00ff00");
+    assert_eq!(palette.added.0 .1, default.added.0 .1);
+    assert_eq!(palette.removed, default.removed);
+    assert_eq!(palette.context, default.context);
+    assert_eq!(palette.color_tags().len(), default.color_tags().len());
+}
+
 #[test]
 pub fn test_flags() {
     let mut flags = RenderFlags::new();
@@ -821,6 +872,32 @@ pub fn test_flags() {
     );
 }
 
+#[test]
+pub fn test_fold_context() {
+    let mut hunk = Hunk::new(DiffKind::Unstaged);
+    for i in 0..32 {
+        let mut line = create_line(i, 0, 0);
+        if i == 0 || i == 31 {
+            line.origin = DiffLineType::Addition;
+        }
+        hunk.lines.push(line);
+    }
+    hunk.fold_context();
+    // 30 context lines minus 3 around each change
+    assert_eq!(hunk.folds.len(), 1);
+    assert_eq!(hunk.folds[0].start, 4);
+    assert_eq!(hunk.folds[0].len, 24);
+    assert_eq!(hunk.folded_at(&hunk.lines[4]), Some(24));
+    assert!(!hunk.is_folded_away(4));
+    assert!(hunk.is_folded_away(27));
+    assert!(!hunk.is_folded_away(28));
+
+    // short run is not folded
+    hunk.lines.truncate(10);
+    hunk.fold_context();
+    assert!(hunk.folds.is_empty());
+}
+
 #[gtk4::test]
 pub fn test_cursor_position() {
     let buffer = initialize();
@@ -1044,3 +1121,35 @@ pub fn test_choose_cursor_position() {
     assert!(diffs.last_op.get().is_none());
     assert!(iter.line() == diffs.unstaged.as_ref().unwrap().files[0].view.line_no.get());
 }
+
+#[test]
+fn test_recent_paths() {
+    use crate::status_view::headerbar::{recent_paths, MAX_RECENT_PATHS};
+    let repos: Vec<std::path::PathBuf> = (0..MAX_RECENT_PATHS + 1)
+        .map(|i| crate::tests::temp_repo(&format!("recent-{}", i)).0)
+        .collect();
+    let paths: Vec<String> = repos
+        .iter()
+        .map(|path| path.to_str().unwrap().to_string())
+        .collect();
+    let (a, b) = (paths[0].clone(), paths[1].clone());
+
+    // current goes first, duplicates and not repos are dropped
+    assert_eq!(
+        recent_paths(
+            vec![
+                b.clone(),
+                format!("{}/", a),
+                String::from("/surely/not/a/repo"),
+                a.clone()
+            ],
+            Some(&a)
+        ),
+        vec![a.clone(), b.clone()]
+    );
+    assert_eq!(recent_paths(paths.clone(), None).len(), MAX_RECENT_PATHS);
+
+    for path in repos {
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
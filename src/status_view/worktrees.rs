@@ -0,0 +1,161 @@
+// SPDX-FileCopyrightText: 2024 Aleksey Ganzha <aganzha@yandex.ru>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::Status;
+use crate::dialogs::{alert, DangerDialog, YES};
+use crate::git::worktree;
+use crate::Event;
+use async_channel::Sender;
+use gtk4::{gio, glib, Align, Button, Label};
+use libadwaita::prelude::*;
+use libadwaita::{
+    ActionRow, ApplicationWindow, PreferencesDialog, PreferencesGroup, PreferencesPage,
+};
+use std::path::Path;
+
+impl worktree::WorktreeInfo {
+    fn render(
+        &self,
+        group: &PreferencesGroup,
+        path: &Path,
+        dialog: &PreferencesDialog,
+        window: &ApplicationWindow,
+        sender: Sender<Event>,
+    ) -> ActionRow {
+        let title = match (&self.branch, &self.name) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(name)) => name.clone(),
+            (None, None) => String::from("main"),
+        };
+        let row = ActionRow::builder()
+            .title(glib::markup_escape_text(&title))
+            .subtitle(glib::markup_escape_text(&self.path.to_string_lossy()))
+            .build();
+        if self.is_current {
+            row.add_suffix(
+                &Label::builder()
+                    .label("current")
+                    .css_classes(["dim-label"])
+                    .build(),
+            );
+            return row;
+        }
+        if self.exists {
+            let open_button = Button::builder()
+                .icon_name("folder-open-symbolic")
+                .tooltip_text("Open worktree")
+                .valign(Align::Center)
+                .css_classes(["flat"])
+                .build();
+            open_button.connect_clicked({
+                let worktree_path = self.path.clone();
+                let dialog = dialog.clone();
+                move |_| {
+                    dialog.close();
+                    sender
+                        .send_blocking(Event::OpenRepo(worktree_path.clone()))
+                        .expect("cant send through channel");
+                }
+            });
+            row.add_suffix(&open_button);
+            row.set_activatable_widget(Some(&open_button));
+            return row;
+        }
+        // dir was removed by hand. switching there is not possible
+        row.add_css_class("error");
+        let prune_button = Button::builder()
+            .label("Prune")
+            .tooltip_text("Worktree directory is missing")
+            .valign(Align::Center)
+            .css_classes(["destructive-action"])
+            .build();
+        prune_button.connect_clicked({
+            let path = path.to_path_buf();
+            let name = self.name.clone().unwrap_or_default();
+            let window = window.clone();
+            let group = group.clone();
+            let row = row.clone();
+            move |_| {
+                glib::spawn_future_local({
+                    let path = path.clone();
+                    let name = name.clone();
+                    let window = window.clone();
+                    let group = group.clone();
+                    let row = row.clone();
+                    async move {
+                        let response = alert(DangerDialog(
+                            String::from("Prune worktree"),
+                            format!(
+                                "Directory of worktree {} does not exist.\nRemove it from repository?",
+                                name
+                            ),
+                        ))
+                        .choose_future(&window)
+                        .await;
+                        if response != YES {
+                            return;
+                        }
+                        let pruned = gio::spawn_blocking(move || worktree::prune(path, name))
+                            .await
+                            .unwrap_or_else(|e| {
+                                alert(format!("{:?}", e)).present(Some(&window));
+                                Ok(())
+                            })
+                            .map(|_| true)
+                            .unwrap_or_else(|e| {
+                                alert(e).present(Some(&window));
+                                false
+                            });
+                        if pruned {
+                            group.remove(&row);
+                        }
+                    }
+                });
+            }
+        });
+        row.add_suffix(&prune_button);
+        row
+    }
+}
+
+impl Status {
+    pub fn show_worktrees_dialog(&self, window: &ApplicationWindow) {
+        let window = window.clone();
+        let path = self.path.clone().unwrap();
+        let sender = self.sender.clone();
+        glib::spawn_future_local({
+            async move {
+                let worktrees = gio::spawn_blocking({
+                    let path = path.clone();
+                    move || worktree::list(path)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(Vec::new())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    Vec::new()
+                });
+
+                let dialog = PreferencesDialog::builder().title("Worktrees").build();
+                let page = PreferencesPage::builder()
+                    .title("Worktrees")
+                    .icon_name("folder-symbolic")
+                    .build();
+                let group = PreferencesGroup::builder()
+                    .description("New worktree could be created for a branch in branches view")
+                    .build();
+                for info in &worktrees {
+                    let row = info.render(&group, &path, &dialog, &window, sender.clone());
+                    group.add(&row);
+                }
+                page.add(&group);
+                dialog.add(&page);
+                dialog.present(Some(&window));
+            }
+        });
+    }
+}
@@ -3,8 +3,22 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use crate::Line;
+use std::cell::RefCell;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+#[cfg(feature = "syntax")]
+use gtk4::glib;
+#[cfg(feature = "syntax")]
+use log::{debug, error};
+#[cfg(feature = "syntax")]
+use std::collections::HashMap;
+#[cfg(feature = "syntax")]
+use std::fs;
+#[cfg(feature = "syntax")]
+use std::path::PathBuf;
+#[cfg(feature = "syntax")]
+use std::sync::{Arc, OnceLock};
 #[cfg(feature = "syntax")]
 use tree_sitter::Parser;
 
@@ -19,11 +33,48 @@ pub enum LanguageWrapper {
     Python(Parser),
     #[cfg(feature = "syntax")]
     TypeScript(Parser),
+    #[cfg(feature = "syntax")]
+    Keywords(Arc<Vec<String>>),
+}
+
+// highlighting could be slow on huge diffs
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
 }
 
 #[cfg(not(feature = "syntax"))]
-pub fn choose_parser(path: &Path) -> Option<LanguageWrapper> {
-    Some(LanguageWrapper::None)
+pub fn language_key(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(not(feature = "syntax"))]
+#[derive(Default)]
+pub struct Parsers;
+
+#[cfg(not(feature = "syntax"))]
+impl Parsers {
+    pub fn get(&mut self, _key: Option<&str>) -> Option<&mut LanguageWrapper> {
+        None
+    }
+}
+
+thread_local! {
+    static PARSERS: RefCell<Parsers> = RefCell::new(Parsers::default());
+}
+
+pub fn with_parsers<R>(f: impl FnOnce(&mut Parsers) -> R) -> R {
+    PARSERS.with_borrow_mut(f)
+}
+
+#[cfg(not(feature = "syntax"))]
+pub fn file_ranges(_path: &Path, _content: &str) -> (Vec<(usize, usize)>, Vec<(usize, usize)>) {
+    (Vec::new(), Vec::new())
 }
 
 #[cfg(not(feature = "syntax"))]
@@ -31,36 +82,162 @@ impl LanguageWrapper {
     pub fn parse_hunk(&self, _hunk: &mut Hunk) {}
 }
 
+/// User supplied syntaxes live in ~/.config/stage/syntax.
+/// Each file is named after extension it highlights (e.g. toml.keywords)
+/// and contains keywords separated by whitespace. Lines starting
+/// with # are comments. Format is described in README
+/// (Syntax highlighting section).
 #[cfg(feature = "syntax")]
-pub fn choose_parser(path: &Path) -> Option<LanguageWrapper> {
-    let path_str = path.to_str().unwrap();
-    let mut parser = Parser::new();
+pub fn user_syntax_dir() -> PathBuf {
+    glib::user_config_dir().join("stage").join("syntax")
+}
+
+#[cfg(feature = "syntax")]
+pub const USER_SYNTAX_EXT: &str = "keywords";
+
+#[cfg(feature = "syntax")]
+fn parse_keywords(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split_whitespace())
+        .map(|word| word.to_string())
+        .collect()
+}
 
-    if path_str.ends_with(".rs") {
-        parser
-            .set_language(&tree_sitter_rust::LANGUAGE.into())
-            .expect("Error loading Rust grammar");
-        return Some(LanguageWrapper::Rust(parser));
+#[cfg(feature = "syntax")]
+fn load_user_syntaxes(dir: &Path) -> HashMap<String, Arc<Vec<String>>> {
+    let mut result = HashMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("no user syntaxes in {:?} {:?}", dir, e);
+            return result;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(USER_SYNTAX_EXT) {
+            continue;
+        }
+        let Some(ext) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        // broken syntax must not break diff. just skip it
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let keywords = parse_keywords(&content);
+                if keywords.is_empty() {
+                    error!("empty user syntax {:?}", path);
+                    continue;
+                }
+                debug!("user syntax for .{} {:?}", ext, path);
+                result.insert(ext.to_string(), Arc::new(keywords));
+            }
+            Err(e) => {
+                error!("cant load user syntax {:?} {:?}", path, e);
+            }
+        }
     }
-    if path_str.ends_with(".py") {
-        parser
-            .set_language(&tree_sitter_python::LANGUAGE.into())
-            .expect("Error loading Python grammar");
-        return Some(LanguageWrapper::Python(parser));
+    result
+}
+
+#[cfg(feature = "syntax")]
+fn user_syntaxes() -> &'static HashMap<String, Arc<Vec<String>>> {
+    static USER_SYNTAXES: OnceLock<HashMap<String, Arc<Vec<String>>>> = OnceLock::new();
+    USER_SYNTAXES.get_or_init(|| load_user_syntaxes(&user_syntax_dir()))
+}
+
+/// Key for parser cache. Same key - same language.
+#[cfg(feature = "syntax")]
+pub fn language_key(path: &Path) -> Option<String> {
+    if !is_enabled() {
+        return None;
     }
-    if path_str.ends_with(".ts") {
-        parser
-            .set_language(&tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into())
-            .expect("Error loading TypeScript grammar");
-        return Some(LanguageWrapper::TypeScript(parser));
+    let ext = path.extension()?.to_str()?;
+    match ext {
+        "rs" | "py" | "ts" | "tsx" => Some(ext.to_string()),
+        ext if user_syntaxes().contains_key(ext) => Some(ext.to_string()),
+        _ => None,
     }
-    if path_str.ends_with(".tsx") {
-        parser
-            .set_language(&tree_sitter_typescript::LANGUAGE_TSX.into())
-            .expect("Error loading TSX grammar");
-        return Some(LanguageWrapper::TypeScript(parser)); // Treat TSX as TypeScript
+}
+
+#[cfg(feature = "syntax")]
+fn make_parser(key: &str) -> Option<LanguageWrapper> {
+    let mut parser = Parser::new();
+    match key {
+        "rs" => {
+            parser
+                .set_language(&tree_sitter_rust::LANGUAGE.into())
+                .expect("Error loading Rust grammar");
+            Some(LanguageWrapper::Rust(parser))
+        }
+        "py" => {
+            parser
+                .set_language(&tree_sitter_python::LANGUAGE.into())
+                .expect("Error loading Python grammar");
+            Some(LanguageWrapper::Python(parser))
+        }
+        "ts" => {
+            parser
+                .set_language(&tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into())
+                .expect("Error loading TypeScript grammar");
+            Some(LanguageWrapper::TypeScript(parser))
+        }
+        "tsx" => {
+            parser
+                .set_language(&tree_sitter_typescript::LANGUAGE_TSX.into())
+                .expect("Error loading TSX grammar");
+            Some(LanguageWrapper::TypeScript(parser)) // Treat TSX as TypeScript
+        }
+        ext => user_syntaxes()
+            .get(ext)
+            .map(|keywords| LanguageWrapper::Keywords(keywords.clone())),
+    }
+}
+
+/// Parsers are created once per language and reused for all
+/// files and all diffs. make_diff runs on gio blocking pool,
+/// so each thread keeps its own set (Parser is not Sync).
+#[cfg(feature = "syntax")]
+#[derive(Default)]
+pub struct Parsers {
+    parsers: HashMap<String, LanguageWrapper>,
+    #[cfg(test)]
+    created: usize,
+}
+
+#[cfg(feature = "syntax")]
+impl Parsers {
+    pub fn get(&mut self, key: Option<&str>) -> Option<&mut LanguageWrapper> {
+        let key = key?;
+        if !self.parsers.contains_key(key) {
+            let parser = make_parser(key)?;
+            #[cfg(test)]
+            {
+                self.created += 1;
+            }
+            self.parsers.insert(key.to_string(), parser);
+        }
+        self.parsers.get_mut(key)
+    }
+
+    #[cfg(test)]
+    pub fn created(&self) -> usize {
+        self.created
     }
-    None
+}
+
+/// Keyword and identifier byte ranges of whole file,
+/// same as for hunk.
+#[cfg(feature = "syntax")]
+pub fn file_ranges(path: &Path, content: &str) -> (Vec<(usize, usize)>, Vec<(usize, usize)>) {
+    with_parsers(|parsers| {
+        parsers
+            .get(language_key(path).as_deref())
+            .map(|parser| internal::collect_ranges(content, parser))
+            .unwrap_or_default()
+    })
 }
 
 #[cfg(feature = "syntax")]
@@ -179,9 +356,25 @@ mod internal {
                     "symbol",
                     "bigint",
                 ],
-                LanguageWrapper::None => unreachable!("no way"),
+                LanguageWrapper::Keywords(_) | LanguageWrapper::None => unreachable!("no way"),
+            }
+        }
+    }
+
+    // user syntaxes have no grammar. just find whole words
+    pub fn keyword_ranges(content: &str, keywords: &[String]) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        let mut start: Option<usize> = None;
+        for (i, c) in content.char_indices().chain([(content.len(), ' ')]) {
+            if c.is_alphanumeric() || c == '_' {
+                start.get_or_insert(i);
+            } else if let Some(from) = start.take() {
+                if keywords.iter().any(|k| k == &content[from..i]) {
+                    result.push((from, i));
+                }
             }
         }
+        result
     }
 
     pub fn get_node_range<'a>(
@@ -249,8 +442,12 @@ mod internal {
     ) -> (Vec<(usize, usize)>, Vec<(usize, usize)>) {
         let mut result = Vec::new();
         let mut result_1 = Vec::new();
-        if let LanguageWrapper::None = parser {
-            return (result, result_1);
+        match parser {
+            LanguageWrapper::None => return (result, result_1),
+            LanguageWrapper::Keywords(keywords) => {
+                return (keyword_ranges(content, keywords), result_1);
+            }
+            _ => {}
         }
         let tree = match parser {
             LanguageWrapper::Rust(p) => p.parse(content, None).unwrap(),
@@ -347,4 +544,64 @@ mod tests {
             assert_eq!(line.byte_indexes_to_char_indexes(&byte_indexes), expected);
         }
     }
+
+    #[cfg(feature = "syntax")]
+    #[test]
+    fn test_parsers_are_reused() {
+        let mut parsers = Parsers::default();
+        for path in ["src/main.rs", "src/lib.rs", "setup.py", "src/git.rs"] {
+            let key = language_key(Path::new(path));
+            assert!(parsers.get(key.as_deref()).is_some());
+        }
+        assert!(parsers
+            .get(language_key(Path::new("README.md")).as_deref())
+            .is_none());
+        // 4 files, but only 2 languages
+        assert_eq!(parsers.created(), 2);
+    }
+
+    // timing, not a check. run with
+    // cargo test bench_parsers -- --ignored --nocapture
+    #[cfg(feature = "syntax")]
+    #[test]
+    #[ignore]
+    fn bench_parsers_reuse() {
+        let path = Path::new("src/main.rs");
+        let content = "pub fn main() {\n    let mut x = 1;\n    for i in 0..10 { x += i; }\n}\n";
+        let rounds = 1000;
+
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            let mut parser = make_parser(&language_key(path).unwrap()).unwrap();
+            internal::collect_ranges(content, &mut parser);
+        }
+        let fresh = start.elapsed();
+
+        let mut parsers = Parsers::default();
+        let start = std::time::Instant::now();
+        for _ in 0..rounds {
+            let parser = parsers.get(language_key(path).as_deref()).unwrap();
+            internal::collect_ranges(content, parser);
+        }
+        let reused = start.elapsed();
+
+        println!(
+            "{} hunks. parser per hunk {:?}, reused parser {:?}",
+            rounds, fresh, reused
+        );
+        assert_eq!(parsers.created(), 1);
+        assert!(reused < fresh);
+    }
+
+    #[cfg(feature = "syntax")]
+    #[test]
+    fn test_user_keywords() {
+        let keywords = parse_keywords("# comment line\nrule when\n  then\n");
+        assert_eq!(keywords, vec!["rule", "when", "then"]);
+        let content = "rule ruler when\nthen_ then";
+        assert_eq!(
+            internal::keyword_ranges(content, &keywords),
+            vec![(0, 4), (11, 15), (22, 26)]
+        );
+    }
 }
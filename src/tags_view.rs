@@ -13,7 +13,7 @@ use gtk4::subclass::prelude::*;
 use gtk4::{
     gdk, gio, glib, pango, Box, Button, EventControllerKey, GestureClick, Label, ListBox, ListItem,
     ListView, Orientation, PositionType, ScrolledWindow, SearchBar, SearchEntry, SelectionMode,
-    SignalListItemFactory, SingleSelection, Spinner, TextView, Widget, WrapMode,
+    SignalListItemFactory, SingleSelection, Spinner, TextView, ToggleButton, Widget, WrapMode,
 };
 use std::cell::RefCell;
 use std::path::PathBuf;
@@ -21,6 +21,7 @@ use std::rc::Rc;
 
 use crate::dialogs::{alert, confirm_dialog_factory, DangerDialog, PROCEED, YES};
 use crate::git::{remote, tag};
+use crate::keymap;
 use crate::{CurrentWindow, DARK_CLASS, LIGHT_CLASS};
 use log::trace;
 use std::cell::Cell;
@@ -122,7 +123,7 @@ mod tag_list {
     use gtk4::glib;
     use gtk4::prelude::*;
     use gtk4::subclass::prelude::*;
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     #[derive(Properties, Default)]
     #[properties(wrapper_type = super::TagList)]
@@ -131,12 +132,18 @@ mod tag_list {
         pub original_list: RefCell<Vec<super::tag::Tag>>,
         pub search_term: RefCell<(String, usize)>,
 
+        // tags come in repo order (by name) unless newest first is asked
+        pub sort_by_date: Cell<bool>,
+
         // does not used for now
         #[property(get, set)]
         pub selected_pos: RefCell<u32>,
 
         #[property(get, set)]
         pub push_button: RefCell<gtk4::Button>,
+
+        #[property(get, set)]
+        pub push_all_button: RefCell<gtk4::Button>,
     }
 
     #[glib::object_subclass]
@@ -220,10 +227,11 @@ impl TagList {
                     append_to_existing = true;
                 }
 
+                let by_date = tag_list.imp().sort_by_date.get();
                 let tags = gio::spawn_blocking({
                     let search_term = search_term.clone();
                     let repo_path = repo_path.clone();
-                    move || tag::get_tag_list(repo_path, start_oid, search_term)
+                    move || tag::get_tag_list(repo_path, start_oid, search_term, by_date)
                 })
                 .await
                 .unwrap_or_else(|e| {
@@ -276,6 +284,16 @@ impl TagList {
         });
     }
 
+    // tags are paged, so resorting means loading them again
+    pub fn sort(&self, by_date: bool, repo_path: PathBuf, widget: &impl IsA<Widget>) {
+        self.imp().sort_by_date.replace(by_date);
+        self.imp().original_list.borrow_mut().clear();
+        let current_length = self.imp().list.borrow().len();
+        self.imp().list.borrow_mut().clear();
+        self.items_changed(0, current_length as u32, 0);
+        self.get_tags_inside(repo_path, None, widget);
+    }
+
     pub fn reset_search(&self) {
         self.imp().search_term.take();
         let orig_le = self.imp().original_list.borrow().len();
@@ -371,23 +389,83 @@ impl TagList {
         });
     }
 
-    pub fn kill_tag(&self, repo_path: PathBuf, window: &Window, sender: Sender<crate::Event>) {
+    pub fn push_all_tags(
+        &self,
+        repo_path: PathBuf,
+        remote_name: String,
+        window: &Window,
+        sender: Sender<crate::Event>,
+    ) {
+        let window = window.clone();
+        let spinner = Spinner::builder().spinning(true).build();
+
+        let push_btn = self.push_all_button();
+        push_btn.set_child(Some(&spinner));
+        push_btn.set_sensitive(false);
+
+        glib::spawn_future_local({
+            let push_btn = push_btn.clone();
+            async move {
+                let result = gio::spawn_blocking({
+                    let sender = sender.clone();
+                    move || remote::push_tags(repo_path, remote_name, sender)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok((Vec::new(), Vec::new()))
+                });
+                push_btn.set_child(None::<&Widget>);
+                push_btn.set_icon_name("mail-send-receive-symbolic");
+                push_btn.set_sensitive(true);
+                let (pushed, skipped) = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        alert(e).present(Some(&window));
+                        return;
+                    }
+                };
+                let mut message = format!("Pushed {} tags", pushed.len());
+                if !skipped.is_empty() {
+                    message.push_str(&format!(
+                        ". Skipped, differ on remote: {}",
+                        skipped.join(", ")
+                    ));
+                }
+                sender
+                    .send_blocking(crate::Event::Toast(message))
+                    .expect("cant send through sender");
+            }
+        });
+    }
+
+    pub fn kill_tag(
+        &self,
+        repo_path: PathBuf,
+        remote_name: Option<String>,
+        window: &Window,
+        sender: Sender<crate::Event>,
+    ) {
         glib::spawn_future_local({
             let tags_list = self.clone();
             let window = window.clone();
             async move {
                 let (tag_name, selected_pos) = tags_list.get_selected_tag();
-                let tg_name = tag_name.clone();
-                let result = gio::spawn_blocking(move || tag::kill_tag(repo_path, tg_name, sender))
-                    .await
-                    .unwrap_or_else(|e| {
-                        alert(format!("{:?}", e)).present(Some(&window));
-                        Ok(None)
-                    })
-                    .unwrap_or_else(|e| {
-                        alert(e).present(Some(&window));
-                        None
-                    });
+                let result = gio::spawn_blocking({
+                    let repo_path = repo_path.clone();
+                    let tag_name = tag_name.clone();
+                    let sender = sender.clone();
+                    move || tag::kill_tag(repo_path, tag_name, sender)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(None)
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                    None
+                });
                 if result.is_none() {
                     return;
                 }
@@ -416,6 +494,33 @@ impl TagList {
                         break;
                     }
                 }
+                // retracting tag from remote is a separate decision
+                let Some(remote_name) = remote_name else {
+                    return;
+                };
+                let response = alert(DangerDialog(
+                    String::from("Delete tag on remote"),
+                    format!(
+                        "Tag refs/tags/{} will be deleted on remote {} too.",
+                        tag_name, remote_name
+                    ),
+                ))
+                .choose_future(&window)
+                .await;
+                if response != YES {
+                    return;
+                }
+                gio::spawn_blocking(move || {
+                    remote::delete_tag(repo_path, remote_name, tag_name, sender)
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    alert(format!("{:?}", e)).present(Some(&window));
+                    Ok(())
+                })
+                .unwrap_or_else(|e| {
+                    alert(e).present(Some(&window));
+                });
             }
         });
     }
@@ -850,9 +955,25 @@ pub fn headerbar_factory(
         .use_markup(true)
         .label("Tags")
         .build();
+    let sort_btn = ToggleButton::builder()
+        .icon_name("view-sort-descending-symbolic")
+        .tooltip_text("Newest first")
+        .can_focus(false)
+        .active(tag_list.imp().sort_by_date.get())
+        .build();
+    sort_btn.connect_toggled({
+        let tag_list = tag_list.clone();
+        let list_view = list_view.clone();
+        let repo_path = repo_path.clone();
+        move |btn| {
+            tag_list.sort(btn.is_active(), repo_path.clone(), &list_view);
+        }
+    });
+
     let hb = HeaderBar::builder().build();
     hb.set_title_widget(Some(&search));
     hb.pack_start(&title);
+    hb.pack_start(&sort_btn);
 
     let cherry_pick_btn = Button::builder()
         .icon_name("emblem-shared-symbolic")
@@ -943,8 +1064,14 @@ pub fn headerbar_factory(
         let window = window.clone();
         let tag_list = tag_list.clone();
         let repo_path = repo_path.clone();
+        let remote_name = remote_name.clone();
         move |_| {
-            tag_list.kill_tag(repo_path.clone(), &window, sender.clone());
+            tag_list.kill_tag(
+                repo_path.clone(),
+                remote_name.clone(),
+                &window,
+                sender.clone(),
+            );
         }
     });
 
@@ -965,6 +1092,7 @@ pub fn headerbar_factory(
         let window = window.clone();
         let tag_list = tag_list.clone();
         let repo_path = repo_path.clone();
+        let remote_name = remote_name.clone();
         move |_| {
             tag_list.push_tag(
                 repo_path.clone(),
@@ -975,12 +1103,40 @@ pub fn headerbar_factory(
         }
     });
 
+    let push_all_btn = Button::builder()
+        .label("Push all")
+        .use_underline(true)
+        .can_focus(false)
+        .tooltip_text("Push all tags which are absent on remote")
+        .sensitive(remote_name.is_some())
+        .icon_name("mail-send-receive-symbolic")
+        .can_shrink(true)
+        .build();
+
+    tag_list.set_push_all_button(&push_all_btn);
+
+    push_all_btn.connect_clicked({
+        let sender = sender.clone();
+        let window = window.clone();
+        let tag_list = tag_list.clone();
+        let repo_path = repo_path.clone();
+        move |_| {
+            tag_list.push_all_tags(
+                repo_path.clone(),
+                remote_name.clone().unwrap(),
+                &window,
+                sender.clone(),
+            );
+        }
+    });
+
     hb.pack_end(&new_btn);
     hb.pack_end(&kill_btn);
     hb.pack_end(&reset_btn);
     hb.pack_end(&cherry_pick_btn);
     hb.pack_end(&revert_btn);
     hb.pack_end(&push_btn);
+    hb.pack_end(&push_all_btn);
     hb
 }
 
@@ -1096,15 +1252,15 @@ pub fn show_tags_window(
         let main_sender = main_sender.clone();
         let repo_path = repo_path.clone();
         let remote_name = remote_name.clone();
-        move |_, key, _, modifier| {
-            match (key, modifier) {
-                (gdk::Key::w, gdk::ModifierType::CONTROL_MASK) => {
-                    window.close();
-                }
-                (gdk::Key::Escape, _) => {
+        move |controller, key, _, modifier| {
+            match keymap::action(controller, "tags") {
+                // closing is the same in all windows and is not remapped
+                None if key == gdk::Key::Escape
+                    || (key == gdk::Key::w && modifier == gdk::ModifierType::CONTROL_MASK) =>
+                {
                     window.close();
                 }
-                (gdk::Key::s, _) => {
+                Some("search") => {
                     let search_bar = hb.title_widget().unwrap();
                     let search_bar = search_bar.downcast_ref::<SearchBar>().unwrap();
                     let search_entry = search_bar.child().unwrap();
@@ -1112,7 +1268,7 @@ pub fn show_tags_window(
                     trace!("enter search");
                     search_entry.grab_focus();
                 }
-                (gdk::Key::c | gdk::Key::n, _) => {
+                Some("create") => {
                     let tag_list = get_tags_list(&list_view);
                     tag_list.create_tag(
                         repo_path.clone(),
@@ -1121,11 +1277,27 @@ pub fn show_tags_window(
                         main_sender.clone(),
                     );
                 }
-                (gdk::Key::k | gdk::Key::d, _) => {
+                Some("kill") => {
                     let tag_list = get_tags_list(&list_view);
-                    tag_list.kill_tag(repo_path.clone(), &window, main_sender.clone());
+                    tag_list.kill_tag(
+                        repo_path.clone(),
+                        remote_name.clone(),
+                        &window,
+                        main_sender.clone(),
+                    );
+                }
+                Some("push_all") => {
+                    if let Some(remote_name) = remote_name.clone() {
+                        let tag_list = get_tags_list(&list_view);
+                        tag_list.push_all_tags(
+                            repo_path.clone(),
+                            remote_name,
+                            &window,
+                            main_sender.clone(),
+                        );
+                    }
                 }
-                (gdk::Key::p, _) => {
+                Some("push") => {
                     if let Some(remote_name) = remote_name.clone() {
                         let tag_list = get_tags_list(&list_view);
                         tag_list.push_tag(
@@ -1136,8 +1308,8 @@ pub fn show_tags_window(
                         );
                     }
                 }
-                (key, modifier) => {
-                    trace!("key pressed {:?} {:?}", key, modifier);
+                action => {
+                    trace!("key pressed {:?} {:?} {:?}", action, key, modifier);
                 }
             }
             glib::Propagation::Proceed
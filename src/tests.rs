@@ -28,3 +28,41 @@ pub fn initialize() -> TextBuffer {
     }
     buffer
 }
+
+// fresh repo in tmp dir. name must be unique across tests,
+// they are run in parallel
+#[cfg(test)]
+pub fn temp_repo(name: &str) -> (std::path::PathBuf, git2::Repository) {
+    let path = std::env::temp_dir().join(format!("stage-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let repo = git2::Repository::init(&path).unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "test").unwrap();
+    config.set_str("user.email", "test@example.org").unwrap();
+    (path, repo)
+}
+
+// writes files to workdir and commits them on top of HEAD
+#[cfg(test)]
+pub fn commit_files(repo: &git2::Repository, files: &[(&str, &str)], message: &str) -> git2::Oid {
+    let workdir = repo.workdir().unwrap();
+    let mut index = repo.index().unwrap();
+    for (file, content) in files {
+        let full_path = workdir.join(file);
+        std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+        std::fs::write(&full_path, content).unwrap();
+        index.add_path(std::path::Path::new(file)).unwrap();
+    }
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let me = repo.signature().unwrap();
+    let parents: Vec<git2::Commit> = repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &me, &me, message, &tree, &parents)
+        .unwrap()
+}